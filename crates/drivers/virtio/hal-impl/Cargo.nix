@@ -14,6 +14,7 @@ mk {
     ;
     virtio-drivers = virtioDriversWith [];
     inherit (localCrates)
+      sel4
       sel4-immediate-sync-once-cell
       sel4-shared-memory
       sel4-abstract-allocator
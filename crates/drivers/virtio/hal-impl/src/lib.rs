@@ -7,8 +7,11 @@
 #![no_std]
 
 use core::alloc::Layout;
+use core::ops::Range;
 use core::ptr::{self, NonNull};
 
+use sel4::{sel4_cfg, sel4_cfg_attr};
+
 use one_shot_mutex::sync::OneShotMutex;
 use virtio_drivers::{BufferDirection, Hal, PAGE_SIZE, PhysAddr};
 
@@ -19,56 +22,160 @@ use sel4_shared_memory::SharedMemoryRef;
 
 static GLOBAL_STATE: ImmediateSyncOnceCell<OneShotMutex<State>> = ImmediateSyncOnceCell::new();
 
-struct State {
-    dma_region: SharedMemoryRef<'static, [u8]>,
-    dma_region_paddr: usize,
-    bounce_buffer_allocator: ByRange<WithAlignmentBound<BasicAllocator>>,
+/// A function which applies a [`sel4::CacheOp`] to a range of virtual addresses in the DMA region,
+/// typically with [`sel4::cap::VSpace::vspace_cache_op_range`].
+#[sel4_cfg(ARCH_ARM)]
+pub type CacheOpFn = fn(sel4::CacheOp, Range<usize>);
+
+#[sel4_cfg(ARCH_ARM)]
+type MaybeCacheOpFn = Option<CacheOpFn>;
+
+#[sel4_cfg(not(ARCH_ARM))]
+type MaybeCacheOpFn = ();
+
+/// A physically contiguous region of memory which is mapped into the driver's address space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DmaRegion {
+    pub size: usize,
+    pub vaddr: usize,
+    pub paddr: usize,
 }
 
-impl State {
+#[sel4_cfg_attr(not(ARCH_ARM), allow(dead_code))]
+struct Region {
+    memory: SharedMemoryRef<'static, [u8]>,
+    vaddr: usize,
+    paddr: usize,
+    allocator: ByRange<WithAlignmentBound<BasicAllocator>>,
+}
+
+impl Region {
+    fn new(region: DmaRegion, min_alignment: usize) -> Self {
+        let ptr = NonNull::new(ptr::slice_from_raw_parts_mut(
+            region.vaddr as *mut _,
+            region.size,
+        ))
+        .unwrap();
+
+        let memory = unsafe { SharedMemoryRef::new(ptr) };
+
+        let max_alignment = 1
+            << region
+                .vaddr
+                .trailing_zeros()
+                .min(region.paddr.trailing_zeros());
+
+        assert!(min_alignment <= max_alignment);
+
+        let allocator = ByRange::new(WithAlignmentBound::new(
+            BasicAllocator::new(region.size),
+            max_alignment,
+        ));
+
+        Self {
+            memory,
+            vaddr: region.vaddr,
+            paddr: region.paddr,
+            allocator,
+        }
+    }
+
     fn offset_to_paddr(&self, offset: usize) -> PhysAddr {
-        self.dma_region_paddr
-            .checked_add(offset)
-            .unwrap()
-            .try_into()
-            .unwrap()
+        self.paddr.checked_add(offset).unwrap().try_into().unwrap()
     }
 
     fn paddr_to_offset(&self, paddr: PhysAddr) -> usize {
         usize::try_from(paddr)
             .unwrap()
-            .checked_sub(self.dma_region_paddr)
+            .checked_sub(self.paddr)
             .unwrap()
     }
 }
 
+#[sel4_cfg_attr(not(ARCH_ARM), allow(dead_code))]
+struct State {
+    /// Used for bounce buffers, and, if `coherent_region` is absent, for [`Hal::dma_alloc`].
+    dma_region: Region,
+    /// Used for [`Hal::dma_alloc`], if present.
+    coherent_region: Option<Region>,
+    bounce_buffer_alignment: usize,
+    cache_op: MaybeCacheOpFn,
+}
+
+impl State {
+    fn dma_alloc_region(&mut self) -> &mut Region {
+        self.coherent_region
+            .as_mut()
+            .unwrap_or(&mut self.dma_region)
+    }
+
+    /// Bounce buffers are aligned and padded to [`State::bounce_buffer_alignment`], so that cache
+    /// maintenance on one never touches a cache line shared with another.
+    fn bounce_buffer_layout(&self, len: usize) -> Layout {
+        let align = self.bounce_buffer_alignment;
+        Layout::from_size_align(len.next_multiple_of(align), align).unwrap()
+    }
+
+    #[sel4_cfg(ARCH_ARM)]
+    fn apply_cache_op(&self, op: sel4::CacheOp, offset_range: Range<usize>) {
+        if let Some(cache_op) = self.cache_op {
+            let vaddr = self.dma_region.vaddr;
+            (cache_op)(op, (vaddr + offset_range.start)..(vaddr + offset_range.end));
+        }
+    }
+}
+
 pub struct HalImpl;
 
 impl HalImpl {
     pub fn init(dma_region_size: usize, dma_region_vaddr: usize, dma_region_paddr: usize) {
-        let dma_region_ptr = NonNull::new(ptr::slice_from_raw_parts_mut(
-            dma_region_vaddr as *mut _,
-            dma_region_size,
-        ))
-        .unwrap();
-
-        let dma_region = unsafe { SharedMemoryRef::new(dma_region_ptr) };
-
-        let max_alignment = 1
-            << dma_region_vaddr
-                .trailing_zeros()
-                .min(dma_region_paddr.trailing_zeros());
+        let dma_region = DmaRegion {
+            size: dma_region_size,
+            vaddr: dma_region_vaddr,
+            paddr: dma_region_paddr,
+        };
+        Self::init_inner(dma_region, None, 1, Default::default())
+    }
 
-        let bounce_buffer_allocator = ByRange::new(WithAlignmentBound::new(
-            BasicAllocator::new(dma_region_size),
-            max_alignment,
-        ));
+    /// Like [`HalImpl::init`], but for platforms where DMA is not cache-coherent.
+    ///
+    /// Bounce buffers for [`Hal::share`] are allocated from `dma_region`, which may be mapped
+    /// cacheable. `cache_op` is called on them when ownership passes between the CPU and the
+    /// device. They are aligned and padded to `cache_line_size`, which must be a power of two no
+    /// smaller than the largest cache line size in the system, so that they never share a cache
+    /// line with another buffer.
+    ///
+    /// Memory for [`Hal::dma_alloc`], such as virtqueue rings, is accessed by the CPU and the
+    /// device concurrently, so no cache maintenance could make it coherent. It is instead allocated
+    /// from `coherent_region`, which must be mapped uncached.
+    #[sel4_cfg(ARCH_ARM)]
+    pub fn init_with_cache_op(
+        dma_region: DmaRegion,
+        coherent_region: DmaRegion,
+        cache_line_size: usize,
+        cache_op: CacheOpFn,
+    ) {
+        assert!(cache_line_size.is_power_of_two());
+        Self::init_inner(
+            dma_region,
+            Some(coherent_region),
+            cache_line_size,
+            Some(cache_op),
+        )
+    }
 
+    fn init_inner(
+        dma_region: DmaRegion,
+        coherent_region: Option<DmaRegion>,
+        bounce_buffer_alignment: usize,
+        cache_op: MaybeCacheOpFn,
+    ) {
         GLOBAL_STATE
             .set(OneShotMutex::new(State {
-                dma_region,
-                dma_region_paddr,
-                bounce_buffer_allocator,
+                dma_region: Region::new(dma_region, bounce_buffer_alignment),
+                coherent_region: coherent_region.map(|region| Region::new(region, PAGE_SIZE)),
+                bounce_buffer_alignment,
+                cache_op,
             }))
             .ok()
             .unwrap();
@@ -78,29 +185,26 @@ impl HalImpl {
 unsafe impl Hal for HalImpl {
     fn dma_alloc(pages: usize, _direction: BufferDirection) -> (PhysAddr, NonNull<u8>) {
         let mut state = GLOBAL_STATE.get().unwrap().lock();
+        let region = state.dma_alloc_region();
         assert!(pages > 0);
         let layout = Layout::from_size_align(pages * PAGE_SIZE, PAGE_SIZE).unwrap();
-        let bounce_buffer_range = state.bounce_buffer_allocator.allocate(layout).unwrap();
-        let bounce_buffer_ptr = state
-            .dma_region
-            .as_mut_ptr()
-            .index(bounce_buffer_range.clone());
-        bounce_buffer_ptr.fill(0);
-        let vaddr = bounce_buffer_ptr.as_raw_ptr().cast::<u8>();
-        let paddr = state.offset_to_paddr(bounce_buffer_range.start);
+        let range = region.allocator.allocate(layout).unwrap();
+        let ptr = region.memory.as_mut_ptr().index(range.clone());
+        ptr.fill(0);
+        let vaddr = ptr.as_raw_ptr().cast::<u8>();
+        let paddr = region.offset_to_paddr(range.start);
         (paddr, vaddr)
     }
 
     unsafe fn dma_dealloc(paddr: PhysAddr, _vaddr: NonNull<u8>, pages: usize) -> i32 {
         let mut state = GLOBAL_STATE.get().unwrap().lock();
-        let bounce_buffer_range = {
-            let start = state.paddr_to_offset(paddr);
+        let region = state.dma_alloc_region();
+        let range = {
+            let start = region.paddr_to_offset(paddr);
             let size = pages * PAGE_SIZE;
             start..(start + size)
         };
-        state
-            .bounce_buffer_allocator
-            .deallocate(bounce_buffer_range);
+        region.allocator.deallocate(range);
         0
     }
 
@@ -111,33 +215,43 @@ unsafe impl Hal for HalImpl {
     unsafe fn share(buffer: NonNull<[u8]>, _direction: BufferDirection) -> PhysAddr {
         let mut state = GLOBAL_STATE.get().unwrap().lock();
         assert!(!buffer.is_empty());
-        let layout = Layout::from_size_align(buffer.len(), 1).unwrap();
-        let bounce_buffer_range = state.bounce_buffer_allocator.allocate(layout).unwrap();
+        let layout = state.bounce_buffer_layout(buffer.len());
+        let bounce_buffer_range = state.dma_region.allocator.allocate(layout).unwrap();
         let buffer_slice = unsafe { buffer.as_ref() };
         state
             .dma_region
+            .memory
             .as_mut_ptr()
-            .index(bounce_buffer_range.clone())
+            .index(bounce_buffer_range.start..(bounce_buffer_range.start + buffer.len()))
             .copy_from_slice(buffer_slice);
-        state.offset_to_paddr(bounce_buffer_range.start)
+        sel4::sel4_cfg_if! {
+            if #[sel4_cfg(ARCH_ARM)] {
+                state.apply_cache_op(sel4::CacheOp::CleanData, bounce_buffer_range.clone());
+            }
+        }
+        state.dma_region.offset_to_paddr(bounce_buffer_range.start)
     }
 
     unsafe fn unshare(paddr: PhysAddr, mut buffer: NonNull<[u8]>, direction: BufferDirection) {
         let mut state = GLOBAL_STATE.get().unwrap().lock();
         let bounce_buffer_range = {
-            let start = state.paddr_to_offset(paddr);
-            start..(start + buffer.len())
+            let start = state.dma_region.paddr_to_offset(paddr);
+            start..(start + state.bounce_buffer_layout(buffer.len()).size())
         };
         if direction != BufferDirection::DriverToDevice {
+            sel4::sel4_cfg_if! {
+                if #[sel4_cfg(ARCH_ARM)] {
+                    state.apply_cache_op(sel4::CacheOp::InvalidateData, bounce_buffer_range.clone());
+                }
+            }
             let buffer_slice = unsafe { buffer.as_mut() };
             state
                 .dma_region
+                .memory
                 .as_mut_ptr()
-                .index(bounce_buffer_range.clone())
+                .index(bounce_buffer_range.start..(bounce_buffer_range.start + buffer.len()))
                 .copy_into_slice(buffer_slice);
         }
-        state
-            .bounce_buffer_allocator
-            .deallocate(bounce_buffer_range);
+        state.dma_region.allocator.deallocate(bounce_buffer_range);
     }
 }
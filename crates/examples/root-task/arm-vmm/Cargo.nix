@@ -0,0 +1,21 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "arm-vmm";
+  dependencies = {
+    inherit (localCrates)
+      sel4
+      sel4-arm-vmm
+      sel4-cspace-allocator
+      sel4-untyped-manager
+      sel4-vspace-manager
+    ;
+    sel4-root-task = localCrates.sel4-root-task // { features = [ "alloc" ]; };
+  };
+}
@@ -0,0 +1,197 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Boots a minimal guest on QEMU's `virt` machine.
+//!
+//! The guest is a tiny program packaged as a Linux arm64 `Image`, so that it is loaded and started
+//! as a Linux kernel would be. It checks that it has been passed a device tree, writes a message to
+//! a UART which is emulated here, and then powers itself off with PSCI.
+
+#![no_std]
+#![no_main]
+
+use core::arch::global_asm;
+use core::ptr;
+use core::slice;
+
+use sel4::Fault;
+use sel4_arm_vmm::{Exit, LinuxImage, VmBuilder, VmResources, qemu_arm_virt};
+use sel4_cspace_allocator::CSpaceAllocator;
+use sel4_root_task::{Never, root_task};
+use sel4_untyped_manager::UntypedManager;
+use sel4_vspace_manager::VSpaceManager;
+
+const GUEST_RAM_SIZE: usize = 4 * 1024 * 1024;
+
+// Where guest RAM is also mapped in the root task's VSpace, well clear of the root task's image.
+const GUEST_RAM_VMM_VADDR: usize = 0x10_0000_0000;
+
+// The PL011 UART's address on the `virt` machine. Each byte the guest writes to it is printed.
+const GUEST_UART: usize = 0x0900_0000;
+
+const GUEST_BADGE: sel4::Badge = 1;
+
+#[root_task(heap_size = 1024 * 256)]
+fn main(bootinfo: &sel4::BootInfoPtr) -> Result<Never, sel4_arm_vmm::Error> {
+    let mut cspace = CSpaceAllocator::from_bootinfo(bootinfo);
+    let mut untyped = UntypedManager::from_bootinfo(bootinfo);
+    let mut vspace = VSpaceManager::new(sel4::init_thread::slot::VSPACE.cap());
+
+    let fault_ep = untyped
+        .allocate_fixed_sized::<sel4::cap_type::Endpoint>(&mut cspace)?
+        .cap::<sel4::cap_type::Endpoint>(&cspace)
+        .unwrap();
+
+    let mut res = VmResources {
+        untyped: &mut untyped,
+        cspace: &mut cspace,
+        vspace: &mut vspace,
+        asid_pool: sel4::init_thread::slot::ASID_POOL.cap(),
+        authority: sel4::init_thread::slot::TCB.cap(),
+    };
+
+    let mut vm = VmBuilder::new(fault_ep, GUEST_BADGE).build(&mut res)?;
+
+    vm.add_ram(
+        &mut res,
+        qemu_arm_virt::RAM_START..(qemu_arm_virt::RAM_START + GUEST_RAM_SIZE),
+        GUEST_RAM_VMM_VADDR,
+    )?;
+
+    let image = LinuxImage::parse(guest_image())?;
+    let info = vm.load_linux(&image, &DEVICE_TREE)?;
+    vm.boot_linux(&info)?;
+
+    loop {
+        let (msg_info, badge) = fault_ep.recv(());
+        assert_eq!(badge, GUEST_BADGE);
+        let fault = sel4::with_ipc_buffer(|ipc_buffer| Fault::new(ipc_buffer, &msg_info));
+        match vm.handle_fault(&fault)? {
+            Exit::Resume => {}
+            Exit::Mmio(access) if access.addr() == GUEST_UART && access.is_write() => {
+                sel4::debug_put_char(access.write_value().unwrap() as u8);
+                vm.complete_mmio(&access, 0)?;
+            }
+            Exit::SystemOff => break,
+            Exit::SystemReset => panic!("the guest did not find its device tree"),
+            exit => panic!("unexpected exit: {exit:?}"),
+        }
+        sel4::with_ipc_buffer_mut(|ipc_buffer| {
+            sel4::reply(ipc_buffer, sel4::MessageInfo::new(0, 0, 0, 0))
+        });
+    }
+
+    vm.reclaim(&mut res)?;
+
+    sel4::debug_println!("TEST_PASS");
+
+    sel4::init_thread::suspend_self()
+}
+
+// An empty device tree, with nothing but a root node.
+const DEVICE_TREE: [u8; 72] = be_bytes([
+    // Header
+    0xd00d_feed, // magic
+    72,          // totalsize
+    56,          // off_dt_struct
+    72,          // off_dt_strings
+    40,          // off_mem_rsvmap
+    17,          // version
+    16,          // last_comp_version
+    0,           // boot_cpuid_phys
+    0,           // size_dt_strings
+    16,          // size_dt_struct
+    // Memory reservation block, with only its terminating entry
+    0,
+    0,
+    0,
+    0,
+    // Structure block
+    1, // FDT_BEGIN_NODE, followed by an empty name
+    0,
+    2, // FDT_END_NODE
+    9, // FDT_END
+]);
+
+const fn be_bytes(words: [u32; 18]) -> [u8; 72] {
+    let mut bytes = [0; 72];
+    let mut i = 0;
+    while i < bytes.len() {
+        bytes[i] = words[i / 4].to_be_bytes()[i % 4];
+        i += 1;
+    }
+    bytes
+}
+
+fn guest_image() -> &'static [u8] {
+    unsafe extern "C" {
+        static guest_image_start: u8;
+        static guest_image_end: u8;
+    }
+
+    unsafe {
+        let start = ptr::addr_of!(guest_image_start);
+        let end = ptr::addr_of!(guest_image_end);
+        slice::from_raw_parts(start, end.offset_from_unsigned(start))
+    }
+}
+
+// The guest, with the header described in Linux's `Documentation/arch/arm64/booting.rst`. It runs
+// with its MMU off, so it must be position-independent.
+global_asm! {
+    r#"
+        .section .rodata.guest_image, "a"
+        .balign 8
+
+        .global guest_image_start
+        guest_image_start:
+            b 1f                        // code0
+            .word 0                     // code1
+            .quad 0                     // text_offset
+            .quad guest_image_end - guest_image_start // image_size
+            .quad 0                     // flags
+            .quad 0, 0, 0               // res2, res3, res4
+            .ascii "ARM\x64"            // magic
+            .word 0                     // res5
+
+        1:
+            // Check that x0 points to the device tree's magic.
+            ldr w1, [x0]
+            movz w2, #0x0dd0
+            movk w2, #0xedfe, lsl #16
+            cmp w1, w2
+            b.ne 3f
+
+            // Write the message to the UART, one byte at a time.
+            adr x1, 5f
+            movz x2, #0x0900, lsl #16
+        2:
+            ldrb w3, [x1], #1
+            cbz w3, 4f
+            strb w3, [x2]
+            b 2b
+
+        3:
+            // PSCI_SYSTEM_RESET
+            movz w0, #0x0009
+            movk w0, #0x8400, lsl #16
+            hvc #0
+
+        4:
+            // PSCI_SYSTEM_OFF
+            movz w0, #0x0008
+            movk w0, #0x8400, lsl #16
+            hvc #0
+            b 4b
+
+        5:
+            .asciz "Hello from the guest!\n"
+
+            .balign 8
+        .global guest_image_end
+        guest_image_end:
+    "#
+}
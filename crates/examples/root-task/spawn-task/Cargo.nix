@@ -4,15 +4,18 @@
 # SPDX-License-Identifier: BSD-2-Clause
 #
 
-{ mk, versions, localCrates }:
+{ mk, localCrates }:
 
 mk {
   package.name = "spawn-task";
   dependencies = {
-    object = { version = versions.object; default-features = false; features = [ "read" ]; };
     inherit (localCrates)
       sel4
+      sel4-cspace-allocator
       sel4-root-task
+      sel4-spawn
+      sel4-untyped-manager
+      sel4-vspace-manager
     ;
   };
 }
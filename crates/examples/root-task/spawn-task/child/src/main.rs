@@ -12,7 +12,8 @@ mod runtime;
 fn main() -> ! {
     sel4::debug_println!("In child task");
 
-    sel4::cap::Notification::from_bits(1).signal();
+    // See sel4_spawn::child_slots.
+    sel4::cap::Notification::from_bits(6).signal();
 
     sel4::cap::Tcb::from_bits(2).tcb_suspend().unwrap();
 
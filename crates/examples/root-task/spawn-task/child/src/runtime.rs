@@ -4,21 +4,14 @@
 // SPDX-License-Identifier: BSD-2-Clause
 //
 
-use core::ptr;
-
 use one_shot_mutex::sync::RawOneShotMutex;
 
-use sel4::CapTypeForFrameObjectOfFixedSize;
 use sel4_dlmalloc::{StaticDlmalloc, StaticHeap};
 use sel4_panicking::catch_unwind;
 use sel4_panicking_env::abort;
 
 use crate::main;
 
-const STACK_SIZE: usize = 1024 * 64;
-
-sel4_runtime_common::declare_stack!(STACK_SIZE);
-
 const HEAP_SIZE: usize = 1024 * 64;
 
 static STATIC_HEAP: StaticHeap<HEAP_SIZE> = StaticHeap::new();
@@ -29,13 +22,14 @@ static GLOBAL_ALLOCATOR: StaticDlmalloc<RawOneShotMutex> =
 
 sel4_panicking_env::register_debug_put_char!(sel4::debug_put_char);
 
-sel4_runtime_common::declare_entrypoint_with_stack_init! {
-    entrypoint()
+// The parent provides the stack, and passes the address of the IPC buffer after `argc` and `argv`.
+sel4_runtime_common::declare_entrypoint! {
+    entrypoint(argc: usize, argv: *const *const u8, ipc_buffer: *mut sel4::IpcBuffer)
 }
 
-fn entrypoint() -> ! {
+fn entrypoint(_argc: usize, _argv: *const *const u8, ipc_buffer: *mut sel4::IpcBuffer) -> ! {
     unsafe {
-        sel4::set_ipc_buffer(get_ipc_buffer().as_mut().unwrap());
+        sel4::set_ipc_buffer(ipc_buffer.as_mut().unwrap());
     }
 
     match catch_unwind(main) {
@@ -44,12 +38,3 @@ fn entrypoint() -> ! {
         Err(_) => abort!("main() panicked"),
     }
 }
-
-fn get_ipc_buffer() -> *mut sel4::IpcBuffer {
-    unsafe extern "C" {
-        static _end: usize;
-    }
-    (ptr::addr_of!(_end) as usize)
-        .next_multiple_of(sel4::cap_type::Granule::FRAME_OBJECT_TYPE.bytes())
-        as *mut sel4::IpcBuffer
-}
@@ -6,21 +6,14 @@
 
 #![no_std]
 #![no_main]
-#![allow(clippy::useless_conversion)]
-
-extern crate alloc;
 
 use core::ptr;
 
-use object::{File, Object};
-
+use sel4_cspace_allocator::CSpaceAllocator;
 use sel4_root_task::{Never, root_task};
-
-mod child_vspace;
-mod object_allocator;
-
-use child_vspace::create_child_vspace;
-use object_allocator::ObjectAllocator;
+use sel4_spawn::{ElfFile, ProcessBuilder, SpawnResources};
+use sel4_untyped_manager::UntypedManager;
+use sel4_vspace_manager::VSpaceManager;
 
 const CHILD_ELF_CONTENTS: &[u8] = include_bytes!(env!("CHILD_ELF"));
 
@@ -28,67 +21,44 @@ const CHILD_ELF_CONTENTS: &[u8] = include_bytes!(env!("CHILD_ELF"));
 fn main(bootinfo: &sel4::BootInfoPtr) -> sel4::Result<Never> {
     sel4::debug_println!("In root task");
 
-    let mut object_allocator = ObjectAllocator::new(bootinfo);
-
-    let free_page_addr = init_free_page_addr(bootinfo);
-
-    let child_image = File::parse(CHILD_ELF_CONTENTS).unwrap();
-
-    let (child_vspace, ipc_buffer_addr, ipc_buffer_cap) = create_child_vspace(
-        &mut object_allocator,
-        &child_image,
-        sel4::init_thread::slot::VSPACE.cap(),
-        free_page_addr,
-        sel4::init_thread::slot::ASID_POOL.cap(),
-    );
-
-    let inter_task_nfn = object_allocator.allocate_fixed_sized::<sel4::cap_type::Notification>();
+    let mut cspace = CSpaceAllocator::from_bootinfo(bootinfo);
+    let mut untyped = UntypedManager::from_bootinfo(bootinfo);
+    let mut vspace = VSpaceManager::new(sel4::init_thread::slot::VSPACE.cap());
+
+    let mut res = SpawnResources {
+        untyped: &mut untyped,
+        cspace: &mut cspace,
+        vspace: &mut vspace,
+        scratch_vaddr: init_free_page_addr(bootinfo),
+        asid_pool: sel4::init_thread::slot::ASID_POOL.cap(),
+        authority: sel4::init_thread::slot::TCB.cap(),
+    };
+
+    let inter_task_nfn = res
+        .untyped
+        .allocate_fixed_sized::<sel4::cap_type::Notification>(res.cspace)
+        .unwrap()
+        .cap::<sel4::cap_type::Notification>(res.cspace)
+        .unwrap();
 
-    let child_cnode_size_bits = 2;
-    let child_cnode =
-        object_allocator.allocate_variable_sized::<sel4::cap_type::CNode>(child_cnode_size_bits);
+    let child_image = ElfFile::parse(CHILD_ELF_CONTENTS).unwrap();
 
-    child_cnode
-        .absolute_cptr_from_bits_with_depth(1, child_cnode_size_bits)
-        .mint(
-            &sel4::init_thread::slot::CNODE
+    let child = ProcessBuilder::new(&child_image)
+        .arg(b"spawn-task-child")
+        .grant(
+            sel4::init_thread::slot::CNODE
                 .cap()
                 .absolute_cptr(inter_task_nfn),
             sel4::CapRights::write_only(),
             0,
         )
+        .spawn(&mut res)
         .unwrap();
 
-    let child_tcb = object_allocator.allocate_fixed_sized::<sel4::cap_type::Tcb>();
-
-    child_tcb
-        .tcb_configure(
-            sel4::init_thread::slot::NULL.cptr(),
-            child_cnode,
-            sel4::CNodeCapData::new(0, sel4::WORD_SIZE - child_cnode_size_bits),
-            child_vspace,
-            ipc_buffer_addr as sel4::Word,
-            ipc_buffer_cap,
-        )
-        .unwrap();
-
-    child_cnode
-        .absolute_cptr_from_bits_with_depth(2, child_cnode_size_bits)
-        .mint(
-            &sel4::init_thread::slot::CNODE
-                .cap()
-                .absolute_cptr(child_tcb),
-            sel4::CapRights::all(),
-            0,
-        )
-        .unwrap();
-
-    let mut ctx = sel4::UserContext::default();
-    *ctx.pc_mut() = child_image.entry().try_into().unwrap();
-    child_tcb.tcb_write_all_registers(true, &mut ctx).unwrap();
-
     inter_task_nfn.wait();
 
+    child.kill(res.untyped, res.cspace).unwrap();
+
     sel4::debug_println!("TEST_PASS");
 
     sel4::init_thread::suspend_self()
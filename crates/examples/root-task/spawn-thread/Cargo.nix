@@ -9,13 +9,10 @@
 mk {
   package.name = "spawn-thread";
   dependencies = {
-    inherit (versions) cfg-if;
+    inherit (versions) one-shot-mutex;
     inherit (localCrates)
       sel4
-      sel4-root-task
-      sel4-elf-header
-      sel4-stack
     ;
-    sel4-initialize-tls = localCrates.sel4-initialize-tls // { features = [ "on-heap" ]; };
+    sel4-root-task = localCrates.sel4-root-task // { features = [ "alloc" ]; };
   };
 }
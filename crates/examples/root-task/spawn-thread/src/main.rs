@@ -8,28 +8,19 @@
 #![no_main]
 #![feature(never_type)]
 
-extern crate alloc;
-
-use alloc::boxed::Box;
 use core::cell::UnsafeCell;
-use core::mem;
 use core::ops::Range;
-use core::panic::UnwindSafe;
 use core::ptr;
 
-use cfg_if::cfg_if;
-
-use sel4_elf_header::{ElfHeader, PT_TLS};
-use sel4_initialize_tls::{TlsImage, UncheckedTlsImage};
-use sel4_root_task::{
-    Never, abort, panicking::catch_unwind, root_task, set_global_allocator_mutex_notification,
-};
-use sel4_stack::Stack;
+use one_shot_mutex::sync::OneShotMutex;
 
-static SECONDARY_THREAD_STACK: Stack<4096> = Stack::new();
+use sel4_root_task::thread::{self, ThreadResourceAllocator, ThreadResources};
+use sel4_root_task::{Never, root_task, set_global_allocator_mutex_notification};
 
 static SECONDARY_THREAD_IPC_BUFFER_FRAME: IpcBufferFrame = IpcBufferFrame::new();
 
+static THREAD_RESOURCES: SingleThreadResources = SingleThreadResources::new();
+
 #[root_task(heap_size = 1024 * 64)]
 fn main(bootinfo: &sel4::BootInfoPtr) -> sel4::Result<Never> {
     sel4::debug_println!("In primary thread");
@@ -40,31 +31,23 @@ fn main(bootinfo: &sel4::BootInfoPtr) -> sel4::Result<Never> {
         object_allocator.allocate_fixed_sized::<sel4::cap_type::Notification>(),
     );
 
-    let inter_thread_nfn = object_allocator.allocate_fixed_sized::<sel4::cap_type::Notification>();
-
-    let secondary_thread_tcb = object_allocator.allocate_fixed_sized::<sel4::cap_type::Tcb>();
+    THREAD_RESOURCES.put(ThreadResources {
+        tcb: object_allocator.allocate_fixed_sized::<sel4::cap_type::Tcb>(),
+        notification: object_allocator.allocate_fixed_sized::<sel4::cap_type::Notification>(),
+        ipc_buffer_frame: SECONDARY_THREAD_IPC_BUFFER_FRAME.cap(bootinfo),
+        ipc_buffer: SECONDARY_THREAD_IPC_BUFFER_FRAME.ptr(),
+    });
 
-    secondary_thread_tcb.tcb_configure(
-        sel4::init_thread::slot::NULL.cptr(),
-        sel4::init_thread::slot::CNODE.cap(),
-        sel4::CNodeCapData::new(0, 0),
-        sel4::init_thread::slot::VSPACE.cap(),
-        SECONDARY_THREAD_IPC_BUFFER_FRAME.ptr() as sel4::Word,
-        SECONDARY_THREAD_IPC_BUFFER_FRAME.cap(bootinfo),
-    )?;
+    thread::set_thread_resource_allocator(&THREAD_RESOURCES);
 
-    let secondary_thread_fn = SecondaryThreadFn::new(move || {
-        unsafe { sel4::set_ipc_buffer(SECONDARY_THREAD_IPC_BUFFER_FRAME.ptr().as_mut().unwrap()) }
+    let handle = thread::Builder::new().stack_size(4096 * 4).spawn(|| {
         sel4::debug_println!("In secondary thread");
-        inter_thread_nfn.signal();
-        secondary_thread_tcb.tcb_suspend().unwrap();
-        unreachable!()
-    });
+        thread::current().id()
+    })?;
 
-    secondary_thread_tcb
-        .tcb_write_all_registers(true, &mut create_user_context(secondary_thread_fn))?;
+    let secondary_thread_id = handle.join().unwrap();
 
-    inter_thread_nfn.wait();
+    assert_ne!(secondary_thread_id, thread::current().id());
 
     sel4::debug_println!("TEST_PASS");
 
@@ -73,6 +56,30 @@ fn main(bootinfo: &sel4::BootInfoPtr) -> sel4::Result<Never> {
 
 // // //
 
+struct SingleThreadResources(OneShotMutex<Option<ThreadResources>>);
+
+impl SingleThreadResources {
+    const fn new() -> Self {
+        Self(OneShotMutex::new(None))
+    }
+
+    fn put(&self, resources: ThreadResources) {
+        *self.0.lock() = Some(resources);
+    }
+}
+
+impl ThreadResourceAllocator for SingleThreadResources {
+    fn allocate(&self) -> Option<ThreadResources> {
+        self.0.lock().take()
+    }
+
+    fn free(&self, resources: ThreadResources) {
+        self.put(resources);
+    }
+}
+
+// // //
+
 struct ObjectAllocator {
     empty_slots: Range<usize>,
     ut: sel4::cap::Untyped,
@@ -100,25 +107,6 @@ impl ObjectAllocator {
             .unwrap();
         sel4::init_thread::Slot::from_index(slot_index).cap()
     }
-
-    #[allow(dead_code)]
-    fn allocate_variable_sized<T: sel4::CapTypeForObjectOfVariableSize>(
-        &mut self,
-        size_bits: usize,
-    ) -> sel4::Cap<T> {
-        let slot_index = self.empty_slots.next().unwrap();
-        self.ut
-            .untyped_retype(
-                &T::object_blueprint(size_bits),
-                &sel4::init_thread::slot::CNODE
-                    .cap()
-                    .absolute_cptr_for_self(),
-                slot_index,
-                1,
-            )
-            .unwrap();
-        sel4::init_thread::Slot::from_index(slot_index).cap()
-    }
 }
 
 fn find_largest_kernel_untyped(bootinfo: &sel4::BootInfo) -> sel4::cap::Untyped {
@@ -135,105 +123,6 @@ fn find_largest_kernel_untyped(bootinfo: &sel4::BootInfo) -> sel4::cap::Untyped
 
 // // //
 
-fn create_user_context(f: SecondaryThreadFn) -> sel4::UserContext {
-    let mut ctx = sel4::UserContext::default();
-
-    *ctx.sp_mut() = (SECONDARY_THREAD_STACK.bottom().ptr() as usize)
-        .try_into()
-        .unwrap();
-    *ctx.pc_mut() = (secondary_thread_entrypoint as *const () as usize)
-        .try_into()
-        .unwrap();
-    *ctx.c_param_mut(0) = f.into_arg();
-
-    let tls_reservation = get_tls_image().initialize_on_heap();
-    *user_context_thread_pointer_mut(&mut ctx) = tls_reservation.thread_pointer() as sel4::Word;
-    mem::forget(tls_reservation);
-
-    cfg_if! {
-        if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
-            ctx.inner_mut().gp = riscv_get_gp();
-        }
-    }
-
-    ctx
-}
-
-fn user_context_thread_pointer_mut(ctx: &mut sel4::UserContext) -> &mut sel4::Word {
-    cfg_if! {
-        if #[cfg(target_arch = "aarch64")] {
-            &mut ctx.inner_mut().tpidr_el0
-        } else if #[cfg(target_arch = "arm")] {
-            &mut ctx.inner_mut().tpidrurw
-        } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
-            &mut ctx.inner_mut().tp
-        } else if #[cfg(target_arch = "x86_64")] {
-            &mut ctx.inner_mut().fs_base
-        } else {
-            compile_error!("unsupported architecture");
-        }
-    }
-}
-
-#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
-fn riscv_get_gp() -> sel4::Word {
-    let val: sel4::Word;
-    unsafe {
-        core::arch::asm!("mv {}, gp", out(reg) val);
-    }
-    val
-}
-
-// // //
-
-unsafe extern "C" fn secondary_thread_entrypoint(arg: sel4::Word) -> ! {
-    let f = unsafe { SecondaryThreadFn::from_arg(arg) };
-    let _ = catch_unwind(|| f.run());
-    abort!("secondary thread panicked")
-}
-
-struct SecondaryThreadFn(Box<dyn FnOnce() -> ! + UnwindSafe + Send + 'static>);
-
-impl SecondaryThreadFn {
-    fn new(f: impl FnOnce() -> ! + UnwindSafe + Send + 'static) -> Self {
-        Self(Box::new(f))
-    }
-
-    fn run(self) -> ! {
-        (self.0)()
-    }
-
-    fn into_arg(self) -> sel4::Word {
-        Box::into_raw(Box::new(self)) as sel4::Word
-    }
-
-    unsafe fn from_arg(arg: sel4::Word) -> Self {
-        unsafe { *Box::from_raw(arg as *mut Self) }
-    }
-}
-
-// // //
-
-fn get_tls_image() -> TlsImage {
-    unsafe extern "C" {
-        static __ehdr_start: ElfHeader;
-    }
-    let phdrs = unsafe {
-        assert!(__ehdr_start.is_magic_valid());
-        __ehdr_start.locate_phdrs()
-    };
-    let phdr = phdrs.iter().find(|phdr| phdr.p_type == PT_TLS).unwrap();
-    let unchecked = UncheckedTlsImage {
-        vaddr: phdr.p_vaddr,
-        filesz: phdr.p_filesz,
-        memsz: phdr.p_memsz,
-        align: phdr.p_align,
-    };
-    unchecked.check().unwrap()
-}
-
-// // //
-
 #[repr(C, align(4096))]
 struct IpcBufferFrame(UnsafeCell<[u8; GRANULE_SIZE]>);
 
@@ -0,0 +1,39 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, versions, serdeWith, postcardWith }:
+
+mk {
+  package.name = "sel4-benchmark-log";
+  dependencies = {
+    inherit (versions) cfg-if;
+    serde = serdeWith [ "derive" ] // { optional = true; };
+    postcard = postcardWith [] // { optional = true; };
+    sel4 = localCrates.sel4 // { default-features = false; optional = true; };
+  };
+  features = {
+    alloc = [
+      "serde?/alloc"
+    ];
+    serde = [
+      "dep:serde"
+    ];
+    postcard = [
+      "alloc"
+      "serde"
+      "dep:postcard"
+    ];
+    sel4 = [
+      "alloc"
+      "dep:sel4"
+    ];
+    full = [
+      "alloc"
+      "serde"
+      "postcard"
+    ];
+  };
+}
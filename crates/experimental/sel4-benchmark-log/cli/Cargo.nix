@@ -0,0 +1,15 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, versions }:
+
+mk {
+  package.name = "sel4-benchmark-log-cli";
+  dependencies = {
+    inherit (versions) clap hex;
+    sel4-benchmark-log = localCrates.sel4-benchmark-log // { features = [ "full" ]; };
+  };
+}
@@ -0,0 +1,126 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use clap::{Command, arg};
+
+use sel4_benchmark_log::{Names, Report, Stats, histogram_bucket_range};
+
+fn main() {
+    let matches = Command::new("")
+        .arg(arg!(--histograms "Show a histogram of durations for each row"))
+        .arg(arg!(<raw_report>))
+        .get_matches();
+    let report_hex = matches.get_one::<String>("raw_report").unwrap();
+    let report = Report::recv(&hex::decode(report_hex).unwrap()).unwrap();
+    let show_histograms = matches.get_flag("histograms");
+
+    if let Some(summary) = &report.kernel_entries {
+        print_table(
+            "kernel entries by path",
+            summary
+                .by_path
+                .iter()
+                .map(|(path, stats)| (format!("{path:?}"), stats)),
+            show_histograms,
+        );
+        print_table(
+            "syscalls",
+            summary.by_syscall.iter().map(|(key, stats)| {
+                let mut label = syscall_name(&report.names, key.syscall_id);
+                if key.is_fastpath {
+                    label.push_str(" (fastpath)");
+                }
+                (label, stats)
+            }),
+            show_histograms,
+        );
+        print_table(
+            "syscalls by cap type and message label",
+            summary.by_invocation.iter().map(|(key, stats)| {
+                let mut label = format!(
+                    "{} cap_type={} label={}",
+                    syscall_name(&report.names, key.syscall_id),
+                    key.cap_type,
+                    key.invocation_label,
+                );
+                if let Some(name) = report.names.invocation_labels.get(&key.invocation_label) {
+                    label.push_str(&format!(" ({name} if a kernel object)"));
+                }
+                (label, stats)
+            }),
+            show_histograms,
+        );
+    }
+
+    if let Some(summary) = &report.tracepoints {
+        print_table(
+            "tracepoints",
+            summary
+                .by_id
+                .iter()
+                .map(|(id, stats)| (format!("{id}"), stats)),
+            show_histograms,
+        );
+    }
+}
+
+fn syscall_name(names: &Names, id: i32) -> String {
+    match names.syscalls.get(&id) {
+        Some(name) => name.clone(),
+        None => format!("syscall {id}"),
+    }
+}
+
+fn print_table<'a>(
+    title: &str,
+    rows: impl Iterator<Item = (String, &'a Stats)>,
+    show_histograms: bool,
+) {
+    let rows = rows.collect::<Vec<_>>();
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    println!("{title}:");
+    println!(
+        "  {:width$}  {:>10}  {:>14}  {:>10}  {:>10}  {:>10}",
+        "", "count", "total", "mean", "min", "max",
+    );
+    for (label, stats) in rows {
+        println!(
+            "  {label:width$}  {:>10}  {:>14}  {:>10}  {:>10}  {:>10}",
+            stats.count,
+            stats.total,
+            display_option(stats.mean()),
+            stats.min,
+            stats.max,
+        );
+        if show_histograms {
+            print_histogram(&stats.histogram);
+        }
+    }
+    println!();
+}
+
+fn print_histogram(histogram: &BTreeMap<u32, u64>) {
+    const BAR_WIDTH: u64 = 40;
+    let max = histogram.values().copied().max().unwrap_or(0);
+    for (bucket, count) in histogram {
+        let range = histogram_bucket_range(*bucket);
+        let bar = "#".repeat(usize::try_from(count * BAR_WIDTH / max).unwrap().max(1));
+        println!(
+            "      {:>20}  {count:>10}  {bar}",
+            format!("{}..={}", range.start(), range.end()),
+        );
+    }
+}
+
+fn display_option(value: Option<impl Display>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "-".to_owned(),
+    }
+}
@@ -0,0 +1,334 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Readers for the kernel's benchmark log buffer, along with aggregation and export of their
+//! contents.
+//!
+//! A root task can ask the kernel to log to a buffer with `sel4::benchmark_set_log_buffer`, clear
+//! it with `sel4::benchmark_reset_log`, and stop logging with `sel4::benchmark_finalize_log`,
+//! which returns the number of entries written. Depending on the kernel's configuration, the
+//! buffer then contains either kernel entry records (`CONFIG_BENCHMARK_TRACK_KERNEL_ENTRIES`),
+//! which can be read with [`KernelEntryLog`], or tracepoint records
+//! (`CONFIG_BENCHMARK_TRACEPOINTS`), which can be read with [`TracepointLog`].
+//!
+//! This crate does not depend on the kernel's configuration, so that it can also be used on the
+//! host. With the `alloc` feature, records can be aggregated into a [`Report`], which, with the
+//! `postcard` feature, can be sent to the host for presentation by `sel4-benchmark-report`.
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use core::iter::FusedIterator;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "alloc")] {
+        mod summary;
+        pub use summary::{
+            histogram_bucket, histogram_bucket_range, InvocationKey, KernelEntrySummary, Names,
+            Report, Stats, SyscallKey, TracepointSummary,
+        };
+    }
+}
+
+#[cfg(feature = "postcard")]
+mod with_postcard;
+
+#[cfg(feature = "sel4")]
+mod with_sel4;
+
+/// The word size of the kernel which wrote a log.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum WordSize {
+    Bits32,
+    Bits64,
+}
+
+impl WordSize {
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::Bits32 => 4,
+            Self::Bits64 => 8,
+        }
+    }
+
+    fn read(self, bytes: &[u8]) -> u64 {
+        match self {
+            Self::Bits32 => read_u32(bytes).into(),
+            Self::Bits64 => read_u64(bytes),
+        }
+    }
+}
+
+/// The kernel's `entry_type_t`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum EntryPath {
+    Interrupt,
+    UnknownSyscall,
+    UserLevelFault,
+    DebugFault,
+    VmFault,
+    Syscall,
+    UnimplementedDevice,
+    /// `Entry_VCPUFault` on Arm, or `Entry_VMExit` on x86.
+    Virtualization,
+}
+
+impl EntryPath {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0 => Self::Interrupt,
+            1 => Self::UnknownSyscall,
+            2 => Self::UserLevelFault,
+            3 => Self::DebugFault,
+            4 => Self::VmFault,
+            5 => Self::Syscall,
+            6 => Self::UnimplementedDevice,
+            _ => Self::Virtualization,
+        }
+    }
+}
+
+/// The kernel's `kernel_entry_t`, which describes why the kernel was entered.
+///
+/// The fields of `kernel_entry_t` other than `path` share storage. The kernel only fills in
+/// [`syscall_id`](Self::syscall_id), [`cap_type`](Self::cap_type),
+/// [`is_fastpath`](Self::is_fastpath), and [`invocation_tag`](Self::invocation_tag) for
+/// [`EntryPath::Syscall`], and only fills in the latter three for syscalls which invoke a
+/// capability. For other paths, [`core`](Self::core) and [`word`](Self::word) hold, for example,
+/// the IRQ number or fault information.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct KernelEntry {
+    pub path: EntryPath,
+    raw: u32,
+}
+
+impl KernelEntry {
+    /// The size of `kernel_entry_t`, which is packed.
+    pub const fn size(word_size: WordSize) -> usize {
+        1 + word_size.bytes()
+    }
+
+    fn parse(bytes: &[u8]) -> Self {
+        Self {
+            path: EntryPath::from_bits(bytes[0]),
+            raw: read_u32(&bytes[1..]),
+        }
+    }
+
+    fn bits(&self, start: u32, width: u32) -> u32 {
+        (self.raw >> start) & ((1 << width) - 1)
+    }
+
+    pub fn core(&self) -> u32 {
+        self.bits(0, 3)
+    }
+
+    pub fn word(&self) -> u32 {
+        self.bits(3, 26)
+    }
+
+    /// The syscall's ID, as found in `sel4::sys::syscall_id`.
+    ///
+    /// The kernel only records the low 4 bits of the negated ID.
+    pub fn syscall_id(&self) -> i32 {
+        -(self.bits(0, 4) as i32)
+    }
+
+    /// The kernel's internal tag for the type of the invoked capability.
+    pub fn cap_type(&self) -> u32 {
+        self.bits(4, 5)
+    }
+
+    pub fn is_fastpath(&self) -> bool {
+        self.bits(9, 1) != 0
+    }
+
+    /// The low 19 bits of the label of the message with which the capability was invoked.
+    pub fn invocation_tag(&self) -> u32 {
+        self.bits(10, 19)
+    }
+}
+
+/// The kernel's `benchmark_track_kernel_entry_t`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct KernelEntryRecord {
+    /// The timestamp, in cycles, at which the kernel was entered.
+    pub start_time: u64,
+    /// The number of cycles spent in the kernel.
+    pub duration: u32,
+    pub entry: KernelEntry,
+}
+
+impl KernelEntryRecord {
+    /// The size of `benchmark_track_kernel_entry_t`, including trailing padding.
+    ///
+    /// This is the same for both word sizes on the architectures which these crates support,
+    /// because `start_time` is 8-byte aligned on each of them. On ia32, where it is only 4-byte
+    /// aligned, the record is 20 bytes, so logs from ia32 kernels cannot be read with this crate.
+    pub const SIZE: usize = 24;
+
+    fn parse(bytes: &[u8]) -> Self {
+        Self {
+            start_time: read_u64(&bytes[0..]),
+            duration: read_u32(&bytes[8..]),
+            entry: KernelEntry::parse(&bytes[12..]),
+        }
+    }
+}
+
+/// The kernel's `benchmark_tracepoint_log_entry_t`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct TracepointRecord {
+    /// The ID passed to `TRACE_POINT_START` and `TRACE_POINT_STOP` in the kernel.
+    pub id: u64,
+    /// The number of cycles between the start and stop of the tracepoint.
+    pub duration: u64,
+}
+
+impl TracepointRecord {
+    pub const fn size(word_size: WordSize) -> usize {
+        2 * word_size.bytes()
+    }
+
+    fn parse(bytes: &[u8], word_size: WordSize) -> Self {
+        Self {
+            id: word_size.read(bytes),
+            duration: word_size.read(&bytes[word_size.bytes()..]),
+        }
+    }
+}
+
+/// A log buffer containing kernel entry records.
+#[derive(Debug, Copy, Clone)]
+pub struct KernelEntryLog<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> KernelEntryLog<'a> {
+    /// Creates a reader for the first `num_entries` records in `bytes`, where `num_entries` is
+    /// the value returned by `sel4::benchmark_finalize_log`.
+    ///
+    /// If the buffer is too small to hold `num_entries` records, then only those which fit are
+    /// read.
+    pub fn new(bytes: &'a [u8], num_entries: usize) -> Self {
+        Self {
+            bytes: truncate(bytes, KernelEntryRecord::SIZE, num_entries),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len() / KernelEntryRecord::SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<KernelEntryRecord> {
+        Some(KernelEntryRecord::parse(
+            self.bytes.chunks_exact(KernelEntryRecord::SIZE).nth(i)?,
+        ))
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = KernelEntryRecord> + FusedIterator + 'a {
+        self.bytes
+            .chunks_exact(KernelEntryRecord::SIZE)
+            .map(KernelEntryRecord::parse)
+    }
+}
+
+/// A log buffer containing tracepoint records.
+#[derive(Debug, Copy, Clone)]
+pub struct TracepointLog<'a> {
+    bytes: &'a [u8],
+    word_size: WordSize,
+}
+
+impl<'a> TracepointLog<'a> {
+    /// Creates a reader for the first `num_entries` records in `bytes`, where `num_entries` is
+    /// the value returned by `sel4::benchmark_finalize_log`.
+    ///
+    /// If the buffer is too small to hold `num_entries` records, then only those which fit are
+    /// read.
+    pub fn new(bytes: &'a [u8], num_entries: usize, word_size: WordSize) -> Self {
+        Self {
+            bytes: truncate(bytes, TracepointRecord::size(word_size), num_entries),
+            word_size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len() / TracepointRecord::size(self.word_size)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<TracepointRecord> {
+        let size = TracepointRecord::size(self.word_size);
+        Some(TracepointRecord::parse(
+            self.bytes.chunks_exact(size).nth(i)?,
+            self.word_size,
+        ))
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = TracepointRecord> + FusedIterator + 'a {
+        let word_size = self.word_size;
+        self.bytes
+            .chunks_exact(TracepointRecord::size(word_size))
+            .map(move |chunk| TracepointRecord::parse(chunk, word_size))
+    }
+}
+
+fn truncate(bytes: &[u8], record_size: usize, num_entries: usize) -> &[u8] {
+    let num_entries = num_entries.min(bytes.len() / record_size);
+    &bytes[..num_entries * record_size]
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kernel_entry_layout() {
+        let mut buf = [0u8; 2 * KernelEntryRecord::SIZE];
+        buf[0..8].copy_from_slice(&1234u64.to_le_bytes());
+        buf[8..12].copy_from_slice(&567u32.to_le_bytes());
+        buf[12] = 5;
+        let info: u32 = 3 | (12 << 4) | (1 << 9) | (0x4_0001 << 10);
+        buf[13..17].copy_from_slice(&info.to_le_bytes());
+
+        let log = KernelEntryLog::new(&buf, 3);
+        assert_eq!(log.len(), 2);
+        let record = log.get(0).unwrap();
+        assert_eq!(record.start_time, 1234);
+        assert_eq!(record.duration, 567);
+        assert_eq!(record.entry.path, EntryPath::Syscall);
+        assert_eq!(record.entry.syscall_id(), -3);
+        assert_eq!(record.entry.cap_type(), 12);
+        assert!(record.entry.is_fastpath());
+        assert_eq!(record.entry.invocation_tag(), 0x4_0001);
+    }
+}
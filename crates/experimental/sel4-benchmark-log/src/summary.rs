@@ -0,0 +1,359 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::ops::RangeInclusive;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{EntryPath, KernelEntryLog, KernelEntryRecord, TracepointLog, TracepointRecord};
+
+/// Returns the index of the [`Stats::histogram`] bucket for `duration`.
+///
+/// Bucket `0` holds durations of `0`, and bucket `n > 0` holds durations in
+/// `2^(n - 1)..=2^n - 1`.
+pub fn histogram_bucket(duration: u64) -> u32 {
+    u64::BITS - duration.leading_zeros()
+}
+
+/// Returns the range of durations held by the [`Stats::histogram`] bucket with index `bucket`.
+pub fn histogram_bucket_range(bucket: u32) -> RangeInclusive<u64> {
+    match bucket {
+        0 => 0..=0,
+        _ => {
+            let start = 1 << (bucket - 1);
+            start..=start | (start - 1)
+        }
+    }
+}
+
+/// Statistics about a set of durations, in cycles.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Stats {
+    pub count: u64,
+    pub total: u64,
+    pub min: u64,
+    pub max: u64,
+    /// Counts of durations by [`histogram_bucket`]. Empty buckets are omitted.
+    pub histogram: BTreeMap<u32, u64>,
+}
+
+impl Stats {
+    pub fn add(&mut self, duration: u64) {
+        if self.count == 0 {
+            self.min = duration;
+            self.max = duration;
+        } else {
+            self.min = self.min.min(duration);
+            self.max = self.max.max(duration);
+        }
+        self.count += 1;
+        self.total = self.total.saturating_add(duration);
+        *self
+            .histogram
+            .entry(histogram_bucket(duration))
+            .or_default() += 1;
+    }
+
+    pub fn mean(&self) -> Option<u64> {
+        self.total.checked_div(self.count)
+    }
+}
+
+/// Key for [`KernelEntrySummary::by_syscall`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SyscallKey {
+    pub syscall_id: i32,
+    pub is_fastpath: bool,
+}
+
+/// Key for [`KernelEntrySummary::by_invocation`].
+///
+/// For invocations of kernel objects, `invocation_label` is a value found in
+/// `sel4::sys::invocation_label`. For IPC, it is the label chosen by the sender.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InvocationKey {
+    pub syscall_id: i32,
+    pub cap_type: u32,
+    pub invocation_label: u32,
+}
+
+/// Aggregated kernel entry records.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct KernelEntrySummary {
+    pub by_path: BTreeMap<EntryPath, Stats>,
+    pub by_syscall: BTreeMap<SyscallKey, Stats>,
+    /// Syscall entries, by invoked capability type and message label.
+    ///
+    /// The kernel does not clear these fields for syscalls which do not invoke a capability (e.g.
+    /// `seL4_Recv`), so for such syscalls this grouping is not meaningful.
+    pub by_invocation: BTreeMap<InvocationKey, Stats>,
+}
+
+impl KernelEntrySummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_log(log: &KernelEntryLog) -> Self {
+        let mut this = Self::new();
+        this.extend(log.iter());
+        this
+    }
+
+    pub fn add(&mut self, record: &KernelEntryRecord) {
+        let duration = record.duration.into();
+        let entry = &record.entry;
+        self.by_path.entry(entry.path).or_default().add(duration);
+        if entry.path == EntryPath::Syscall {
+            let syscall_id = entry.syscall_id();
+            self.by_syscall
+                .entry(SyscallKey {
+                    syscall_id,
+                    is_fastpath: entry.is_fastpath(),
+                })
+                .or_default()
+                .add(duration);
+            self.by_invocation
+                .entry(InvocationKey {
+                    syscall_id,
+                    cap_type: entry.cap_type(),
+                    invocation_label: entry.invocation_tag(),
+                })
+                .or_default()
+                .add(duration);
+        }
+    }
+}
+
+impl Extend<KernelEntryRecord> for KernelEntrySummary {
+    fn extend<T: IntoIterator<Item = KernelEntryRecord>>(&mut self, iter: T) {
+        for record in iter {
+            self.add(&record);
+        }
+    }
+}
+
+/// Aggregated tracepoint records.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct TracepointSummary {
+    pub by_id: BTreeMap<u64, Stats>,
+}
+
+impl TracepointSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_log(log: &TracepointLog) -> Self {
+        let mut this = Self::new();
+        this.extend(log.iter());
+        this
+    }
+
+    pub fn add(&mut self, record: &TracepointRecord) {
+        self.by_id
+            .entry(record.id)
+            .or_default()
+            .add(record.duration);
+    }
+}
+
+impl Extend<TracepointRecord> for TracepointSummary {
+    fn extend<T: IntoIterator<Item = TracepointRecord>>(&mut self, iter: T) {
+        for record in iter {
+            self.add(&record);
+        }
+    }
+}
+
+/// Names for the syscall IDs and invocation labels of a particular kernel configuration, which
+/// a host cannot otherwise know.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Names {
+    pub syscalls: BTreeMap<i32, String>,
+    pub invocation_labels: BTreeMap<u32, String>,
+}
+
+/// Summaries of a benchmark log, as exported to the host.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Report {
+    pub kernel_entries: Option<KernelEntrySummary>,
+    pub tracepoints: Option<TracepointSummary>,
+    pub names: Names,
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::WordSize;
+
+    #[test]
+    fn histogram_bucket_edges() {
+        assert_eq!(histogram_bucket(0), 0);
+        assert_eq!(histogram_bucket(1), 1);
+        assert_eq!(histogram_bucket(2), 2);
+        assert_eq!(histogram_bucket(3), 2);
+        assert_eq!(histogram_bucket(4), 3);
+        assert_eq!(histogram_bucket(u64::MAX), 64);
+    }
+
+    #[test]
+    fn histogram_bucket_ranges() {
+        assert_eq!(histogram_bucket_range(0), 0..=0);
+        assert_eq!(histogram_bucket_range(1), 1..=1);
+        assert_eq!(histogram_bucket_range(2), 2..=3);
+        assert_eq!(histogram_bucket_range(64), 1 << 63..=u64::MAX);
+        for bucket in 0..=64 {
+            let range = histogram_bucket_range(bucket);
+            assert_eq!(histogram_bucket(*range.start()), bucket);
+            assert_eq!(histogram_bucket(*range.end()), bucket);
+            if bucket < 64 {
+                assert_eq!(*histogram_bucket_range(bucket + 1).start(), range.end() + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.mean(), None);
+        for duration in [5, 0, 7] {
+            stats.add(duration);
+        }
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.total, 12);
+        assert_eq!(stats.min, 0);
+        assert_eq!(stats.max, 7);
+        assert_eq!(stats.mean(), Some(4));
+        assert_eq!(stats.histogram, BTreeMap::from([(0, 1), (3, 2)]));
+    }
+
+    #[test]
+    fn stats_total_saturates() {
+        let mut stats = Stats::default();
+        stats.add(u64::MAX);
+        stats.add(1);
+        assert_eq!(stats.total, u64::MAX);
+    }
+
+    fn kernel_entry_record(duration: u32, path: u8, info: u32) -> [u8; KernelEntryRecord::SIZE] {
+        let mut buf = [0; KernelEntryRecord::SIZE];
+        buf[8..12].copy_from_slice(&duration.to_le_bytes());
+        buf[12] = path;
+        buf[13..17].copy_from_slice(&info.to_le_bytes());
+        buf
+    }
+
+    // The fields of a syscall entry, as packed by the kernel.
+    fn syscall_info(negated_syscall_id: u32, cap_type: u32, fastpath: bool, label: u32) -> u32 {
+        negated_syscall_id | (cap_type << 4) | (u32::from(fastpath) << 9) | (label << 10)
+    }
+
+    const INTERRUPT: u8 = 0;
+    const SYSCALL: u8 = 5;
+
+    #[test]
+    fn kernel_entry_summary_of_empty_log() {
+        let log = KernelEntryLog::new(&[], 0);
+        assert_eq!(
+            KernelEntrySummary::from_log(&log),
+            KernelEntrySummary::new()
+        );
+    }
+
+    #[test]
+    fn kernel_entry_summary() {
+        let buf = [
+            kernel_entry_record(10, SYSCALL, syscall_info(1, 3, true, 7)),
+            kernel_entry_record(20, SYSCALL, syscall_info(1, 3, false, 7)),
+            kernel_entry_record(30, SYSCALL, syscall_info(1, 3, false, 8)),
+            kernel_entry_record(40, INTERRUPT, 5 << 3),
+        ]
+        .concat();
+        let log = KernelEntryLog::new(&buf, 4);
+        let summary = KernelEntrySummary::from_log(&log);
+
+        let stats = |durations: &[u64]| {
+            let mut stats = Stats::default();
+            for duration in durations {
+                stats.add(*duration);
+            }
+            stats
+        };
+
+        assert_eq!(
+            summary.by_path,
+            BTreeMap::from([
+                (EntryPath::Syscall, stats(&[10, 20, 30])),
+                (EntryPath::Interrupt, stats(&[40])),
+            ])
+        );
+        assert_eq!(
+            summary.by_syscall,
+            BTreeMap::from([
+                (
+                    SyscallKey {
+                        syscall_id: -1,
+                        is_fastpath: true
+                    },
+                    stats(&[10])
+                ),
+                (
+                    SyscallKey {
+                        syscall_id: -1,
+                        is_fastpath: false
+                    },
+                    stats(&[20, 30])
+                ),
+            ])
+        );
+        let invocation = |invocation_label| InvocationKey {
+            syscall_id: -1,
+            cap_type: 3,
+            invocation_label,
+        };
+        assert_eq!(
+            summary.by_invocation,
+            BTreeMap::from([
+                (invocation(7), stats(&[10, 20])),
+                (invocation(8), stats(&[30])),
+            ])
+        );
+    }
+
+    #[test]
+    fn tracepoint_summary_of_empty_log() {
+        let log = TracepointLog::new(&[], 0, WordSize::Bits64);
+        assert_eq!(TracepointSummary::from_log(&log), TracepointSummary::new());
+    }
+
+    #[test]
+    fn tracepoint_summary() {
+        let buf = [[1, 100], [2, 5], [1, 300]]
+            .iter()
+            .flat_map(|record: &[u32; 2]| record.iter().flat_map(|word| word.to_le_bytes()))
+            .collect::<Vec<u8>>();
+        let log = TracepointLog::new(&buf, 3, WordSize::Bits32);
+        let summary = TracepointSummary::from_log(&log);
+        assert_eq!(summary.by_id.len(), 2);
+        assert_eq!(summary.by_id[&1].count, 2);
+        assert_eq!(summary.by_id[&1].total, 400);
+        assert_eq!(summary.by_id[&1].min, 100);
+        assert_eq!(summary.by_id[&2].max, 5);
+    }
+}
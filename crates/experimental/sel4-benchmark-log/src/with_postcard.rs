@@ -0,0 +1,41 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use postcard::ser_flavors::Flavor;
+
+use crate::Report;
+
+struct LameFlavor<F> {
+    send_byte: F,
+}
+
+impl<F> LameFlavor<F> {
+    fn new(send_byte: F) -> Self {
+        Self { send_byte }
+    }
+}
+
+impl<F: FnMut(u8) -> Result<(), E>, E> Flavor for &mut LameFlavor<F> {
+    type Output = ();
+
+    fn try_push(&mut self, data: u8) -> postcard::Result<()> {
+        (self.send_byte)(data).map_err(|_| postcard::Error::SerdeSerCustom)
+    }
+
+    fn finalize(self) -> postcard::Result<Self::Output> {
+        Ok(())
+    }
+}
+
+impl Report {
+    pub fn send<F: FnMut(u8) -> Result<(), E>, E>(&self, send_byte: F) -> postcard::Result<()> {
+        postcard::serialize_with_flavor(self, &mut LameFlavor::new(send_byte))
+    }
+
+    pub fn recv(bytes: &[u8]) -> postcard::Result<Self> {
+        postcard::from_bytes(bytes)
+    }
+}
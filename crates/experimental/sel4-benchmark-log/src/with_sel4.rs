@@ -0,0 +1,32 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::borrow::ToOwned;
+
+use sel4::sys::{invocation_label, syscall_id};
+
+use crate::Names;
+
+impl Names {
+    /// Returns the names for the kernel configuration against which this crate was built.
+    ///
+    /// Only syscalls whose IDs fit in a kernel entry record are included.
+    pub fn for_this_kernel() -> Self {
+        let mut this = Self::default();
+        for id in -15..0 {
+            if let Some(name) = syscall_id::name(id) {
+                this.syscalls.insert(id, name.to_owned());
+            }
+        }
+        for label in 0.. {
+            match invocation_label::name(label) {
+                Some(name) => this.invocation_labels.insert(label, name.to_owned()),
+                None => break,
+            };
+        }
+        this
+    }
+}
@@ -0,0 +1,149 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Typed capabilities sent alongside a message.
+//!
+//! A sender passes a tuple of up to three [`Cap`]s (or `()`), which are placed in the IPC
+//! buffer's extra caps. A receiver reads a tuple of the same arity, with each [`Cap<T>`] replaced
+//! by a [`ReceivedCap<T>`].
+//!
+//! Because a thread has only one receive slot, at most one capability per message can be
+//! transferred. The kernel stops processing a message's extra caps once it cannot transfer one,
+//! which is reported as [`CapTransferError::MissingCaps`]. Endpoint capabilities which refer to
+//! the endpoint on which the message was received are never transferred. Instead, their badges
+//! are reported as [`ReceivedCap::Unwrapped`].
+//!
+//! Capabilities travel beside a message's serialized data rather than as fields of it. The data is
+//! written to the message registers by `postcard`, which offers a field no way to reach the IPC
+//! buffer's extra caps, and a received capability is not the sender's [`Cap<T>`] in any case: it
+//! is either the receiver's receive slot or a badge.
+//!
+//! As with a message's data, the types of its capabilities are a matter of protocol between
+//! sender and receiver, and are not checked.
+
+use core::fmt;
+
+use sel4::{Badge, Cap, CapType, IpcBuffer, MessageInfo, Word};
+
+/// A capability received alongside a message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReceivedCap<T: CapType> {
+    /// The capability was transferred into the receiver's receive slot.
+    Transferred(Cap<T>),
+    /// The capability was a badged capability to the endpoint on which the message was received,
+    /// and so was unwrapped by the kernel to its badge.
+    Unwrapped(Badge),
+}
+
+impl<T: CapType> ReceivedCap<T> {
+    pub fn transferred(self) -> Option<Cap<T>> {
+        match self {
+            Self::Transferred(cap) => Some(cap),
+            Self::Unwrapped(_) => None,
+        }
+    }
+
+    pub fn unwrapped(self) -> Option<Badge> {
+        match self {
+            Self::Transferred(_) => None,
+            Self::Unwrapped(badge) => Some(badge),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CapTransferError {
+    /// Fewer capabilities than expected accompanied the message, either because the sender sent
+    /// fewer, or because the kernel could not transfer one of them.
+    MissingCaps { expected: usize, received: usize },
+    /// A capability was transferred, but the receive slot was not set as a path of depth
+    /// [`sel4::WORD_SIZE`] relative to the receiver's CSpace root, so it cannot be referred to by
+    /// a [`Cap`].
+    UnaddressableRecvSlot,
+}
+
+impl fmt::Display for CapTransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingCaps { expected, received } => {
+                write!(f, "expected {expected} caps, but received {received}")
+            }
+            Self::UnaddressableRecvSlot => write!(f, "receive slot is not addressable"),
+        }
+    }
+}
+
+/// Capabilities which can be sent alongside a message.
+pub trait SendCaps {
+    /// Writes the capabilities' CPtrs to `caps_or_badges` and returns how many there are.
+    fn write_caps(&self, caps_or_badges: &mut [Word]) -> usize;
+}
+
+/// Capabilities which can be received alongside a message.
+pub trait RecvCaps: Sized {
+    fn read_caps(info: &MessageInfo, ipc_buffer: &IpcBuffer) -> Result<Self, CapTransferError>;
+}
+
+fn read_cap<T: CapType>(
+    info: &MessageInfo,
+    ipc_buffer: &IpcBuffer,
+    i: usize,
+) -> Result<ReceivedCap<T>, CapTransferError> {
+    Ok(if info.caps_unwrapped() & (1 << i) != 0 {
+        ReceivedCap::Unwrapped(ipc_buffer.caps_or_badges()[i])
+    } else {
+        let slot = ipc_buffer.recv_slot();
+        if slot.path().depth() != sel4::WORD_SIZE {
+            return Err(CapTransferError::UnaddressableRecvSlot);
+        }
+        ReceivedCap::Transferred(Cap::from_bits(slot.path().bits()))
+    })
+}
+
+fn check_count(info: &MessageInfo, expected: usize) -> Result<(), CapTransferError> {
+    let received = info.extra_caps();
+    if received < expected {
+        return Err(CapTransferError::MissingCaps { expected, received });
+    }
+    Ok(())
+}
+
+impl SendCaps for () {
+    fn write_caps(&self, _caps_or_badges: &mut [Word]) -> usize {
+        0
+    }
+}
+
+impl RecvCaps for () {
+    fn read_caps(_info: &MessageInfo, _ipc_buffer: &IpcBuffer) -> Result<Self, CapTransferError> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_for_tuple {
+    ($n:literal: $($t:ident $i:tt),*) => {
+        impl<$($t: CapType),*> SendCaps for ($(Cap<$t>,)*) {
+            fn write_caps(&self, caps_or_badges: &mut [Word]) -> usize {
+                $(caps_or_badges[$i] = self.$i.bits();)*
+                $n
+            }
+        }
+
+        impl<$($t: CapType),*> RecvCaps for ($(ReceivedCap<$t>,)*) {
+            fn read_caps(
+                info: &MessageInfo,
+                ipc_buffer: &IpcBuffer,
+            ) -> Result<Self, CapTransferError> {
+                check_count(info, $n)?;
+                Ok(($(read_cap::<$t>(info, ipc_buffer, $i)?,)*))
+            }
+        }
+    };
+}
+
+impl_for_tuple!(1: A 0);
+impl_for_tuple!(2: A 0, B 1);
+impl_for_tuple!(3: A 0, B 1, C 2);
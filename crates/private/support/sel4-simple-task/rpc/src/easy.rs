@@ -12,6 +12,8 @@ use serde::{Deserialize, Serialize};
 
 use sel4::{Badge, IpcBuffer, MessageInfo, MessageInfoBuilder, Word, cap::Endpoint};
 
+use crate::caps::{CapTransferError, RecvCaps, SendCaps};
+
 const BYTES_PER_WORD: usize = mem::size_of::<Word>() / mem::size_of::<u8>();
 
 #[derive(Clone)]
@@ -39,6 +41,31 @@ impl<T: Serialize> Client<T> {
         let recv_info = self.endpoint.call(send_info);
         recv_data(&recv_info)
     }
+
+    pub fn send_with_caps(&self, data: &T, caps: &impl SendCaps) -> Result<(), Error> {
+        let info = prepare_data_with_caps_for_send(data, caps)?;
+        self.endpoint.send(info);
+        Ok(())
+    }
+
+    /// Like [`call`](Self::call), but with capabilities sent alongside the request and received
+    /// alongside the reply.
+    ///
+    /// Any capability transferred with the reply is placed in the receive slot of the current
+    /// thread's IPC buffer.
+    pub fn call_with_caps<U: for<'a> Deserialize<'a>, C: RecvCaps>(
+        &self,
+        data: &T,
+        caps: &impl SendCaps,
+    ) -> Result<(U, C), Error> {
+        let send_info = prepare_data_with_caps_for_send(data, caps)?;
+        let recv_info = self.endpoint.call(send_info);
+        sel4::with_ipc_buffer(|ipc_buffer| {
+            let data = recv_data_with_ipc_buffer(&recv_info, ipc_buffer)?;
+            let caps = C::read_caps(&recv_info, ipc_buffer)?;
+            Ok((data, caps))
+        })
+    }
 }
 
 pub mod server {
@@ -66,6 +93,22 @@ pub mod server {
         sel4::with_ipc_buffer_mut(|ipc_buffer| sel4::reply(ipc_buffer, info));
         Ok(())
     }
+
+    pub fn send_with_caps<T: Serialize>(
+        endpoint: Endpoint,
+        data: &T,
+        caps: &impl SendCaps,
+    ) -> Result<(), Error> {
+        let info = prepare_data_with_caps_for_send(data, caps)?;
+        endpoint.send(info);
+        Ok(())
+    }
+
+    pub fn reply_with_caps<T: Serialize>(data: &T, caps: &impl SendCaps) -> Result<(), Error> {
+        let info = prepare_data_with_caps_for_send(data, caps)?;
+        sel4::with_ipc_buffer_mut(|ipc_buffer| sel4::reply(ipc_buffer, info));
+        Ok(())
+    }
 }
 
 pub struct Reception<'a> {
@@ -98,6 +141,14 @@ impl<'a> Reception<'a> {
     pub fn read<T: for<'b> Deserialize<'b>>(&self) -> Result<T, Error> {
         recv_data_with_ipc_buffer(&self.info, self.ipc_buffer())
     }
+
+    /// Returns the capabilities which accompanied the message.
+    ///
+    /// Any transferred capability is in the receive slot of [`ipc_buffer`](Self::ipc_buffer),
+    /// and must be moved elsewhere before the next capability is received.
+    pub fn caps<C: RecvCaps>(&self) -> Result<C, Error> {
+        C::read_caps(&self.info, self.ipc_buffer()).map_err(Into::into)
+    }
 }
 
 // // //
@@ -111,6 +162,19 @@ pub fn prepare_data_for_send<T: Serialize>(data: &T) -> Result<MessageInfo, Erro
         .build())
 }
 
+pub fn prepare_data_with_caps_for_send<T: Serialize>(
+    data: &T,
+    caps: &impl SendCaps,
+) -> Result<MessageInfo, Error> {
+    let info = prepare_data_for_send(data)?;
+    let extra_caps =
+        sel4::with_ipc_buffer_mut(|ipc_buffer| caps.write_caps(ipc_buffer.caps_or_badges_mut()));
+    Ok(MessageInfoBuilder::default()
+        .length(info.length())
+        .extra_caps(extra_caps)
+        .build())
+}
+
 pub fn prepare_bytes_for_send(bytes: &[u8]) -> Result<MessageInfo, Error> {
     sel4::with_ipc_buffer_mut(|ipc_buffer| {
         ipc_buffer.msg_bytes_mut()[..bytes.len()].copy_from_slice(bytes);
@@ -141,6 +205,7 @@ fn recv_bytes_with_ipc_buffer<'a>(ipc_buffer: &'a IpcBuffer, info: &MessageInfo)
 pub enum Error {
     SeL4Error(sel4::Error),
     PostcardError(postcard::Error),
+    CapTransferError(CapTransferError),
 }
 
 impl fmt::Display for Error {
@@ -148,6 +213,7 @@ impl fmt::Display for Error {
         match self {
             Self::SeL4Error(err) => write!(f, "seL4 error: {}", err),
             Self::PostcardError(err) => write!(f, "postcard error: {}", err),
+            Self::CapTransferError(err) => write!(f, "cap transfer error: {}", err),
         }
     }
 }
@@ -163,3 +229,9 @@ impl From<postcard::Error> for Error {
         Self::PostcardError(err)
     }
 }
+
+impl From<CapTransferError> for Error {
+    fn from(err: CapTransferError) -> Self {
+        Self::CapTransferError(err)
+    }
+}
@@ -6,5 +6,7 @@
 
 #![no_std]
 
+pub mod caps;
+
 #[cfg(feature = "postcard")]
 pub mod easy;
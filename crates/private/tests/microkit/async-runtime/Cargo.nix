@@ -0,0 +1,14 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "tests-microkit-async-runtime";
+  dependencies = {
+    sel4-microkit = localCrates.sel4-microkit // { features = [ "async" ]; };
+  };
+}
@@ -0,0 +1,30 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+#![no_std]
+#![no_main]
+
+use sel4_microkit::{
+    Handler, MessageInfo, NullHandler, get_mr, protection_domain, system_description,
+};
+
+system_description!(path = "x.system", pd = "client");
+
+use channels::SERVER;
+use memory_regions::shared;
+
+#[protection_domain]
+fn init() -> impl Handler {
+    assert!(shared::WRITE);
+    unsafe {
+        shared::region().as_mut()[0] = 41;
+    }
+    let reply = SERVER.pp_call(MessageInfo::new(0, 0));
+    assert_eq!(reply.count(), 1);
+    assert_eq!(get_mr(0), 42);
+    SERVER.notify();
+    NullHandler::new()
+}
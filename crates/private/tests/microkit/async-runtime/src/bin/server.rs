@@ -0,0 +1,46 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+#![no_std]
+#![no_main]
+
+use sel4_microkit::async_runtime::{AsyncHandler, Runtime};
+use sel4_microkit::{
+    Handler, MessageInfo, debug_println, protection_domain, set_mr, system_description,
+};
+
+system_description!(path = "x.system", pd = "server");
+
+use memory_regions::shared;
+
+#[protection_domain(heap_size = 0x10000)]
+fn init() -> impl Handler {
+    assert!(shared::READ && !shared::WRITE);
+    assert_ne!(shared::paddr(), 0);
+    assert_eq!(shared::paddr() % shared::PAGE_SIZE, 0);
+    AsyncHandler::new(main)
+}
+
+async fn main(rt: Runtime) {
+    rt.serve_protected(|channel, msg_info| async move {
+        assert_eq!(channel, channels::CLIENT);
+        assert_eq!(msg_info.count(), 0);
+        // The client wrote to the shared region before calling.
+        let value = unsafe { shared::region().as_ref()[0] };
+        set_mr(0, (value + 1).into());
+        MessageInfo::new(0, 1)
+    });
+
+    rt.spawn({
+        let rt = rt.clone();
+        async move {
+            rt.notified(channels::CLIENT).await;
+            // The client has nothing left to do.
+            children::CLIENT.stop().unwrap();
+            debug_println!("TEST_PASS");
+        }
+    });
+}
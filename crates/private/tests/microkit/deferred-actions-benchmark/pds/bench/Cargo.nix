@@ -0,0 +1,17 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "tests-microkit-deferred-actions-benchmark-pds-bench";
+  dependencies = {
+    inherit (localCrates)
+      sel4
+      sel4-microkit
+    ;
+  };
+}
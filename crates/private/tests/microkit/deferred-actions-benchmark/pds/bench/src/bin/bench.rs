@@ -0,0 +1,101 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Compares the number of kernel entries made by a driver which notifies each of its clients
+//! immediately with that made by one which defers those notifications using
+//! [`sel4_microkit::DeferredActions`].
+//!
+//! Kernel entries are only counted if the kernel is configured with
+//! `KernelBenchmarks=track_utilisation`.
+
+#![no_std]
+#![no_main]
+
+use sel4_microkit::{
+    Channel, ChannelSet, Child, Handler, Infallible, debug_println, protection_domain,
+};
+
+const NUM_EVENTS: u64 = 1000;
+
+struct Driver {
+    name: &'static str,
+    child: Child,
+    event: Channel,
+}
+
+const DRIVERS: [Driver; 2] = [
+    Driver {
+        name: "immediate",
+        child: Child::new(0),
+        event: Channel::new(0),
+    },
+    Driver {
+        name: "batched",
+        child: Child::new(1),
+        event: Channel::new(5),
+    },
+];
+
+#[protection_domain]
+fn init() -> impl Handler {
+    let mut kernel_entries = [0; DRIVERS.len()];
+    for (driver, kernel_entries) in DRIVERS.iter().zip(kernel_entries.iter_mut()) {
+        // The drivers have a higher priority than this protection domain, so each event is
+        // handled before `notify()` returns.
+        *kernel_entries = measure(driver.child, || {
+            for _ in 0..NUM_EVENTS {
+                driver.event.notify();
+            }
+        });
+    }
+    report(&kernel_entries);
+    debug_println!("TEST_PASS");
+    HandlerImpl {}
+}
+
+#[sel4::sel4_cfg(BENCHMARK_TRACK_UTILISATION)]
+fn measure(child: Child, f: impl FnOnce()) -> Option<u64> {
+    sel4::benchmark_reset_thread_utilisation(child.tcb());
+    f();
+    sel4::benchmark_get_thread_utilisation(child.tcb());
+    let ix = sel4::sys::benchmark_track_util_ipc_index::BENCHMARK_TCB_NUMBER_KERNEL_ENTRIES;
+    Some(sel4_microkit::with_msg_regs(|regs| regs[ix as usize]))
+}
+
+#[sel4::sel4_cfg(not(BENCHMARK_TRACK_UTILISATION))]
+fn measure(_child: Child, f: impl FnOnce()) -> Option<u64> {
+    f();
+    None
+}
+
+fn report(kernel_entries: &[Option<u64>]) {
+    for (driver, kernel_entries) in DRIVERS.iter().zip(kernel_entries) {
+        match kernel_entries {
+            Some(n) => debug_println!(
+                "{}: {n} kernel entries for {NUM_EVENTS} events ({} per event)",
+                driver.name,
+                *n as f64 / NUM_EVENTS as f64,
+            ),
+            None => debug_println!(
+                "{}: kernel entries not counted (requires KernelBenchmarks=track_utilisation)",
+                driver.name,
+            ),
+        }
+    }
+    if let [Some(immediate), Some(batched)] = kernel_entries {
+        assert!(batched < immediate);
+    }
+}
+
+struct HandlerImpl {}
+
+impl Handler for HandlerImpl {
+    type Error = Infallible;
+
+    fn notified(&mut self, _channels: ChannelSet) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
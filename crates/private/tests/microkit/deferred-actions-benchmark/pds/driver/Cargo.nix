@@ -0,0 +1,16 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "tests-microkit-deferred-actions-benchmark-pds-driver";
+  dependencies = {
+    inherit (localCrates)
+      sel4-microkit
+    ;
+  };
+}
@@ -0,0 +1,58 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+#![no_std]
+#![no_main]
+
+use sel4_microkit::{
+    Channel, ChannelSet, DeferredActions, Handler, Infallible, pd_name, protection_domain,
+};
+
+const EVENT: Channel = Channel::new(0);
+
+const CLIENTS: [Channel; 4] = [
+    Channel::new(1),
+    Channel::new(2),
+    Channel::new(3),
+    Channel::new(4),
+];
+
+#[protection_domain]
+fn init() -> impl Handler {
+    HandlerImpl {
+        batched: match pd_name().unwrap() {
+            "immediate" => false,
+            "batched" => true,
+            name => panic!("unexpected protection domain name: {name}"),
+        },
+        deferred_actions: DeferredActions::new(),
+    }
+}
+
+struct HandlerImpl {
+    batched: bool,
+    deferred_actions: DeferredActions,
+}
+
+impl Handler for HandlerImpl {
+    type Error = Infallible;
+
+    fn notified(&mut self, channels: ChannelSet) -> Result<(), Self::Error> {
+        assert!(channels.contains(EVENT));
+        for client in CLIENTS {
+            if self.batched {
+                self.deferred_actions.defer_notify(client);
+            } else {
+                client.notify();
+            }
+        }
+        Ok(())
+    }
+
+    fn take_deferred_actions(&mut self) -> DeferredActions {
+        self.deferred_actions.take()
+    }
+}
@@ -0,0 +1,18 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "tests-root-task-fault-handler";
+  dependencies = {
+    inherit (localCrates)
+      sel4
+      sel4-fault-handler
+      sel4-root-task
+    ;
+  };
+}
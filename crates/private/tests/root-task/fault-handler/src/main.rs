@@ -0,0 +1,352 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+#![no_std]
+#![no_main]
+
+use core::arch::global_asm;
+use core::ops::Range;
+use core::ptr;
+
+use sel4::{Badge, Fault, Word, cap};
+use sel4_fault_handler::{Action, FaultHandler, FaultingThread, handle_next};
+use sel4_root_task::{debug_println, root_task};
+
+const CHILD_BADGE: Badge = 1;
+
+const SYSCALL_NUMBER: Word = 1234;
+const SYSCALL_RET: Word = 42;
+const EXIT_SYSCALL_NUMBER: Word = 1235;
+
+static LOADED_VALUE: Word = 0x1337;
+
+#[cfg(target_arch = "x86_64")]
+const UNDEFINED_INSTRUCTION_SIZE: usize = 2;
+#[cfg(not(target_arch = "x86_64"))]
+const UNDEFINED_INSTRUCTION_SIZE: usize = 4;
+
+#[root_task]
+fn main(bootinfo: &sel4::BootInfoPtr) -> ! {
+    let mut object_allocator = ObjectAllocator::new(bootinfo);
+
+    let fault_ep = object_allocator.allocate_fixed_sized::<sel4::cap_type::Endpoint>();
+    let child = object_allocator.allocate_fixed_sized::<sel4::cap_type::Tcb>();
+
+    let cnode = sel4::init_thread::slot::CNODE.cap();
+    let badged_fault_ep = sel4::init_thread::Slot::<sel4::cap_type::Endpoint>::from_index(
+        object_allocator.allocate_slot(),
+    )
+    .cap();
+    cnode
+        .absolute_cptr(badged_fault_ep)
+        .mint(
+            &cnode.absolute_cptr(fault_ep),
+            sel4::CapRights::all(),
+            CHILD_BADGE,
+        )
+        .unwrap();
+
+    // The child runs only the code in `global_asm!` below, which needs neither a stack nor an IPC
+    // buffer.
+    child
+        .tcb_configure(
+            badged_fault_ep.cptr(),
+            cnode,
+            sel4::CNodeCapData::new(0, 0),
+            sel4::init_thread::slot::VSPACE.cap(),
+            0,
+            sel4::init_thread::slot::NULL.cap().cast(),
+        )
+        .unwrap();
+
+    let mut ctx = sel4::UserContext::default();
+    *ctx.pc_mut() = symbol_addr(ptr::addr_of!(child_start));
+    child.tcb_write_all_registers(true, &mut ctx).unwrap();
+
+    // Resuming without modifying the program counter restarts the syscall instruction.
+    for _ in 0..2 {
+        expect_fault(fault_ep, child, |thread, fault| {
+            let Fault::UnknownSyscall(fault) = fault else {
+                panic!("unexpected fault: {fault:?}");
+            };
+            assert_fault_ip(
+                thread,
+                fault.fault_ip(),
+                ptr::addr_of!(child_unknown_syscall),
+            );
+            assert_eq!(thread.linux_syscall_number().unwrap(), SYSCALL_NUMBER);
+            Action::Resume
+        });
+    }
+
+    // Completing the syscall skips it.
+    expect_fault(fault_ep, child, |thread, fault| {
+        let Fault::UnknownSyscall(fault) = fault else {
+            panic!("unexpected fault: {fault:?}");
+        };
+        assert_fault_ip(
+            thread,
+            fault.fault_ip(),
+            ptr::addr_of!(child_unknown_syscall),
+        );
+        thread.complete_linux_syscall(SYSCALL_RET).unwrap();
+        Action::Resume
+    });
+
+    // Resuming after a VM fault retries the access, which faults again until the address it uses
+    // is fixed up.
+    for fix_up in [false, true] {
+        expect_fault(fault_ep, child, |thread, fault| {
+            let Fault::VmFault(fault) = fault else {
+                panic!("unexpected fault: {fault:?}");
+            };
+            assert_fault_ip(thread, fault.ip(), ptr::addr_of!(child_vm_fault));
+            assert_eq!(fault.addr(), 0);
+            if fix_up {
+                *thread.regs_mut().unwrap().c_param_mut(1) =
+                    symbol_addr(ptr::addr_of!(LOADED_VALUE));
+            }
+            Action::Resume
+        });
+    }
+
+    // An undefined instruction must be skipped explicitly.
+    expect_fault(fault_ep, child, |thread, fault| {
+        let Fault::UserException(fault) = fault else {
+            panic!("unexpected fault: {fault:?}");
+        };
+        assert_fault_ip(
+            thread,
+            fault.fault_ip(),
+            ptr::addr_of!(child_user_exception),
+        );
+        thread.skip_instruction(UNDEFINED_INSTRUCTION_SIZE).unwrap();
+        Action::Resume
+    });
+
+    // The child reports the results of the syscall and of the retried access.
+    let check_exit = |thread: &mut FaultingThread, fault: &Fault| {
+        let Fault::UnknownSyscall(fault) = fault else {
+            panic!("unexpected fault: {fault:?}");
+        };
+        assert_fault_ip(thread, fault.fault_ip(), ptr::addr_of!(child_exit));
+        assert_eq!(thread.linux_syscall_number().unwrap(), EXIT_SYSCALL_NUMBER);
+        assert_eq!(thread.linux_syscall_arg(0).unwrap(), SYSCALL_RET);
+        assert_eq!(thread.linux_syscall_arg(1).unwrap(), LOADED_VALUE);
+        Action::Kill
+    };
+
+    expect_fault(fault_ep, child, check_exit);
+
+    // Killing only suspends the thread, so resuming it restarts the syscall instruction.
+    child.tcb_resume().unwrap();
+    expect_fault(fault_ep, child, check_exit);
+
+    debug_println!("TEST_PASS");
+
+    sel4::init_thread::suspend_self()
+}
+
+fn expect_fault(
+    fault_ep: cap::Endpoint,
+    child: cap::Tcb,
+    f: impl FnOnce(&mut FaultingThread, &Fault) -> Action,
+) {
+    handle_next(fault_ep, &mut OneShotHandler { child, f: Some(f) }).unwrap();
+}
+
+struct OneShotHandler<F> {
+    child: cap::Tcb,
+    f: Option<F>,
+}
+
+impl<F: FnOnce(&mut FaultingThread, &Fault) -> Action> FaultHandler for OneShotHandler<F> {
+    fn thread(&mut self, badge: Badge) -> Option<cap::Tcb> {
+        assert_eq!(badge, CHILD_BADGE);
+        Some(self.child)
+    }
+
+    fn handle_fault(&mut self, thread: &mut FaultingThread, fault: &Fault) -> Action {
+        (self.f.take().unwrap())(thread, fault)
+    }
+}
+
+// The program counter of a faulting thread, as reported both in the fault message and by its
+// registers, is that of the faulting instruction.
+fn assert_fault_ip<T>(thread: &mut FaultingThread, fault_ip: Word, symbol: *const T) {
+    assert_eq!(fault_ip, symbol_addr(symbol));
+    assert_eq!(*thread.regs().unwrap().pc(), fault_ip);
+}
+
+fn symbol_addr<T>(symbol: *const T) -> Word {
+    symbol as usize as Word
+}
+
+// // //
+
+unsafe extern "C" {
+    static child_start: u8;
+    static child_unknown_syscall: u8;
+    static child_vm_fault: u8;
+    static child_user_exception: u8;
+    static child_exit: u8;
+}
+
+// The child:
+// - makes a syscall with the Linux syscall number `SYSCALL_NUMBER`, which is not a valid seL4
+//   syscall
+// - loads a word from address 0, which is unmapped
+// - executes an undefined instruction
+// - makes a syscall with the Linux syscall number `EXIT_SYSCALL_NUMBER`, passing the return value
+//   of the first syscall and the loaded word as its first and second arguments
+//
+// Where the seL4 syscall number is passed in a different register than the Linux one, that
+// register is cleared, since 0 is not a valid seL4 syscall number either.
+global_asm! {
+    r#"
+        .section .text.child, "ax", %progbits
+
+        .global child_start
+        .global child_unknown_syscall
+        .global child_vm_fault
+        .global child_user_exception
+        .global child_exit
+    "#,
+    #[cfg(target_arch = "aarch64")]
+    r#"
+        child_start:
+            mov x7, #0
+            mov x8, #{syscall}
+        child_unknown_syscall:
+            svc #0
+            mov x1, #0
+        child_vm_fault:
+            ldr x2, [x1]
+        child_user_exception:
+            udf #0
+            mov x1, x2
+            mov x8, #{exit}
+        child_exit:
+            svc #0
+        1:  b 1b
+    "#,
+    #[cfg(target_arch = "arm")]
+    r#"
+            .arm
+        child_start:
+            movw r7, #{syscall}
+        child_unknown_syscall:
+            svc #0
+            mov r1, #0
+        child_vm_fault:
+            ldr r2, [r1]
+        child_user_exception:
+            udf #0
+            mov r1, r2
+            movw r7, #{exit}
+        child_exit:
+            svc #0
+        1:  b 1b
+    "#,
+    #[cfg(any(target_arch = "riscv64", target_arch = "riscv32"))]
+    r#"
+        child_start:
+            li a7, {syscall}
+        child_unknown_syscall:
+            ecall
+            li a1, 0
+        child_vm_fault:
+    "#,
+    #[cfg(target_arch = "riscv64")]
+    r#"
+            ld a2, 0(a1)
+    "#,
+    #[cfg(target_arch = "riscv32")]
+    r#"
+            lw a2, 0(a1)
+    "#,
+    #[cfg(any(target_arch = "riscv64", target_arch = "riscv32"))]
+    r#"
+        child_user_exception:
+        .option push
+        .option norvc
+            unimp
+        .option pop
+            mv a1, a2
+            li a7, {exit}
+        child_exit:
+            ecall
+        1:  j 1b
+    "#,
+    #[cfg(target_arch = "x86_64")]
+    r#"
+        child_start:
+            xor edx, edx
+            mov eax, {syscall}
+        child_unknown_syscall:
+            syscall
+            xor esi, esi
+        child_vm_fault:
+            mov r8, [rsi]
+        child_user_exception:
+            ud2
+            mov rdi, rax
+            mov rsi, r8
+            mov eax, {exit}
+        child_exit:
+            syscall
+        1:  jmp 1b
+    "#,
+    syscall = const SYSCALL_NUMBER,
+    exit = const EXIT_SYSCALL_NUMBER,
+}
+
+// // //
+
+struct ObjectAllocator {
+    empty_slots: Range<usize>,
+    ut: sel4::cap::Untyped,
+}
+
+impl ObjectAllocator {
+    fn new(bootinfo: &sel4::BootInfo) -> Self {
+        Self {
+            empty_slots: bootinfo.empty().range(),
+            ut: find_largest_kernel_untyped(bootinfo),
+        }
+    }
+
+    fn allocate_slot(&mut self) -> usize {
+        self.empty_slots.next().unwrap()
+    }
+
+    fn allocate_fixed_sized<T: sel4::CapTypeForObjectOfFixedSize>(&mut self) -> sel4::Cap<T> {
+        let slot_index = self.allocate_slot();
+        self.ut
+            .untyped_retype(
+                &T::object_blueprint(),
+                &sel4::init_thread::slot::CNODE
+                    .cap()
+                    .absolute_cptr_for_self(),
+                slot_index,
+                1,
+            )
+            .unwrap();
+        sel4::init_thread::Slot::from_index(slot_index).cap()
+    }
+}
+
+fn find_largest_kernel_untyped(bootinfo: &sel4::BootInfo) -> sel4::cap::Untyped {
+    let (ut_ix, _desc) = bootinfo
+        .untyped_list()
+        .iter()
+        .enumerate()
+        .filter(|(_i, desc)| !desc.is_device())
+        .max_by_key(|(_i, desc)| desc.size_bits())
+        .unwrap();
+
+    bootinfo.untyped().index(ut_ix).cap()
+}
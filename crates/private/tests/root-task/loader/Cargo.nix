@@ -12,6 +12,7 @@ mk {
     inherit (versions) fdt;
     inherit (localCrates)
       sel4
+      sel4-fdt-devices
       sel4-root-task
       sel4-platform-info
     ;
@@ -9,6 +9,7 @@
 #![feature(thread_local)]
 #![allow(clippy::single_match)]
 
+use sel4_fdt_devices::{DeviceTree, compatible};
 use sel4_platform_info::PLATFORM_INFO;
 use sel4_root_task::{debug_print, debug_println, root_task};
 
@@ -56,6 +57,21 @@ fn main(bootinfo: &sel4::BootInfoPtr) -> ! {
         }
     }
 
+    match DeviceTree::from_bootinfo(bootinfo) {
+        Ok(dt) => {
+            for device in dt.all_compatible(compatible::PL011) {
+                debug_println!(
+                    "{:?}: reg {:x?}, interrupt {:?}",
+                    device,
+                    device.reg(0),
+                    device.interrupt(0),
+                );
+            }
+        }
+        Err(sel4_fdt_devices::Error::NoDeviceTree) => {}
+        Err(err) => panic!("{err}"),
+    }
+
     // for ut in bootinfo.kernel_untyped_list() {
     //     debug_println!("k {:x?} {}", ut.paddr, ut.isDevice);
     // }
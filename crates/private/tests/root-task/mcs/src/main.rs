@@ -0,0 +1,195 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+#![no_std]
+#![no_main]
+
+use sel4_root_task::{debug_println, root_task};
+
+#[root_task]
+fn main(bootinfo: &sel4::BootInfoPtr) -> ! {
+    sel4::sel4_cfg_if! {
+        if #[sel4_cfg(KERNEL_MCS)] {
+            mcs::test(bootinfo);
+        } else {
+            let _ = bootinfo;
+            debug_println!("skipping: not an MCS kernel");
+        }
+    }
+
+    debug_println!("TEST_PASS");
+
+    sel4::init_thread::suspend_self()
+}
+
+#[sel4::sel4_cfg(KERNEL_MCS)]
+mod mcs {
+    use core::ops::Range;
+
+    use sel4_root_task::debug_println;
+
+    pub(crate) fn test(bootinfo: &sel4::BootInfo) {
+        let mut object_allocator = ObjectAllocator::new(bootinfo);
+        test_sched_context_invocations(bootinfo, &mut object_allocator);
+        test_ipc_syscalls(&mut object_allocator);
+    }
+
+    fn test_sched_context_invocations(
+        bootinfo: &sel4::BootInfo,
+        object_allocator: &mut ObjectAllocator,
+    ) {
+        const BUDGET: sel4::Time = 1000;
+        const PERIOD: sel4::Time = 1000;
+
+        let sc = object_allocator.allocate_variable_sized::<sel4::cap_type::SchedContext>(
+            sel4::sys::seL4_MinSchedContextBits.try_into().unwrap(),
+        );
+        let tcb = object_allocator.allocate_fixed_sized::<sel4::cap_type::Tcb>();
+        let nfn = object_allocator.allocate_fixed_sized::<sel4::cap_type::Notification>();
+
+        bootinfo
+            .sched_control()
+            .index(0)
+            .cap()
+            .sched_control_configure_flags(sc, BUDGET, PERIOD, 0, 0, 0)
+            .unwrap();
+
+        // A fresh scheduling context has not been charged for any time
+        assert_eq!(sc.sched_context_consumed().unwrap().consumed, 0);
+
+        sc.sched_context_bind(nfn).unwrap();
+        sc.sched_context_unbind_object(nfn).unwrap();
+
+        sc.sched_context_bind(tcb).unwrap();
+
+        // The bound thread is not runnable, so this returns immediately
+        let yielded = sc.sched_context_yield_to().unwrap();
+        debug_println!("consumed after yield: {}", yielded.consumed);
+
+        sc.sched_context_unbind_object(tcb).unwrap();
+
+        // An object which is not bound cannot be unbound
+        assert_eq!(
+            sc.sched_context_unbind_object(tcb),
+            Err(sel4::Error::IllegalOperation)
+        );
+
+        sc.sched_context_bind(tcb).unwrap();
+        sc.unbind().unwrap();
+
+        // The initial thread's own scheduling context is charged for the work above, and reading
+        // its consumption resets it, so an immediate second read sees only the time in between
+        let own_sc = sel4::init_thread::slot::SC.cap();
+        let before_reset = own_sc.sched_context_consumed().unwrap().consumed;
+        let after_reset = own_sc.sched_context_consumed().unwrap().consumed;
+        debug_println!("initial thread consumed: {before_reset}, then: {after_reset}");
+        assert!(before_reset > 0);
+        assert!(after_reset < before_reset);
+    }
+
+    fn test_ipc_syscalls(object_allocator: &mut ObjectAllocator) {
+        const BADGE: sel4::Badge = 0b101;
+
+        let ep = object_allocator.allocate_fixed_sized::<sel4::cap_type::Endpoint>();
+        let nfn = object_allocator.allocate_fixed_sized::<sel4::cap_type::Notification>();
+        let reply = object_allocator
+            .allocate(sel4::ObjectBlueprint::Reply)
+            .cast::<sel4::cap_type::Reply>();
+
+        let cnode = sel4::init_thread::slot::CNODE.cap();
+        let badged_nfn = sel4::init_thread::Slot::<sel4::cap_type::Notification>::from_index(
+            object_allocator.empty_slots.next().unwrap(),
+        )
+        .cap();
+        cnode
+            .absolute_cptr(badged_nfn)
+            .mint(&cnode.absolute_cptr(nfn), sel4::CapRights::all(), BADGE)
+            .unwrap();
+
+        // Polling returns the pending bits, which are 0 when nothing is pending
+        assert_eq!(nfn.poll(), 0);
+        badged_nfn.signal();
+        assert_eq!(nfn.poll(), BADGE);
+        assert_eq!(nfn.poll(), 0);
+
+        assert_eq!(nfn.nb_wait().1, 0);
+        badged_nfn.signal();
+        assert_eq!(nfn.nb_wait().1, BADGE);
+
+        badged_nfn.signal();
+        assert_eq!(nfn.wait().1, BADGE);
+
+        // Sending to a notification signals it, so this receives the signal it sends
+        let (_info, badge) = badged_nfn.nb_send_wait(sel4::MessageInfo::new(0, 0, 0, 0), nfn);
+        assert_eq!(badge, BADGE);
+
+        // A signal to a notification bound to the receiving thread is received through an
+        // endpoint too, with or without blocking
+        let tcb = sel4::init_thread::slot::TCB.cap();
+        tcb.tcb_bind_notification(nfn).unwrap();
+
+        assert_eq!(ep.nb_recv(reply).1, 0);
+        badged_nfn.signal();
+        assert_eq!(ep.nb_recv(reply).1, BADGE);
+
+        badged_nfn.signal();
+        assert_eq!(ep.recv(reply).1, BADGE);
+
+        tcb.tcb_unbind_notification().unwrap();
+    }
+
+    struct ObjectAllocator {
+        empty_slots: Range<usize>,
+        ut: sel4::cap::Untyped,
+    }
+
+    impl ObjectAllocator {
+        fn new(bootinfo: &sel4::BootInfo) -> Self {
+            Self {
+                empty_slots: bootinfo.empty().range(),
+                ut: find_largest_kernel_untyped(bootinfo),
+            }
+        }
+
+        fn allocate(&mut self, blueprint: sel4::ObjectBlueprint) -> sel4::cap::Unspecified {
+            let slot_index = self.empty_slots.next().unwrap();
+            self.ut
+                .untyped_retype(
+                    &blueprint,
+                    &sel4::init_thread::slot::CNODE
+                        .cap()
+                        .absolute_cptr_for_self(),
+                    slot_index,
+                    1,
+                )
+                .unwrap();
+            sel4::init_thread::Slot::from_index(slot_index).cap()
+        }
+
+        fn allocate_fixed_sized<T: sel4::CapTypeForObjectOfFixedSize>(&mut self) -> sel4::Cap<T> {
+            self.allocate(T::object_blueprint()).cast()
+        }
+
+        fn allocate_variable_sized<T: sel4::CapTypeForObjectOfVariableSize>(
+            &mut self,
+            size_bits: usize,
+        ) -> sel4::Cap<T> {
+            self.allocate(T::object_blueprint(size_bits)).cast()
+        }
+    }
+
+    fn find_largest_kernel_untyped(bootinfo: &sel4::BootInfo) -> sel4::cap::Untyped {
+        let (ut_ix, _desc) = bootinfo
+            .untyped_list()
+            .iter()
+            .enumerate()
+            .filter(|(_i, desc)| !desc.is_device())
+            .max_by_key(|(_i, desc)| desc.size_bits())
+            .unwrap();
+
+        bootinfo.untyped().index(ut_ix).cap()
+    }
+}
@@ -0,0 +1,19 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "sel4-arm-vmm";
+  dependencies = {
+    inherit (localCrates)
+      sel4
+      sel4-cspace-allocator
+      sel4-untyped-manager
+      sel4-vspace-manager
+    ;
+  };
+}
@@ -0,0 +1,732 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Building blocks for virtual machine monitors on AArch64 kernels configured with
+//! `ARM_HYPERVISOR_SUPPORT`.
+//!
+//! A [`Vm`] is a guest with a single VCPU: a TCB bound to a VCPU, running in a VSpace which
+//! translates guest physical addresses, along with an emulated GICv2 distributor ([`VGic`]).
+//! [`VmBuilder`] creates all of these from an [`UntypedManager`].
+//!
+//! Faults raised by the guest arrive on the fault endpoint given to [`VmBuilder::new`], and should
+//! be passed to [`Vm::handle_fault`]. It handles what it can, and reports the rest as an [`Exit`]:
+//! - Data aborts on the distributor are emulated.
+//! - Other data aborts are returned as [`Exit::Mmio`], so that the VMM can emulate devices of its
+//!   own and then complete the access with [`Vm::complete_mmio`].
+//! - PSCI calls made with `SMC` or `HVC` are handled on behalf of the guest, except for those
+//!   which power it off or reset it.
+//! - `VGICMaintenance` faults and `VPPIEvent` faults are passed to the [`VGic`].
+//!
+//! Physical interrupts are passed through to the guest with [`Vm::route_irq`].
+//!
+//! Guest RAM is added with [`Vm::add_ram`], and device frames are passed through with
+//! [`Vm::map_device`]. [`Vm::load_linux`] and [`Vm::boot_linux`] boot a Linux kernel `Image`
+//! according to the arm64 boot protocol. See [`qemu_arm_virt`] for the addresses needed to boot a
+//! guest on QEMU's `virt` machine.
+//!
+//! This crate's unit tests cover the emulation of the distributor, PSCI, and MMIO accesses, none
+//! of which makes syscalls. They run on an AArch64 host, against libsel4 for an AArch64
+//! configuration with `ARM_HYPERVISOR_SUPPORT` (see `armVmmHostTests` in
+//! `hacking/nix/top-level/aggregates.nix`).
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use sel4::{
+    Badge, CacheOp, CapRights, CapTypeForObjectOfFixedSize, Esr, ExceptionClass, Fault,
+    FrameObjectType, VCpuReg, VmAttributes, Word,
+};
+use sel4_cspace_allocator::{CSpaceAllocator, Slot};
+use sel4_untyped_manager::{Object, UntypedManager};
+use sel4_vspace_manager::{UntypedTranslationTableAllocator, VSpaceManager};
+
+mod linux;
+mod mmio;
+mod vgic;
+
+pub mod psci;
+
+pub use linux::{LinuxBootInfo, LinuxImage};
+pub use mmio::MmioAccess;
+pub use vgic::{DISTRIBUTOR_SIZE, IrqSource, NUM_PRIVATE_IRQS, VGic};
+
+#[sel4::sel4_cfg(not(all(ARCH_AARCH64, ARM_HYPERVISOR_SUPPORT)))]
+compile_error!("unsupported configuration");
+
+/// Addresses and interrupts of QEMU's `virt` machine with a GICv2 (`-machine virt,gic-version=2`).
+pub mod qemu_arm_virt {
+    pub const GIC_DISTRIBUTOR: usize = 0x0800_0000;
+    /// Where the guest expects to find the GIC CPU interface.
+    pub const GIC_CPU_INTERFACE: usize = 0x0801_0000;
+    /// The physical address of the virtual CPU interface, which should be mapped in place of the
+    /// CPU interface.
+    pub const GIC_VIRTUAL_CPU_INTERFACE: usize = 0x0804_0000;
+    pub const GIC_CPU_INTERFACE_SIZE: usize = 0x2000;
+    pub const RAM_START: usize = 0x4000_0000;
+    pub const VIRTUAL_TIMER_IRQ: usize = 27;
+}
+
+const GRANULE_SIZE: usize = FrameObjectType::GRANULE.bytes();
+
+// SCTLR_EL1 with all RES1 bits set, and the MMU and caches off.
+const SCTLR_EL1_MMU_OFF: Word = 0x30d0_0800;
+
+// EL1h with all of DAIF masked.
+const SPSR_EL1H_MASKED: Word = 0x3c5;
+
+/// The resources with which VMs are created.
+pub struct VmResources<'a> {
+    pub untyped: &'a mut UntypedManager,
+    pub cspace: &'a mut CSpaceAllocator,
+    /// The caller's own VSpace, in which guest RAM is also mapped.
+    pub vspace: &'a mut VSpaceManager,
+    pub asid_pool: sel4::cap::AsidPool,
+    /// The TCB whose maximum controlled priority bounds that of the VCPU thread.
+    pub authority: sel4::cap::Tcb,
+}
+
+/// What the VMM must do after [`Vm::handle_fault`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exit {
+    /// The fault has been handled. Reply to the fault to resume the guest.
+    Resume,
+    /// The guest accessed an address which is neither guest RAM nor the distributor. Complete the
+    /// access with [`Vm::complete_mmio`], and then reply to the fault.
+    Mmio(MmioAccess),
+    /// The guest turned its only VCPU off with `PSCI_CPU_OFF`.
+    CpuOff,
+    /// The guest called `PSCI_SYSTEM_OFF`.
+    SystemOff,
+    /// The guest called `PSCI_SYSTEM_RESET`.
+    SystemReset,
+    /// The fault is not one that this crate can handle.
+    Unhandled,
+}
+
+/// Describes a VM to be created.
+pub struct VmBuilder {
+    fault_endpoint: sel4::cap::Endpoint,
+    badge: Badge,
+    priority: Word,
+    gic_distributor: usize,
+    num_irqs: usize,
+    num_list_registers: usize,
+    // Only used on MCS configurations.
+    sched_context: Option<sel4::CPtr>,
+}
+
+impl VmBuilder {
+    pub const DEFAULT_PRIORITY: Word = 0;
+    pub const DEFAULT_NUM_IRQS: usize = 256;
+    /// The number of list registers implemented by all GICv2 virtualization extensions.
+    pub const DEFAULT_NUM_LIST_REGISTERS: usize = 4;
+
+    /// Faults raised by the guest will be sent to `fault_endpoint`, carrying `badge`.
+    pub fn new(fault_endpoint: sel4::cap::Endpoint, badge: Badge) -> Self {
+        Self {
+            fault_endpoint,
+            badge,
+            priority: Self::DEFAULT_PRIORITY,
+            gic_distributor: qemu_arm_virt::GIC_DISTRIBUTOR,
+            num_irqs: Self::DEFAULT_NUM_IRQS,
+            num_list_registers: Self::DEFAULT_NUM_LIST_REGISTERS,
+            sched_context: None,
+        }
+    }
+
+    pub fn priority(mut self, priority: Word) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// The guest physical address of the emulated distributor.
+    pub fn gic_distributor(mut self, addr: usize) -> Self {
+        self.gic_distributor = addr;
+        self
+    }
+
+    pub fn num_irqs(mut self, num_irqs: usize) -> Self {
+        self.num_irqs = num_irqs;
+        self
+    }
+
+    pub fn num_list_registers(mut self, num_list_registers: usize) -> Self {
+        self.num_list_registers = num_list_registers;
+        self
+    }
+
+    /// The scheduling context with which the VCPU thread runs. On MCS configurations, this is
+    /// required.
+    #[sel4::sel4_cfg(KERNEL_MCS)]
+    pub fn sched_context(mut self, sched_context: sel4::cap::SchedContext) -> Self {
+        self.sched_context = Some(sched_context.cptr());
+        self
+    }
+
+    /// Creates the VM. The VCPU thread remains suspended until the guest is booted.
+    pub fn build(&self, res: &mut VmResources) -> Result<Vm, Error> {
+        // Without a scheduling context, the guest would never run.
+        if sel4::sel4_cfg_bool!(KERNEL_MCS) && self.sched_context.is_none() {
+            return Err(Error::MissingSchedContext);
+        }
+        let mut vm = Vm {
+            tcb: sel4::cap::Tcb::from_bits(0),
+            vcpu: sel4::cap::VCpu::from_bits(0),
+            guest_vspace: VSpaceManager::new(sel4::cap::VSpace::from_bits(0)),
+            vmm_vspace: res.vspace.vspace(),
+            vgic: VGic::new(
+                sel4::cap::VCpu::from_bits(0),
+                self.num_irqs,
+                self.num_list_registers,
+            ),
+            gic_distributor: self.gic_distributor,
+            ram: Vec::new(),
+            routes: Vec::new(),
+            objects: Vec::new(),
+            caller_slots: Vec::new(),
+        };
+        match self.build_into(res, &mut vm) {
+            Ok(()) => Ok(vm),
+            Err(err) => {
+                let _ = vm.reclaim(res);
+                Err(err)
+            }
+        }
+    }
+
+    fn build_into(&self, res: &mut VmResources, vm: &mut Vm) -> Result<(), Error> {
+        let vspace = vm.allocate::<sel4::cap_type::VSpace>(res)?;
+        res.asid_pool.asid_pool_assign(vspace)?;
+        vm.guest_vspace = VSpaceManager::new(vspace);
+
+        // The guest cannot make seL4 system calls, so its CSpace holds nothing but its fault
+        // endpoint.
+        let cnode = vm
+            .allocate_object(
+                res,
+                &sel4::ObjectBlueprint::CNode {
+                    size_bits: GUEST_CNODE_SIZE_BITS,
+                },
+            )?
+            .cast::<sel4::cap_type::CNode>();
+        cnode
+            .absolute_cptr_from_bits_with_depth(
+                GUEST_FAULT_ENDPOINT_SLOT as sel4::CPtrBits,
+                GUEST_CNODE_SIZE_BITS,
+            )
+            .mint(
+                &sel4::init_thread::slot::CNODE
+                    .cap()
+                    .absolute_cptr(self.fault_endpoint),
+                CapRights::all(),
+                self.badge,
+            )?;
+        let cnode_guard = sel4::CNodeCapData::new(0, sel4::WORD_SIZE - GUEST_CNODE_SIZE_BITS);
+
+        let tcb = vm.allocate::<sel4::cap_type::Tcb>(res)?;
+        vm.tcb = tcb;
+        let vcpu = vm.allocate::<sel4::cap_type::VCpu>(res)?;
+        vm.vcpu = vcpu;
+        vm.vgic = VGic::new(vcpu, self.num_irqs, self.num_list_registers);
+
+        sel4::sel4_cfg_if! {
+            if #[sel4_cfg(KERNEL_MCS)] {
+                tcb.tcb_configure(
+                    cnode,
+                    cnode_guard,
+                    vspace,
+                    0,
+                    sel4::cap::Granule::from_bits(0),
+                )?;
+                // The fault endpoint is looked up in the caller's CSpace, so it must carry the
+                // badge there.
+                let slot = res.cspace.allocate()?;
+                vm.caller_slots.push(slot);
+                res.cspace.absolute_cptr(slot).mint(
+                    &sel4::init_thread::slot::CNODE.cap().absolute_cptr(self.fault_endpoint),
+                    CapRights::all(),
+                    self.badge,
+                )?;
+                tcb.tcb_set_sched_params(
+                    res.authority,
+                    self.priority,
+                    self.priority,
+                    sel4::cap::SchedContext::from_cptr(self.sched_context.unwrap()),
+                    res.cspace.cap(slot).ok_or(Error::SlotNotDirectlyAddressable)?,
+                )?;
+            } else {
+                tcb.tcb_configure(
+                    sel4::CPtr::from_bits(GUEST_FAULT_ENDPOINT_SLOT as sel4::CPtrBits),
+                    cnode,
+                    cnode_guard,
+                    vspace,
+                    0,
+                    sel4::cap::Granule::from_bits(0),
+                )?;
+                tcb.tcb_set_sched_params(res.authority, self.priority, self.priority)?;
+            }
+        }
+
+        vcpu.vcpu_set_tcb(tcb)?;
+
+        Ok(())
+    }
+}
+
+const GUEST_CNODE_SIZE_BITS: usize = 1;
+
+const GUEST_FAULT_ENDPOINT_SLOT: usize = 1;
+
+#[derive(Debug, Clone)]
+struct GuestRam {
+    ipa: Range<usize>,
+    vmm_vaddr: usize,
+    frame_type: FrameObjectType,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct IrqRoute {
+    badge: Badge,
+    virq: usize,
+}
+
+/// A guest with a single VCPU.
+pub struct Vm {
+    tcb: sel4::cap::Tcb,
+    vcpu: sel4::cap::VCpu,
+    guest_vspace: VSpaceManager,
+    vmm_vspace: sel4::cap::VSpace,
+    vgic: VGic,
+    gic_distributor: usize,
+    ram: Vec<GuestRam>,
+    routes: Vec<IrqRoute>,
+    objects: Vec<Object>,
+    caller_slots: Vec<Slot>,
+}
+
+impl Vm {
+    pub fn tcb(&self) -> sel4::cap::Tcb {
+        self.tcb
+    }
+
+    pub fn vcpu(&self) -> sel4::cap::VCpu {
+        self.vcpu
+    }
+
+    /// The VSpace which translates guest physical addresses.
+    pub fn guest_vspace(&mut self) -> &mut VSpaceManager {
+        &mut self.guest_vspace
+    }
+
+    pub fn vgic(&mut self) -> &mut VGic {
+        &mut self.vgic
+    }
+
+    /// Backs the guest physical address range `ipa` with RAM, which is also mapped in the caller's
+    /// VSpace starting at `vmm_vaddr`.
+    ///
+    /// Large pages are used when the addresses and size allow.
+    pub fn add_ram(
+        &mut self,
+        res: &mut VmResources,
+        ipa: Range<usize>,
+        vmm_vaddr: usize,
+    ) -> Result<(), Error> {
+        if [ipa.start, ipa.end, vmm_vaddr]
+            .iter()
+            .any(|addr| addr % GRANULE_SIZE != 0)
+        {
+            return Err(Error::Misaligned);
+        }
+        let large_page_size = FrameObjectType::LargePage.bytes();
+        let frame_type = if [ipa.start, ipa.end, vmm_vaddr]
+            .iter()
+            .all(|addr| addr % large_page_size == 0)
+        {
+            FrameObjectType::LargePage
+        } else {
+            FrameObjectType::GRANULE
+        };
+        self.ram.push(GuestRam {
+            ipa: ipa.start..ipa.start,
+            vmm_vaddr,
+            frame_type,
+        });
+        for offset in (0..ipa.len()).step_by(frame_type.bytes()) {
+            let object = res.untyped.allocate(res.cspace, &frame_type.blueprint())?;
+            let object_slot = object.slot();
+            let frame = object.cap::<sel4::cap_type::UnspecifiedPage>(res.cspace);
+            self.objects.push(object);
+            let frame = frame.ok_or(Error::SlotNotDirectlyAddressable)?;
+
+            let slot = res.cspace.allocate()?;
+            self.caller_slots.push(slot);
+            res.cspace
+                .absolute_cptr(slot)
+                .copy(&res.cspace.absolute_cptr(object_slot), CapRights::all())?;
+            let vmm_frame = res
+                .cspace
+                .cap::<sel4::cap_type::UnspecifiedPage>(slot)
+                .ok_or(Error::SlotNotDirectlyAddressable)?;
+
+            self.map_in_guest(
+                res,
+                frame,
+                frame_type,
+                ipa.start + offset,
+                VmAttributes::default(),
+            )?;
+            res.vspace.map_unspecified(
+                &mut UntypedTranslationTableAllocator {
+                    untyped: &mut *res.untyped,
+                    cspace: &mut *res.cspace,
+                },
+                vmm_frame,
+                frame_type,
+                vmm_vaddr + offset,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            )?;
+            // Only frames which have been mapped in both VSpaces are recorded, so that reclamation
+            // only unmaps what exists.
+            self.ram.last_mut().unwrap().ipa.end = ipa.start + offset + frame_type.bytes();
+        }
+        Ok(())
+    }
+
+    /// Passes the device memory at `paddr` through to the guest at `ipa`.
+    pub fn map_device(
+        &mut self,
+        res: &mut VmResources,
+        paddr: usize,
+        ipa: usize,
+        size: usize,
+    ) -> Result<(), Error> {
+        if [paddr, ipa, size]
+            .iter()
+            .any(|addr| addr % GRANULE_SIZE != 0)
+        {
+            return Err(Error::Misaligned);
+        }
+        for offset in (0..size).step_by(GRANULE_SIZE) {
+            let object = res.untyped.allocate_at(
+                res.cspace,
+                &FrameObjectType::GRANULE.blueprint(),
+                paddr + offset,
+            )?;
+            let frame = object.cap::<sel4::cap_type::UnspecifiedPage>(res.cspace);
+            self.objects.push(object);
+            self.map_in_guest(
+                res,
+                frame.ok_or(Error::SlotNotDirectlyAddressable)?,
+                FrameObjectType::GRANULE,
+                ipa + offset,
+                VmAttributes::default() & !VmAttributes::PAGE_CACHEABLE,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Copies `data` into guest RAM at `ipa`, and cleans it to the point of coherency so that it
+    /// is visible to a guest running with its MMU and caches off.
+    pub fn write_guest_ram(&mut self, ipa: usize, data: &[u8]) -> Result<(), Error> {
+        let vaddr = self.ram_vaddr(ipa..(ipa + data.len()))?;
+        unsafe {
+            (vaddr as *mut u8).copy_from_nonoverlapping(data.as_ptr(), data.len());
+        }
+        self.vmm_vspace
+            .vspace_cache_op_range(CacheOp::CleanData, vaddr..(vaddr + data.len()))?;
+        Ok(())
+    }
+
+    pub fn read_guest_ram(&self, ipa: usize, buf: &mut [u8]) -> Result<(), Error> {
+        let vaddr = self.ram_vaddr(ipa..(ipa + buf.len()))?;
+        unsafe {
+            buf.as_mut_ptr()
+                .copy_from_nonoverlapping(vaddr as *const u8, buf.len());
+        }
+        Ok(())
+    }
+
+    fn ram_vaddr(&self, ipa: Range<usize>) -> Result<usize, Error> {
+        self.ram
+            .iter()
+            .find(|ram| ram.ipa.start <= ipa.start && ipa.end <= ram.ipa.end)
+            .map(|ram| ram.vmm_vaddr + (ipa.start - ram.ipa.start))
+            .ok_or(Error::NotGuestRam(ipa))
+    }
+
+    /// Copies `image` and `dtb` into the lowest region of guest RAM.
+    ///
+    /// `dtb` must describe the guest as it will be seen, including its RAM, and can specify a
+    /// command line in `/chosen/bootargs`. An initramfs should be built into the kernel.
+    pub fn load_linux(&mut self, image: &LinuxImage, dtb: &[u8]) -> Result<LinuxBootInfo, Error> {
+        let ram = self
+            .ram
+            .iter()
+            .min_by_key(|ram| ram.ipa.start)
+            .ok_or(Error::GuestRamTooSmall)?
+            .ipa
+            .clone();
+        let info = linux::plan(image, dtb, ram)?;
+        self.write_guest_ram(info.kernel_entry, image.data())?;
+        self.write_guest_ram(info.dtb_addr, dtb)?;
+        Ok(info)
+    }
+
+    /// Starts the VCPU at the kernel's entry point, with its MMU off and interrupts masked, as
+    /// required by the arm64 boot protocol.
+    pub fn boot_linux(&mut self, info: &LinuxBootInfo) -> Result<(), Error> {
+        self.vcpu
+            .vcpu_write_regs(VCpuReg::SCTLR, SCTLR_EL1_MMU_OFF)?;
+        let mut ctx = sel4::UserContext::default();
+        *ctx.pc_mut() = info.kernel_entry as Word;
+        *ctx.gpr_mut(0) = info.dtb_addr as Word;
+        *ctx.spsr_mut() = SPSR_EL1H_MASKED;
+        self.tcb.tcb_write_all_registers(true, &mut ctx)?;
+        Ok(())
+    }
+
+    /// Passes the physical interrupt whose handler is `handler` through to the guest as `virq`.
+    ///
+    /// `handler` must be bound to a notification whose signals carry `badge`. When that
+    /// notification is received, pass its badge to [`Vm::handle_notification`].
+    pub fn route_irq(
+        &mut self,
+        handler: sel4::cap::IrqHandler,
+        badge: Badge,
+        virq: usize,
+    ) -> Result<(), Error> {
+        self.vgic
+            .set_source(virq, IrqSource::Passthrough(handler))?;
+        self.routes.push(IrqRoute { badge, virq });
+        Ok(())
+    }
+
+    /// Injects the interrupts routed with [`Vm::route_irq`] whose badges intersect `badge`.
+    pub fn handle_notification(&mut self, badge: Badge) -> Result<(), Error> {
+        for i in 0..self.routes.len() {
+            let route = self.routes[i];
+            if badge & route.badge != 0 {
+                self.vgic.inject(route.virq)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a fault raised by the guest.
+    pub fn handle_fault(&mut self, fault: &Fault) -> Result<Exit, Error> {
+        match fault {
+            Fault::VmFault(fault) if !fault.is_prefetch() => {
+                self.handle_data_abort(fault.esr(), fault.addr() as usize)
+            }
+            Fault::VCpuFault(fault) => self.handle_vcpu_fault(Esr::from_raw(fault.hsr())),
+            Fault::VGicMaintenance(fault) => {
+                self.vgic
+                    .handle_maintenance(fault.idx().map(|idx| idx as usize))?;
+                Ok(Exit::Resume)
+            }
+            Fault::VPpiEvent(fault) => {
+                let virq = fault.irq() as usize;
+                self.vgic.set_source(virq, IrqSource::Vppi)?;
+                self.vgic.inject(virq)?;
+                Ok(Exit::Resume)
+            }
+            _ => Ok(Exit::Unhandled),
+        }
+    }
+
+    /// Completes an access reported as [`Exit::Mmio`]. `read_value` is ignored for writes.
+    pub fn complete_mmio(&mut self, access: &MmioAccess, read_value: u64) -> Result<(), Error> {
+        let mut ctx = self.tcb.tcb_read_all_registers(false)?;
+        access.complete(&mut ctx, read_value);
+        self.tcb.tcb_write_all_registers(false, &mut ctx)?;
+        Ok(())
+    }
+
+    fn handle_data_abort(&mut self, esr: Esr, addr: usize) -> Result<Exit, Error> {
+        let mut ctx = self.tcb.tcb_read_all_registers(false)?;
+        let Some(access) = MmioAccess::decode(esr, addr, &ctx) else {
+            return Ok(Exit::Unhandled);
+        };
+        if !(self.gic_distributor..(self.gic_distributor + DISTRIBUTOR_SIZE)).contains(&addr) {
+            return Ok(Exit::Mmio(access));
+        }
+        let offset = addr - self.gic_distributor;
+        let read_value = match access.write_value() {
+            Some(value) => {
+                self.vgic.write(offset, access.width(), value)?;
+                0
+            }
+            None => self.vgic.read(offset, access.width()),
+        };
+        access.complete(&mut ctx, read_value);
+        self.tcb.tcb_write_all_registers(false, &mut ctx)?;
+        Ok(Exit::Resume)
+    }
+
+    fn handle_vcpu_fault(&mut self, esr: Esr) -> Result<Exit, Error> {
+        let class = esr.exception_class();
+        if !matches!(class, ExceptionClass::Hvc | ExceptionClass::Smc) {
+            return Ok(Exit::Unhandled);
+        }
+        let mut ctx = self.tcb.tcb_read_all_registers(false)?;
+        let function_id = *ctx.gpr(0);
+        let ret = if psci::is_psci_call(function_id) {
+            match psci::handle(function_id, *ctx.gpr(1)) {
+                psci::Outcome::Return(ret) => ret,
+                psci::Outcome::CpuOff => return Ok(Exit::CpuOff),
+                psci::Outcome::SystemOff => return Ok(Exit::SystemOff),
+                psci::Outcome::SystemReset => return Ok(Exit::SystemReset),
+            }
+        } else {
+            // SMCCC's "unknown function" value.
+            i64::from(psci::return_code::NOT_SUPPORTED) as Word
+        };
+        *ctx.gpr_mut(0) = ret;
+        // The preferred return address of a trapped SMC is the SMC itself, whereas that of an HVC
+        // is the following instruction.
+        if class == ExceptionClass::Smc {
+            mmio::advance_pc(&mut ctx, esr);
+        }
+        self.tcb.tcb_write_all_registers(false, &mut ctx)?;
+        Ok(Exit::Resume)
+    }
+
+    fn map_in_guest(
+        &mut self,
+        res: &mut VmResources,
+        frame: sel4::cap::UnspecifiedPage,
+        frame_type: FrameObjectType,
+        ipa: usize,
+        attrs: VmAttributes,
+    ) -> Result<(), Error> {
+        let untyped = &mut *res.untyped;
+        let cspace = &mut *res.cspace;
+        let objects = &mut self.objects;
+        // Translation tables belong to the VM, so that they are freed along with it.
+        let mut allocator =
+            |ty: sel4::TranslationTableObjectType| -> Result<_, sel4_vspace_manager::Error> {
+                let object = untyped.allocate(cspace, &ty.blueprint())?;
+                let cap = object.cap(cspace);
+                objects.push(object);
+                cap.ok_or(sel4_vspace_manager::Error::Untyped(
+                    sel4_untyped_manager::Error::SlotNotDirectlyAddressable,
+                ))
+            };
+        self.guest_vspace.map_unspecified(
+            &mut allocator,
+            frame,
+            frame_type,
+            ipa,
+            CapRights::all(),
+            attrs,
+        )?;
+        Ok(())
+    }
+
+    /// Destroys the VM and returns all of the objects backing it to `res.untyped`.
+    pub fn reclaim(mut self, res: &mut VmResources) -> Result<(), Error> {
+        for ram in self.ram.drain(..) {
+            for offset in (0..ram.ipa.len()).step_by(ram.frame_type.bytes()) {
+                res.vspace.unmap(ram.vmm_vaddr + offset)?;
+            }
+        }
+        for slot in self.caller_slots.drain(..) {
+            res.cspace.delete_and_free(slot)?;
+        }
+        while let Some(object) = self.objects.pop() {
+            res.untyped.free(res.cspace, object)?;
+        }
+        Ok(())
+    }
+
+    fn allocate_object(
+        &mut self,
+        res: &mut VmResources,
+        blueprint: &sel4::ObjectBlueprint,
+    ) -> Result<sel4::cap::Unspecified, Error> {
+        let object = res.untyped.allocate(res.cspace, blueprint)?;
+        let cap = object.cap(res.cspace);
+        self.objects.push(object);
+        cap.ok_or(Error::SlotNotDirectlyAddressable)
+    }
+
+    fn allocate<T: CapTypeForObjectOfFixedSize>(
+        &mut self,
+        res: &mut VmResources,
+    ) -> Result<sel4::Cap<T>, Error> {
+        Ok(self.allocate_object(res, &T::object_blueprint())?.cast())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    InvalidKernelImage,
+    InvalidDeviceTree,
+    /// Guest RAM is too small to hold the kernel and its device tree.
+    GuestRamTooSmall,
+    NotGuestRam(Range<usize>),
+    InvalidIrq(usize),
+    Misaligned,
+    SlotNotDirectlyAddressable,
+    /// No scheduling context was provided on an MCS configuration.
+    MissingSchedContext,
+    CSpace(sel4_cspace_allocator::Error),
+    Untyped(sel4_untyped_manager::Error),
+    VSpace(sel4_vspace_manager::Error),
+    Sel4(sel4::Error),
+}
+
+impl From<sel4_cspace_allocator::Error> for Error {
+    fn from(err: sel4_cspace_allocator::Error) -> Self {
+        Self::CSpace(err)
+    }
+}
+
+impl From<sel4_untyped_manager::Error> for Error {
+    fn from(err: sel4_untyped_manager::Error) -> Self {
+        Self::Untyped(err)
+    }
+}
+
+impl From<sel4_vspace_manager::Error> for Error {
+    fn from(err: sel4_vspace_manager::Error) -> Self {
+        Self::VSpace(err)
+    }
+}
+
+impl From<sel4::Error> for Error {
+    fn from(err: sel4::Error) -> Self {
+        Self::Sel4(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidKernelImage => write!(f, "invalid Linux kernel image"),
+            Self::InvalidDeviceTree => write!(f, "invalid device tree"),
+            Self::GuestRamTooSmall => write!(f, "guest RAM is too small"),
+            Self::NotGuestRam(range) => {
+                write!(f, "{:#x}..{:#x} is not guest RAM", range.start, range.end)
+            }
+            Self::InvalidIrq(irq) => write!(f, "invalid virtual IRQ {irq}"),
+            Self::Misaligned => write!(f, "misaligned address or size"),
+            Self::SlotNotDirectlyAddressable => write!(f, "slot is not directly addressable"),
+            Self::MissingSchedContext => write!(f, "no scheduling context was provided"),
+            Self::CSpace(err) => write!(f, "{err}"),
+            Self::Untyped(err) => write!(f, "{err}"),
+            Self::VSpace(err) => write!(f, "{err}"),
+            Self::Sel4(err) => write!(f, "seL4 error: {err:?}"),
+        }
+    }
+}
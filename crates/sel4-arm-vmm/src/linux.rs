@@ -0,0 +1,105 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::ops::Range;
+
+use crate::Error;
+
+const MAGIC: u32 = 0x644d_5241; // "ARM\x64"
+const MAGIC_OFFSET: usize = 0x38;
+const HEADER_SIZE: usize = 0x40;
+
+// Used by kernels older than 3.17, whose headers have an image size of 0.
+const DEFAULT_TEXT_OFFSET: usize = 0x8_0000;
+
+const KERNEL_BASE_ALIGN: usize = 2 * 1024 * 1024;
+
+const DTB_MAGIC: u32 = 0xd00d_feed;
+const DTB_MAX_SIZE: usize = 2 * 1024 * 1024;
+
+/// A Linux arm64 kernel `Image`, as described in `Documentation/arch/arm64/booting.rst`.
+#[derive(Debug, Copy, Clone)]
+pub struct LinuxImage<'a> {
+    data: &'a [u8],
+    text_offset: usize,
+    image_size: usize,
+}
+
+impl<'a> LinuxImage<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_SIZE || read_u32(data, MAGIC_OFFSET) != MAGIC {
+            return Err(Error::InvalidKernelImage);
+        }
+        let image_size = read_u64(data, 0x10) as usize;
+        let (text_offset, image_size) = if image_size == 0 {
+            (DEFAULT_TEXT_OFFSET, data.len())
+        } else {
+            (read_u64(data, 0x08) as usize, image_size)
+        };
+        if image_size < data.len() {
+            return Err(Error::InvalidKernelImage);
+        }
+        Ok(Self {
+            data,
+            text_offset,
+            image_size,
+        })
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The offset from a 2 MiB aligned base address at which the image must be placed.
+    pub fn text_offset(&self) -> usize {
+        self.text_offset
+    }
+
+    /// The size of the memory the kernel occupies once running, including its BSS.
+    pub fn image_size(&self) -> usize {
+        self.image_size
+    }
+}
+
+/// Where a kernel and its device tree have been placed in guest RAM.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LinuxBootInfo {
+    pub kernel_entry: usize,
+    pub dtb_addr: usize,
+}
+
+/// Places a kernel at the lowest suitable address in `ram`, followed by its device tree.
+pub(crate) fn plan(
+    image: &LinuxImage,
+    dtb: &[u8],
+    ram: Range<usize>,
+) -> Result<LinuxBootInfo, Error> {
+    if dtb.len() < 8
+        || u32::from_be_bytes(dtb[..4].try_into().unwrap()) != DTB_MAGIC
+        || u32::from_be_bytes(dtb[4..8].try_into().unwrap()) as usize > dtb.len()
+        || dtb.len() > DTB_MAX_SIZE
+    {
+        return Err(Error::InvalidDeviceTree);
+    }
+    let kernel_entry = ram.start.next_multiple_of(KERNEL_BASE_ALIGN) + image.text_offset();
+    // The device tree must not share a 2 MiB region with the kernel.
+    let dtb_addr = (kernel_entry + image.image_size()).next_multiple_of(KERNEL_BASE_ALIGN);
+    if dtb_addr + dtb.len() > ram.end {
+        return Err(Error::GuestRamTooSmall);
+    }
+    Ok(LinuxBootInfo {
+        kernel_entry,
+        dtb_addr,
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..][..4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..][..8].try_into().unwrap())
+}
@@ -0,0 +1,203 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{Esr, UserContext, Word};
+
+// Register number which refers to the zero register in load and store instructions.
+const ZERO_REGISTER: usize = 31;
+
+/// A guest load or store which trapped on an address not backed by guest RAM.
+///
+/// Only accesses for which the hardware provides a full syndrome (single-register loads and stores
+/// without writeback) can be emulated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MmioAccess {
+    esr: Esr,
+    addr: usize,
+    width: usize,
+    write_value: Option<u64>,
+    register: usize,
+    sign_extend: bool,
+    sixty_four_bit_register: bool,
+}
+
+impl MmioAccess {
+    const ISV: Word = 1 << 24;
+    const SAS_SHIFT: u32 = 22;
+    const SSE: Word = 1 << 21;
+    const SRT_SHIFT: u32 = 16;
+    const SF: Word = 1 << 15;
+    const WNR: Word = 1 << 6;
+
+    pub(crate) fn decode(esr: Esr, addr: usize, ctx: &UserContext) -> Option<Self> {
+        let iss = esr.iss();
+        if iss & Self::ISV == 0 {
+            return None;
+        }
+        let width = 1 << ((iss >> Self::SAS_SHIFT) & 0b11);
+        let register = ((iss >> Self::SRT_SHIFT) & 0b1_1111) as usize;
+        let write_value = (iss & Self::WNR != 0).then(|| read_gpr(ctx, register) & mask(width));
+        Some(Self {
+            esr,
+            addr,
+            width,
+            write_value,
+            register,
+            sign_extend: iss & Self::SSE != 0,
+            sixty_four_bit_register: iss & Self::SF != 0,
+        })
+    }
+
+    /// The guest physical address of the access.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// The width of the access in bytes.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.write_value.is_some()
+    }
+
+    /// The value being stored, if this is a write.
+    pub fn write_value(&self) -> Option<u64> {
+        self.write_value
+    }
+
+    /// Completes the access on the guest's behalf, placing `read_value` in the destination register
+    /// if this is a read, and advancing the PC past the trapped instruction.
+    pub(crate) fn complete(&self, ctx: &mut UserContext, read_value: u64) {
+        if !self.is_write() {
+            self.complete_read(ctx, read_value);
+        }
+        advance_pc(ctx, self.esr);
+    }
+
+    // Places the result of a read in the destination register, extended as the trapped
+    // instruction would have.
+    fn complete_read(&self, ctx: &mut UserContext, value: u64) {
+        let mut value = value & mask(self.width);
+        if self.sign_extend && self.width < 8 {
+            let shift = 64 - self.width * 8;
+            value = (((value << shift) as i64) >> shift) as u64;
+        }
+        if !self.sixty_four_bit_register {
+            value &= mask(4);
+        }
+        if self.register != ZERO_REGISTER {
+            *ctx.gpr_mut(self.register) = value;
+        }
+    }
+}
+
+fn read_gpr(ctx: &UserContext, register: usize) -> u64 {
+    if register == ZERO_REGISTER {
+        0
+    } else {
+        *ctx.gpr(register)
+    }
+}
+
+pub(crate) fn mask(width: usize) -> u64 {
+    if width >= 8 {
+        u64::MAX
+    } else {
+        (1 << (width * 8)) - 1
+    }
+}
+
+/// Advances the PC past the instruction described by `esr`.
+pub(crate) fn advance_pc(ctx: &mut UserContext, esr: Esr) {
+    *ctx.pc_mut() += if esr.is_32_bit_instruction() { 4 } else { 2 };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IL: Word = 1 << 25;
+    const ADDR: usize = 0x900_0000;
+
+    // Builds the syndrome of a data abort with a valid instruction syndrome.
+    fn esr(width: usize, register: usize, write: bool) -> Esr {
+        let sas = width.trailing_zeros() as Word;
+        Esr::from_raw(
+            IL | MmioAccess::ISV
+                | (sas << MmioAccess::SAS_SHIFT)
+                | ((register as Word) << MmioAccess::SRT_SHIFT)
+                | if write { MmioAccess::WNR } else { 0 },
+        )
+    }
+
+    #[test]
+    fn without_syndrome() {
+        let ctx = UserContext::default();
+        assert_eq!(MmioAccess::decode(Esr::from_raw(IL), ADDR, &ctx), None);
+    }
+
+    #[test]
+    fn write() {
+        let mut ctx = UserContext::default();
+        *ctx.gpr_mut(3) = 0x1122_3344_5566_7788;
+        let access = MmioAccess::decode(esr(2, 3, true), ADDR, &ctx).unwrap();
+        assert_eq!(access.addr(), ADDR);
+        assert_eq!(access.width(), 2);
+        assert!(access.is_write());
+        assert_eq!(access.write_value(), Some(0x7788));
+    }
+
+    #[test]
+    fn write_of_zero_register() {
+        let mut ctx = UserContext::default();
+        *ctx.pc_mut() = 0x1000;
+        let access = MmioAccess::decode(esr(8, ZERO_REGISTER, true), ADDR, &ctx).unwrap();
+        assert_eq!(access.write_value(), Some(0));
+        access.complete(&mut ctx, 0);
+        assert_eq!(*ctx.pc(), 0x1004);
+    }
+
+    #[test]
+    fn read() {
+        let mut ctx = UserContext::default();
+        *ctx.gpr_mut(5) = u64::MAX;
+        *ctx.pc_mut() = 0x1000;
+        let access = MmioAccess::decode(esr(4, 5, false), ADDR, &ctx).unwrap();
+        assert!(!access.is_write());
+        assert_eq!(access.write_value(), None);
+        access.complete(&mut ctx, 0xaabb_ccdd_eeff_0011);
+        assert_eq!(*ctx.gpr(5), 0xeeff_0011);
+        assert_eq!(*ctx.pc(), 0x1004);
+    }
+
+    #[test]
+    fn sign_extended_read() {
+        let mut ctx = UserContext::default();
+        let iss = MmioAccess::SSE | MmioAccess::SF;
+        let syndrome = Esr::from_raw(esr(1, 0, false).into_raw() | iss);
+        let access = MmioAccess::decode(syndrome, ADDR, &ctx).unwrap();
+        access.complete(&mut ctx, 0x80);
+        assert_eq!(*ctx.gpr(0), 0xffff_ffff_ffff_ff80);
+
+        // Into a 32-bit register.
+        let syndrome = Esr::from_raw(syndrome.into_raw() & !MmioAccess::SF);
+        let access = MmioAccess::decode(syndrome, ADDR, &ctx).unwrap();
+        access.complete(&mut ctx, 0x80);
+        assert_eq!(*ctx.gpr(0), 0xffff_ff80);
+    }
+
+    #[test]
+    fn sixteen_bit_instruction() {
+        let mut ctx = UserContext::default();
+        *ctx.pc_mut() = 0x1000;
+        let syndrome = Esr::from_raw(esr(4, 0, true).into_raw() & !IL);
+        let access = MmioAccess::decode(syndrome, ADDR, &ctx).unwrap();
+        access.complete(&mut ctx, 0);
+        assert_eq!(*ctx.pc(), 0x1002);
+    }
+}
@@ -0,0 +1,216 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Return codes from the Arm Power State Coordination Interface (PSCI).
+//!
+//! PSCI function identifiers are [`SmcFunctionId`]s.
+
+use sel4::{SmcFunctionId, Word};
+
+pub mod return_code {
+    pub const SUCCESS: i32 = 0;
+    pub const NOT_SUPPORTED: i32 = -1;
+    pub const INVALID_PARAMETERS: i32 = -2;
+    pub const DENIED: i32 = -3;
+    pub const ALREADY_ON: i32 = -4;
+}
+
+/// The PSCI version implemented, 1.0.
+pub const VERSION: u32 = 1 << 16;
+
+// MIGRATE_INFO_TYPE value meaning that no Trusted OS requires migration.
+const NO_MIGRATION_REQUIRED: i32 = 2;
+
+// AFFINITY_INFO value meaning that the affinity instance is on.
+const AFFINITY_ON: i32 = 0;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Return(Word),
+    CpuOff,
+    SystemOff,
+    SystemReset,
+}
+
+/// Handles a call on behalf of a guest with a single VCPU, whose `MPIDR` affinity is 0.
+pub(crate) fn handle(function_id: Word, arg: Word) -> Outcome {
+    let ret = |value: i32| Outcome::Return(i64::from(value) as Word);
+    match smc32_variant(function_id) {
+        SmcFunctionId::PSCI_VERSION => Outcome::Return(VERSION.into()),
+        // Treated as a standby request from which the guest immediately wakes.
+        SmcFunctionId::PSCI_CPU_SUSPEND => ret(return_code::SUCCESS),
+        SmcFunctionId::PSCI_CPU_OFF => Outcome::CpuOff,
+        SmcFunctionId::PSCI_CPU_ON => ret(if is_self(arg) {
+            return_code::ALREADY_ON
+        } else {
+            return_code::INVALID_PARAMETERS
+        }),
+        SmcFunctionId::PSCI_AFFINITY_INFO => ret(if is_self(arg) {
+            AFFINITY_ON
+        } else {
+            return_code::INVALID_PARAMETERS
+        }),
+        SmcFunctionId::PSCI_MIGRATE_INFO_TYPE => ret(NO_MIGRATION_REQUIRED),
+        SmcFunctionId::PSCI_SYSTEM_OFF => Outcome::SystemOff,
+        SmcFunctionId::PSCI_SYSTEM_RESET => Outcome::SystemReset,
+        SmcFunctionId::PSCI_FEATURES => ret(if is_supported(arg) {
+            return_code::SUCCESS
+        } else {
+            return_code::NOT_SUPPORTED
+        }),
+        _ => ret(return_code::NOT_SUPPORTED),
+    }
+}
+
+pub(crate) fn is_psci_call(function_id: Word) -> bool {
+    // PSCI occupies the first 0x20 functions of the standard secure service range.
+    smc32_variant(function_id).into_raw() & !0x1f == SmcFunctionId::PSCI_VERSION.into_raw()
+}
+
+// PSCI functions behave the same whether called with the SMC32 or SMC64 convention.
+fn smc32_variant(function_id: Word) -> SmcFunctionId {
+    SmcFunctionId::from_raw(function_id as u32).with_smc64(false)
+}
+
+fn is_self(mpidr: Word) -> bool {
+    // Only the affinity fields are significant.
+    mpidr & 0xff_00ff_ffff == 0
+}
+
+fn is_supported(function_id: Word) -> bool {
+    matches!(
+        smc32_variant(function_id),
+        SmcFunctionId::PSCI_VERSION
+            | SmcFunctionId::PSCI_CPU_SUSPEND
+            | SmcFunctionId::PSCI_CPU_OFF
+            | SmcFunctionId::PSCI_CPU_ON
+            | SmcFunctionId::PSCI_AFFINITY_INFO
+            | SmcFunctionId::PSCI_MIGRATE_INFO_TYPE
+            | SmcFunctionId::PSCI_SYSTEM_OFF
+            | SmcFunctionId::PSCI_SYSTEM_RESET
+            | SmcFunctionId::PSCI_FEATURES
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(function_id: SmcFunctionId, arg: Word) -> Outcome {
+        handle(function_id.into_raw().into(), arg)
+    }
+
+    fn ret(value: i32) -> Outcome {
+        Outcome::Return(i64::from(value) as Word)
+    }
+
+    #[test]
+    fn version() {
+        assert_eq!(
+            call(SmcFunctionId::PSCI_VERSION, 0),
+            Outcome::Return(0x1_0000)
+        );
+    }
+
+    #[test]
+    fn smc64_variants() {
+        for function_id in [
+            SmcFunctionId::PSCI_CPU_ON,
+            SmcFunctionId::PSCI_AFFINITY_INFO,
+        ] {
+            assert_eq!(call(function_id.with_smc64(true), 0), call(function_id, 0));
+        }
+        assert!(is_psci_call(
+            SmcFunctionId::PSCI_CPU_ON
+                .with_smc64(true)
+                .into_raw()
+                .into()
+        ));
+    }
+
+    #[test]
+    fn power() {
+        assert_eq!(call(SmcFunctionId::PSCI_CPU_OFF, 0), Outcome::CpuOff);
+        assert_eq!(call(SmcFunctionId::PSCI_SYSTEM_OFF, 0), Outcome::SystemOff);
+        assert_eq!(
+            call(SmcFunctionId::PSCI_SYSTEM_RESET, 0),
+            Outcome::SystemReset
+        );
+        assert_eq!(
+            call(SmcFunctionId::PSCI_CPU_SUSPEND, 0),
+            ret(return_code::SUCCESS)
+        );
+    }
+
+    #[test]
+    fn other_cpus() {
+        assert_eq!(
+            call(SmcFunctionId::PSCI_CPU_ON, 0),
+            ret(return_code::ALREADY_ON)
+        );
+        assert_eq!(
+            call(SmcFunctionId::PSCI_CPU_ON, 1),
+            ret(return_code::INVALID_PARAMETERS)
+        );
+        assert_eq!(call(SmcFunctionId::PSCI_AFFINITY_INFO, 0), ret(AFFINITY_ON));
+        assert_eq!(
+            call(SmcFunctionId::PSCI_AFFINITY_INFO, 1 << 32),
+            ret(return_code::INVALID_PARAMETERS)
+        );
+        // Bits other than the affinity fields are ignored.
+        assert_eq!(
+            call(SmcFunctionId::PSCI_AFFINITY_INFO, 1 << 31),
+            ret(AFFINITY_ON)
+        );
+    }
+
+    #[test]
+    fn migrate_info_type() {
+        assert_eq!(
+            call(SmcFunctionId::PSCI_MIGRATE_INFO_TYPE, 0),
+            ret(NO_MIGRATION_REQUIRED)
+        );
+    }
+
+    #[test]
+    fn features() {
+        let features = |function_id: SmcFunctionId| {
+            call(SmcFunctionId::PSCI_FEATURES, function_id.into_raw().into())
+        };
+        assert_eq!(
+            features(SmcFunctionId::PSCI_CPU_ON),
+            ret(return_code::SUCCESS)
+        );
+        assert_eq!(
+            features(SmcFunctionId::PSCI_SYSTEM_RESET.with_smc64(true)),
+            ret(return_code::SUCCESS)
+        );
+        assert_eq!(
+            features(SmcFunctionId::from_raw(0x8400_0005)),
+            ret(return_code::NOT_SUPPORTED)
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        let function_id = SmcFunctionId::from_raw(0x8400_0005);
+        assert!(is_psci_call(function_id.into_raw().into()));
+        assert_eq!(call(function_id, 0), ret(return_code::NOT_SUPPORTED));
+    }
+
+    #[test]
+    fn not_psci() {
+        assert!(!is_psci_call(
+            SmcFunctionId::SMCCC_VERSION.into_raw().into()
+        ));
+        assert!(!is_psci_call(
+            SmcFunctionId::PSCI_VERSION.into_raw() as Word + 0x20
+        ));
+        assert!(!is_psci_call(
+            SmcFunctionId::sip(false, 0).into_raw().into()
+        ));
+    }
+}
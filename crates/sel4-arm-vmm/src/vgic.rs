@@ -0,0 +1,543 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::Error;
+
+/// The size of the GICv2 distributor register frame.
+pub const DISTRIBUTOR_SIZE: usize = 0x1000;
+
+/// The number of interrupt IDs reserved for SGIs and PPIs, which are private to each CPU.
+pub const NUM_PRIVATE_IRQS: usize = 32;
+
+const NUM_SGIS: usize = 16;
+
+// Interrupt IDs 1020 and above are reserved.
+const MAX_NUM_IRQS: usize = 1020;
+
+mod offset {
+    use core::ops::Range;
+
+    pub const CTLR: usize = 0x000;
+    pub const TYPER: usize = 0x004;
+    pub const IIDR: usize = 0x008;
+    pub const IGROUPR: Range<usize> = 0x080..0x100;
+    pub const ISENABLER: Range<usize> = 0x100..0x180;
+    pub const ICENABLER: Range<usize> = 0x180..0x200;
+    pub const ISPENDR: Range<usize> = 0x200..0x280;
+    pub const ICPENDR: Range<usize> = 0x280..0x300;
+    pub const ISACTIVER: Range<usize> = 0x300..0x380;
+    pub const ICACTIVER: Range<usize> = 0x380..0x400;
+    pub const IPRIORITYR: Range<usize> = 0x400..0x800;
+    pub const ITARGETSR: Range<usize> = 0x800..0xc00;
+    pub const ICFGR: Range<usize> = 0xc00..0xd00;
+    pub const SGIR: usize = 0xf00;
+    pub const ICPIDR2: usize = 0xfe8;
+}
+
+// Implementer: ARM, revision 0, variant 0, product 0x02.
+const IIDR_VALUE: u32 = 0x0200_043b;
+
+// Architecture revision: GICv2.
+const ICPIDR2_VALUE: u32 = 2 << 4;
+
+/// What must happen when the guest completes an interrupt.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IrqSource {
+    /// The interrupt is raised by the VMM itself, and needs no acknowledgement.
+    Virtual,
+    /// The interrupt is a PPI delivered to the VMM as a `VPpiEvent`, which must be acknowledged
+    /// with `seL4_ARM_VCPU_AckVPPI`.
+    Vppi,
+    /// The interrupt is a physical interrupt passed through to the guest, whose handler must be
+    /// acknowledged.
+    Passthrough(sel4::cap::IrqHandler),
+}
+
+#[derive(Copy, Clone, Debug)]
+struct IrqState {
+    enabled: bool,
+    pending: bool,
+    // Whether the interrupt occupies a list register.
+    active: bool,
+    group: bool,
+    priority: u8,
+    target: u8,
+    config: u8,
+    source: IrqSource,
+}
+
+impl IrqState {
+    const fn new() -> Self {
+        Self {
+            enabled: false,
+            pending: false,
+            active: false,
+            group: false,
+            priority: 0,
+            target: 1,
+            config: 0,
+            source: IrqSource::Virtual,
+        }
+    }
+}
+
+/// An emulated GICv2 distributor for a guest with a single VCPU.
+///
+/// The guest's accesses to the distributor register frame are passed to [`VGic::read`] and
+/// [`VGic::write`]. Interrupts which are both enabled and pending are delivered to the guest
+/// through the VCPU's list registers. The guest accesses the CPU interface directly, through the
+/// platform's virtual CPU interface (`GICV`) frame, which should be mapped at the guest physical
+/// address at which the guest expects to find `GICC`.
+///
+/// When the guest completes an interrupt, the kernel raises a `VGICMaintenance` fault, which must
+/// be passed to [`VGic::handle_maintenance`] to free the list register and acknowledge the
+/// interrupt's [`IrqSource`].
+pub struct VGic {
+    vcpu: sel4::cap::VCpu,
+    enabled: bool,
+    irqs: Vec<IrqState>,
+    list_registers: Vec<Option<usize>>,
+    overflow: VecDeque<usize>,
+}
+
+impl VGic {
+    /// Creates a distributor with `num_irqs` interrupt IDs (rounded up to a multiple of 32) which
+    /// injects interrupts using the first `num_list_registers` list registers of `vcpu`.
+    pub fn new(vcpu: sel4::cap::VCpu, num_irqs: usize, num_list_registers: usize) -> Self {
+        let num_irqs = num_irqs.max(NUM_PRIVATE_IRQS).next_multiple_of(32);
+        assert!(num_irqs <= MAX_NUM_IRQS.next_multiple_of(32));
+        assert!(num_list_registers > 0);
+        let mut irqs = vec![IrqState::new(); num_irqs];
+        // SGIs are always enabled and edge-triggered.
+        for irq in &mut irqs[..NUM_SGIS] {
+            irq.enabled = true;
+            irq.config = 0b10;
+        }
+        Self {
+            vcpu,
+            enabled: false,
+            irqs,
+            list_registers: vec![None; num_list_registers],
+            overflow: VecDeque::new(),
+        }
+    }
+
+    pub fn num_irqs(&self) -> usize {
+        self.irqs.len()
+    }
+
+    /// Sets what must happen when the guest completes `virq`.
+    pub fn set_source(&mut self, virq: usize, source: IrqSource) -> Result<(), Error> {
+        self.irq_mut(virq)?.source = source;
+        Ok(())
+    }
+
+    /// Marks `virq` as pending, delivering it to the guest if possible.
+    pub fn inject(&mut self, virq: usize) -> Result<(), Error> {
+        self.irq_mut(virq)?.pending = true;
+        self.deliver(virq)
+    }
+
+    /// Handles a `VGICMaintenance` fault for the list register `index`.
+    pub fn handle_maintenance(&mut self, index: Option<usize>) -> Result<(), Error> {
+        if let Some(virq) = index.and_then(|i| self.list_registers.get_mut(i)?.take()) {
+            let irq = &mut self.irqs[virq];
+            irq.active = false;
+            match irq.source {
+                IrqSource::Virtual => {}
+                IrqSource::Vppi => self.vcpu.vcpu_ack_vppi(virq as sel4::Word)?,
+                IrqSource::Passthrough(handler) => handler.irq_handler_ack()?,
+            }
+            self.deliver(virq)?;
+        }
+        self.drain_overflow()
+    }
+
+    /// Handles a read of `width` bytes at `offset` within the distributor register frame.
+    pub fn read(&self, offset: usize, width: usize) -> u64 {
+        let word = self.read_word(offset & !0b11);
+        u64::from(word >> ((offset & 0b11) * 8)) & crate::mmio::mask(width)
+    }
+
+    /// Handles a write of `width` bytes at `offset` within the distributor register frame.
+    pub fn write(&mut self, offset: usize, width: usize, value: u64) -> Result<(), Error> {
+        let shift = (offset & 0b11) * 8;
+        let mask = (crate::mmio::mask(width) << shift) as u32;
+        self.write_word(offset & !0b11, (value << shift) as u32 & mask, mask)
+    }
+
+    fn irq_mut(&mut self, virq: usize) -> Result<&mut IrqState, Error> {
+        self.irqs.get_mut(virq).ok_or(Error::InvalidIrq(virq))
+    }
+
+    fn deliver(&mut self, virq: usize) -> Result<(), Error> {
+        let irq = &self.irqs[virq];
+        if !(self.enabled && irq.enabled && irq.pending) || irq.active {
+            return Ok(());
+        }
+        match self.list_registers.iter().position(Option::is_none) {
+            Some(index) => {
+                // List registers only hold the upper 5 bits of the priority.
+                self.vcpu.vcpu_inject_irq(
+                    virq as u16,
+                    irq.priority >> 3,
+                    irq.group.into(),
+                    index as u8,
+                )?;
+                self.list_registers[index] = Some(virq);
+                let irq = &mut self.irqs[virq];
+                irq.pending = false;
+                irq.active = true;
+            }
+            None => {
+                if !self.overflow.contains(&virq) {
+                    self.overflow.push_back(virq);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn drain_overflow(&mut self) -> Result<(), Error> {
+        while self.list_registers.iter().any(Option::is_none) {
+            match self.overflow.pop_front() {
+                Some(virq) => self.deliver(virq)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn deliver_all(&mut self) -> Result<(), Error> {
+        for virq in 0..self.irqs.len() {
+            self.deliver(virq)?;
+        }
+        Ok(())
+    }
+
+    fn read_word(&self, offset: usize) -> u32 {
+        match offset {
+            offset::CTLR => self.enabled.into(),
+            offset::TYPER => (self.irqs.len() / 32 - 1) as u32,
+            offset::IIDR => IIDR_VALUE,
+            offset::ICPIDR2 => ICPIDR2_VALUE,
+            _ if offset::IGROUPR.contains(&offset) => {
+                self.read_bits(offset - offset::IGROUPR.start, |irq| irq.group)
+            }
+            _ if offset::ISENABLER.contains(&offset) => {
+                self.read_bits(offset - offset::ISENABLER.start, |irq| irq.enabled)
+            }
+            _ if offset::ICENABLER.contains(&offset) => {
+                self.read_bits(offset - offset::ICENABLER.start, |irq| irq.enabled)
+            }
+            _ if offset::ISPENDR.contains(&offset) => {
+                self.read_bits(offset - offset::ISPENDR.start, |irq| irq.pending)
+            }
+            _ if offset::ICPENDR.contains(&offset) => {
+                self.read_bits(offset - offset::ICPENDR.start, |irq| irq.pending)
+            }
+            _ if offset::ISACTIVER.contains(&offset) => {
+                self.read_bits(offset - offset::ISACTIVER.start, |irq| irq.active)
+            }
+            _ if offset::ICACTIVER.contains(&offset) => {
+                self.read_bits(offset - offset::ICACTIVER.start, |irq| irq.active)
+            }
+            _ if offset::IPRIORITYR.contains(&offset) => {
+                self.read_bytes(offset - offset::IPRIORITYR.start, |irq| irq.priority)
+            }
+            _ if offset::ITARGETSR.contains(&offset) => {
+                self.read_bytes(offset - offset::ITARGETSR.start, |irq| irq.target)
+            }
+            _ if offset::ICFGR.contains(&offset) => {
+                let first = (offset - offset::ICFGR.start) * 4;
+                (0..16)
+                    .filter_map(|i| Some(u32::from(self.irqs.get(first + i)?.config) << (i * 2)))
+                    .fold(0, |acc, field| acc | field)
+            }
+            _ => 0,
+        }
+    }
+
+    fn write_word(&mut self, offset: usize, value: u32, mask: u32) -> Result<(), Error> {
+        match offset {
+            offset::CTLR => {
+                if mask & 1 != 0 {
+                    self.enabled = value & 1 != 0;
+                    self.deliver_all()?;
+                }
+            }
+            offset::SGIR => {
+                let sgi = (value & 0xf) as usize;
+                let target_list = (value >> 16) & 0xff;
+                let targets_self = match (value >> 24) & 0b11 {
+                    0 => target_list & 1 != 0,
+                    2 => true,
+                    _ => false,
+                };
+                if targets_self {
+                    self.inject(sgi)?;
+                }
+            }
+            _ if offset::IGROUPR.contains(&offset) => {
+                let first = (offset - offset::IGROUPR.start) * 8;
+                self.write_bits(first, mask, |this, irq| {
+                    this.irqs[irq].group = value & (1 << (irq - first)) != 0;
+                    Ok(())
+                })?;
+            }
+            _ if offset::ISENABLER.contains(&offset) => {
+                let first = (offset - offset::ISENABLER.start) * 8;
+                self.write_bits(first, value, |this, irq| {
+                    this.irqs[irq].enabled = true;
+                    this.deliver(irq)
+                })?;
+            }
+            _ if offset::ICENABLER.contains(&offset) => {
+                let first = (offset - offset::ICENABLER.start) * 8;
+                self.write_bits(first, value, |this, irq| {
+                    if irq >= NUM_SGIS {
+                        this.irqs[irq].enabled = false;
+                    }
+                    Ok(())
+                })?;
+            }
+            // The pending state of SGIs can only be changed through GICD_SPENDSGIRn and
+            // GICD_CPENDSGIRn, which are not implemented.
+            _ if offset::ISPENDR.contains(&offset) => {
+                let first = (offset - offset::ISPENDR.start) * 8;
+                self.write_bits(first, value, |this, irq| {
+                    if irq >= NUM_SGIS {
+                        this.inject(irq)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            _ if offset::ICPENDR.contains(&offset) => {
+                let first = (offset - offset::ICPENDR.start) * 8;
+                self.write_bits(first, value, |this, irq| {
+                    if irq >= NUM_SGIS {
+                        this.irqs[irq].pending = false;
+                    }
+                    Ok(())
+                })?;
+            }
+            _ if offset::IPRIORITYR.contains(&offset) => {
+                self.write_bytes(
+                    offset - offset::IPRIORITYR.start,
+                    value,
+                    mask,
+                    |irq, byte| irq.priority = byte,
+                );
+            }
+            _ if offset::ITARGETSR.contains(&offset) => {
+                // The targets of private interrupts are read-only.
+                if offset - offset::ITARGETSR.start >= NUM_PRIVATE_IRQS {
+                    self.write_bytes(
+                        offset - offset::ITARGETSR.start,
+                        value,
+                        mask,
+                        |irq, byte| irq.target = byte,
+                    );
+                }
+            }
+            _ if offset::ICFGR.contains(&offset) => {
+                let first = (offset - offset::ICFGR.start) * 4;
+                for i in 0..16 {
+                    let virq = first + i;
+                    if virq >= NUM_SGIS
+                        && (mask >> (i * 2)) & 0b11 != 0
+                        && let Some(irq) = self.irqs.get_mut(virq)
+                    {
+                        irq.config = ((value >> (i * 2)) & 0b10) as u8;
+                    }
+                }
+            }
+            // Other registers are read-only or unimplemented.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn read_bits(&self, byte_offset: usize, f: impl Fn(&IrqState) -> bool) -> u32 {
+        let first = byte_offset * 8;
+        (0..32)
+            .filter(|i| self.irqs.get(first + i).is_some_and(&f))
+            .fold(0, |acc, i| acc | (1 << i))
+    }
+
+    fn read_bytes(&self, byte_offset: usize, f: impl Fn(&IrqState) -> u8) -> u32 {
+        (0..4)
+            .filter_map(|i| Some(u32::from(f(self.irqs.get(byte_offset + i)?)) << (i * 8)))
+            .fold(0, |acc, byte| acc | byte)
+    }
+
+    fn write_bits(
+        &mut self,
+        first: usize,
+        bits: u32,
+        mut f: impl FnMut(&mut Self, usize) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        for i in 0..32 {
+            let virq = first + i;
+            if bits & (1 << i) != 0 && virq < self.irqs.len() {
+                f(self, virq)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, first: usize, value: u32, mask: u32, f: impl Fn(&mut IrqState, u8)) {
+        for i in 0..4 {
+            if (mask >> (i * 8)) & 0xff != 0
+                && let Some(irq) = self.irqs.get_mut(first + i)
+            {
+                f(irq, (value >> (i * 8)) as u8);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Range;
+
+    use super::*;
+
+    const SPI: usize = 40;
+
+    // Accesses which the distributor handles without injecting interrupts make no syscalls, so a
+    // VCPU capability is not needed while the distributor is disabled.
+    fn vgic() -> VGic {
+        VGic::new(sel4::cap::VCpu::from_bits(0), 64, 4)
+    }
+
+    fn read32(vgic: &VGic, offset: usize) -> u64 {
+        vgic.read(offset, 4)
+    }
+
+    fn write32(vgic: &mut VGic, offset: usize, value: u32) {
+        vgic.write(offset, 4, value.into()).unwrap();
+    }
+
+    fn bit_offset(range: Range<usize>, irq: usize) -> (usize, u32) {
+        (range.start + irq / 32 * 4, 1 << (irq % 32))
+    }
+
+    #[test]
+    fn identification() {
+        let vgic = vgic();
+        assert_eq!(vgic.num_irqs(), 64);
+        assert_eq!(read32(&vgic, offset::TYPER), 1);
+        assert_eq!(read32(&vgic, offset::IIDR), IIDR_VALUE.into());
+        assert_eq!(read32(&vgic, offset::ICPIDR2), ICPIDR2_VALUE.into());
+        assert_eq!(read32(&vgic, offset::CTLR), 0);
+    }
+
+    #[test]
+    fn num_irqs_is_rounded_up() {
+        assert_eq!(
+            VGic::new(sel4::cap::VCpu::from_bits(0), 0, 1).num_irqs(),
+            32
+        );
+        assert_eq!(
+            VGic::new(sel4::cap::VCpu::from_bits(0), 33, 1).num_irqs(),
+            64
+        );
+    }
+
+    #[test]
+    fn sgis_are_always_enabled() {
+        let mut vgic = vgic();
+        assert_eq!(read32(&vgic, offset::ISENABLER.start), 0xffff);
+        write32(&mut vgic, offset::ICENABLER.start, u32::MAX);
+        assert_eq!(read32(&vgic, offset::ISENABLER.start), 0xffff);
+    }
+
+    #[test]
+    fn enable_and_disable() {
+        let mut vgic = vgic();
+        let (offset, bit) = bit_offset(offset::ISENABLER, SPI);
+        write32(&mut vgic, offset, bit);
+        assert_eq!(read32(&vgic, offset), bit.into());
+        let (offset, bit) = bit_offset(offset::ICENABLER, SPI);
+        assert_eq!(read32(&vgic, offset), bit.into());
+        write32(&mut vgic, offset, bit);
+        assert_eq!(read32(&vgic, offset), 0);
+    }
+
+    #[test]
+    fn set_and_clear_pending() {
+        let mut vgic = vgic();
+        let (offset, bit) = bit_offset(offset::ISPENDR, SPI);
+        write32(&mut vgic, offset, bit);
+        assert_eq!(read32(&vgic, offset), bit.into());
+        let (offset, bit) = bit_offset(offset::ICPENDR, SPI);
+        write32(&mut vgic, offset, bit);
+        assert_eq!(read32(&vgic, offset), 0);
+    }
+
+    #[test]
+    fn sgi_pending_bits_ignore_writes() {
+        let mut vgic = vgic();
+        write32(&mut vgic, offset::ISPENDR.start, u32::MAX);
+        assert_eq!(read32(&vgic, offset::ISPENDR.start), 0xffff_0000);
+        vgic.inject(1).unwrap();
+        write32(&mut vgic, offset::ICPENDR.start, u32::MAX);
+        assert_eq!(read32(&vgic, offset::ICPENDR.start), 0b10);
+    }
+
+    #[test]
+    fn inject() {
+        let mut vgic = vgic();
+        vgic.inject(SPI).unwrap();
+        let (offset, bit) = bit_offset(offset::ISPENDR, SPI);
+        assert_eq!(read32(&vgic, offset), bit.into());
+        assert_eq!(vgic.inject(64), Err(Error::InvalidIrq(64)));
+    }
+
+    #[test]
+    fn priority_byte_access() {
+        let mut vgic = vgic();
+        vgic.write(offset::IPRIORITYR.start + SPI + 1, 1, 0xa0)
+            .unwrap();
+        assert_eq!(vgic.read(offset::IPRIORITYR.start + SPI + 1, 1), 0xa0);
+        assert_eq!(read32(&vgic, offset::IPRIORITYR.start + SPI), 0xa000);
+        write32(&mut vgic, offset::IPRIORITYR.start + SPI, 0x4030_2010);
+        assert_eq!(vgic.read(offset::IPRIORITYR.start + SPI + 2, 2), 0x4030);
+    }
+
+    #[test]
+    fn private_targets_are_read_only() {
+        let mut vgic = vgic();
+        write32(&mut vgic, offset::ITARGETSR.start, 0x0202_0202);
+        assert_eq!(read32(&vgic, offset::ITARGETSR.start), 0x0101_0101);
+        write32(&mut vgic, offset::ITARGETSR.start + SPI, 0x0202_0202);
+        assert_eq!(read32(&vgic, offset::ITARGETSR.start + SPI), 0x0202_0202);
+    }
+
+    #[test]
+    fn sgi_configuration_is_read_only() {
+        let mut vgic = vgic();
+        write32(&mut vgic, offset::ICFGR.start, 0);
+        assert_eq!(read32(&vgic, offset::ICFGR.start), 0xaaaa_aaaa);
+        // Only the upper bit of each field is writable.
+        write32(&mut vgic, offset::ICFGR.start + 8, u32::MAX);
+        assert_eq!(read32(&vgic, offset::ICFGR.start + 8), 0xaaaa_aaaa);
+    }
+
+    #[test]
+    fn out_of_range_irqs() {
+        let mut vgic = vgic();
+        let (offset, _) = bit_offset(offset::ISENABLER, 64);
+        write32(&mut vgic, offset, u32::MAX);
+        assert_eq!(read32(&vgic, offset), 0);
+        assert_eq!(read32(&vgic, offset::IPRIORITYR.start + 64), 0);
+    }
+}
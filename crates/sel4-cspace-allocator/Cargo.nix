@@ -0,0 +1,17 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "sel4-cspace-allocator";
+  dependencies = {
+    inherit (localCrates)
+      sel4
+      sel4-abstract-allocator
+    ;
+  };
+}
@@ -0,0 +1,392 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A CSpace slot allocator.
+//!
+//! [`CSpaceAllocator`] manages the free slots of a top-level CNode, such as the root task's initial
+//! CNode, and of any second-level CNodes which it creates from an untyped once the top-level CNode
+//! runs low. Slots can be freed and reused, and contiguous ranges of slots can be reserved for
+//! batched [`untyped_retype`](sel4::cap::Untyped::untyped_retype) invocations.
+//!
+//! Whether the slots of second-level CNodes are directly addressable by a [`CPtr`](sel4::CPtr)
+//! depends on the depth of the top-level CNode. If it resolves all [`WORD_SIZE`](sel4::WORD_SIZE)
+//! bits of a CPtr, as the root task's initial CNode does, then second-level CNodes are reachable
+//! only through [`AbsoluteCPtr`]s rooted at them. Otherwise, second-level CNodes are given guards
+//! which consume the remaining bits, and their slots are directly addressable.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::fmt;
+use core::ops::Range;
+
+use sel4::{AbsoluteCPtr, CNodeCapData, CPtr, CPtrBits, CapRights, CapType, WORD_SIZE};
+use sel4_abstract_allocator::basic::BasicAllocator;
+use sel4_abstract_allocator::{AbstractAllocator, ByRange};
+
+/// A slot managed by a [`CSpaceAllocator`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Slot {
+    node: usize,
+    index: usize,
+}
+
+impl Slot {
+    /// The index of this slot within its CNode.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// A contiguous range of slots within a single CNode managed by a [`CSpaceAllocator`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SlotRange {
+    node: usize,
+    range: Range<usize>,
+}
+
+impl SlotRange {
+    /// The indices of these slots within their CNode.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Slot {
+        assert!(i < self.len());
+        Slot {
+            node: self.node,
+            index: self.range.start + i,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Slot> + '_ {
+        self.range.clone().map(|index| Slot {
+            node: self.node,
+            index,
+        })
+    }
+}
+
+impl From<Slot> for SlotRange {
+    fn from(slot: Slot) -> Self {
+        Self {
+            node: slot.node,
+            range: slot.index..(slot.index + 1),
+        }
+    }
+}
+
+struct Node<A: AbstractAllocator> {
+    // Destination for untyped_retype into this CNode.
+    dst: AbsoluteCPtr,
+    // Slot i is at (slot_root, slot_path_base | i, slot_depth).
+    slot_root: sel4::cap::CNode,
+    slot_path_base: CPtrBits,
+    slot_depth: usize,
+    directly_addressable: bool,
+    first_slot: usize,
+    slots: ByRange<A>,
+}
+
+impl<A: AbstractAllocator> Node<A> {
+    fn allocate(&mut self, n: usize) -> Option<Range<usize>> {
+        let range = self
+            .slots
+            .allocate(Layout::from_size_align(n, 1).unwrap())
+            .ok()?;
+        Some((self.first_slot + range.start)..(self.first_slot + range.end))
+    }
+
+    fn deallocate(&mut self, range: Range<usize>) {
+        self.slots
+            .deallocate((range.start - self.first_slot)..(range.end - self.first_slot))
+    }
+
+    fn path_bits(&self, index: usize) -> CPtrBits {
+        self.slot_path_base | CPtrBits::try_from(index).unwrap()
+    }
+}
+
+/// Allocates slots from a top-level CNode and from second-level CNodes which it creates as
+/// needed.
+///
+/// `A` is the allocator used to track the free slots of each CNode. Slots passed to
+/// [`free`](CSpaceAllocator::free) must be empty.
+pub struct CSpaceAllocator<A: AbstractAllocator = BasicAllocator> {
+    nodes: Vec<Node<A>>,
+    top_depth: usize,
+    second_level_radix_bits: usize,
+    untyped: Option<sel4::cap::Untyped>,
+    // Slots of the top-level CNode which are kept back from allocation, so that growing does not
+    // fail just because the top-level CNode has filled up: one for the capability to the next
+    // second-level CNode and, if second-level CNodes are guarded, one in which to create it
+    // before minting.
+    growth_slot: Option<usize>,
+    tmp_slot: Option<usize>,
+    make_slot_allocator: fn(usize) -> A,
+}
+
+impl CSpaceAllocator {
+    /// Manages the root task's initial CNode, allocating from
+    /// [`BootInfo::empty`](sel4::BootInfo::empty).
+    pub fn from_bootinfo(bootinfo: &sel4::BootInfo) -> Self {
+        Self::new(
+            sel4::init_thread::slot::CNODE.cap(),
+            WORD_SIZE,
+            bootinfo.empty().range(),
+            |num_slots| BasicAllocator::with_granule_size(num_slots, 1),
+        )
+    }
+}
+
+impl<A: AbstractAllocator> CSpaceAllocator<A> {
+    /// Manages `free_slots` of `top`, which must be the root of the current thread's CSpace and
+    /// resolve `top_depth` bits (its guard size plus its radix).
+    ///
+    /// `make_slot_allocator(n)` must return an allocator for the range `0..n`.
+    pub fn new(
+        top: sel4::cap::CNode,
+        top_depth: usize,
+        free_slots: Range<usize>,
+        make_slot_allocator: fn(usize) -> A,
+    ) -> Self {
+        assert!(top_depth <= WORD_SIZE);
+        let top_node = Node {
+            dst: top.absolute_cptr_for_self(),
+            slot_root: top,
+            slot_path_base: 0,
+            slot_depth: top_depth,
+            directly_addressable: true,
+            first_slot: free_slots.start,
+            slots: ByRange::new(make_slot_allocator(free_slots.len())),
+        };
+        Self {
+            nodes: alloc::vec![top_node],
+            top_depth,
+            second_level_radix_bits: 0,
+            untyped: None,
+            growth_slot: None,
+            tmp_slot: None,
+            make_slot_allocator,
+        }
+    }
+
+    /// Enables the creation of second-level CNodes with `2^radix_bits` slots from `untyped` once
+    /// the existing CNodes are exhausted.
+    ///
+    /// Each second-level CNode occupies a slot of the top-level CNode. One such slot (two, if
+    /// second-level CNodes are guarded) is reserved immediately, and so this fails with
+    /// [`Error::OutOfSlots`] if the top-level CNode does not have enough free slots. After each
+    /// second-level CNode is created, and whenever a top-level slot is freed, another slot is
+    /// reserved if one is free.
+    pub fn grow_from(
+        &mut self,
+        untyped: sel4::cap::Untyped,
+        radix_bits: usize,
+    ) -> Result<(), Error> {
+        assert!(radix_bits > 0);
+        assert!(self.top_depth == WORD_SIZE || self.top_depth + radix_bits <= WORD_SIZE);
+        if self.untyped.is_none() {
+            let guarded = self.top_depth != WORD_SIZE;
+            let reserved = self.nodes[0]
+                .allocate(if guarded { 2 } else { 1 })
+                .ok_or(Error::OutOfSlots)?;
+            self.growth_slot = Some(reserved.start);
+            if guarded {
+                self.tmp_slot = Some(reserved.start + 1);
+            }
+        }
+        self.untyped = Some(untyped);
+        self.second_level_radix_bits = radix_bits;
+        Ok(())
+    }
+
+    pub fn allocate(&mut self) -> Result<Slot, Error> {
+        Ok(self.allocate_range(1)?.get(0))
+    }
+
+    /// Allocates `n` contiguous slots within a single CNode.
+    pub fn allocate_range(&mut self, n: usize) -> Result<SlotRange, Error> {
+        if let Some(range) = self.allocate_range_without_growing(n) {
+            return Ok(range);
+        }
+        if self.untyped.is_none() {
+            return Err(Error::OutOfSlots);
+        }
+        if n > 1 << self.second_level_radix_bits {
+            return Err(Error::RangeTooLarge);
+        }
+        let i = self.grow()?;
+        let range = self.nodes[i].allocate(n).unwrap();
+        Ok(SlotRange { node: i, range })
+    }
+
+    fn allocate_range_without_growing(&mut self, n: usize) -> Option<SlotRange> {
+        self.nodes.iter_mut().enumerate().find_map(|(i, node)| {
+            Some(SlotRange {
+                node: i,
+                range: node.allocate(n)?,
+            })
+        })
+    }
+
+    /// Returns `slot` to the pool of free slots. The slot must be empty.
+    pub fn free(&mut self, slot: Slot) {
+        self.free_range(slot.into())
+    }
+
+    /// Returns `range`, which must have been allocated as a whole, to the pool of free slots. The
+    /// slots must be empty.
+    pub fn free_range(&mut self, range: SlotRange) {
+        self.nodes[range.node].deallocate(range.range);
+        if range.node == 0 && self.untyped.is_some() && self.growth_slot.is_none() {
+            self.growth_slot = self.nodes[0].allocate(1).map(|range| range.start);
+        }
+    }
+
+    /// Deletes the capability in `slot` and then frees it.
+    pub fn delete_and_free(&mut self, slot: Slot) -> Result<(), Error> {
+        self.absolute_cptr(slot).delete()?;
+        self.free(slot);
+        Ok(())
+    }
+
+    pub fn absolute_cptr(&self, slot: Slot) -> AbsoluteCPtr {
+        let node = &self.nodes[slot.node];
+        node.slot_root
+            .absolute_cptr_from_bits_with_depth(node.path_bits(slot.index), node.slot_depth)
+    }
+
+    /// Returns the [`CPtr`] for `slot` in the current thread's CSpace, if it is directly
+    /// addressable.
+    pub fn cptr(&self, slot: Slot) -> Option<CPtr> {
+        let node = &self.nodes[slot.node];
+        node.directly_addressable
+            .then(|| CPtr::from_bits(node.path_bits(slot.index) << (WORD_SIZE - node.slot_depth)))
+    }
+
+    pub fn cap<T: CapType>(&self, slot: Slot) -> Option<sel4::Cap<T>> {
+        self.cptr(slot).map(CPtr::cast)
+    }
+
+    /// Returns the `dst` and `dst_offset` arguments for an
+    /// [`untyped_retype`](sel4::cap::Untyped::untyped_retype) into `range`.
+    pub fn retype_dst(&self, range: &SlotRange) -> (AbsoluteCPtr, usize) {
+        (self.nodes[range.node].dst, range.range.start)
+    }
+
+    /// Retypes `untyped` into one object per slot in `range`.
+    pub fn retype(
+        &self,
+        untyped: sel4::cap::Untyped,
+        blueprint: &sel4::ObjectBlueprint,
+        range: &SlotRange,
+    ) -> Result<(), Error> {
+        let (dst, dst_offset) = self.retype_dst(range);
+        untyped.untyped_retype(blueprint, &dst, dst_offset, range.len())?;
+        Ok(())
+    }
+
+    /// The number of CNodes, including the top-level CNode, managed by this allocator.
+    pub fn num_cnodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn grow(&mut self) -> Result<usize, Error> {
+        let untyped = self.untyped.unwrap();
+        let radix_bits = self.second_level_radix_bits;
+        let top_slot = self.growth_slot.ok_or(Error::OutOfSlots)?;
+        let top = self.nodes[0].slot_root;
+        let top_slot_cptr = self.absolute_cptr(Slot {
+            node: 0,
+            index: top_slot,
+        });
+        let blueprint = sel4::ObjectBlueprint::CNode {
+            size_bits: radix_bits,
+        };
+        let node = if self.top_depth == WORD_SIZE {
+            // The new CNode is reachable only through its own capability.
+            untyped.untyped_retype(&blueprint, &self.nodes[0].dst, top_slot, 1)?;
+            let cnode =
+                CPtr::from_bits(top_slot.try_into().unwrap()).cast::<sel4::cap_type::CNode>();
+            Node {
+                dst: cnode.absolute_cptr_for_self(),
+                slot_root: cnode,
+                slot_path_base: 0,
+                slot_depth: radix_bits,
+                directly_addressable: false,
+                first_slot: 0,
+                slots: ByRange::new((self.make_slot_allocator)(1 << radix_bits)),
+            }
+        } else {
+            // Retype into the reserved temporary slot and then mint with a guard which consumes
+            // the bits between the top-level CNode and the new CNode's radix.
+            let tmp = Slot {
+                node: 0,
+                index: self.tmp_slot.unwrap(),
+            };
+            let tmp_cptr = self.absolute_cptr(tmp);
+            let (tmp_dst, tmp_offset) = self.retype_dst(&tmp.into());
+            untyped.untyped_retype(&blueprint, &tmp_dst, tmp_offset, 1)?;
+            let guard_size = WORD_SIZE - self.top_depth - radix_bits;
+            let minted = top_slot_cptr.mint(
+                &tmp_cptr,
+                CapRights::all(),
+                CNodeCapData::skip(guard_size).into_word(),
+            );
+            // The temporary slot stays reserved for the next growth.
+            tmp_cptr.delete()?;
+            minted?;
+            let top_slot_bits = CPtrBits::try_from(top_slot).unwrap();
+            Node {
+                dst: top.absolute_cptr_from_bits_with_depth(top_slot_bits, self.top_depth),
+                slot_root: top,
+                slot_path_base: top_slot_bits << (guard_size + radix_bits),
+                slot_depth: WORD_SIZE,
+                directly_addressable: true,
+                first_slot: 0,
+                slots: ByRange::new((self.make_slot_allocator)(1 << radix_bits)),
+            }
+        };
+        self.nodes.push(node);
+        self.growth_slot = self.nodes[0].allocate(1).map(|range| range.start);
+        Ok(self.nodes.len() - 1)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    OutOfSlots,
+    RangeTooLarge,
+    Sel4(sel4::Error),
+}
+
+impl From<sel4::Error> for Error {
+    fn from(err: sel4::Error) -> Self {
+        Self::Sel4(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfSlots => write!(f, "out of slots"),
+            Self::RangeTooLarge => write!(f, "range larger than a second-level CNode"),
+            Self::Sel4(err) => write!(f, "seL4 error: {err:?}"),
+        }
+    }
+}
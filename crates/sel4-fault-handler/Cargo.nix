@@ -0,0 +1,16 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "sel4-fault-handler";
+  dependencies = {
+    inherit (localCrates) sel4;
+    sel4-linux-syscall-types = localCrates.sel4-linux-syscall-types // { optional = true; };
+    sel4-vspace-manager = localCrates.sel4-vspace-manager // { optional = true; };
+  };
+}
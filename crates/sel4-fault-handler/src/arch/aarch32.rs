@@ -0,0 +1,26 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{UserContext, Word};
+
+const CPSR_THUMB: Word = 1 << 5;
+
+pub(crate) fn syscall_instruction_size(regs: &UserContext) -> Word {
+    // svc, in either the Thumb or Arm instruction set
+    if *regs.cpsr() & CPSR_THUMB != 0 { 2 } else { 4 }
+}
+
+pub(crate) fn linux_syscall_number(regs: &UserContext) -> Word {
+    *regs.gpr(7)
+}
+
+pub(crate) fn linux_syscall_arg(regs: &UserContext, ix: usize) -> Word {
+    *regs.gpr(ix)
+}
+
+pub(crate) fn set_linux_syscall_ret(regs: &mut UserContext, val: Word) {
+    *regs.gpr_mut(0) = val;
+}
@@ -0,0 +1,24 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{UserContext, Word};
+
+pub(crate) fn syscall_instruction_size(_regs: &UserContext) -> Word {
+    // svc
+    4
+}
+
+pub(crate) fn linux_syscall_number(regs: &UserContext) -> Word {
+    *regs.gpr(8)
+}
+
+pub(crate) fn linux_syscall_arg(regs: &UserContext, ix: usize) -> Word {
+    *regs.gpr(ix)
+}
+
+pub(crate) fn set_linux_syscall_ret(regs: &mut UserContext, val: Word) {
+    *regs.gpr_mut(0) = val;
+}
@@ -0,0 +1,35 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+sel4::sel4_cfg_if! {
+    if #[sel4_cfg(ARCH_AARCH64)] {
+        #[path = "aarch64.rs"]
+        mod imp;
+    } else if #[sel4_cfg(ARCH_AARCH32)] {
+        #[path = "aarch32.rs"]
+        mod imp;
+    } else if #[sel4_cfg(ARCH_RISCV)] {
+        #[path = "riscv.rs"]
+        mod imp;
+    } else if #[sel4_cfg(ARCH_X86_64)] {
+        #[path = "x86_64.rs"]
+        mod imp;
+    } else {
+        compile_error!("unsupported architecture");
+    }
+}
+
+// HACK for rustfmt
+#[cfg(false)]
+mod aarch32;
+#[cfg(false)]
+mod aarch64;
+#[cfg(false)]
+mod riscv;
+#[cfg(false)]
+mod x86_64;
+
+pub(crate) use imp::*;
@@ -0,0 +1,33 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{UserContext, Word};
+
+pub(crate) fn syscall_instruction_size(_regs: &UserContext) -> Word {
+    // syscall
+    2
+}
+
+pub(crate) fn linux_syscall_number(regs: &UserContext) -> Word {
+    regs.inner().rax
+}
+
+pub(crate) fn linux_syscall_arg(regs: &UserContext, ix: usize) -> Word {
+    let regs = regs.inner();
+    match ix {
+        0 => regs.rdi,
+        1 => regs.rsi,
+        2 => regs.rdx,
+        3 => regs.r10,
+        4 => regs.r8,
+        5 => regs.r9,
+        _ => panic!(),
+    }
+}
+
+pub(crate) fn set_linux_syscall_ret(regs: &mut UserContext, val: Word) {
+    regs.inner_mut().rax = val;
+}
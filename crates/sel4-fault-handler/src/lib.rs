@@ -0,0 +1,237 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A framework for handling faults of other threads.
+//!
+//! A [`FaultHandler`] receives each fault message delivered to a fault endpoint, along with a
+//! [`FaultingThread`], through which it can inspect and modify the faulting thread's registers
+//! (with `seL4_TCB_ReadRegisters` and `seL4_TCB_WriteRegisters`). It then decides, with an
+//! [`Action`], whether the thread should be resumed or killed. Common cases are supported
+//! directly:
+//!
+//! - restarting the faulting instruction, e.g. after mapping a page in response to a
+//!   [`Fault::VmFault`] (with the `sel4-vspace-manager` feature, [`map_faulting_page`]):
+//!   [`Action::Resume`]
+//! - skipping the faulting instruction: [`FaultingThread::skip_instruction`] and
+//!   [`FaultingThread::skip_syscall`]
+//! - emulating a Linux syscall which caused a [`Fault::UnknownSyscall`]:
+//!   [`FaultingThread::linux_syscall_arg`] and [`FaultingThread::complete_linux_syscall`], or,
+//!   with the `sel4-linux-syscall-types` feature, [`FaultingThread::linux_syscall`]
+//! - suspending the thread: [`Action::Kill`]
+//!
+//! Each thread whose faults are handled must have, as its fault endpoint, a capability to the
+//! endpoint passed to [`run`] or [`handle_next`] with a badge which identifies it to
+//! [`FaultHandler::thread`].
+//!
+//! Only the non-MCS kernel API is supported.
+
+#![no_std]
+
+use sel4::{Badge, Fault, MessageInfo, UserContext, Word, cap};
+
+mod arch;
+
+#[sel4::sel4_cfg(KERNEL_MCS)]
+compile_error!("sel4-fault-handler does not support the MCS kernel API");
+
+/// Handles faults of a set of threads.
+pub trait FaultHandler {
+    /// Returns the TCB of the thread whose fault messages carry `badge`.
+    ///
+    /// Faults of threads for which this returns `None` are left unhandled, which leaves those
+    /// threads blocked.
+    fn thread(&mut self, badge: Badge) -> Option<cap::Tcb>;
+
+    fn handle_fault(&mut self, thread: &mut FaultingThread, fault: &Fault) -> Action;
+}
+
+/// What to do with a faulting thread once its fault has been handled.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Action {
+    /// Resume the thread with any modifications made to its registers through the
+    /// [`FaultingThread`].
+    ///
+    /// Unless its program counter has been modified, the thread restarts the faulting
+    /// instruction.
+    Resume,
+    /// Suspend the thread.
+    Kill,
+}
+
+/// A thread which is blocked on a fault.
+pub struct FaultingThread {
+    badge: Badge,
+    tcb: cap::Tcb,
+    regs: Option<UserContext>,
+    regs_modified: bool,
+}
+
+impl FaultingThread {
+    fn new(badge: Badge, tcb: cap::Tcb) -> Self {
+        Self {
+            badge,
+            tcb,
+            regs: None,
+            regs_modified: false,
+        }
+    }
+
+    pub fn badge(&self) -> Badge {
+        self.badge
+    }
+
+    pub fn tcb(&self) -> cap::Tcb {
+        self.tcb
+    }
+
+    fn regs_inner(&mut self) -> sel4::Result<&mut UserContext> {
+        if self.regs.is_none() {
+            self.regs = Some(self.tcb.tcb_read_all_registers(false)?);
+        }
+        Ok(self.regs.as_mut().unwrap())
+    }
+
+    /// Returns the thread's registers, which are read once and then cached.
+    ///
+    /// The program counter is that of the faulting instruction.
+    pub fn regs(&mut self) -> sel4::Result<&UserContext> {
+        self.regs_inner().map(|regs| &*regs)
+    }
+
+    /// Returns the thread's registers for modification. Modifications are written back to the
+    /// thread when it is resumed.
+    pub fn regs_mut(&mut self) -> sel4::Result<&mut UserContext> {
+        self.regs_modified = true;
+        self.regs_inner()
+    }
+
+    /// Advances the thread's program counter past the faulting instruction, which is `size`
+    /// bytes long.
+    pub fn skip_instruction(&mut self, size: usize) -> sel4::Result<()> {
+        let pc = self.regs_mut()?.pc_mut();
+        *pc = pc.wrapping_add(size.try_into().unwrap());
+        Ok(())
+    }
+
+    /// Advances the thread's program counter past the syscall instruction which caused a
+    /// [`Fault::UnknownSyscall`].
+    pub fn skip_syscall(&mut self) -> sel4::Result<()> {
+        let regs = self.regs_mut()?;
+        let size = arch::syscall_instruction_size(regs);
+        let pc = regs.pc_mut();
+        *pc = pc.wrapping_add(size);
+        Ok(())
+    }
+
+    /// Completes the emulation of a Linux syscall which caused a [`Fault::UnknownSyscall`], by
+    /// setting its return value and skipping the syscall instruction.
+    ///
+    /// The syscall's number and arguments can be obtained with
+    /// [`linux_syscall_number`](Self::linux_syscall_number) and
+    /// [`linux_syscall_arg`](Self::linux_syscall_arg).
+    pub fn complete_linux_syscall(&mut self, ret: Word) -> sel4::Result<()> {
+        arch::set_linux_syscall_ret(self.regs_mut()?, ret);
+        self.skip_syscall()
+    }
+
+    /// Returns the syscall number of the Linux syscall which caused a
+    /// [`Fault::UnknownSyscall`].
+    pub fn linux_syscall_number(&mut self) -> sel4::Result<Word> {
+        Ok(arch::linux_syscall_number(self.regs()?))
+    }
+
+    /// Returns argument `ix` (which must be less than 6) of the Linux syscall which caused a
+    /// [`Fault::UnknownSyscall`].
+    pub fn linux_syscall_arg(&mut self, ix: usize) -> sel4::Result<Word> {
+        assert!(ix < 6);
+        Ok(arch::linux_syscall_arg(self.regs()?, ix))
+    }
+
+    /// Parses the Linux syscall which caused a [`Fault::UnknownSyscall`].
+    #[cfg(feature = "sel4-linux-syscall-types")]
+    pub fn linux_syscall(&mut self) -> sel4::Result<Result<LinuxSyscall, ParseLinuxSyscallError>> {
+        use sel4_linux_syscall_types::{IteratorAsSyscallArgs, SyscallNumber, SyscallWordArg};
+
+        let sysnum = self.linux_syscall_number()? as SyscallNumber;
+        let mut args = [0; 6];
+        for (ix, arg) in args.iter_mut().enumerate() {
+            *arg = self.linux_syscall_arg(ix)? as SyscallWordArg;
+        }
+        Ok(LinuxSyscall::parse(
+            sysnum,
+            IteratorAsSyscallArgs::new(args.into_iter()),
+        ))
+    }
+
+    fn finish(mut self, action: Action) -> sel4::Result<()> {
+        match action {
+            Action::Resume => {
+                if self.regs_modified
+                    && let Some(regs) = self.regs.as_mut()
+                {
+                    self.tcb.tcb_write_all_registers(false, regs)?;
+                }
+                // An empty reply leaves the registers as they are and restarts the thread.
+                sel4::with_ipc_buffer_mut(|ipc_buffer| {
+                    sel4::reply(ipc_buffer, MessageInfo::new(0, 0, 0, 0))
+                });
+            }
+            Action::Kill => {
+                self.tcb.tcb_suspend()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sel4-linux-syscall-types")]
+pub type LinuxSyscall = sel4_linux_syscall_types::Syscall;
+
+#[cfg(feature = "sel4-linux-syscall-types")]
+pub type ParseLinuxSyscallError = sel4_linux_syscall_types::ParseSyscallError<
+    sel4_linux_syscall_types::IteratorAsSyscallArgs<core::array::IntoIter<usize, 6>>,
+>;
+
+/// Maps `frame` in `vspace`, the VSpace of a thread which raised `fault`, so that it covers the
+/// faulting address. The thread can then retry the access by being resumed with
+/// [`Action::Resume`].
+///
+/// Returns the address at which `frame` was mapped.
+#[cfg(feature = "sel4-vspace-manager")]
+pub fn map_faulting_page<T: sel4::CapTypeForFrameObjectOfFixedSize>(
+    vspace: &mut sel4_vspace_manager::VSpaceManager,
+    allocator: &mut impl sel4_vspace_manager::TranslationTableAllocator,
+    fault: &sel4::VmFault,
+    frame: sel4::Cap<T>,
+    rights: sel4::CapRights,
+    attrs: sel4::VmAttributes,
+) -> Result<usize, sel4_vspace_manager::Error> {
+    let addr = fault.addr() as usize;
+    let vaddr = addr - addr % T::FRAME_OBJECT_TYPE.bytes();
+    vspace.map(allocator, frame, vaddr, rights, attrs)?;
+    Ok(vaddr)
+}
+
+/// Receives and handles one fault message from `fault_ep`.
+pub fn handle_next(fault_ep: cap::Endpoint, handler: &mut impl FaultHandler) -> sel4::Result<()> {
+    let (info, badge) = fault_ep.recv(());
+    let fault = sel4::with_ipc_buffer(|ipc_buffer| Fault::new(ipc_buffer, &info));
+    let Some(tcb) = handler.thread(badge) else {
+        return Ok(());
+    };
+    let mut thread = FaultingThread::new(badge, tcb);
+    let action = handler.handle_fault(&mut thread, &fault);
+    thread.finish(action)
+}
+
+/// Handles fault messages from `fault_ep` until an error occurs.
+pub fn run(fault_ep: cap::Endpoint, handler: &mut impl FaultHandler) -> sel4::Error {
+    loop {
+        if let Err(err) = handle_next(fault_ep, handler) {
+            return err;
+        }
+    }
+}
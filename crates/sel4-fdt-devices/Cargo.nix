@@ -0,0 +1,17 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, versions }:
+
+mk {
+  package.name = "sel4-fdt-devices";
+  dependencies = {
+    inherit (versions) fdt;
+    inherit (localCrates)
+      sel4
+    ;
+  };
+}
@@ -0,0 +1,472 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Device discovery for root tasks, driven by the flattened device tree that the kernel passes
+//! along in the [`BootInfo`](sel4::BootInfo) extra region.
+//!
+//! [`DeviceTree`] looks up nodes by `compatible` and translates their `reg` and `interrupts`
+//! properties into physical address ranges and IRQ numbers. [`DeviceMapper`] then finds the device
+//! untyped covering a region, retypes and maps it, and obtains an IRQ handler cap for the device's
+//! interrupt.
+//!
+//! Address translation through parent `ranges` properties is not performed, so `reg` values are
+//! assumed to already be CPU physical addresses. This holds for the simple buses found on the
+//! platforms this project supports.
+
+#![no_std]
+
+use core::fmt;
+use core::ops::Range;
+
+use fdt::Fdt;
+use fdt::node::FdtNode;
+
+mod mapper;
+
+pub use mapper::{DeviceMapper, MappedDevice};
+
+/// `compatible` strings for devices supported by drivers in this project.
+pub mod compatible {
+    /// For `sel4-pl011-driver`.
+    pub const PL011: &[&str] = &["arm,pl011"];
+
+    /// For `sel4-sp804-driver`.
+    pub const SP804: &[&str] = &["arm,sp804"];
+
+    /// For `virtio-drivers`'s MMIO transport.
+    pub const VIRTIO_MMIO: &[&str] = &["virtio,mmio"];
+}
+
+/// A parsed device tree.
+pub struct DeviceTree<'a> {
+    fdt: Fdt<'a>,
+}
+
+impl<'a> DeviceTree<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        Ok(Self {
+            fdt: Fdt::new(bytes).map_err(Error::Fdt)?,
+        })
+    }
+
+    /// Parses the device tree found in `bootinfo`'s extra region.
+    pub fn from_bootinfo(bootinfo: &'a sel4::BootInfoPtr) -> Result<Self, Error> {
+        let extra = bootinfo
+            .extra()
+            .find(|extra| extra.id == sel4::BootInfoExtraId::Fdt)
+            .ok_or(Error::NoDeviceTree)?;
+        Self::new(extra.content())
+    }
+
+    pub fn fdt(&self) -> &Fdt<'a> {
+        &self.fdt
+    }
+
+    /// Returns the first node whose `compatible` property contains any of `compatible`.
+    pub fn find_compatible(&self, compatible: &[&str]) -> Option<Device<'_, 'a>> {
+        self.all_compatible(compatible).next()
+    }
+
+    /// Returns all nodes whose `compatible` property contains any of `compatible`.
+    pub fn all_compatible<'b, 'c>(
+        &'b self,
+        compatible: &'c [&'c str],
+    ) -> impl Iterator<Item = Device<'b, 'a>> + use<'a, 'b, 'c> {
+        self.fdt
+            .all_nodes()
+            .filter(|node| {
+                node.compatible()
+                    .is_some_and(|c| c.all().any(|c| compatible.contains(&c)))
+            })
+            .map(|node| Device { tree: self, node })
+    }
+
+    fn interrupt_parent_of(&self, node: &FdtNode<'_, 'a>) -> Option<FdtNode<'_, 'a>> {
+        let phandle = node
+            .property("interrupt-parent")
+            .or_else(|| self.fdt.find_node("/")?.property("interrupt-parent"))?
+            .as_usize()?;
+        self.fdt.find_phandle(phandle.try_into().ok()?)
+    }
+}
+
+/// A device tree node describing a device.
+#[derive(Copy, Clone)]
+pub struct Device<'b, 'a> {
+    tree: &'b DeviceTree<'a>,
+    node: FdtNode<'b, 'a>,
+}
+
+impl<'b, 'a> Device<'b, 'a> {
+    pub fn node(&self) -> &FdtNode<'b, 'a> {
+        &self.node
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.node.name
+    }
+
+    /// The physical address ranges in this node's `reg` property.
+    pub fn regs(&self) -> impl Iterator<Item = Range<usize>> + use<'a, 'b> {
+        self.node.reg().into_iter().flatten().map(|region| {
+            let start = region.starting_address as usize;
+            start..(start + region.size.unwrap_or(0))
+        })
+    }
+
+    pub fn reg(&self, i: usize) -> Result<Range<usize>, Error> {
+        self.regs().nth(i).ok_or(Error::MissingReg(i))
+    }
+
+    /// The interrupts in this node's `interrupts` property, decoded according to its interrupt
+    /// parent. `interrupts-extended` is not supported.
+    pub fn interrupts(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<Interrupt, Error>> + use<'a, 'b>, Error> {
+        let value = self
+            .node
+            .property("interrupts")
+            .map(|prop| prop.value)
+            .unwrap_or(&[]);
+        let (cells, is_gic) = if value.is_empty() {
+            (1, false)
+        } else {
+            let parent = self
+                .tree
+                .interrupt_parent_of(&self.node)
+                .ok_or(Error::MissingProperty("interrupt-parent"))?;
+            let cells = parent
+                .property("#interrupt-cells")
+                .and_then(|prop| prop.as_usize())
+                .ok_or(Error::MissingProperty("#interrupt-cells"))?;
+            let is_gic = parent
+                .compatible()
+                .is_some_and(|c| c.all().any(|c| GIC_COMPATIBLE.contains(&c)));
+            if cells == 0 {
+                return Err(Error::UnsupportedInterruptSpecifier);
+            }
+            (cells, is_gic)
+        };
+        Ok(value
+            .chunks(cells * CELL_SIZE)
+            .map(move |specifier| Interrupt::decode(is_gic, specifier)))
+    }
+
+    pub fn interrupt(&self, i: usize) -> Result<Interrupt, Error> {
+        self.interrupts()?
+            .nth(i)
+            .ok_or(Error::MissingInterrupt(i))?
+    }
+}
+
+impl fmt::Debug for Device<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Device")
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+const CELL_SIZE: usize = 4;
+
+const GIC_COMPATIBLE: &[&str] = &[
+    "arm,gic-400",
+    "arm,cortex-a15-gic",
+    "arm,cortex-a9-gic",
+    "arm,cortex-a7-gic",
+    "arm,gic-v3",
+];
+
+/// An interrupt, as understood by `seL4_IRQControl_Get*`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Interrupt {
+    pub irq: usize,
+    pub edge_triggered: bool,
+}
+
+impl Interrupt {
+    // GIC interrupt specifiers are of the form <type number flags>, where type 0 is an SPI and type 1
+    // is a PPI. Others are assumed to be of the form <number> or <number flags>.
+    fn decode(is_gic: bool, specifier: &[u8]) -> Result<Self, Error> {
+        if specifier.len() % CELL_SIZE != 0 {
+            return Err(Error::UnsupportedInterruptSpecifier);
+        }
+        let cell = |i: usize| {
+            specifier
+                .get(i * CELL_SIZE..(i + 1) * CELL_SIZE)
+                .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()) as usize)
+        };
+        let (irq, flags) = match (is_gic, specifier.len() / CELL_SIZE) {
+            (true, 3) => {
+                let offset = match cell(0).unwrap() {
+                    0 => GIC_SPI_OFFSET,
+                    1 => GIC_PPI_OFFSET,
+                    _ => return Err(Error::UnsupportedInterruptSpecifier),
+                };
+                (cell(1).unwrap() + offset, cell(2).unwrap())
+            }
+            (false, 1) => (cell(0).unwrap(), 0),
+            (false, 2) => (cell(0).unwrap(), cell(1).unwrap()),
+            _ => return Err(Error::UnsupportedInterruptSpecifier),
+        };
+        Ok(Self {
+            irq,
+            edge_triggered: flags & IRQ_TYPE_EDGE_BOTH != 0,
+        })
+    }
+}
+
+const GIC_SPI_OFFSET: usize = 32;
+const GIC_PPI_OFFSET: usize = 16;
+
+const IRQ_TYPE_EDGE_BOTH: usize = 0b11;
+
+#[derive(Debug)]
+pub enum Error {
+    NoDeviceTree,
+    Fdt(fdt::FdtError),
+    NoSuchDevice,
+    MissingProperty(&'static str),
+    MissingReg(usize),
+    MissingInterrupt(usize),
+    UnsupportedInterruptSpecifier,
+    NoDeviceUntyped(Range<usize>),
+    DeviceUntypedAlreadyUsed(Range<usize>),
+    OutOfSlots,
+    Sel4(sel4::Error),
+}
+
+impl From<sel4::Error> for Error {
+    fn from(err: sel4::Error) -> Self {
+        Self::Sel4(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoDeviceTree => write!(f, "no device tree in bootinfo"),
+            Self::Fdt(err) => write!(f, "failed to parse device tree: {err:?}"),
+            Self::NoSuchDevice => write!(f, "no compatible device"),
+            Self::MissingProperty(name) => write!(f, "missing property: {name}"),
+            Self::MissingReg(i) => write!(f, "missing reg entry {i}"),
+            Self::MissingInterrupt(i) => write!(f, "missing interrupt {i}"),
+            Self::UnsupportedInterruptSpecifier => write!(f, "unsupported interrupt specifier"),
+            Self::NoDeviceUntyped(region) => {
+                write!(f, "no device untyped covers {region:#x?}")
+            }
+            Self::DeviceUntypedAlreadyUsed(region) => {
+                write!(f, "device untyped already used beyond {region:#x?}")
+            }
+            Self::OutOfSlots => write!(f, "out of slots"),
+            Self::Sel4(err) => write!(f, "seL4 error: {err:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+
+    const FDT_BEGIN_NODE: u32 = 1;
+    const FDT_END_NODE: u32 = 2;
+    const FDT_PROP: u32 = 3;
+    const FDT_END: u32 = 9;
+
+    /// Builds a minimal flattened device tree.
+    #[derive(Default)]
+    struct FdtBuilder {
+        structs: Vec<u8>,
+        strings: Vec<u8>,
+    }
+
+    impl FdtBuilder {
+        fn token(&mut self, token: u32) {
+            self.structs.extend_from_slice(&token.to_be_bytes());
+        }
+
+        fn pad(&mut self) {
+            self.structs
+                .resize(self.structs.len().next_multiple_of(4), 0);
+        }
+
+        fn begin_node(&mut self, name: &str) -> &mut Self {
+            self.token(FDT_BEGIN_NODE);
+            self.structs.extend_from_slice(name.as_bytes());
+            self.structs.push(0);
+            self.pad();
+            self
+        }
+
+        fn end_node(&mut self) -> &mut Self {
+            self.token(FDT_END_NODE);
+            self
+        }
+
+        fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+            let name_offset = u32::try_from(self.strings.len()).unwrap();
+            self.strings.extend_from_slice(name.as_bytes());
+            self.strings.push(0);
+            self.token(FDT_PROP);
+            self.token(value.len().try_into().unwrap());
+            self.token(name_offset);
+            self.structs.extend_from_slice(value);
+            self.pad();
+            self
+        }
+
+        fn prop_cells(&mut self, name: &str, cells: &[u32]) -> &mut Self {
+            let value = cells
+                .iter()
+                .flat_map(|cell| cell.to_be_bytes())
+                .collect::<Vec<_>>();
+            self.prop(name, &value)
+        }
+
+        fn build(&mut self) -> Vec<u8> {
+            self.token(FDT_END);
+            let header_size = 40;
+            let mem_rsvmap_size = 16;
+            let off_dt_struct = header_size + mem_rsvmap_size;
+            let off_dt_strings = off_dt_struct + self.structs.len();
+            let total_size = off_dt_strings + self.strings.len();
+            let mut blob = Vec::new();
+            for field in [
+                0xd00d_feed,
+                total_size,
+                off_dt_struct,
+                off_dt_strings,
+                header_size,
+                17,
+                16,
+                0,
+                self.strings.len(),
+                self.structs.len(),
+            ] {
+                blob.extend_from_slice(&u32::try_from(field).unwrap().to_be_bytes());
+            }
+            blob.resize(off_dt_struct, 0);
+            blob.extend_from_slice(&self.structs);
+            blob.extend_from_slice(&self.strings);
+            blob
+        }
+    }
+
+    fn example_fdt() -> Vec<u8> {
+        FdtBuilder::default()
+            .begin_node("")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[1])
+            .prop_cells("interrupt-parent", &[1])
+            .begin_node("intc@8000000")
+            .prop("compatible", b"arm,cortex-a15-gic\0")
+            .prop_cells("#interrupt-cells", &[3])
+            .prop("interrupt-controller", &[])
+            .prop_cells("phandle", &[1])
+            .end_node()
+            .begin_node("pl011@9000000")
+            .prop("compatible", b"arm,pl011\0arm,primecell\0")
+            .prop_cells("reg", &[0x900_0000, 0x1000, 0x900_2000, 0x10])
+            .prop_cells("interrupts", &[0, 1, 4, 1, 14, 1])
+            .end_node()
+            .begin_node("timer@9010000")
+            .prop("compatible", b"arm,sp804\0")
+            .prop_cells("reg", &[0x901_0000, 0x1000])
+            .end_node()
+            .end_node()
+            .build()
+    }
+
+    #[test]
+    fn regs_and_interrupts() {
+        let blob = example_fdt();
+        let tree = DeviceTree::new(&blob).unwrap();
+
+        let uart = tree.find_compatible(compatible::PL011).unwrap();
+        assert_eq!(uart.name(), "pl011@9000000");
+        assert_eq!(uart.reg(0).unwrap(), 0x900_0000..0x900_1000);
+        assert_eq!(uart.reg(1).unwrap(), 0x900_2000..0x900_2010);
+        assert!(matches!(uart.reg(2), Err(Error::MissingReg(2))));
+        assert_eq!(
+            uart.interrupt(0).unwrap(),
+            Interrupt {
+                irq: 33,
+                edge_triggered: false,
+            }
+        );
+        assert_eq!(
+            uart.interrupt(1).unwrap(),
+            Interrupt {
+                irq: 30,
+                edge_triggered: true,
+            }
+        );
+        assert!(matches!(uart.interrupt(2), Err(Error::MissingInterrupt(2))));
+
+        let timer = tree.find_compatible(compatible::SP804).unwrap();
+        assert_eq!(timer.reg(0).unwrap(), 0x901_0000..0x901_1000);
+        assert!(timer.interrupts().unwrap().next().is_none());
+
+        assert_eq!(tree.all_compatible(&["arm,pl011", "arm,sp804"]).count(), 2);
+        assert!(tree.find_compatible(compatible::VIRTIO_MMIO).is_none());
+    }
+
+    fn cells(cells: &[u32]) -> Vec<u8> {
+        cells.iter().flat_map(|cell| cell.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn decode_interrupt() {
+        assert_eq!(
+            Interrupt::decode(true, &cells(&[0, 10, 1])).unwrap(),
+            Interrupt {
+                irq: 42,
+                edge_triggered: true,
+            }
+        );
+        assert_eq!(
+            Interrupt::decode(true, &cells(&[1, 11, 8])).unwrap(),
+            Interrupt {
+                irq: 27,
+                edge_triggered: false,
+            }
+        );
+        assert!(matches!(
+            Interrupt::decode(true, &cells(&[2, 0, 0])),
+            Err(Error::UnsupportedInterruptSpecifier)
+        ));
+        assert!(matches!(
+            Interrupt::decode(true, &cells(&[0, 10])),
+            Err(Error::UnsupportedInterruptSpecifier)
+        ));
+        assert_eq!(
+            Interrupt::decode(false, &cells(&[7])).unwrap(),
+            Interrupt {
+                irq: 7,
+                edge_triggered: false,
+            }
+        );
+        assert_eq!(
+            Interrupt::decode(false, &cells(&[7, 2])).unwrap(),
+            Interrupt {
+                irq: 7,
+                edge_triggered: true,
+            }
+        );
+        assert!(matches!(
+            Interrupt::decode(false, &cells(&[7, 2, 0])),
+            Err(Error::UnsupportedInterruptSpecifier)
+        ));
+        assert!(matches!(
+            Interrupt::decode(false, &[0, 0, 7]),
+            Err(Error::UnsupportedInterruptSpecifier)
+        ));
+    }
+}
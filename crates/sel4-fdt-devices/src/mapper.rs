@@ -0,0 +1,220 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::ops::Range;
+
+use sel4::CapTypeForObjectOfFixedSize;
+use sel4::init_thread::{self, Slot};
+
+use crate::{Device, Error, Interrupt};
+
+const GRANULE_SIZE: usize = sel4::FrameObjectType::GRANULE.bytes();
+
+const MAX_NUM_UNTYPEDS: usize = sel4::sel4_cfg_usize!(MAX_NUM_BOOTINFO_UNTYPED_CAPS);
+
+sel4::sel4_cfg_if! {
+    if #[sel4_cfg(any(ARCH_ARM, ARCH_RISCV))] {
+        const DEVICE_VM_ATTRIBUTES: sel4::VmAttributes =
+            sel4::VmAttributes::EXECUTE_NEVER;
+    } else {
+        const DEVICE_VM_ATTRIBUTES: sel4::VmAttributes = sel4::VmAttributes::CACHE_DISABLED;
+    }
+}
+
+/// A device whose first MMIO region has been mapped and whose first interrupt, if any, has been
+/// claimed.
+#[derive(Debug)]
+pub struct MappedDevice {
+    /// Points to the start of the device's `reg` region, which need not be page-aligned.
+    pub mmio: *mut (),
+    pub irq_handler: Option<sel4::cap::IrqHandler>,
+    pub interrupt: Option<Interrupt>,
+}
+
+/// Retypes and maps device regions and obtains IRQ handler caps in the root task's CSpace and
+/// VSpace.
+///
+/// Regions are carved out of the device untypeds in
+/// [`BootInfo::untyped_list`](sel4::BootInfo::untyped_list). Because the kernel allocates from an untyped in increasing
+/// address order, regions within the same device untyped must be mapped in increasing address
+/// order. This type assumes that it has exclusive use of the device untypeds from which it carves,
+/// and the resulting frame caps must not be deleted.
+pub struct DeviceMapper<'a> {
+    bootinfo: &'a sel4::BootInfo,
+    empty_slots: Range<usize>,
+    watermarks: [usize; MAX_NUM_UNTYPEDS],
+    hold_slots: Option<[Slot; 2]>,
+}
+
+impl<'a> DeviceMapper<'a> {
+    /// Slots for new caps are taken from `empty_slots`, which must be a subset of
+    /// [`BootInfo::empty`](sel4::BootInfo::empty).
+    pub fn new(bootinfo: &'a sel4::BootInfo, empty_slots: Range<usize>) -> Self {
+        Self {
+            bootinfo,
+            empty_slots,
+            watermarks: [0; MAX_NUM_UNTYPEDS],
+            hold_slots: None,
+        }
+    }
+
+    /// The slots which have not yet been used.
+    pub fn remaining_slots(&self) -> Range<usize> {
+        self.empty_slots.clone()
+    }
+
+    /// Maps `device`'s first `reg` region at `vaddr` and obtains an IRQ handler cap for its first
+    /// interrupt, if it has one.
+    ///
+    /// See [`DeviceMapper::map_region`] for requirements on `vaddr`.
+    pub fn bring_up(&mut self, device: &Device, vaddr: usize) -> Result<MappedDevice, Error> {
+        let mmio = self.map_region(device.reg(0)?, vaddr)?;
+        let interrupt = device.interrupts()?.next().transpose()?;
+        let irq_handler = interrupt
+            .map(|interrupt| self.get_irq_handler(interrupt))
+            .transpose()?;
+        Ok(MappedDevice {
+            mmio,
+            irq_handler,
+            interrupt,
+        })
+    }
+
+    /// Retypes frames covering `region` from the device untyped which contains it, and maps them
+    /// contiguously starting at `vaddr`.
+    ///
+    /// `vaddr` must be page-aligned, and translation structures for the frames must already be
+    /// present in the initial thread's VSpace. Returns a pointer corresponding to `region.start`.
+    pub fn map_region(&mut self, region: Range<usize>, vaddr: usize) -> Result<*mut (), Error> {
+        assert_eq!(vaddr % GRANULE_SIZE, 0);
+        let pages = region.start - region.start % GRANULE_SIZE
+            ..region
+                .end
+                .max(region.start + 1)
+                .next_multiple_of(GRANULE_SIZE);
+        let num_pages = pages.len() / GRANULE_SIZE;
+
+        let (ut_ix, ut_desc) = self
+            .bootinfo
+            .untyped_list()
+            .iter()
+            .enumerate()
+            .find(|(_i, desc)| {
+                let ut_start = desc.paddr();
+                let ut_end = ut_start + (1 << desc.size_bits());
+                desc.is_device() && ut_start <= pages.start && pages.end <= ut_end
+            })
+            .ok_or_else(|| Error::NoDeviceUntyped(region.clone()))?;
+        let ut = self.bootinfo.untyped().index(ut_ix).cap();
+
+        let mut cur_paddr = ut_desc.paddr() + self.watermarks[ut_ix];
+        if cur_paddr > pages.start {
+            return Err(Error::DeviceUntypedAlreadyUsed(region));
+        }
+
+        let first_frame_slot = self.alloc_slots(num_pages)?.start;
+        let [hold_slot, scratch_slot] = self.hold_slots()?;
+        let hold = cnode_absolute_cptr(hold_slot);
+        let scratch = cnode_absolute_cptr(scratch_slot);
+
+        // Allocate dummies to advance the untyped's watermark to the target region. At least one
+        // of them must always be retained, or else the kernel will reset the watermark.
+        let mut holding = false;
+        while cur_paddr < pages.start {
+            let size_bits = usize::try_from(cur_paddr.trailing_zeros())
+                .unwrap()
+                .min((pages.start - cur_paddr).ilog2().try_into().unwrap());
+            ut.untyped_retype(
+                &sel4::ObjectBlueprint::Untyped { size_bits },
+                &init_thread_cnode_absolute_cptr(),
+                scratch_slot.index(),
+                1,
+            )?;
+            if holding {
+                hold.delete()?;
+            }
+            hold.move_(&scratch)?;
+            holding = true;
+            cur_paddr += 1 << size_bits;
+        }
+
+        ut.untyped_retype(
+            &sel4::cap_type::Granule::object_blueprint(),
+            &init_thread_cnode_absolute_cptr(),
+            first_frame_slot,
+            num_pages,
+        )?;
+
+        if holding {
+            hold.delete()?;
+        }
+
+        self.watermarks[ut_ix] = pages.end - ut_desc.paddr();
+
+        for i in 0..num_pages {
+            let frame = Slot::from_index(first_frame_slot + i)
+                .downcast::<sel4::cap_type::Granule>()
+                .cap();
+            assert_eq!(frame.frame_get_address()?, pages.start + i * GRANULE_SIZE);
+            frame.frame_map(
+                init_thread::slot::VSPACE.cap(),
+                vaddr + i * GRANULE_SIZE,
+                sel4::CapRights::read_write(),
+                DEVICE_VM_ATTRIBUTES,
+            )?;
+        }
+
+        Ok((vaddr + region.start % GRANULE_SIZE) as *mut ())
+    }
+
+    /// Obtains an IRQ handler cap for `interrupt`.
+    pub fn get_irq_handler(
+        &mut self,
+        interrupt: Interrupt,
+    ) -> Result<sel4::cap::IrqHandler, Error> {
+        let slot =
+            Slot::from_index(self.alloc_slots(1)?.start).downcast::<sel4::cap_type::IrqHandler>();
+        let irq = interrupt.irq.try_into().unwrap();
+        let dst = cnode_absolute_cptr(slot.upcast());
+        let irq_control = init_thread::slot::IRQ_CONTROL.cap();
+        sel4::sel4_cfg_if! {
+            if #[sel4_cfg(any(ARCH_ARM, ARCH_RISCV))] {
+                irq_control.irq_control_get_trigger(irq, interrupt.edge_triggered, &dst)?;
+            } else {
+                irq_control.irq_control_get(irq, &dst)?;
+            }
+        }
+        Ok(slot.cap())
+    }
+
+    fn alloc_slots(&mut self, n: usize) -> Result<Range<usize>, Error> {
+        if self.empty_slots.len() < n {
+            return Err(Error::OutOfSlots);
+        }
+        let start = self.empty_slots.start;
+        self.empty_slots.start += n;
+        Ok(start..self.empty_slots.start)
+    }
+
+    fn hold_slots(&mut self) -> Result<[Slot; 2], Error> {
+        if self.hold_slots.is_none() {
+            let slots = self.alloc_slots(2)?;
+            self.hold_slots = Some([
+                Slot::from_index(slots.start),
+                Slot::from_index(slots.start + 1),
+            ]);
+        }
+        Ok(self.hold_slots.unwrap())
+    }
+}
+
+fn init_thread_cnode_absolute_cptr() -> sel4::AbsoluteCPtr {
+    init_thread::slot::CNODE.cap().absolute_cptr_for_self()
+}
+
+fn cnode_absolute_cptr(slot: Slot) -> sel4::AbsoluteCPtr {
+    init_thread::slot::CNODE.cap().absolute_cptr(slot.cptr())
+}
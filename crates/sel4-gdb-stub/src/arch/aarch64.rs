@@ -0,0 +1,42 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{UserContext, Word};
+
+// x0-x30, sp, pc, cpsr
+pub(crate) const REG_SIZES: [usize; 34] = {
+    let mut sizes = [8; 34];
+    sizes[33] = 4;
+    sizes
+};
+
+pub(crate) fn breakpoint_insn(kind: usize) -> Option<&'static [u8]> {
+    match kind {
+        // brk #0
+        4 => Some(&[0x00, 0x00, 0x20, 0xd4]),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_reg(ctx: &UserContext, ix: usize) -> Word {
+    match ix {
+        0..=30 => *ctx.gpr(ix),
+        31 => *ctx.sp(),
+        32 => *ctx.pc(),
+        33 => *ctx.spsr(),
+        _ => unreachable!(),
+    }
+}
+
+pub(crate) fn set_reg(ctx: &mut UserContext, ix: usize, val: Word) {
+    match ix {
+        0..=30 => *ctx.gpr_mut(ix) = val,
+        31 => *ctx.sp_mut() = val,
+        32 => *ctx.pc_mut() = val,
+        33 => *ctx.spsr_mut() = val,
+        _ => unreachable!(),
+    }
+}
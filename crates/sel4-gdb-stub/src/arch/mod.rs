@@ -0,0 +1,32 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// Registers are numbered and sized as in GDB's default register layout for each architecture.
+
+sel4::sel4_cfg_if! {
+    if #[sel4_cfg(ARCH_AARCH64)] {
+        #[path = "aarch64.rs"]
+        mod imp;
+    } else if #[sel4_cfg(ARCH_RISCV64)] {
+        #[path = "riscv64.rs"]
+        mod imp;
+    } else if #[sel4_cfg(ARCH_X86_64)] {
+        #[path = "x86_64.rs"]
+        mod imp;
+    } else {
+        compile_error!("unsupported architecture");
+    }
+}
+
+// HACK for rustfmt
+#[cfg(false)]
+mod aarch64;
+#[cfg(false)]
+mod riscv64;
+#[cfg(false)]
+mod x86_64;
+
+pub(crate) use imp::*;
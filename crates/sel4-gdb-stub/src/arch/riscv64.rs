@@ -0,0 +1,79 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{UserContext, Word};
+
+// x0-x31, pc
+pub(crate) const REG_SIZES: [usize; 33] = [8; 33];
+
+pub(crate) fn breakpoint_insn(kind: usize) -> Option<&'static [u8]> {
+    match kind {
+        // c.ebreak
+        2 => Some(&[0x02, 0x90]),
+        // ebreak
+        4 => Some(&[0x73, 0x00, 0x10, 0x00]),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_reg(ctx: &UserContext, ix: usize) -> Word {
+    if ix == 0 {
+        return 0;
+    }
+    *reg(ctx.inner(), ix)
+}
+
+pub(crate) fn set_reg(ctx: &mut UserContext, ix: usize, val: Word) {
+    // x0 is hardwired to zero.
+    if ix != 0 {
+        *reg_mut(ctx.inner_mut(), ix) = val;
+    }
+}
+
+macro_rules! reg_accessor {
+    ($name:ident, $($ref_kw:tt)*) => {
+        fn $name(ctx: $($ref_kw)* sel4::sys::seL4_UserContext, ix: usize) -> $($ref_kw)* Word {
+            match ix {
+                1 => $($ref_kw)* ctx.ra,
+                2 => $($ref_kw)* ctx.sp,
+                3 => $($ref_kw)* ctx.gp,
+                4 => $($ref_kw)* ctx.tp,
+                5 => $($ref_kw)* ctx.t0,
+                6 => $($ref_kw)* ctx.t1,
+                7 => $($ref_kw)* ctx.t2,
+                8 => $($ref_kw)* ctx.s0,
+                9 => $($ref_kw)* ctx.s1,
+                10 => $($ref_kw)* ctx.a0,
+                11 => $($ref_kw)* ctx.a1,
+                12 => $($ref_kw)* ctx.a2,
+                13 => $($ref_kw)* ctx.a3,
+                14 => $($ref_kw)* ctx.a4,
+                15 => $($ref_kw)* ctx.a5,
+                16 => $($ref_kw)* ctx.a6,
+                17 => $($ref_kw)* ctx.a7,
+                18 => $($ref_kw)* ctx.s2,
+                19 => $($ref_kw)* ctx.s3,
+                20 => $($ref_kw)* ctx.s4,
+                21 => $($ref_kw)* ctx.s5,
+                22 => $($ref_kw)* ctx.s6,
+                23 => $($ref_kw)* ctx.s7,
+                24 => $($ref_kw)* ctx.s8,
+                25 => $($ref_kw)* ctx.s9,
+                26 => $($ref_kw)* ctx.s10,
+                27 => $($ref_kw)* ctx.s11,
+                28 => $($ref_kw)* ctx.t3,
+                29 => $($ref_kw)* ctx.t4,
+                30 => $($ref_kw)* ctx.t5,
+                31 => $($ref_kw)* ctx.t6,
+                32 => $($ref_kw)* ctx.pc,
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+reg_accessor!(reg, &);
+reg_accessor!(reg_mut, &mut);
@@ -0,0 +1,65 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{UserContext, Word};
+
+// rax, rbx, rcx, rdx, rsi, rdi, rbp, rsp, r8-r15, rip, eflags, cs, ss, ds, es, fs, gs
+pub(crate) const REG_SIZES: [usize; 24] = [
+    8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 4, 4, 4, 4, 4, 4, 4,
+];
+
+pub(crate) fn breakpoint_insn(kind: usize) -> Option<&'static [u8]> {
+    match kind {
+        // int3
+        1 => Some(&[0xcc]),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_reg(ctx: &UserContext, ix: usize) -> Word {
+    match ix {
+        0..=17 => *reg(ctx.inner(), ix),
+        // Segment registers are not part of the thread's context.
+        _ => 0,
+    }
+}
+
+pub(crate) fn set_reg(ctx: &mut UserContext, ix: usize, val: Word) {
+    if let 0..=17 = ix {
+        *reg_mut(ctx.inner_mut(), ix) = val;
+    }
+}
+
+macro_rules! reg_accessor {
+    ($name:ident, $($ref_kw:tt)*) => {
+        fn $name(ctx: $($ref_kw)* sel4::sys::seL4_UserContext, ix: usize) -> $($ref_kw)* Word {
+            match ix {
+                0 => $($ref_kw)* ctx.rax,
+                1 => $($ref_kw)* ctx.rbx,
+                2 => $($ref_kw)* ctx.rcx,
+                3 => $($ref_kw)* ctx.rdx,
+                4 => $($ref_kw)* ctx.rsi,
+                5 => $($ref_kw)* ctx.rdi,
+                6 => $($ref_kw)* ctx.rbp,
+                7 => $($ref_kw)* ctx.rsp,
+                8 => $($ref_kw)* ctx.r8,
+                9 => $($ref_kw)* ctx.r9,
+                10 => $($ref_kw)* ctx.r10,
+                11 => $($ref_kw)* ctx.r11,
+                12 => $($ref_kw)* ctx.r12,
+                13 => $($ref_kw)* ctx.r13,
+                14 => $($ref_kw)* ctx.r14,
+                15 => $($ref_kw)* ctx.r15,
+                16 => $($ref_kw)* ctx.rip,
+                17 => $($ref_kw)* ctx.rflags,
+                _ => unreachable!(),
+            }
+        }
+    };
+}
+
+reg_accessor!(reg, &);
+reg_accessor!(reg_mut, &mut);
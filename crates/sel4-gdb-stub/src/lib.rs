@@ -0,0 +1,549 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A stub which allows GDB to debug a seL4 thread over a serial connection using the GDB remote
+//! serial protocol.
+//!
+//! The stub runs in its own thread, with capabilities to the target thread's TCB and to an
+//! endpoint on which it receives the target's faults. The target's registers are accessed with
+//! `seL4_TCB_ReadRegisters` and `seL4_TCB_WriteRegisters`, and its memory is accessed through a
+//! [`TargetMemory`] implementation, such as [`SharedMappings`].
+//!
+//! Software breakpoints are implemented by writing trap instructions into the target's memory,
+//! and are reported to the stub as faults. Single-stepping requires `HARDWARE_DEBUG_API`.
+//!
+//! While the target is running, the stub polls both the serial device, in order to respond to
+//! interrupts from GDB (e.g. Ctrl-C), and the fault endpoint. So that the stub can distinguish
+//! fault messages from the absence of a message, the target's fault endpoint capability must
+//! have a nonzero badge.
+//!
+//! The serial device must be driven directly by the stub, rather than over IPC, because the stub
+//! holds the target's implicit reply capability while the target is stopped at a fault.
+//!
+//! Registers are numbered according to GDB's default register layout for each architecture, so
+//! no target description is provided. Only the non-MCS kernel API is supported.
+
+#![no_std]
+
+use core::ops::Range;
+
+use sel4::{Fault, MessageInfo, UserContext, Word, cap};
+use sel4_driver_interfaces::serial::{Read, Write};
+
+mod arch;
+mod memory;
+mod packet;
+
+pub use memory::{MappedRegion, MemoryAccessError, SharedMappings, TargetMemory};
+
+use packet::{Connection, INTERRUPT, PACKET_SIZE, Response, decode_hex, parse_hex};
+
+#[sel4::sel4_cfg(KERNEL_MCS)]
+compile_error!("sel4-gdb-stub does not support the MCS kernel API");
+
+const MAX_SW_BREAKPOINTS: usize = 64;
+
+const MAX_BREAKPOINT_INSN_SIZE: usize = 4;
+
+// Memory is transferred in chunks of this size.
+const CHUNK_SIZE: usize = 256;
+
+// GDB's target-independent signal numbers.
+mod signal {
+    pub(crate) const INT: u8 = 2;
+    pub(crate) const ILL: u8 = 4;
+    pub(crate) const TRAP: u8 = 5;
+    pub(crate) const KILL: u8 = 9;
+    pub(crate) const SEGV: u8 = 11;
+    pub(crate) const SYS: u8 = 12;
+}
+
+/// The thread being debugged.
+pub struct Target<M> {
+    pub tcb: cap::Tcb,
+    /// The endpoint on which the stub receives the target's faults.
+    ///
+    /// The target's fault endpoint capability must be minted from this endpoint with a nonzero
+    /// badge.
+    pub fault_ep: cap::Endpoint,
+    pub memory: M,
+}
+
+/// A GDB remote serial protocol stub.
+///
+/// This type contains buffers for the largest packets it supports, so it is several kilobytes in
+/// size.
+pub struct GdbStub<S, M> {
+    conn: Connection<S>,
+    target: Target<M>,
+    state: State,
+    detached: bool,
+    single_stepping: bool,
+    sw_breakpoints: [Option<SwBreakpoint>; MAX_SW_BREAKPOINTS],
+    response: Response,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum State {
+    Running,
+    Stopped {
+        signal: u8,
+        // Whether the target is waiting for a reply to a fault message.
+        in_fault: bool,
+    },
+}
+
+#[derive(Debug, Copy, Clone)]
+struct SwBreakpoint {
+    addr: usize,
+    len: usize,
+    orig: [u8; MAX_BREAKPOINT_INSN_SIZE],
+}
+
+impl SwBreakpoint {
+    fn orig(&self) -> &[u8] {
+        &self.orig[..self.len]
+    }
+}
+
+enum Action {
+    Reply,
+    Resume,
+    Detach,
+    None,
+}
+
+impl<S: Read<u8> + Write<u8>, M: TargetMemory> GdbStub<S, M> {
+    /// Creates a stub for `target`, which is suspended until GDB resumes it.
+    pub fn new(serial: S, target: Target<M>) -> Self {
+        target.tcb.tcb_suspend().unwrap();
+        Self {
+            conn: Connection::new(serial),
+            target,
+            state: State::Stopped {
+                signal: signal::TRAP,
+                in_fault: false,
+            },
+            detached: false,
+            single_stepping: false,
+            sw_breakpoints: [None; MAX_SW_BREAKPOINTS],
+            response: Response::new(),
+        }
+    }
+
+    pub fn run(&mut self) -> ! {
+        let mut buf = [0; PACKET_SIZE];
+        loop {
+            let packet = self.conn.recv_packet(&mut buf);
+            if self.detached {
+                // A new session has begun.
+                self.interrupt();
+                self.detached = false;
+            }
+            self.response.clear();
+            match self.handle_packet(packet) {
+                Action::Reply => self.conn.send_packet(self.response.as_bytes()),
+                Action::Resume => {
+                    self.wait_for_stop();
+                    self.response.clear();
+                    self.push_stop_reply();
+                    self.conn.send_packet(self.response.as_bytes());
+                }
+                Action::Detach => {
+                    self.conn.send_packet(b"OK");
+                    self.detached = true;
+                }
+                Action::None => {}
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: &[u8]) -> Action {
+        let Some((cmd, args)) = packet.split_first() else {
+            return Action::Reply;
+        };
+        let ok = match cmd {
+            b'?' => {
+                self.push_stop_reply();
+                Some(())
+            }
+            b'g' => {
+                let ctx = self.read_registers();
+                for ix in 0..arch::REG_SIZES.len() {
+                    self.push_reg(&ctx, ix);
+                }
+                Some(())
+            }
+            b'G' => self.write_all_registers(args),
+            b'p' => parse_hex(args)
+                .filter(|ix| *ix < arch::REG_SIZES.len())
+                .map(|ix| {
+                    let ctx = self.read_registers();
+                    self.push_reg(&ctx, ix);
+                }),
+            b'P' => self.write_register(args),
+            b'm' => self.read_memory(args),
+            b'M' => self.write_memory(args),
+            b'c' | b's' => {
+                let step = *cmd == b's';
+                return match self.resume(args, step) {
+                    Some(()) => Action::Resume,
+                    None => {
+                        self.response.push(b"E01");
+                        Action::Reply
+                    }
+                };
+            }
+            b'D' => {
+                self.detach();
+                return Action::Detach;
+            }
+            b'k' => {
+                self.kill();
+                return Action::None;
+            }
+            b'H' | b'T' => {
+                // There is only one thread.
+                self.response.push(b"OK");
+                Some(())
+            }
+            b'q' => {
+                self.handle_query(args);
+                Some(())
+            }
+            b'Z' | b'z' => self.handle_breakpoint(*cmd == b'Z', args),
+            _ => Some(()),
+        };
+        if ok.is_none() {
+            self.response.clear();
+            self.response.push(b"E01");
+        }
+        Action::Reply
+    }
+
+    fn handle_query(&mut self, args: &[u8]) {
+        let name = args.split(|c| *c == b':').next().unwrap();
+        match name {
+            b"Supported" => self.response.push(b"PacketSize=1000"),
+            b"Attached" => self.response.push(b"1"),
+            b"C" => self.response.push(b"QC1"),
+            b"fThreadInfo" => self.response.push(b"m1"),
+            b"sThreadInfo" => self.response.push(b"l"),
+            _ => {}
+        }
+    }
+
+    fn push_stop_reply(&mut self) {
+        let signal = match self.state {
+            State::Stopped { signal, .. } => signal,
+            State::Running => unreachable!(),
+        };
+        self.response.push(b"S");
+        self.response.push_hex(&[signal]);
+    }
+
+    // // //
+
+    fn read_registers(&self) -> UserContext {
+        self.target.tcb.tcb_read_all_registers(false).unwrap()
+    }
+
+    fn write_registers(&self, mut ctx: UserContext) {
+        self.target
+            .tcb
+            .tcb_write_all_registers(false, &mut ctx)
+            .unwrap()
+    }
+
+    fn push_reg(&mut self, ctx: &UserContext, ix: usize) {
+        let val = arch::get_reg(ctx, ix).to_le_bytes();
+        self.response.push_hex(&val[..arch::REG_SIZES[ix]]);
+    }
+
+    fn write_all_registers(&mut self, args: &[u8]) -> Option<()> {
+        let mut ctx = self.read_registers();
+        let mut rest = args;
+        for (ix, size) in arch::REG_SIZES.iter().enumerate() {
+            if rest.is_empty() {
+                break;
+            }
+            let (this, next) = rest.split_at_checked(size * 2)?;
+            arch::set_reg(&mut ctx, ix, decode_reg(this)?);
+            rest = next;
+        }
+        self.write_registers(ctx);
+        self.response.push(b"OK");
+        Some(())
+    }
+
+    fn write_register(&mut self, args: &[u8]) -> Option<()> {
+        let (ix, val) = split_once(args, b'=')?;
+        let ix = parse_hex(ix).filter(|ix| *ix < arch::REG_SIZES.len())?;
+        if val.len() != arch::REG_SIZES[ix] * 2 {
+            return None;
+        }
+        let mut ctx = self.read_registers();
+        arch::set_reg(&mut ctx, ix, decode_reg(val)?);
+        self.write_registers(ctx);
+        self.response.push(b"OK");
+        Some(())
+    }
+
+    // // //
+
+    fn read_memory(&mut self, args: &[u8]) -> Option<()> {
+        let range = parse_memory_range(args)?;
+        let mut chunk = [0; CHUNK_SIZE];
+        // Each byte is encoded with two characters.
+        let mut remaining = range.len().min(PACKET_SIZE / 2);
+        let mut cur = range.start;
+        while remaining > 0 {
+            let n = remaining.min(chunk.len());
+            let chunk = &mut chunk[..n];
+            self.target.memory.read(cur, chunk).ok()?;
+            self.hide_sw_breakpoints(cur, chunk);
+            self.response.push_hex(chunk);
+            cur += n;
+            remaining -= n;
+        }
+        Some(())
+    }
+
+    fn write_memory(&mut self, args: &[u8]) -> Option<()> {
+        let (header, data) = split_once(args, b':')?;
+        let range = parse_memory_range(header)?;
+        let mut chunk = [0; CHUNK_SIZE];
+        if data.len() != range.len().checked_mul(2)? {
+            return None;
+        }
+        // Chunks lie within `range`, so their addresses cannot overflow.
+        for (i, src) in data.chunks(CHUNK_SIZE * 2).enumerate() {
+            let chunk = &mut chunk[..src.len() / 2];
+            decode_hex(src, chunk)?;
+            self.target
+                .memory
+                .write(range.start + i * CHUNK_SIZE, chunk)
+                .ok()?;
+        }
+        self.target.memory.sync_instructions(range);
+        self.response.push(b"OK");
+        Some(())
+    }
+
+    // GDB expects memory reads to show the original contents at software breakpoints.
+    fn hide_sw_breakpoints(&self, addr: usize, buf: &mut [u8]) {
+        for bp in self.sw_breakpoints.iter().flatten() {
+            for (j, orig) in bp.orig().iter().enumerate() {
+                if let Some(offset) = (bp.addr + j).checked_sub(addr)
+                    && let Some(b) = buf.get_mut(offset)
+                {
+                    *b = *orig;
+                }
+            }
+        }
+    }
+
+    // // //
+
+    fn handle_breakpoint(&mut self, insert: bool, args: &[u8]) -> Option<()> {
+        let mut fields = args.split(|c| *c == b',');
+        // Only software breakpoints are supported.
+        if fields.next()? != b"0" {
+            return Some(());
+        }
+        let addr = parse_hex(fields.next()?)?;
+        let insn = arch::breakpoint_insn(parse_hex(fields.next()?)?)?;
+        // Breakpoints which wrap around the address space are rejected here so that their ranges
+        // can be computed without overflow elsewhere.
+        addr.checked_add(insn.len())?;
+        let existing = self
+            .sw_breakpoints
+            .iter()
+            .position(|bp| bp.is_some_and(|bp| bp.addr == addr));
+        if insert {
+            if existing.is_none() {
+                let free = self.sw_breakpoints.iter().position(Option::is_none)?;
+                let mut bp = SwBreakpoint {
+                    addr,
+                    len: insn.len(),
+                    orig: [0; MAX_BREAKPOINT_INSN_SIZE],
+                };
+                self.target.memory.read(addr, &mut bp.orig[..bp.len]).ok()?;
+                self.write_insn(addr, insn)?;
+                self.sw_breakpoints[free] = Some(bp);
+            }
+        } else if let Some(i) = existing {
+            self.remove_sw_breakpoint(i)?;
+        }
+        self.response.push(b"OK");
+        Some(())
+    }
+
+    fn remove_sw_breakpoint(&mut self, i: usize) -> Option<()> {
+        let bp = self.sw_breakpoints[i].take().unwrap();
+        self.write_insn(bp.addr, bp.orig())
+    }
+
+    fn remove_all_sw_breakpoints(&mut self) {
+        for i in 0..self.sw_breakpoints.len() {
+            if self.sw_breakpoints[i].is_some() {
+                let _ = self.remove_sw_breakpoint(i);
+            }
+        }
+    }
+
+    fn write_insn(&mut self, addr: usize, insn: &[u8]) -> Option<()> {
+        self.target.memory.write(addr, insn).ok()?;
+        self.target
+            .memory
+            .sync_instructions(addr..addr + insn.len());
+        Some(())
+    }
+
+    fn at_sw_breakpoint(&self) -> bool {
+        let pc = usize::try_from(*self.read_registers().pc()).unwrap();
+        // Depending on the architecture, the PC is either at or just after the trap instruction.
+        self.sw_breakpoints
+            .iter()
+            .flatten()
+            .any(|bp| (bp.addr..=bp.addr + bp.len).contains(&pc))
+    }
+
+    // // //
+
+    fn resume(&mut self, args: &[u8], step: bool) -> Option<()> {
+        let State::Stopped { in_fault, .. } = self.state else {
+            unreachable!()
+        };
+        if !args.is_empty() {
+            let addr = parse_hex(args)?;
+            let mut ctx = self.read_registers();
+            *ctx.pc_mut() = addr.try_into().unwrap();
+            self.write_registers(ctx);
+        }
+        if step || self.single_stepping {
+            self.configure_single_stepping(step)?;
+            self.single_stepping = step;
+        }
+        if in_fault {
+            sel4::with_ipc_buffer_mut(|ipc_buffer| {
+                sel4::reply(ipc_buffer, MessageInfo::new(0, 0, 0, 0))
+            });
+        } else {
+            self.target.tcb.tcb_resume().unwrap();
+        }
+        self.state = State::Running;
+        Some(())
+    }
+
+    #[sel4::sel4_cfg(HARDWARE_DEBUG_API)]
+    fn configure_single_stepping(&self, enable: bool) -> Option<()> {
+        self.target
+            .tcb
+            .tcb_configure_single_stepping(0, enable.into())
+            .ok()
+            .map(|_| ())
+    }
+
+    #[sel4::sel4_cfg(not(HARDWARE_DEBUG_API))]
+    fn configure_single_stepping(&self, enable: bool) -> Option<()> {
+        (!enable).then_some(())
+    }
+
+    fn wait_for_stop(&mut self) {
+        loop {
+            if self.poll_fault() {
+                return;
+            }
+            if self.conn.try_read_byte() == Some(INTERRUPT) {
+                self.interrupt();
+                return;
+            }
+            sel4::r#yield();
+        }
+    }
+
+    fn poll_fault(&mut self) -> bool {
+        let (info, badge) = self.target.fault_ep.nb_recv(());
+        if badge == 0 {
+            return false;
+        }
+        let fault = sel4::with_ipc_buffer(|ipc_buffer| Fault::new(ipc_buffer, &info));
+        let signal = match fault {
+            Fault::VmFault(_) => signal::SEGV,
+            Fault::CapFault(_) | Fault::UnknownSyscall(_) => signal::SYS,
+            Fault::UserException(_) if !self.at_sw_breakpoint() => signal::ILL,
+            _ => signal::TRAP,
+        };
+        self.state = State::Stopped {
+            signal,
+            in_fault: true,
+        };
+        true
+    }
+
+    fn interrupt(&mut self) {
+        if let State::Stopped { .. } = self.state {
+            return;
+        }
+        if !self.poll_fault() {
+            self.target.tcb.tcb_suspend().unwrap();
+            self.state = State::Stopped {
+                signal: signal::INT,
+                in_fault: false,
+            };
+        }
+    }
+
+    fn detach(&mut self) {
+        self.remove_all_sw_breakpoints();
+        let _ = self.resume(&[], false);
+    }
+
+    fn kill(&mut self) {
+        // The target cannot be destroyed with the authority available to the stub, so it is
+        // suspended instead.
+        self.remove_all_sw_breakpoints();
+        self.target.tcb.tcb_suspend().unwrap();
+        self.state = State::Stopped {
+            signal: signal::KILL,
+            in_fault: false,
+        };
+    }
+}
+
+fn split_once(s: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let i = s.iter().position(|c| *c == sep)?;
+    Some((&s[..i], &s[i + 1..]))
+}
+
+/// Parses `addr,length`, rejecting ranges which wrap around the address space.
+fn parse_memory_range(args: &[u8]) -> Option<Range<usize>> {
+    let (addr, len) = split_once(args, b',')?;
+    let addr = parse_hex(addr)?;
+    let len = parse_hex(len)?;
+    Some(addr..addr.checked_add(len)?)
+}
+
+// Registers are transferred in target byte order.
+fn decode_reg(hex: &[u8]) -> Option<Word> {
+    let mut bytes = [0; size_of::<Word>()];
+    decode_hex(hex, &mut bytes[..hex.len() / 2])?;
+    Some(Word::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_range() {
+        assert_eq!(parse_memory_range(b"1000,20"), Some(0x1000..0x1020));
+        assert_eq!(parse_memory_range(b"1000"), None);
+        assert_eq!(parse_memory_range(b"1000,"), None);
+        assert_eq!(parse_memory_range(b"ffffffffffffffff,1"), None);
+    }
+}
@@ -0,0 +1,88 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::ops::Range;
+use core::ptr;
+
+/// Access to the target's memory.
+pub trait TargetMemory {
+    fn read(&mut self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryAccessError>;
+
+    fn write(&mut self, addr: usize, buf: &[u8]) -> Result<(), MemoryAccessError>;
+
+    /// Called after instructions in `range` have been modified, for example to insert a
+    /// breakpoint.
+    ///
+    /// On architectures without coherent instruction caches, implementations must make the
+    /// modification visible to the target's instruction fetches (e.g. with
+    /// `seL4_ARM_VSpace_Unify_Instruction` on the target's VSpace).
+    fn sync_instructions(&mut self, range: Range<usize>) {
+        let _ = range;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryAccessError;
+
+/// A region of the target's address space which is also mapped into the stub's.
+#[derive(Debug, Clone)]
+pub struct MappedRegion {
+    target_start: usize,
+    local_start: *mut u8,
+    size: usize,
+}
+
+impl MappedRegion {
+    /// # Safety
+    ///
+    /// `local_start..local_start + size` must be mapped into the stub's address space, aliasing
+    /// `target_start..target_start + size` in the target's, for the lifetime of this value.
+    pub unsafe fn new(target_start: usize, local_start: *mut u8, size: usize) -> Self {
+        Self {
+            target_start,
+            local_start,
+            size,
+        }
+    }
+
+    fn local_ptr(&self, addr: usize) -> Option<*mut u8> {
+        let offset = addr.checked_sub(self.target_start)?;
+        (offset < self.size).then(|| self.local_start.wrapping_add(offset))
+    }
+}
+
+/// A [`TargetMemory`] implementation which accesses the target's memory through a set of
+/// [`MappedRegion`]s.
+pub struct SharedMappings<T>(pub T);
+
+impl<T: AsRef<[MappedRegion]>> SharedMappings<T> {
+    fn local_ptr(&self, addr: usize) -> Result<*mut u8, MemoryAccessError> {
+        self.0
+            .as_ref()
+            .iter()
+            .find_map(|region| region.local_ptr(addr))
+            .ok_or(MemoryAccessError)
+    }
+}
+
+impl<T: AsRef<[MappedRegion]>> TargetMemory for SharedMappings<T> {
+    fn read(&mut self, addr: usize, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
+        for (i, b) in buf.iter_mut().enumerate() {
+            let addr = addr.checked_add(i).ok_or(MemoryAccessError)?;
+            // The target may be running concurrently.
+            *b = unsafe { ptr::read_volatile(self.local_ptr(addr)?) };
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, addr: usize, buf: &[u8]) -> Result<(), MemoryAccessError> {
+        for (i, b) in buf.iter().enumerate() {
+            let addr = addr.checked_add(i).ok_or(MemoryAccessError)?;
+            unsafe { ptr::write_volatile(self.local_ptr(addr)?, *b) };
+        }
+        Ok(())
+    }
+}
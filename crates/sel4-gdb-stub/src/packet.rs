@@ -0,0 +1,272 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4_driver_interfaces::serial::{Read, Write, nb};
+
+pub(crate) const PACKET_SIZE: usize = 4096;
+
+pub(crate) const INTERRUPT: u8 = 0x03;
+
+/// Framing for the GDB remote serial protocol.
+pub(crate) struct Connection<S> {
+    serial: S,
+}
+
+impl<S: Read<u8> + Write<u8>> Connection<S> {
+    pub(crate) fn new(serial: S) -> Self {
+        Self { serial }
+    }
+
+    pub(crate) fn try_read_byte(&mut self) -> Option<u8> {
+        // Errors such as overruns just lose bytes, which the protocol's checksums account for.
+        self.serial.read().ok()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        loop {
+            if let Some(b) = self.try_read_byte() {
+                return b;
+            }
+        }
+    }
+
+    fn write_byte(&mut self, b: u8) {
+        loop {
+            match self.serial.write(b) {
+                Err(nb::Error::WouldBlock) => continue,
+                _ => return,
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        while let Err(nb::Error::WouldBlock) = self.serial.flush() {}
+    }
+
+    /// Receives a packet, acknowledging it, and returns its payload.
+    pub(crate) fn recv_packet<'a>(&mut self, buf: &'a mut [u8; PACKET_SIZE]) -> &'a [u8] {
+        'outer: loop {
+            // Skip acknowledgements and interrupts outside of packets.
+            while self.read_byte() != b'$' {}
+            let mut len = 0;
+            let mut checksum = 0u8;
+            loop {
+                let b = self.read_byte();
+                if b == b'#' {
+                    break;
+                }
+                if b == b'$' || len == buf.len() {
+                    continue 'outer;
+                }
+                checksum = checksum.wrapping_add(b);
+                buf[len] = b;
+                len += 1;
+            }
+            let expected = [self.read_byte(), self.read_byte()];
+            if decode_hex_byte(expected) == Some(checksum) {
+                self.write_byte(b'+');
+                self.flush();
+                return &buf[..len];
+            }
+            self.write_byte(b'-');
+            self.flush();
+        }
+    }
+
+    /// Sends a packet, retransmitting it until it is acknowledged.
+    pub(crate) fn send_packet(&mut self, payload: &[u8]) {
+        loop {
+            self.write_byte(b'$');
+            let mut checksum = 0u8;
+            for b in payload {
+                checksum = checksum.wrapping_add(*b);
+                self.write_byte(*b);
+            }
+            self.write_byte(b'#');
+            for b in encode_hex_byte(checksum) {
+                self.write_byte(b);
+            }
+            self.flush();
+            loop {
+                match self.read_byte() {
+                    b'+' => return,
+                    b'-' => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// A buffer in which a response is built.
+pub(crate) struct Response {
+    buf: [u8; PACKET_SIZE],
+    len: usize,
+}
+
+impl Response {
+    pub(crate) const fn new() -> Self {
+        Self {
+            buf: [0; PACKET_SIZE],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        self.buf[self.len..][..bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    pub(crate) fn push_hex(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.push(&encode_hex_byte(*b));
+        }
+    }
+}
+
+fn encode_hex_byte(b: u8) -> [u8; 2] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    [DIGITS[usize::from(b >> 4)], DIGITS[usize::from(b & 0xf)]]
+}
+
+fn decode_hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn decode_hex_byte(cs: [u8; 2]) -> Option<u8> {
+    Some((decode_hex_digit(cs[0])? << 4) | decode_hex_digit(cs[1])?)
+}
+
+/// Decodes a big-endian hexadecimal number, as used for addresses and lengths.
+pub(crate) fn parse_hex(s: &[u8]) -> Option<usize> {
+    if s.is_empty() {
+        return None;
+    }
+    s.iter().try_fold(0usize, |acc, c| {
+        acc.checked_mul(16)?
+            .checked_add(decode_hex_digit(*c)?.into())
+    })
+}
+
+/// Decodes a sequence of hexadecimal byte pairs into `dst`, which must be of the corresponding
+/// length.
+pub(crate) fn decode_hex(src: &[u8], dst: &mut [u8]) -> Option<()> {
+    if src.len() != dst.len() * 2 {
+        return None;
+    }
+    for (pair, b) in src.chunks_exact(2).zip(dst) {
+        *b = decode_hex_byte([pair[0], pair[1]])?;
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use sel4_driver_interfaces::serial::ErrorType;
+
+    use super::*;
+
+    struct MockSerial {
+        input: &'static [u8],
+        output: [u8; 64],
+        output_len: usize,
+    }
+
+    impl MockSerial {
+        fn new(input: &'static [u8]) -> Self {
+            Self {
+                input,
+                output: [0; 64],
+                output_len: 0,
+            }
+        }
+
+        fn output(&self) -> &[u8] {
+            &self.output[..self.output_len]
+        }
+    }
+
+    impl ErrorType for MockSerial {
+        type Error = Infallible;
+    }
+
+    impl Read<u8> for MockSerial {
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            let (b, rest) = self.input.split_first().unwrap();
+            self.input = rest;
+            Ok(*b)
+        }
+    }
+
+    impl Write<u8> for MockSerial {
+        fn write(&mut self, b: u8) -> nb::Result<(), Self::Error> {
+            self.output[self.output_len] = b;
+            self.output_len += 1;
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(parse_hex(b"0"), Some(0));
+        assert_eq!(parse_hex(b"1aF"), Some(0x1af));
+        assert_eq!(parse_hex(b""), None);
+        assert_eq!(parse_hex(b"1g"), None);
+        assert_eq!(parse_hex(b"1ffffffffffffffff"), None);
+
+        let mut dst = [0; 3];
+        assert_eq!(decode_hex(b"00ff7A", &mut dst), Some(()));
+        assert_eq!(dst, [0x00, 0xff, 0x7a]);
+        assert_eq!(decode_hex(b"00ff7", &mut dst), None);
+        assert_eq!(decode_hex(b"00ff7z", &mut dst), None);
+    }
+
+    #[test]
+    fn recv_packet() {
+        // Bytes outside of a packet are skipped, and a packet with a bad checksum is rejected.
+        let mut conn = Connection::new(MockSerial::new(b"+\x03$m0,4#00$m0,4#fd"));
+        let mut buf = [0; PACKET_SIZE];
+        assert_eq!(conn.recv_packet(&mut buf), b"m0,4");
+        assert_eq!(conn.serial.output(), b"-+");
+    }
+
+    #[test]
+    fn send_packet() {
+        // The packet is retransmitted until it is acknowledged.
+        let mut conn = Connection::new(MockSerial::new(b"-+"));
+        conn.send_packet(b"OK");
+        assert_eq!(conn.serial.output(), b"$OK#9a$OK#9a");
+    }
+
+    #[test]
+    fn response() {
+        let mut response = Response::new();
+        response.push(b"T05");
+        response.push_hex(&[0xde, 0xad]);
+        assert_eq!(response.as_bytes(), b"T05dead");
+        response.clear();
+        assert_eq!(response.as_bytes(), b"");
+    }
+}
@@ -0,0 +1,27 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, serdeWith }:
+
+mk {
+  package.name = "sel4-host-sim";
+  dependencies = {
+    inherit (localCrates)
+      sel4-bitfield-ops
+    ;
+    sel4 = localCrates.sel4 // { features = [ "host-sim" ]; };
+  };
+  dev-dependencies = {
+    inherit (localCrates)
+      sel4-abstract-allocator
+      sel4-cspace-allocator
+      sel4-untyped-manager
+      sel4-vspace-manager
+    ;
+    sel4-simple-task-rpc = localCrates.sel4-simple-task-rpc // { features = [ "postcard" ]; };
+    serde = serdeWith [ "derive" ];
+  };
+}
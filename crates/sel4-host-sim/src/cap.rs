@@ -0,0 +1,191 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::Word;
+use sel4::sys;
+use sel4_bitfield_ops::Bitfield;
+
+use crate::vspace;
+
+pub(crate) type ObjId = usize;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Rights {
+    pub(crate) write: bool,
+    pub(crate) read: bool,
+    pub(crate) grant: bool,
+    pub(crate) grant_reply: bool,
+}
+
+impl Rights {
+    pub(crate) const ALL: Self = Self {
+        write: true,
+        read: true,
+        grant: true,
+        grant_reply: true,
+    };
+
+    pub(crate) fn from_word(word: Word) -> Self {
+        let rights = sys::seL4_CapRights(Bitfield::new([word]));
+        Self {
+            write: rights.get_capAllowWrite() != 0,
+            read: rights.get_capAllowRead() != 0,
+            grant: rights.get_capAllowGrant() != 0,
+            grant_reply: rights.get_capAllowGrantReply() != 0,
+        }
+    }
+
+    fn intersect(self, other: Self) -> Self {
+        Self {
+            write: self.write && other.write,
+            read: self.read && other.read,
+            grant: self.grant && other.grant,
+            grant_reply: self.grant_reply && other.grant_reply,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub(crate) enum Cap {
+    #[default]
+    Null,
+    Untyped {
+        obj: ObjId,
+    },
+    Endpoint {
+        obj: ObjId,
+        badge: Word,
+        rights: Rights,
+    },
+    Notification {
+        obj: ObjId,
+        badge: Word,
+        rights: Rights,
+    },
+    CNode {
+        obj: ObjId,
+        guard: Word,
+        guard_size: usize,
+    },
+    Tcb {
+        obj: ObjId,
+    },
+    Frame {
+        obj: ObjId,
+        size_bits: usize,
+        /// The VSpace and address at which the frame was mapped through this capability.
+        mapping: Option<(ObjId, Word)>,
+    },
+    /// Level 0 is a VSpace.
+    TranslationTable {
+        obj: ObjId,
+        level: usize,
+    },
+    AsidPool {
+        obj: ObjId,
+    },
+}
+
+impl Cap {
+    pub(crate) fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub(crate) fn obj(&self) -> Option<ObjId> {
+        match *self {
+            Self::Null => None,
+            Self::Untyped { obj }
+            | Self::Endpoint { obj, .. }
+            | Self::Notification { obj, .. }
+            | Self::CNode { obj, .. }
+            | Self::Tcb { obj }
+            | Self::Frame { obj, .. }
+            | Self::TranslationTable { obj, .. }
+            | Self::AsidPool { obj } => Some(obj),
+        }
+    }
+
+    // Corresponds to the kernel's cap type tags, as returned by seL4_DebugCapIdentify.
+    #[allow(dead_code)]
+    pub(crate) fn tag(&self) -> Word {
+        match self {
+            Self::Null => 0,
+            Self::Untyped { .. } => 2,
+            Self::Endpoint { .. } => 4,
+            Self::Notification { .. } => 6,
+            Self::CNode { .. } => 10,
+            Self::Tcb { .. } => 12,
+            Self::Frame { .. } => vspace::FRAME_TAG,
+            Self::TranslationTable { level, .. } => vspace::TABLE_TAGS[*level],
+            Self::AsidPool { .. } => vspace::ASID_POOL_TAG,
+        }
+    }
+
+    pub(crate) fn mask_rights(self, mask: Rights) -> Self {
+        match self {
+            Self::Endpoint { obj, badge, rights } => Self::Endpoint {
+                obj,
+                badge,
+                rights: rights.intersect(mask),
+            },
+            Self::Notification { obj, badge, rights } => Self::Notification {
+                obj,
+                badge,
+                rights: rights.intersect(mask),
+            },
+            _ => self,
+        }
+    }
+
+    // Corresponds to the kernel's updateCapData(). Returns a null cap where the kernel would.
+    pub(crate) fn update_data(self, preserve: bool, data: Word, radix: Option<usize>) -> Self {
+        match self {
+            Self::Endpoint { obj, badge, rights } => {
+                if !preserve && badge == 0 {
+                    Self::Endpoint {
+                        obj,
+                        badge: data,
+                        rights,
+                    }
+                } else {
+                    Self::Null
+                }
+            }
+            Self::Notification { obj, badge, rights } => {
+                if !preserve && badge == 0 {
+                    Self::Notification {
+                        obj,
+                        badge: data,
+                        rights,
+                    }
+                } else {
+                    Self::Null
+                }
+            }
+            Self::CNode { obj, .. } => {
+                let data = sys::seL4_CNode_CapData(Bitfield::new([data]));
+                let guard_size = data.get_guardSize() as usize;
+                if guard_size + radix.unwrap() > sel4::WORD_SIZE {
+                    return Self::Null;
+                }
+                Self::CNode {
+                    obj,
+                    guard: data.get_guard() & mask(guard_size),
+                    guard_size,
+                }
+            }
+            _ => self,
+        }
+    }
+}
+
+pub(crate) fn mask(bits: usize) -> Word {
+    if bits >= sel4::WORD_SIZE {
+        Word::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
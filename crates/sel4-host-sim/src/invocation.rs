@@ -0,0 +1,529 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::mem;
+use std::slice;
+
+use sel4::sys::{api_object, invocation_label, seL4_Error};
+use sel4::{MessageInfo, ObjectBlueprint, UserContext, Word};
+
+use crate::cap::{Cap, ObjId, Rights, mask};
+use crate::ipc::{decode_info, encode_info};
+use crate::kernel::{KResult, Kernel, Object, Slot, SlotRef, Tcb, ThreadState};
+use crate::vspace::{
+    VmInvocation, decode_label, frame_entry_level, vm_object_type, vm_object_types,
+};
+
+const MIN_UNTYPED_BITS: usize = sel4::sys::seL4_MinUntypedBits as usize;
+const MAX_UNTYPED_BITS: usize = sel4::sys::seL4_MaxUntypedBits as usize;
+
+// Corresponds to CONFIG_RETYPE_FAN_OUT_LIMIT.
+const RETYPE_FAN_OUT_LIMIT: usize = 256;
+
+// Depths are passed as seL4_Uint8.
+const DEPTH_MASK: Word = 0xff;
+
+impl Kernel {
+    /// Performs an invocation of a kernel object other than an endpoint or notification.
+    pub(crate) fn invoke(&mut self, tcb: ObjId, slot: SlotRef, cap: Cap, is_call: bool) {
+        let info = decode_info(self.tcb(tcb).frame.info);
+        let result = match cap {
+            Cap::CNode { .. } => self.invoke_cnode(tcb, &info, cap),
+            Cap::Untyped { obj } => self.invoke_untyped(tcb, &info, slot, obj),
+            Cap::Tcb { obj } => self.invoke_tcb(tcb, &info, obj),
+            Cap::Frame { .. } | Cap::TranslationTable { .. } | Cap::AsidPool { .. } => {
+                self.invoke_vm(tcb, &info, slot, cap)
+            }
+            _ => Err(seL4_Error::seL4_IllegalOperation),
+        };
+        if is_call {
+            let (error, length) = match result {
+                Ok(length) => (seL4_Error::seL4_NoError, length),
+                Err(error) => (error, 0),
+            };
+            self.tcb_mut(tcb).frame.info =
+                encode_info(MessageInfo::new(error.into(), 0, 0, length));
+        }
+    }
+
+    fn arg(&self, tcb: ObjId, info: &MessageInfo, i: usize) -> KResult<Word> {
+        if i < info.length() {
+            Ok(self.get_mr(tcb, i))
+        } else {
+            Err(seL4_Error::seL4_TruncatedMessage)
+        }
+    }
+
+    fn extra_cap(&self, tcb: ObjId, info: &MessageInfo, i: usize) -> KResult<(SlotRef, Cap)> {
+        if i >= info.extra_caps() {
+            return Err(seL4_Error::seL4_TruncatedMessage);
+        }
+        let buf = self
+            .ipc_buffer(tcb)
+            .ok_or(seL4_Error::seL4_TruncatedMessage)?;
+        self.lookup_cap(tcb, unsafe { (*buf).caps_or_badges[i] })
+    }
+
+    // // //
+
+    fn invoke_cnode(&mut self, tcb: ObjId, info: &MessageInfo, root: Cap) -> KResult<usize> {
+        let label = info.label() as u32;
+        let dest = self.lookup_target_slot(
+            root,
+            self.arg(tcb, info, 0)?,
+            self.arg(tcb, info, 1)? & DEPTH_MASK,
+        )?;
+        match label {
+            invocation_label::CNodeRevoke => {
+                self.revoke(dest);
+            }
+            invocation_label::CNodeDelete => {
+                self.delete(dest);
+            }
+            invocation_label::CNodeCancelBadgedSends => {
+                let Cap::Endpoint { obj, badge, .. } = self.slot(dest).cap else {
+                    return Err(seL4_Error::seL4_IllegalOperation);
+                };
+                if badge != 0 {
+                    self.cancel_badged_sends(obj, badge);
+                }
+            }
+            invocation_label::CNodeCopy
+            | invocation_label::CNodeMint
+            | invocation_label::CNodeMove
+            | invocation_label::CNodeMutate => {
+                let (_, src_root) = self.extra_cap(tcb, info, 0)?;
+                let src = self.lookup_target_slot(
+                    src_root,
+                    self.arg(tcb, info, 2)?,
+                    self.arg(tcb, info, 3)? & DEPTH_MASK,
+                )?;
+                if !self.slot(dest).cap.is_null() {
+                    return Err(seL4_Error::seL4_DeleteFirst);
+                }
+                let src_cap = self.slot(src).cap;
+                if src_cap.is_null() {
+                    return Err(seL4_Error::seL4_FailedLookup);
+                }
+                let radix = match src_cap {
+                    Cap::CNode { obj, .. } => Some(self.cnode_radix(obj)),
+                    _ => None,
+                };
+                match label {
+                    invocation_label::CNodeCopy => {
+                        let rights = Rights::from_word(self.arg(tcb, info, 4)?);
+                        self.derive(src, dest, src_cap.mask_rights(rights))?;
+                    }
+                    invocation_label::CNodeMint => {
+                        let rights = Rights::from_word(self.arg(tcb, info, 4)?);
+                        let badge = self.arg(tcb, info, 5)?;
+                        let new_cap = src_cap.mask_rights(rights).update_data(false, badge, radix);
+                        self.derive(src, dest, new_cap)?;
+                    }
+                    invocation_label::CNodeMove => {
+                        self.move_cap(src, dest, src_cap);
+                    }
+                    invocation_label::CNodeMutate => {
+                        let data = self.arg(tcb, info, 4)?;
+                        let new_cap = src_cap.update_data(true, data, radix);
+                        if new_cap.is_null() {
+                            return Err(seL4_Error::seL4_IllegalOperation);
+                        }
+                        self.move_cap(src, dest, new_cap);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            _ => return Err(seL4_Error::seL4_IllegalOperation),
+        }
+        Ok(0)
+    }
+
+    // Corresponds to the kernel's deriveCap() followed by cteInsert().
+    fn derive(&mut self, src: SlotRef, dest: SlotRef, cap: Cap) -> KResult<()> {
+        match cap {
+            Cap::Null => return Err(seL4_Error::seL4_IllegalOperation),
+            Cap::Untyped { .. } if self.has_children(src) => {
+                return Err(seL4_Error::seL4_RevokeFirst);
+            }
+            _ => {}
+        }
+        let cap = match cap {
+            Cap::Frame { obj, size_bits, .. } => Cap::Frame {
+                obj,
+                size_bits,
+                mapping: None,
+            },
+            _ => cap,
+        };
+        self.insert(dest, cap, Some(src));
+        Ok(())
+    }
+
+    fn cancel_badged_sends(&mut self, ep: ObjId, badge: Word) {
+        let Object::Endpoint { queue } = self.object(ep) else {
+            unreachable!()
+        };
+        let senders = queue
+            .iter()
+            .copied()
+            .filter(|tcb| {
+                matches!(
+                    self.tcb(*tcb).state,
+                    ThreadState::BlockedOnSend { badge: b, .. } if b == badge
+                )
+            })
+            .collect::<Vec<_>>();
+        for tcb in senders {
+            self.cancel_ipc(tcb);
+        }
+    }
+
+    // // //
+
+    fn invoke_untyped(
+        &mut self,
+        tcb: ObjId,
+        info: &MessageInfo,
+        slot: SlotRef,
+        obj: ObjId,
+    ) -> KResult<usize> {
+        if info.label() as u32 != invocation_label::UntypedRetype {
+            return Err(seL4_Error::seL4_IllegalOperation);
+        }
+        let ty = self.arg(tcb, info, 0)?;
+        let size_bits = self.arg(tcb, info, 1)? as usize;
+        let node_index = self.arg(tcb, info, 2)?;
+        let node_depth = self.arg(tcb, info, 3)?;
+        let node_offset = self.arg(tcb, info, 4)? as usize;
+        let num_objects = self.arg(tcb, info, 5)? as usize;
+        let (_, root) = self.extra_cap(tcb, info, 0)?;
+
+        let blueprint = blueprint(ty, size_bits)?;
+
+        let dest_cnode = if node_depth == 0 {
+            root
+        } else {
+            self.slot(self.lookup_target_slot(root, node_index, node_depth)?)
+                .cap
+        };
+        let Cap::CNode { obj: cnode, .. } = dest_cnode else {
+            return Err(seL4_Error::seL4_FailedLookup);
+        };
+        let num_slots = 1 << self.cnode_radix(cnode);
+        if num_objects == 0
+            || num_objects > RETYPE_FAN_OUT_LIMIT
+            || node_offset >= num_slots
+            || num_objects > num_slots - node_offset
+        {
+            return Err(seL4_Error::seL4_RangeError);
+        }
+        let dest_slots = (node_offset..node_offset + num_objects).map(|i| (cnode, i));
+        if dest_slots
+            .clone()
+            .any(|dest| !self.slot(dest).cap.is_null())
+        {
+            return Err(seL4_Error::seL4_DeleteFirst);
+        }
+
+        // As in the kernel, an untyped without children is reset before use.
+        let reset = !self.has_children(slot);
+        let Object::Untyped {
+            size_bits: untyped_size_bits,
+            free_index,
+        } = self.object_mut(obj)
+        else {
+            unreachable!()
+        };
+        if reset {
+            *free_index = 0;
+        }
+        let object_size = 1 << blueprint.physical_size_bits();
+        let end = free_index.next_multiple_of(object_size) + num_objects * object_size;
+        if end > 1 << *untyped_size_bits {
+            return Err(seL4_Error::seL4_NotEnoughMemory);
+        }
+        *free_index = end;
+
+        for dest in dest_slots {
+            let cap = self.create(blueprint);
+            self.insert(dest, cap, Some(slot));
+        }
+        Ok(0)
+    }
+
+    fn create(&mut self, blueprint: ObjectBlueprint) -> Cap {
+        match blueprint {
+            ObjectBlueprint::Untyped { size_bits } => Cap::Untyped {
+                obj: self.alloc(Object::Untyped {
+                    size_bits,
+                    free_index: 0,
+                }),
+            },
+            ObjectBlueprint::Endpoint => Cap::Endpoint {
+                obj: self.alloc(Object::Endpoint {
+                    queue: Default::default(),
+                }),
+                badge: 0,
+                rights: Rights::ALL,
+            },
+            ObjectBlueprint::Notification => Cap::Notification {
+                obj: self.alloc(Object::Notification {
+                    word: 0,
+                    waiters: Default::default(),
+                    bound_tcb: None,
+                }),
+                badge: 0,
+                rights: Rights::ALL,
+            },
+            ObjectBlueprint::CNode { size_bits } => Cap::CNode {
+                obj: self.alloc(Object::CNode {
+                    slots: vec![Slot::default(); 1 << size_bits],
+                }),
+                guard: 0,
+                guard_size: 0,
+            },
+            ObjectBlueprint::Tcb => Cap::Tcb {
+                obj: self.alloc(Object::Tcb(Box::new(Tcb::new()))),
+            },
+            _ => self.create_vm_object(vm_object_type(blueprint).unwrap()),
+        }
+    }
+
+    // // //
+
+    fn invoke_tcb(&mut self, tcb: ObjId, info: &MessageInfo, target: ObjId) -> KResult<usize> {
+        match info.label() as u32 {
+            invocation_label::TCBReadRegisters => {
+                let suspend = self.arg(tcb, info, 0)? & 1 != 0;
+                let count = self.arg(tcb, info, 1)? as usize;
+                let regs = self.tcb(target).regs.clone();
+                let words = user_context_words(&regs);
+                if count > words.len() {
+                    return Err(seL4_Error::seL4_RangeError);
+                }
+                if suspend {
+                    self.suspend(target);
+                }
+                for (i, word) in words[..count].iter().enumerate() {
+                    self.set_mr(tcb, i, *word);
+                }
+                Ok(count)
+            }
+            invocation_label::TCBWriteRegisters => {
+                let resume = self.arg(tcb, info, 0)? & 1 != 0;
+                let count = self.arg(tcb, info, 1)? as usize;
+                let mut regs = self.tcb(target).regs.clone();
+                let words = user_context_words_mut(&mut regs);
+                for (i, word) in words.iter_mut().enumerate().take(count) {
+                    *word = self.arg(tcb, info, 2 + i)?;
+                }
+                self.tcb_mut(target).regs = regs;
+                if resume {
+                    self.resume(target);
+                }
+                Ok(0)
+            }
+            invocation_label::TCBResume => {
+                self.resume(target);
+                Ok(0)
+            }
+            invocation_label::TCBSuspend => {
+                self.suspend(target);
+                Ok(0)
+            }
+            invocation_label::TCBConfigure => {
+                let cspace_root_data = self.arg(tcb, info, 1)?;
+                let buffer = self.arg(tcb, info, 3)?;
+                let (_, cspace_root) = self.extra_cap(tcb, info, 0)?;
+                self.set_space(target, cspace_root, cspace_root_data)?;
+                self.tcb_mut(target).ipc_buffer = buffer;
+                Ok(0)
+            }
+            invocation_label::TCBSetSpace => {
+                let cspace_root_data = self.arg(tcb, info, 1)?;
+                let (_, cspace_root) = self.extra_cap(tcb, info, 0)?;
+                self.set_space(target, cspace_root, cspace_root_data)?;
+                Ok(0)
+            }
+            invocation_label::TCBSetIPCBuffer => {
+                self.tcb_mut(target).ipc_buffer = self.arg(tcb, info, 0)?;
+                Ok(0)
+            }
+            invocation_label::TCBBindNotification => {
+                let (_, cap) = self.extra_cap(tcb, info, 0)?;
+                let Cap::Notification { obj, rights, .. } = cap else {
+                    return Err(seL4_Error::seL4_IllegalOperation);
+                };
+                if !rights.read || self.tcb(target).bound_notification.is_some() {
+                    return Err(seL4_Error::seL4_IllegalOperation);
+                }
+                let Object::Notification {
+                    waiters, bound_tcb, ..
+                } = self.object_mut(obj)
+                else {
+                    unreachable!()
+                };
+                if bound_tcb.is_some() || !waiters.is_empty() {
+                    return Err(seL4_Error::seL4_IllegalOperation);
+                }
+                *bound_tcb = Some(target);
+                self.tcb_mut(target).bound_notification = Some(obj);
+                Ok(0)
+            }
+            invocation_label::TCBUnbindNotification => {
+                if self.tcb(target).bound_notification.is_none() {
+                    return Err(seL4_Error::seL4_IllegalOperation);
+                }
+                self.unbind_notification(target);
+                Ok(0)
+            }
+            // Scheduling is left to the host.
+            invocation_label::TCBSetPriority
+            | invocation_label::TCBSetMCPriority
+            | invocation_label::TCBSetSchedParams
+            | invocation_label::TCBSetTLSBase => Ok(0),
+            _ => Err(seL4_Error::seL4_IllegalOperation),
+        }
+    }
+
+    // // //
+
+    fn invoke_vm(
+        &mut self,
+        tcb: ObjId,
+        info: &MessageInfo,
+        slot: SlotRef,
+        cap: Cap,
+    ) -> KResult<usize> {
+        let invocation = decode_label(info.label() as u32);
+        match (invocation, cap) {
+            (
+                Some(VmInvocation::PageMap),
+                Cap::Frame {
+                    obj,
+                    size_bits,
+                    mapping,
+                },
+            ) => {
+                let vaddr = self.arg(tcb, info, 0)?;
+                let (_, vspace) = self.extra_cap(tcb, info, 0)?;
+                if mapping.is_some() {
+                    return Err(seL4_Error::seL4_InvalidCapability);
+                }
+                if vaddr & mask(size_bits) != 0 {
+                    return Err(seL4_Error::seL4_AlignmentError);
+                }
+                self.map_vm_object(vspace, obj, frame_entry_level(size_bits), vaddr)?;
+                self.slot_mut(slot).cap = Cap::Frame {
+                    obj,
+                    size_bits,
+                    mapping: Some((vspace.obj().unwrap(), vaddr)),
+                };
+            }
+            (
+                Some(VmInvocation::PageUnmap),
+                Cap::Frame {
+                    obj,
+                    size_bits,
+                    mapping,
+                },
+            ) => {
+                if let Some((vspace, vaddr)) = mapping {
+                    self.unmap_vm_object(vspace, obj, frame_entry_level(size_bits), vaddr);
+                }
+                self.slot_mut(slot).cap = Cap::Frame {
+                    obj,
+                    size_bits,
+                    mapping: None,
+                };
+            }
+            (Some(VmInvocation::TableMap), Cap::TranslationTable { obj, level }) if level > 0 => {
+                let vaddr = self.arg(tcb, info, 0)?;
+                let (_, vspace) = self.extra_cap(tcb, info, 0)?;
+                let Object::TranslationTable { mapping, .. } = self.object(obj) else {
+                    unreachable!()
+                };
+                if mapping.is_some() {
+                    return Err(seL4_Error::seL4_InvalidCapability);
+                }
+                let vaddr = self.map_vm_object(vspace, obj, level - 1, vaddr)?;
+                let Object::TranslationTable { mapping, .. } = self.object_mut(obj) else {
+                    unreachable!()
+                };
+                *mapping = Some((vspace.obj().unwrap(), vaddr));
+            }
+            (Some(VmInvocation::AsidPoolAssign), Cap::AsidPool { .. }) => {
+                let (_, vspace) = self.extra_cap(tcb, info, 0)?;
+                self.assign_asid(vspace)?;
+            }
+            _ => return Err(seL4_Error::seL4_IllegalOperation),
+        }
+        Ok(0)
+    }
+
+    fn set_space(&mut self, target: ObjId, cspace_root: Cap, data: Word) -> KResult<()> {
+        let Cap::CNode { obj, .. } = cspace_root else {
+            return Err(seL4_Error::seL4_IllegalOperation);
+        };
+        let cspace_root = if data != 0 {
+            cspace_root.update_data(false, data, Some(self.cnode_radix(obj)))
+        } else {
+            cspace_root
+        };
+        if cspace_root.is_null() {
+            return Err(seL4_Error::seL4_IllegalOperation);
+        }
+        self.tcb_mut(target).cspace_root = cspace_root;
+        Ok(())
+    }
+}
+
+fn blueprint(ty: Word, size_bits: usize) -> KResult<ObjectBlueprint> {
+    Ok(match ty as u32 {
+        api_object::seL4_UntypedObject => {
+            if !(MIN_UNTYPED_BITS..=MAX_UNTYPED_BITS).contains(&size_bits) {
+                return Err(seL4_Error::seL4_RangeError);
+            }
+            ObjectBlueprint::Untyped { size_bits }
+        }
+        api_object::seL4_TCBObject => ObjectBlueprint::Tcb,
+        api_object::seL4_EndpointObject => ObjectBlueprint::Endpoint,
+        api_object::seL4_NotificationObject => ObjectBlueprint::Notification,
+        api_object::seL4_CapTableObject => {
+            if size_bits < 1 || size_bits >= sel4::WORD_SIZE {
+                return Err(seL4_Error::seL4_RangeError);
+            }
+            ObjectBlueprint::CNode { size_bits }
+        }
+        _ => {
+            return vm_object_types()
+                .map(|(blueprint, _)| blueprint)
+                .find(|blueprint| blueprint.ty().into_sys() == ty as u32)
+                .ok_or(seL4_Error::seL4_InvalidArgument);
+        }
+    })
+}
+
+fn user_context_words(regs: &UserContext) -> &[Word] {
+    let inner = regs.inner();
+    unsafe {
+        slice::from_raw_parts(
+            (inner as *const _).cast::<Word>(),
+            mem::size_of_val(inner) / mem::size_of::<Word>(),
+        )
+    }
+}
+
+fn user_context_words_mut(regs: &mut UserContext) -> &mut [Word] {
+    let inner = regs.inner_mut();
+    unsafe {
+        slice::from_raw_parts_mut(
+            (inner as *mut _).cast::<Word>(),
+            mem::size_of_val(inner) / mem::size_of::<Word>(),
+        )
+    }
+}
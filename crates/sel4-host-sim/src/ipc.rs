@@ -0,0 +1,402 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::sys::{self, syscall_id};
+use sel4::{MessageInfo, Word};
+use sel4_bitfield_ops::Bitfield;
+
+use crate::cap::{Cap, ObjId};
+use crate::kernel::{Caller, Kernel, Object, SlotRef, ThreadState, Wake};
+
+pub(crate) enum Outcome {
+    Completed,
+    Blocked,
+    /// The kernel would have sent a fault message on the thread's behalf.
+    Fault(String),
+}
+
+impl Kernel {
+    pub(crate) fn handle_syscall(&mut self, tcb: ObjId) -> Outcome {
+        let frame = self.tcb(tcb).frame;
+        sel4::sel4_cfg_wrap_match! {
+            match frame.sys {
+                syscall_id::Send => self.handle_send(tcb, true, false),
+                syscall_id::NBSend => self.handle_send(tcb, false, false),
+                syscall_id::Call => self.handle_send(tcb, true, true),
+                syscall_id::Recv => self.handle_recv(tcb, true),
+                syscall_id::NBRecv => self.handle_recv(tcb, false),
+                syscall_id::Reply => {
+                    self.do_reply(tcb);
+                    Outcome::Completed
+                }
+                syscall_id::ReplyRecv => {
+                    self.do_reply(tcb);
+                    self.handle_recv(tcb, true)
+                }
+                syscall_id::Yield => Outcome::Completed,
+                #[sel4_cfg(PRINTING)]
+                syscall_id::DebugPutChar => {
+                    eprint!("{}", frame.dest as u8 as char);
+                    Outcome::Completed
+                }
+                #[sel4_cfg(DEBUG_BUILD)]
+                syscall_id::DebugCapIdentify => {
+                    let tag = self
+                        .lookup_cap(tcb, frame.dest)
+                        .map(|(_, cap)| cap.tag())
+                        .unwrap_or(0);
+                    self.tcb_mut(tcb).frame.badge = tag;
+                    Outcome::Completed
+                }
+                #[sel4_cfg(DEBUG_BUILD)]
+                syscall_id::DebugNameThread | syscall_id::DebugSnapshot => Outcome::Completed,
+                #[sel4_cfg(DEBUG_BUILD)]
+                syscall_id::DebugHalt => Outcome::Fault("seL4_DebugHalt".to_owned()),
+                sys => Outcome::Fault(format!("unsupported syscall {sys}")),
+            }
+        }
+    }
+
+    fn handle_send(&mut self, tcb: ObjId, blocking: bool, is_call: bool) -> Outcome {
+        let cptr = self.tcb(tcb).frame.dest;
+        let Ok((slot, cap)) = self.lookup_cap(tcb, cptr) else {
+            return Outcome::Fault(format!("invalid capability {cptr:#x}"));
+        };
+        match cap {
+            Cap::Endpoint { obj, badge, rights } => {
+                if !rights.write {
+                    return Outcome::Fault(format!("no write right on {cptr:#x}"));
+                }
+                self.send_ipc(
+                    tcb,
+                    obj,
+                    ThreadState::BlockedOnSend {
+                        ep: obj,
+                        badge,
+                        can_grant: rights.grant,
+                        can_grant_reply: rights.grant_reply,
+                        is_call,
+                    },
+                    blocking,
+                )
+            }
+            Cap::Notification { obj, badge, rights } => {
+                if !rights.write {
+                    return Outcome::Fault(format!("no write right on {cptr:#x}"));
+                }
+                self.signal(obj, badge);
+                Outcome::Completed
+            }
+            Cap::Null => Outcome::Fault(format!("invalid capability {cptr:#x}")),
+            _ => {
+                self.invoke(tcb, slot, cap, is_call);
+                Outcome::Completed
+            }
+        }
+    }
+
+    fn handle_recv(&mut self, tcb: ObjId, blocking: bool) -> Outcome {
+        let cptr = self.tcb(tcb).frame.src;
+        let Ok((_, cap)) = self.lookup_cap(tcb, cptr) else {
+            return Outcome::Fault(format!("invalid capability {cptr:#x}"));
+        };
+        match cap {
+            Cap::Endpoint { obj, rights, .. } if rights.read => {
+                if let Some(ntfn) = self.tcb(tcb).bound_notification
+                    && let Some(word) = self.take_signal(ntfn)
+                {
+                    self.complete_signal(tcb, word);
+                    return Outcome::Completed;
+                }
+                self.receive_ipc(tcb, obj, rights.grant, blocking)
+            }
+            Cap::Notification { obj, rights, .. } if rights.read => {
+                if let Some(word) = self.take_signal(obj) {
+                    self.complete_signal(tcb, word);
+                    Outcome::Completed
+                } else if blocking {
+                    self.block(tcb, obj, ThreadState::BlockedOnNotification { ntfn: obj });
+                    Outcome::Blocked
+                } else {
+                    self.complete_signal(tcb, 0);
+                    Outcome::Completed
+                }
+            }
+            _ => Outcome::Fault(format!("cannot receive on {cptr:#x}")),
+        }
+    }
+
+    fn send_ipc(
+        &mut self,
+        sender: ObjId,
+        ep: ObjId,
+        state: ThreadState,
+        blocking: bool,
+    ) -> Outcome {
+        match self.dequeue(ep, |state| {
+            matches!(state, ThreadState::BlockedOnReceive { .. })
+        }) {
+            Some(receiver) => {
+                let ThreadState::BlockedOnReceive { can_grant, .. } = self.tcb(receiver).state
+                else {
+                    unreachable!()
+                };
+                self.complete_ipc(sender, receiver, state, can_grant);
+                match self.tcb(sender).state {
+                    ThreadState::Running => Outcome::Completed,
+                    _ => Outcome::Blocked,
+                }
+            }
+            None if blocking => {
+                self.block(sender, ep, state);
+                Outcome::Blocked
+            }
+            None => Outcome::Completed,
+        }
+    }
+
+    fn receive_ipc(
+        &mut self,
+        receiver: ObjId,
+        ep: ObjId,
+        can_grant: bool,
+        blocking: bool,
+    ) -> Outcome {
+        match self.dequeue(ep, |state| {
+            matches!(state, ThreadState::BlockedOnSend { .. })
+        }) {
+            Some(sender) => {
+                let state = self.tcb(sender).state;
+                self.complete_ipc(sender, receiver, state, can_grant);
+                if self.tcb(sender).state == ThreadState::Running {
+                    self.tcb_mut(sender).wake = Some(Wake::Completed);
+                }
+                Outcome::Completed
+            }
+            None if blocking => {
+                self.block(
+                    receiver,
+                    ep,
+                    ThreadState::BlockedOnReceive { ep, can_grant },
+                );
+                Outcome::Blocked
+            }
+            None => {
+                self.complete_signal(receiver, 0);
+                Outcome::Completed
+            }
+        }
+    }
+
+    // Transfers a message from a sender in `sender_state` to a receiver, leaving the sender either
+    // running or awaiting a reply.
+    fn complete_ipc(
+        &mut self,
+        sender: ObjId,
+        receiver: ObjId,
+        sender_state: ThreadState,
+        receiver_can_grant: bool,
+    ) {
+        let ThreadState::BlockedOnSend {
+            ep,
+            badge,
+            can_grant,
+            can_grant_reply,
+            is_call,
+        } = sender_state
+        else {
+            unreachable!()
+        };
+        self.transfer(sender, receiver, Some(ep), badge, can_grant);
+        self.wake(receiver);
+        self.tcb_mut(sender).state = if !is_call {
+            ThreadState::Running
+        } else if can_grant || can_grant_reply {
+            self.tcb_mut(receiver).caller = Some(Caller {
+                tcb: sender,
+                can_grant: receiver_can_grant,
+            });
+            ThreadState::BlockedOnReply
+        } else {
+            // As in the kernel, a call without the right to a reply leaves the caller inactive.
+            ThreadState::Inactive
+        };
+    }
+
+    fn do_reply(&mut self, replier: ObjId) {
+        if let Some(Caller { tcb, can_grant }) = self.tcb_mut(replier).caller.take()
+            && self.tcb(tcb).state == ThreadState::BlockedOnReply
+        {
+            self.transfer(replier, tcb, None, 0, can_grant);
+            self.wake(tcb);
+        }
+    }
+
+    pub(crate) fn signal(&mut self, ntfn: ObjId, badge: Word) {
+        let Object::Notification {
+            word,
+            waiters,
+            bound_tcb,
+        } = self.object_mut(ntfn)
+        else {
+            unreachable!()
+        };
+        *word |= badge;
+        let (waiter, bound_tcb) = (waiters.pop_front(), *bound_tcb);
+        let target = match (waiter, bound_tcb) {
+            (Some(waiter), _) => waiter,
+            (None, Some(tcb))
+                if matches!(self.tcb(tcb).state, ThreadState::BlockedOnReceive { .. }) =>
+            {
+                self.cancel_ipc_quietly(tcb);
+                tcb
+            }
+            _ => return,
+        };
+        let word = self.take_signal(ntfn).unwrap();
+        self.complete_signal(target, word);
+        self.wake(target);
+    }
+
+    fn take_signal(&mut self, ntfn: ObjId) -> Option<Word> {
+        let Object::Notification { word, .. } = self.object_mut(ntfn) else {
+            unreachable!()
+        };
+        match std::mem::take(word) {
+            0 => None,
+            word => Some(word),
+        }
+    }
+
+    fn complete_signal(&mut self, tcb: ObjId, badge: Word) {
+        let frame = &mut self.tcb_mut(tcb).frame;
+        frame.badge = badge;
+        frame.info = 0;
+    }
+
+    // // //
+
+    fn block(&mut self, tcb: ObjId, queue_obj: ObjId, state: ThreadState) {
+        match self.object_mut(queue_obj) {
+            Object::Endpoint { queue } => queue.push_back(tcb),
+            Object::Notification { waiters, .. } => waiters.push_back(tcb),
+            _ => unreachable!(),
+        }
+        self.tcb_mut(tcb).state = state;
+    }
+
+    fn dequeue(&mut self, ep: ObjId, f: impl Fn(&ThreadState) -> bool) -> Option<ObjId> {
+        let Object::Endpoint { queue } = self.object(ep) else {
+            unreachable!()
+        };
+        let tcb = *queue.front()?;
+        if !f(&self.tcb(tcb).state) {
+            return None;
+        }
+        let Object::Endpoint { queue } = self.object_mut(ep) else {
+            unreachable!()
+        };
+        queue.pop_front()
+    }
+
+    // Removes a thread from an endpoint queue without restarting its syscall.
+    fn cancel_ipc_quietly(&mut self, tcb: ObjId) {
+        if let ThreadState::BlockedOnReceive { ep, .. } = self.tcb(tcb).state
+            && let Object::Endpoint { queue } = self.object_mut(ep)
+        {
+            queue.retain(|t| *t != tcb);
+        }
+    }
+
+    fn wake(&mut self, tcb: ObjId) {
+        let tcb = self.tcb_mut(tcb);
+        tcb.state = ThreadState::Running;
+        tcb.wake = Some(Wake::Completed);
+    }
+
+    // // //
+
+    /// Copies a message, including any capabilities, from `sender`'s most recent syscall to
+    /// `receiver`'s, filling in `receiver`'s message info and badge.
+    fn transfer(
+        &mut self,
+        sender: ObjId,
+        receiver: ObjId,
+        ep: Option<ObjId>,
+        badge: Word,
+        can_grant: bool,
+    ) {
+        let info = decode_info(self.tcb(sender).frame.info);
+        let mut length = info.length().min(sel4::NUM_MESSAGE_REGISTERS);
+        if self.ipc_buffer(sender).is_none() || self.ipc_buffer(receiver).is_none() {
+            length = length.min(sel4::sys::seL4_FastMessageRegisters as usize);
+        }
+        for i in 0..length {
+            let value = self.get_mr(sender, i);
+            self.set_mr(receiver, i, value);
+        }
+
+        let mut extra_caps = 0;
+        let mut caps_unwrapped = 0;
+        if can_grant
+            && info.extra_caps() > 0
+            && let (Some(sender_buf), Some(receiver_buf)) =
+                (self.ipc_buffer(sender), self.ipc_buffer(receiver))
+            && let Some(caps) = (0..info.extra_caps())
+                .map(|i| self.lookup_cap(sender, unsafe { (*sender_buf).caps_or_badges[i] }))
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+        {
+            let mut dest = self.receive_slot(receiver);
+            for (i, (src, cap)) in caps.into_iter().enumerate() {
+                match cap {
+                    Cap::Endpoint { obj, badge, .. } if Some(obj) == ep => {
+                        unsafe {
+                            (*receiver_buf).caps_or_badges[i] = badge;
+                        }
+                        caps_unwrapped |= 1 << i;
+                    }
+                    Cap::Null => break,
+                    _ => {
+                        let Some(dest) = dest.take() else {
+                            break;
+                        };
+                        self.insert(dest, cap, Some(src));
+                    }
+                }
+                extra_caps = i + 1;
+            }
+        }
+
+        let frame = &mut self.tcb_mut(receiver).frame;
+        frame.badge = badge;
+        frame.info = encode_info(MessageInfo::new(
+            info.label(),
+            caps_unwrapped,
+            extra_caps,
+            length,
+        ));
+    }
+
+    // Corresponds to the kernel's getReceiveSlots().
+    fn receive_slot(&self, receiver: ObjId) -> Option<SlotRef> {
+        let buf = unsafe { &*self.ipc_buffer(receiver)? };
+        let (_, root) = self.lookup_cap(receiver, buf.receiveCNode).ok()?;
+        let slot = self
+            .lookup_target_slot(root, buf.receiveIndex, buf.receiveDepth)
+            .ok()?;
+        self.slot(slot).cap.is_null().then_some(slot)
+    }
+}
+
+pub(crate) fn decode_info(word: Word) -> MessageInfo {
+    MessageInfo::from_inner(sys::seL4_MessageInfo(Bitfield::new([word])))
+}
+
+pub(crate) fn encode_info(info: MessageInfo) -> Word {
+    let [word] = info.into_inner().0.into_inner();
+    word
+}
@@ -0,0 +1,501 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+
+use sel4::sys::{self, HostSyscall, seL4_Error};
+use sel4::{UserContext, Word};
+
+use crate::cap::{Cap, ObjId, mask};
+use crate::vspace::frame_entry_level;
+
+pub(crate) type KResult<T> = Result<T, seL4_Error::Type>;
+
+/// A slot, identified by its CNode and index.
+pub(crate) type SlotRef = (ObjId, usize);
+
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct Slot {
+    pub(crate) cap: Cap,
+    // The capability derivation tree is represented by a pointer from each slot to its parent.
+    pub(crate) parent: Option<SlotRef>,
+}
+
+pub(crate) enum Object {
+    Untyped {
+        size_bits: usize,
+        free_index: usize,
+    },
+    CNode {
+        slots: Vec<Slot>,
+    },
+    Endpoint {
+        // Either all senders or all receivers, as in the kernel.
+        queue: VecDeque<ObjId>,
+    },
+    Notification {
+        word: Word,
+        waiters: VecDeque<ObjId>,
+        bound_tcb: Option<ObjId>,
+    },
+    Tcb(Box<Tcb>),
+    // The contents of frames are not modelled.
+    Frame,
+    TranslationTable {
+        level: usize,
+        // Indexed by the bits of virtual addresses which this level translates.
+        entries: BTreeMap<Word, ObjId>,
+        /// The VSpace and address at which this table is mapped.
+        mapping: Option<(ObjId, Word)>,
+        /// Only meaningful for VSpaces.
+        asid_assigned: bool,
+    },
+    AsidPool,
+}
+
+pub(crate) struct Tcb {
+    pub(crate) cspace_root: Cap,
+    pub(crate) ipc_buffer: Word,
+    pub(crate) bound_notification: Option<ObjId>,
+    pub(crate) regs: UserContext,
+    pub(crate) state: ThreadState,
+    /// The registers of the syscall this thread is in, or most recently made.
+    pub(crate) frame: HostSyscall,
+    /// The thread to which a reply from this thread will be delivered, as held by the implicit reply
+    /// capability of the non-MCS kernel.
+    pub(crate) caller: Option<Caller>,
+    /// Set when a blocked syscall has finished, or must be restarted.
+    pub(crate) wake: Option<Wake>,
+    pub(crate) has_host_thread: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ThreadState {
+    Inactive,
+    Running,
+    BlockedOnSend {
+        ep: ObjId,
+        badge: Word,
+        can_grant: bool,
+        can_grant_reply: bool,
+        is_call: bool,
+    },
+    BlockedOnReceive {
+        ep: ObjId,
+        can_grant: bool,
+    },
+    BlockedOnReply,
+    BlockedOnNotification {
+        ntfn: ObjId,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Caller {
+    pub(crate) tcb: ObjId,
+    pub(crate) can_grant: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Wake {
+    Completed,
+    Restart,
+}
+
+impl Tcb {
+    pub(crate) fn new() -> Self {
+        Self {
+            cspace_root: Cap::Null,
+            ipc_buffer: 0,
+            bound_notification: None,
+            regs: UserContext::default(),
+            state: ThreadState::Inactive,
+            frame: HostSyscall::default(),
+            caller: None,
+            wake: None,
+            has_host_thread: false,
+        }
+    }
+}
+
+pub(crate) struct Kernel {
+    objects: Vec<Option<Object>>,
+    /// Threads which have been resumed for the first time, and so need host threads.
+    pub(crate) to_spawn: Vec<ObjId>,
+    /// Set once a simulated thread has panicked, so that threads waiting on it fail too.
+    pub(crate) failure: Option<String>,
+}
+
+impl Kernel {
+    pub(crate) fn new() -> Self {
+        Self {
+            objects: vec![],
+            to_spawn: vec![],
+            failure: None,
+        }
+    }
+
+    pub(crate) fn alloc(&mut self, obj: Object) -> ObjId {
+        self.objects.push(Some(obj));
+        self.objects.len() - 1
+    }
+
+    pub(crate) fn exists(&self, obj: ObjId) -> bool {
+        self.objects[obj].is_some()
+    }
+
+    pub(crate) fn object(&self, obj: ObjId) -> &Object {
+        self.objects[obj].as_ref().unwrap()
+    }
+
+    pub(crate) fn object_mut(&mut self, obj: ObjId) -> &mut Object {
+        self.objects[obj].as_mut().unwrap()
+    }
+
+    pub(crate) fn tcb(&self, obj: ObjId) -> &Tcb {
+        match self.object(obj) {
+            Object::Tcb(tcb) => tcb,
+            _ => panic!("object {obj} is not a TCB"),
+        }
+    }
+
+    pub(crate) fn tcb_mut(&mut self, obj: ObjId) -> &mut Tcb {
+        match self.object_mut(obj) {
+            Object::Tcb(tcb) => tcb,
+            _ => panic!("object {obj} is not a TCB"),
+        }
+    }
+
+    fn cnode_slots(&self, obj: ObjId) -> &[Slot] {
+        match self.object(obj) {
+            Object::CNode { slots } => slots,
+            _ => panic!("object {obj} is not a CNode"),
+        }
+    }
+
+    pub(crate) fn cnode_radix(&self, obj: ObjId) -> usize {
+        self.cnode_slots(obj).len().trailing_zeros() as usize
+    }
+
+    pub(crate) fn slot(&self, slot: SlotRef) -> &Slot {
+        &self.cnode_slots(slot.0)[slot.1]
+    }
+
+    pub(crate) fn slot_mut(&mut self, (obj, index): SlotRef) -> &mut Slot {
+        match self.object_mut(obj) {
+            Object::CNode { slots } => &mut slots[index],
+            _ => panic!("object {obj} is not a CNode"),
+        }
+    }
+
+    // // //
+
+    /// Corresponds to the kernel's resolveAddressBits(). Returns the slot at which resolution
+    /// stopped, and the number of bits of `cptr` which remain unresolved.
+    pub(crate) fn resolve(
+        &self,
+        mut node: Cap,
+        cptr: Word,
+        mut n_bits: usize,
+    ) -> KResult<(SlotRef, usize)> {
+        loop {
+            let Cap::CNode {
+                obj,
+                guard,
+                guard_size,
+            } = node
+            else {
+                return Err(seL4_Error::seL4_FailedLookup);
+            };
+            let radix = self.cnode_radix(obj);
+            let level = radix + guard_size;
+            if guard_size > n_bits || extract(cptr, n_bits - guard_size, guard_size) != guard {
+                return Err(seL4_Error::seL4_FailedLookup);
+            }
+            if level > n_bits {
+                return Err(seL4_Error::seL4_FailedLookup);
+            }
+            let slot = (obj, extract(cptr, n_bits - level, radix) as usize);
+            if n_bits == level {
+                return Ok((slot, 0));
+            }
+            n_bits -= level;
+            node = self.slot(slot).cap;
+            if !matches!(node, Cap::CNode { .. }) {
+                return Ok((slot, n_bits));
+            }
+        }
+    }
+
+    /// Looks up a capability in a thread's CSpace, as the kernel does for syscall arguments.
+    pub(crate) fn lookup_cap(&self, tcb: ObjId, cptr: Word) -> KResult<(SlotRef, Cap)> {
+        let (slot, _) = self.resolve(self.tcb(tcb).cspace_root, cptr, sel4::WORD_SIZE)?;
+        Ok((slot, self.slot(slot).cap))
+    }
+
+    /// Corresponds to the kernel's lookupTargetSlot().
+    pub(crate) fn lookup_target_slot(
+        &self,
+        root: Cap,
+        index: Word,
+        depth: Word,
+    ) -> KResult<SlotRef> {
+        let depth = depth as usize;
+        if depth < 1 || depth > sel4::WORD_SIZE {
+            return Err(seL4_Error::seL4_RangeError);
+        }
+        match self.resolve(root, index, depth)? {
+            (slot, 0) => Ok(slot),
+            _ => Err(seL4_Error::seL4_FailedLookup),
+        }
+    }
+
+    // // //
+
+    fn all_slots(&self) -> impl Iterator<Item = (SlotRef, &Slot)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(obj, object)| match object {
+                Some(Object::CNode { slots }) => Some((obj, slots)),
+                _ => None,
+            })
+            .flat_map(|(obj, slots)| {
+                slots
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, slot)| ((obj, index), slot))
+            })
+    }
+
+    fn children(&self, parent: SlotRef) -> Vec<SlotRef> {
+        self.all_slots()
+            .filter(|(_, slot)| slot.parent == Some(parent))
+            .map(|(slot_ref, _)| slot_ref)
+            .collect()
+    }
+
+    pub(crate) fn has_children(&self, parent: SlotRef) -> bool {
+        self.all_slots()
+            .any(|(_, slot)| slot.parent == Some(parent))
+    }
+
+    pub(crate) fn insert(&mut self, dest: SlotRef, cap: Cap, parent: Option<SlotRef>) {
+        assert!(self.slot(dest).cap.is_null());
+        *self.slot_mut(dest) = Slot { cap, parent };
+    }
+
+    /// Moves a capability along with its position in the derivation tree.
+    pub(crate) fn move_cap(&mut self, src: SlotRef, dest: SlotRef, cap: Cap) {
+        let parent = self.slot(src).parent;
+        for child in self.children(src) {
+            self.slot_mut(child).parent = Some(dest);
+        }
+        *self.slot_mut(src) = Slot::default();
+        self.insert(dest, cap, parent);
+    }
+
+    pub(crate) fn delete(&mut self, slot_ref: SlotRef) {
+        let slot = *self.slot(slot_ref);
+        if slot.cap.is_null() {
+            return;
+        }
+        for child in self.children(slot_ref) {
+            self.slot_mut(child).parent = slot.parent;
+        }
+        // As in the kernel, a frame's mapping belongs to the capability through which it was made.
+        if let Cap::Frame {
+            obj,
+            size_bits,
+            mapping: Some((vspace, vaddr)),
+        } = slot.cap
+        {
+            self.unmap_vm_object(vspace, obj, frame_entry_level(size_bits), vaddr);
+        }
+        *self.slot_mut(slot_ref) = Slot::default();
+        if let Some(obj) = slot.cap.obj()
+            && !self.is_referenced(obj)
+        {
+            self.destroy(obj);
+        }
+    }
+
+    // A thread's CSpace root is a copy of a capability which is not in any slot.
+    fn is_referenced(&self, obj: ObjId) -> bool {
+        self.all_slots()
+            .any(|(_, slot)| slot.cap.obj() == Some(obj))
+            || self.objects.iter().any(|object| match object {
+                Some(Object::Tcb(tcb)) => tcb.cspace_root.obj() == Some(obj),
+                _ => false,
+            })
+    }
+
+    pub(crate) fn revoke(&mut self, slot_ref: SlotRef) {
+        for child in self.children(slot_ref) {
+            self.revoke(child);
+            self.delete(child);
+        }
+    }
+
+    // Called once the final capability to an object has been deleted.
+    fn destroy(&mut self, obj: ObjId) {
+        match self.object(obj) {
+            Object::CNode { slots } => {
+                for index in 0..slots.len() {
+                    self.delete((obj, index));
+                }
+            }
+            Object::Endpoint { queue } => {
+                for tcb in queue.clone() {
+                    self.cancel_ipc(tcb);
+                }
+            }
+            Object::Notification {
+                waiters, bound_tcb, ..
+            } => {
+                let (waiters, bound_tcb) = (waiters.clone(), *bound_tcb);
+                if let Some(tcb) = bound_tcb {
+                    self.tcb_mut(tcb).bound_notification = None;
+                }
+                for tcb in waiters {
+                    self.cancel_ipc(tcb);
+                }
+            }
+            Object::Tcb(_) => {
+                self.suspend(obj);
+                self.unbind_notification(obj);
+                // Host threads may still refer to the TCB, so it is never freed.
+                return;
+            }
+            Object::TranslationTable {
+                level,
+                mapping: Some((vspace, vaddr)),
+                ..
+            } => {
+                let (level, vspace, vaddr) = (*level, *vspace, *vaddr);
+                self.unmap_vm_object(vspace, obj, level - 1, vaddr);
+            }
+            Object::Untyped { .. }
+            | Object::Frame
+            | Object::TranslationTable { .. }
+            | Object::AsidPool => {}
+        }
+        self.objects[obj] = None;
+    }
+
+    // // //
+
+    /// Removes a thread from whatever queue it is blocked on, such that it restarts its syscall once
+    /// it runs again.
+    pub(crate) fn cancel_ipc(&mut self, tcb: ObjId) {
+        let state = self.tcb(tcb).state;
+        let queue = match state {
+            ThreadState::BlockedOnSend { ep, .. } | ThreadState::BlockedOnReceive { ep, .. } => {
+                match self.object_mut(ep) {
+                    Object::Endpoint { queue } => Some(queue),
+                    _ => unreachable!(),
+                }
+            }
+            ThreadState::BlockedOnNotification { ntfn } => match self.object_mut(ntfn) {
+                Object::Notification { waiters, .. } => Some(waiters),
+                _ => unreachable!(),
+            },
+            ThreadState::BlockedOnReply => None,
+            ThreadState::Inactive | ThreadState::Running => return,
+        };
+        if let Some(queue) = queue {
+            queue.retain(|t| *t != tcb);
+        }
+        for caller in self.all_tcbs_mut() {
+            if caller.caller.is_some_and(|caller| caller.tcb == tcb) {
+                caller.caller = None;
+            }
+        }
+        let tcb = self.tcb_mut(tcb);
+        tcb.state = ThreadState::Running;
+        tcb.wake = Some(Wake::Restart);
+    }
+
+    fn all_tcbs_mut(&mut self) -> impl Iterator<Item = &mut Tcb> {
+        self.objects.iter_mut().filter_map(|object| match object {
+            Some(Object::Tcb(tcb)) => Some(&mut **tcb),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn suspend(&mut self, tcb: ObjId) {
+        self.cancel_ipc(tcb);
+        let tcb = self.tcb_mut(tcb);
+        if tcb.wake.is_none() {
+            // If the thread is in the middle of this syscall, it completes once resumed.
+            tcb.wake = Some(Wake::Completed);
+        }
+        tcb.state = ThreadState::Inactive;
+    }
+
+    pub(crate) fn resume(&mut self, obj: ObjId) {
+        let tcb = self.tcb_mut(obj);
+        if tcb.state != ThreadState::Inactive {
+            return;
+        }
+        tcb.state = ThreadState::Running;
+        if !tcb.has_host_thread && *tcb.regs.pc() != 0 {
+            tcb.has_host_thread = true;
+            self.to_spawn.push(obj);
+        }
+    }
+
+    pub(crate) fn unbind_notification(&mut self, tcb: ObjId) {
+        if let Some(ntfn) = mem::take(&mut self.tcb_mut(tcb).bound_notification) {
+            match self.object_mut(ntfn) {
+                Object::Notification { bound_tcb, .. } => *bound_tcb = None,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    // // //
+
+    pub(crate) fn ipc_buffer(&self, tcb: ObjId) -> Option<*mut sys::seL4_IPCBuffer> {
+        match self.tcb(tcb).ipc_buffer {
+            0 => None,
+            addr => Some(addr as *mut sys::seL4_IPCBuffer),
+        }
+    }
+
+    pub(crate) fn get_mr(&self, tcb: ObjId, i: usize) -> Word {
+        let frame = &self.tcb(tcb).frame;
+        if i < frame.mrs.len() {
+            frame.mrs[i]
+        } else {
+            match self.ipc_buffer(tcb) {
+                Some(buf) => unsafe { (*buf).msg[i] },
+                None => 0,
+            }
+        }
+    }
+
+    pub(crate) fn set_mr(&mut self, tcb: ObjId, i: usize, value: Word) {
+        let ipc_buffer = self.ipc_buffer(tcb);
+        let frame = &mut self.tcb_mut(tcb).frame;
+        if i < frame.mrs.len() {
+            frame.mrs[i] = value;
+        } else if let Some(buf) = ipc_buffer {
+            unsafe {
+                (*buf).msg[i] = value;
+            }
+        }
+    }
+}
+
+fn extract(word: Word, start: usize, width: usize) -> Word {
+    if width == 0 {
+        0
+    } else {
+        (word >> start) & mask(width)
+    }
+}
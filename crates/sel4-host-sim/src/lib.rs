@@ -0,0 +1,192 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A simulated seL4 kernel, for testing code which uses the [`sel4`] crate with `cargo test` on
+//! the host.
+//!
+//! Depending on this crate enables the `host-sim` feature of [`sel4`], which replaces the
+//! architecture-specific syscall stubs with calls into an in-process model of the kernel. The
+//! model covers:
+//!
+//! - CNodes, including guards, multi-level lookup, and the capability derivation tree
+//! - `seL4_Untyped_Retype` for untyped, CNode, endpoint, notification, and TCB objects
+//! - copying, minting, moving, mutating, deleting, and revoking capabilities
+//! - endpoint IPC, including badges, calls and replies, and capability transfer
+//! - notifications, including binding to TCBs
+//! - TCBs, each of which is backed by a host thread once it is first resumed
+//! - on x86_64, frames, translation tables, and VSpaces: retyping them, mapping and unmapping them,
+//!   and assigning VSpaces to the initial thread's ASID pool
+//!
+//! Only the structure of address spaces is modelled. Simulated threads run in the host's address
+//! space, and the contents of frames do not exist. A TCB's IPC buffer address is interpreted as a
+//! host pointer, and a TCB's program counter is interpreted as a host function pointer of type
+//! `extern "C-unwind" fn(Word, Word, Word)`, which is called with the TCB's first three C
+//! parameter registers. If a TCB's IPC buffer address is 0 when it is first resumed, then the
+//! simulator allocates one for it. Scheduling is left to the host. If a simulated thread panics,
+//! then any other simulated thread which is, or later becomes, blocked in a syscall panics too.
+//!
+//! Only the non-MCS kernel API is supported.
+//!
+//! As with any other crate which depends on [`sel4`], building this crate requires libsel4, which
+//! is located using `SEL4_PREFIX` or `SEL4_INCLUDE_DIRS`. Here, libsel4 must be configured for the
+//! host's architecture, and without MCS. For example, on an x86_64 host, with a non-MCS x86_64
+//! build of seL4 installed at `$SEL4_PREFIX`:
+//!
+//! ```sh
+//! SEL4_PREFIX=... cargo test -p sel4-host-sim
+//! ```
+//!
+//! This is how CI runs this crate's tests (see `hostSimTests` in
+//! `hacking/nix/top-level/aggregates.nix`).
+//!
+//! ```ignore
+//! let mut sim = Sim::new(&SimConfig::default());
+//! let slots = sim.initial_slots().clone();
+//! sim.run(|| {
+//!     let untyped = sel4::init_thread::Slot::<sel4::cap_type::Untyped>::from_index(
+//!         slots.untyped.start,
+//!     );
+//!     // ...
+//! });
+//! ```
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use sel4::sys::{self, HostSyscall};
+use sel4::{IpcBuffer, Word};
+
+mod cap;
+mod invocation;
+mod ipc;
+mod kernel;
+mod thread;
+mod vspace;
+
+use cap::{Cap, ObjId};
+use kernel::{Kernel, Object, Slot, Tcb, ThreadState};
+use thread::Shared;
+
+#[sel4::sel4_cfg(KERNEL_MCS)]
+compile_error!("sel4-host-sim does not support the MCS kernel API");
+
+#[unsafe(no_mangle)]
+fn __sel4_sys__host_syscall(syscall: &mut HostSyscall) {
+    thread::syscall(syscall)
+}
+
+/// Configuration for a [`Sim`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimConfig {
+    /// The size of the initial thread's CNode.
+    pub cnode_size_bits: usize,
+    /// The sizes of the untyped objects given to the initial thread.
+    pub untyped_size_bits: Vec<usize>,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            cnode_size_bits: 12,
+            untyped_size_bits: vec![20],
+        }
+    }
+}
+
+/// Ranges of slots in the initial thread's CNode, analogous to those found in
+/// [`sel4::BootInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitialSlots {
+    /// Slots containing the untyped objects described by [`SimConfig::untyped_size_bits`], in
+    /// order.
+    pub untyped: Range<usize>,
+    /// Empty slots.
+    pub empty: Range<usize>,
+}
+
+/// An instance of the simulated kernel.
+///
+/// The initial thread's CNode has the same layout as that of a root task, for the slots which
+/// this simulator models. In particular, [`sel4::init_thread::slot::TCB`],
+/// [`sel4::init_thread::slot::CNODE`], and [`sel4::init_thread::slot::ASID_POOL`] are valid.
+pub struct Sim {
+    shared: Arc<Shared>,
+    init_thread: ObjId,
+    initial_slots: InitialSlots,
+}
+
+impl Sim {
+    pub fn new(config: &SimConfig) -> Self {
+        let mut kernel = Kernel::new();
+        let radix = config.cnode_size_bits;
+        let num_slots = 1 << radix;
+        let num_initial_caps = sys::seL4_RootCNodeCapSlots::seL4_NumInitialCaps as usize;
+        let untyped = num_initial_caps..num_initial_caps + config.untyped_size_bits.len();
+        assert!(untyped.end <= num_slots, "initial CNode is too small");
+
+        let cnode = kernel.alloc(Object::CNode {
+            slots: vec![Slot::default(); num_slots],
+        });
+        // As for the root task, the CNode resolves the whole word.
+        let cnode_cap = Cap::CNode {
+            obj: cnode,
+            guard: 0,
+            guard_size: sel4::WORD_SIZE - radix,
+        };
+        let ipc_buffer = Box::leak(Box::new(IpcBuffer::default()));
+        let tcb = kernel.alloc(Object::Tcb(Box::new(Tcb {
+            cspace_root: cnode_cap,
+            ipc_buffer: ipc_buffer as *mut IpcBuffer as Word,
+            state: ThreadState::Running,
+            has_host_thread: true,
+            ..Tcb::new()
+        })));
+
+        kernel.insert(
+            (cnode, sel4::init_thread::slot::TCB.index()),
+            Cap::Tcb { obj: tcb },
+            None,
+        );
+        kernel.insert(
+            (cnode, sel4::init_thread::slot::CNODE.index()),
+            cnode_cap,
+            None,
+        );
+        let asid_pool = kernel.alloc(Object::AsidPool);
+        kernel.insert(
+            (cnode, sel4::init_thread::slot::ASID_POOL.index()),
+            Cap::AsidPool { obj: asid_pool },
+            None,
+        );
+        for (i, size_bits) in untyped.clone().zip(&config.untyped_size_bits) {
+            let obj = kernel.alloc(Object::Untyped {
+                size_bits: *size_bits,
+                free_index: 0,
+            });
+            kernel.insert((cnode, i), Cap::Untyped { obj }, None);
+        }
+
+        Self {
+            shared: Arc::new(Shared::new(kernel)),
+            init_thread: tcb,
+            initial_slots: InitialSlots {
+                empty: untyped.end..num_slots,
+                untyped,
+            },
+        }
+    }
+
+    pub fn initial_slots(&self) -> &InitialSlots {
+        &self.initial_slots
+    }
+
+    /// Runs `f` on the current host thread on behalf of the simulated initial thread.
+    ///
+    /// Within `f`, the [`sel4`] crate's IPC buffer is set to that of the initial thread.
+    pub fn run<T>(&mut self, f: impl FnOnce() -> T) -> T {
+        thread::with_current(&self.shared, self.init_thread, f)
+    }
+}
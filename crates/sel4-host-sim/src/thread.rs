@@ -0,0 +1,156 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+use sel4::sys::{HostSyscall, syscall_id};
+use sel4::{IpcBuffer, Word};
+
+use crate::cap::ObjId;
+use crate::ipc::Outcome;
+use crate::kernel::{Kernel, ThreadState, Wake};
+
+// "C-unwind", so that a panic in a simulated thread unwinds to the catch_unwind in spawn rather
+// than aborting the process.
+type Entry = extern "C-unwind" fn(Word, Word, Word);
+
+pub(crate) struct Shared {
+    kernel: Mutex<Kernel>,
+    // Signalled whenever the kernel state changes in a way that might unblock a thread.
+    cond: Condvar,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<(Arc<Shared>, ObjId)>> = const { RefCell::new(None) };
+}
+
+/// Binds the current host thread to a simulated TCB for the duration of `f`.
+pub(crate) fn with_current<T>(shared: &Arc<Shared>, tcb: ObjId, f: impl FnOnce() -> T) -> T {
+    let ipc_buffer = shared.lock().tcb(tcb).ipc_buffer;
+    let prev = CURRENT.replace(Some((shared.clone(), tcb)));
+    sel4::set_ipc_buffer(unsafe { &mut *(ipc_buffer as *mut IpcBuffer) });
+    let ret = panic::catch_unwind(AssertUnwindSafe(f));
+    sel4::try_with_ipc_buffer_slot_mut(|slot| {
+        *slot.unwrap() = None;
+    });
+    CURRENT.set(prev);
+    ret.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+pub(crate) fn syscall(syscall: &mut HostSyscall) {
+    let (shared, tcb) = CURRENT.with_borrow(|current| current.clone()).expect(
+        "seL4 syscall made from a host thread which is not running on behalf of a simulated TCB",
+    );
+    shared.syscall(tcb, syscall);
+}
+
+impl Shared {
+    pub(crate) fn new(kernel: Kernel) -> Self {
+        Self {
+            kernel: Mutex::new(kernel),
+            cond: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Kernel> {
+        self.kernel.lock().unwrap()
+    }
+
+    fn syscall(self: &Arc<Self>, tcb: ObjId, syscall: &mut HostSyscall) {
+        let mut kernel = self.lock();
+        loop {
+            {
+                let tcb = kernel.tcb_mut(tcb);
+                tcb.frame = *syscall;
+                tcb.wake = None;
+            }
+            let outcome = kernel.handle_syscall(tcb);
+            for new in mem::take(&mut kernel.to_spawn) {
+                self.spawn(&mut kernel, new);
+            }
+            self.cond.notify_all();
+            match outcome {
+                Outcome::Completed => {
+                    let tcb = kernel.tcb_mut(tcb);
+                    if tcb.wake.is_none() {
+                        tcb.wake = Some(Wake::Completed);
+                    }
+                }
+                Outcome::Blocked => {}
+                Outcome::Fault(msg) => {
+                    drop(kernel);
+                    panic!("simulated thread {tcb} faulted: {msg}");
+                }
+            }
+            // A thread which has suspended itself waits here until it is resumed.
+            kernel = self
+                .cond
+                .wait_while(kernel, |kernel| {
+                    let tcb = kernel.tcb(tcb);
+                    kernel.failure.is_none()
+                        && (tcb.wake.is_none() || tcb.state != ThreadState::Running)
+                })
+                .unwrap();
+            if let Some(failure) = &kernel.failure {
+                let failure = failure.clone();
+                drop(kernel);
+                panic!("another simulated thread panicked: {failure}");
+            }
+            if kernel.tcb(tcb).wake == Some(Wake::Completed) {
+                break;
+            }
+        }
+        *syscall = kernel.tcb(tcb).frame;
+        drop(kernel);
+        if syscall.sys == syscall_id::Yield {
+            thread::yield_now();
+        }
+    }
+
+    // Called with the kernel locked, when a TCB which has never run is resumed.
+    fn spawn(self: &Arc<Self>, kernel: &mut Kernel, tcb: ObjId) {
+        let regs = &kernel.tcb(tcb).regs;
+        let entry: Entry = unsafe { mem::transmute::<usize, Entry>(*regs.pc() as usize) };
+        let args = [0, 1, 2].map(|i| *regs.c_param(i));
+        if kernel.tcb(tcb).ipc_buffer == 0 {
+            let ipc_buffer = Box::leak(Box::new(IpcBuffer::default()));
+            kernel.tcb_mut(tcb).ipc_buffer = ipc_buffer as *mut IpcBuffer as Word;
+        }
+        let shared = self.clone();
+        thread::Builder::new()
+            .name(format!("sel4-tcb-{tcb}"))
+            .spawn(move || {
+                let ret = panic::catch_unwind(|| {
+                    with_current(&shared, tcb, || entry(args[0], args[1], args[2]))
+                });
+                let mut kernel = shared.lock();
+                if let Err(payload) = ret {
+                    kernel.failure = Some(panic_message(&*payload));
+                }
+                // A thread which returns from its entry point is suspended, and gets a new host
+                // thread if it is resumed again.
+                kernel.suspend(tcb);
+                kernel.tcb_mut(tcb).has_host_thread = false;
+                shared.cond.notify_all();
+            })
+            .unwrap();
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_owned()
+    }
+}
@@ -0,0 +1,239 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::BTreeMap;
+
+use sel4::sys::seL4_Error;
+use sel4::{FrameObjectType, ObjectBlueprint, TranslationTableObjectType, Word, vspace_levels};
+
+use crate::cap::{Cap, ObjId, mask};
+use crate::kernel::{KResult, Kernel, Object};
+
+/// Frame and translation table invocations, independent of their architecture-specific labels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum VmInvocation {
+    PageMap,
+    PageUnmap,
+    TableMap,
+    AsidPoolAssign,
+}
+
+sel4::sel4_cfg_if! {
+    if #[sel4_cfg(ARCH_X86_64)] {
+        use sel4::sys::invocation_label;
+
+        const MODELLED: bool = true;
+
+        pub(crate) fn decode_label(label: u32) -> Option<VmInvocation> {
+            Some(match label {
+                invocation_label::X86PageMap => VmInvocation::PageMap,
+                invocation_label::X86PageUnmap => VmInvocation::PageUnmap,
+                invocation_label::X86PDPTMap
+                | invocation_label::X86PageDirectoryMap
+                | invocation_label::X86PageTableMap => VmInvocation::TableMap,
+                invocation_label::X86ASIDPoolAssign => VmInvocation::AsidPoolAssign,
+                _ => return None,
+            })
+        }
+
+        // Correspond to the kernel's cap type tags. Tables are indexed by level.
+        pub(crate) const FRAME_TAG: Word = 1;
+        pub(crate) const TABLE_TAGS: [Word; vspace_levels::NUM_LEVELS] = [9, 7, 5, 3];
+        pub(crate) const ASID_POOL_TAG: Word = 13;
+    } else {
+        // Frames and translation tables are only modelled on x86_64, so no such capabilities can
+        // be created elsewhere.
+        const MODELLED: bool = false;
+
+        pub(crate) fn decode_label(_label: u32) -> Option<VmInvocation> {
+            None
+        }
+
+        pub(crate) const FRAME_TAG: Word = 0;
+        pub(crate) const TABLE_TAGS: [Word; vspace_levels::NUM_LEVELS] =
+            [0; vspace_levels::NUM_LEVELS];
+        pub(crate) const ASID_POOL_TAG: Word = 0;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum VmObjectType {
+    Frame {
+        size_bits: usize,
+    },
+    /// Level 0 is a VSpace.
+    TranslationTable {
+        level: usize,
+    },
+}
+
+/// The frame and translation table objects which can be retyped from untyped memory.
+pub(crate) fn vm_object_types() -> impl Iterator<Item = (ObjectBlueprint, VmObjectType)> {
+    let frames = (0..sel4::WORD_SIZE)
+        .filter_map(FrameObjectType::from_bits)
+        .map(|ty| {
+            (
+                ty.blueprint(),
+                VmObjectType::Frame {
+                    size_bits: ty.bits(),
+                },
+            )
+        });
+    let tables = (0..vspace_levels::NUM_LEVELS).map(|level| {
+        (
+            TranslationTableObjectType::from_level(level)
+                .unwrap()
+                .blueprint(),
+            VmObjectType::TranslationTable { level },
+        )
+    });
+    frames.chain(tables).filter(|_| MODELLED)
+}
+
+pub(crate) fn vm_object_type(blueprint: ObjectBlueprint) -> Option<VmObjectType> {
+    vm_object_types()
+        .find(|(b, _)| *b == blueprint)
+        .map(|(_, ty)| ty)
+}
+
+impl Kernel {
+    pub(crate) fn create_vm_object(&mut self, ty: VmObjectType) -> Cap {
+        match ty {
+            VmObjectType::Frame { size_bits } => Cap::Frame {
+                obj: self.alloc(Object::Frame),
+                size_bits,
+                mapping: None,
+            },
+            VmObjectType::TranslationTable { level } => Cap::TranslationTable {
+                obj: self.alloc(Object::TranslationTable {
+                    level,
+                    entries: BTreeMap::new(),
+                    mapping: None,
+                    asid_assigned: false,
+                }),
+                level,
+            },
+        }
+    }
+
+    pub(crate) fn assign_asid(&mut self, vspace: Cap) -> KResult<()> {
+        let Cap::TranslationTable { obj, level: 0 } = vspace else {
+            return Err(seL4_Error::seL4_InvalidCapability);
+        };
+        let Object::TranslationTable { asid_assigned, .. } = self.object_mut(obj) else {
+            unreachable!()
+        };
+        if *asid_assigned {
+            return Err(seL4_Error::seL4_InvalidCapability);
+        }
+        *asid_assigned = true;
+        Ok(())
+    }
+
+    /// Maps the frame or translation table `obj` at `vaddr` in `vspace`, as an entry of a table at
+    /// `entry_level`. Returns `vaddr` rounded down to the span of the entry.
+    pub(crate) fn map_vm_object(
+        &mut self,
+        vspace: Cap,
+        obj: ObjId,
+        entry_level: usize,
+        vaddr: Word,
+    ) -> KResult<Word> {
+        let Cap::TranslationTable {
+            obj: vspace,
+            level: 0,
+        } = vspace
+        else {
+            return Err(seL4_Error::seL4_InvalidCapability);
+        };
+        if !matches!(
+            self.object(vspace),
+            Object::TranslationTable {
+                asid_assigned: true,
+                ..
+            }
+        ) {
+            return Err(seL4_Error::seL4_InvalidCapability);
+        }
+        if vaddr >> vspace_levels::span_bits(0) != 0 {
+            return Err(seL4_Error::seL4_InvalidArgument);
+        }
+        let table = self.lookup_table(vspace, vaddr, entry_level)?;
+        if self.entry(table, entry_level, vaddr).is_some() {
+            return Err(seL4_Error::seL4_DeleteFirst);
+        }
+        self.set_entry(table, entry_level, vaddr, Some(obj));
+        Ok(vaddr & !mask(vspace_levels::step_bits(entry_level)))
+    }
+
+    /// Removes the entry for `obj` at `vaddr` in `vspace`, if it is still present.
+    pub(crate) fn unmap_vm_object(
+        &mut self,
+        vspace: ObjId,
+        obj: ObjId,
+        entry_level: usize,
+        vaddr: Word,
+    ) {
+        if !self.exists(vspace) {
+            return;
+        }
+        if let Ok(table) = self.lookup_table(vspace, vaddr, entry_level)
+            && self.entry(table, entry_level, vaddr) == Some(obj)
+        {
+            self.set_entry(table, entry_level, vaddr, None);
+        }
+    }
+
+    /// Walks the tables of `vspace` toward `vaddr`, returning the table at `level`.
+    fn lookup_table(&self, vspace: ObjId, vaddr: Word, level: usize) -> KResult<ObjId> {
+        let mut table = vspace;
+        for l in 0..level {
+            table = match self.entry(table, l, vaddr) {
+                Some(next) if matches!(self.object(next), Object::TranslationTable { .. }) => next,
+                _ => return Err(seL4_Error::seL4_FailedLookup),
+            };
+        }
+        Ok(table)
+    }
+
+    // Entries referring to objects which have since been destroyed are treated as empty.
+    fn entry(&self, table: ObjId, level: usize, vaddr: Word) -> Option<ObjId> {
+        let Object::TranslationTable { entries, .. } = self.object(table) else {
+            unreachable!()
+        };
+        entries
+            .get(&entry_index(level, vaddr))
+            .copied()
+            .filter(|obj| self.exists(*obj))
+    }
+
+    fn set_entry(&mut self, table: ObjId, level: usize, vaddr: Word, obj: Option<ObjId>) {
+        let Object::TranslationTable { entries, .. } = self.object_mut(table) else {
+            unreachable!()
+        };
+        let index = entry_index(level, vaddr);
+        match obj {
+            Some(obj) => {
+                entries.insert(index, obj);
+            }
+            None => {
+                entries.remove(&index);
+            }
+        }
+    }
+}
+
+/// The level of the table whose entries are frames of `size_bits`.
+pub(crate) fn frame_entry_level(size_bits: usize) -> usize {
+    (vspace_levels::HIGHEST_LEVEL_WITH_PAGE_ENTRIES..vspace_levels::NUM_LEVELS)
+        .find(|level| vspace_levels::step_bits(*level) == size_bits)
+        .unwrap()
+}
+
+fn entry_index(level: usize, vaddr: Word) -> Word {
+    let step_bits = vspace_levels::step_bits(level);
+    (vaddr >> step_bits) & mask(vspace_levels::span_bits(level) - step_bits)
+}
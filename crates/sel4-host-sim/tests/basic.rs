@@ -0,0 +1,153 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{CNodeCapData, CPtr, CapRights, MessageInfo, ObjectBlueprint, UserContext, Word};
+use sel4::{cap, cap_type, init_thread};
+use sel4_host_sim::{Sim, SimConfig};
+
+#[test]
+fn retype_mint_revoke() {
+    let mut sim = Sim::new(&SimConfig::default());
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let cnode = init_thread::slot::CNODE.cap();
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let ntfn = init_thread::Slot::<cap_type::Notification>::from_index(slots.empty.start);
+        let badged = init_thread::Slot::<cap_type::Notification>::from_index(slots.empty.start + 1);
+        let spare = slots.empty.start + 2;
+
+        untyped
+            .untyped_retype(
+                &ObjectBlueprint::Notification,
+                &cnode.absolute_cptr_for_self(),
+                ntfn.index(),
+                1,
+            )
+            .unwrap();
+        assert_eq!(
+            untyped.untyped_retype(
+                &ObjectBlueprint::Notification,
+                &cnode.absolute_cptr_for_self(),
+                ntfn.index(),
+                1,
+            ),
+            Err(sel4::Error::DeleteFirst),
+        );
+
+        cnode
+            .absolute_cptr(badged.cptr())
+            .mint(&cnode.absolute_cptr(ntfn.cptr()), CapRights::all(), 0b101)
+            .unwrap();
+        badged.cap().signal();
+        let (_, badge) = ntfn.cap().wait();
+        assert_eq!(badge, 0b101);
+
+        cnode.absolute_cptr(ntfn.cptr()).revoke().unwrap();
+        assert_eq!(
+            cnode
+                .absolute_cptr(CPtr::from_bits(spare as Word))
+                .copy(&cnode.absolute_cptr(badged.cptr()), CapRights::all()),
+            Err(sel4::Error::FailedLookup),
+        );
+    });
+}
+
+extern "C-unwind" fn server(ep: Word, _: Word, _: Word) {
+    let ep = cap::Endpoint::from_bits(ep);
+    let (_, badge) = ep.recv(());
+    sel4::with_ipc_buffer_mut(|ipc_buffer| {
+        ipc_buffer.msg_regs_mut()[0] += badge;
+        sel4::reply(ipc_buffer, MessageInfo::new(0, 0, 0, 1));
+    });
+}
+
+#[test]
+fn call_and_reply() {
+    let mut sim = Sim::new(&SimConfig::default());
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let cnode = init_thread::slot::CNODE.cap();
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let ep = init_thread::Slot::<cap_type::Endpoint>::from_index(slots.empty.start);
+        let badged = init_thread::Slot::<cap_type::Endpoint>::from_index(slots.empty.start + 1);
+        let tcb = init_thread::Slot::<cap_type::Tcb>::from_index(slots.empty.start + 2);
+
+        for (blueprint, slot) in [
+            (ObjectBlueprint::Endpoint, ep.index()),
+            (ObjectBlueprint::Tcb, tcb.index()),
+        ] {
+            untyped
+                .untyped_retype(&blueprint, &cnode.absolute_cptr_for_self(), slot, 1)
+                .unwrap();
+        }
+        cnode
+            .absolute_cptr(badged.cptr())
+            .mint(&cnode.absolute_cptr(ep.cptr()), CapRights::all(), 7)
+            .unwrap();
+
+        let tcb = tcb.cap();
+        tcb.tcb_set_space(
+            CPtr::from_bits(0),
+            cnode,
+            CNodeCapData::new(0, 0),
+            cap::VSpace::from_bits(0),
+        )
+        .unwrap();
+        let mut regs = UserContext::default();
+        *regs.pc_mut() = server as usize as Word;
+        *regs.c_param_mut(0) = ep.cptr_bits();
+        tcb.tcb_write_all_registers(true, &mut regs).unwrap();
+
+        sel4::with_ipc_buffer_mut(|ipc_buffer| ipc_buffer.msg_regs_mut()[0] = 35);
+        let info = badged.cap().call(MessageInfo::new(0, 0, 0, 1));
+        assert_eq!(info.length(), 1);
+        assert_eq!(
+            sel4::with_ipc_buffer(|ipc_buffer| ipc_buffer.msg_regs()[0]),
+            42
+        );
+    });
+}
+
+extern "C-unwind" fn panicker(_: Word, _: Word, _: Word) {
+    panic!("panicker panicked");
+}
+
+#[test]
+#[should_panic(expected = "another simulated thread panicked: panicker panicked")]
+fn panic_propagates() {
+    let mut sim = Sim::new(&SimConfig::default());
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let cnode = init_thread::slot::CNODE.cap();
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let ntfn = init_thread::Slot::<cap_type::Notification>::from_index(slots.empty.start);
+        let tcb = init_thread::Slot::<cap_type::Tcb>::from_index(slots.empty.start + 1);
+
+        for (blueprint, slot) in [
+            (ObjectBlueprint::Notification, ntfn.index()),
+            (ObjectBlueprint::Tcb, tcb.index()),
+        ] {
+            untyped
+                .untyped_retype(&blueprint, &cnode.absolute_cptr_for_self(), slot, 1)
+                .unwrap();
+        }
+
+        let tcb = tcb.cap();
+        tcb.tcb_set_space(
+            CPtr::from_bits(0),
+            cnode,
+            CNodeCapData::new(0, 0),
+            cap::VSpace::from_bits(0),
+        )
+        .unwrap();
+        let mut regs = UserContext::default();
+        *regs.pc_mut() = panicker as usize as Word;
+        tcb.tcb_write_all_registers(true, &mut regs).unwrap();
+
+        // Nothing signals this notification, so this returns only by panicking.
+        ntfn.cap().wait();
+    });
+}
@@ -0,0 +1,198 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// These views are plain byte parsing, but the `sel4` crate only builds on a host with the
+// simulator's backend, so they are tested here.
+
+use sel4::{
+    BootInfoExtraTruncatedError, X86AcpiRsdp, X86Framebuffer, X86MemoryMap, X86MemoryMapEntry,
+    X86MemoryMapEntryType, X86Vbe,
+};
+
+#[test]
+fn x86_framebuffer() {
+    let mut content = [0; 22];
+    content[0..8].copy_from_slice(&0xfd00_0000u64.to_ne_bytes());
+    content[8..12].copy_from_slice(&4096u32.to_ne_bytes());
+    content[12..16].copy_from_slice(&1024u32.to_ne_bytes());
+    content[16..20].copy_from_slice(&768u32.to_ne_bytes());
+    content[20] = 32;
+    content[21] = 1;
+    let fb = X86Framebuffer::new(&content).unwrap();
+    assert_eq!(fb.addr(), 0xfd00_0000);
+    assert_eq!(fb.pitch(), 4096);
+    assert_eq!(fb.width(), 1024);
+    assert_eq!(fb.height(), 768);
+    assert_eq!(fb.bpp(), 32);
+    assert_eq!(fb.ty(), 1);
+    assert_eq!(fb.size(), 4096 * 768);
+
+    assert_eq!(
+        X86Framebuffer::new(&content[..21]),
+        Err(BootInfoExtraTruncatedError {
+            expected: 22,
+            actual: 21,
+        })
+    );
+}
+
+fn memory_map_entry(base_addr: u64, length: u64, ty: u32) -> [u8; 24] {
+    let mut entry = [0; 24];
+    entry[0..4].copy_from_slice(&20u32.to_ne_bytes());
+    entry[4..12].copy_from_slice(&base_addr.to_ne_bytes());
+    entry[12..20].copy_from_slice(&length.to_ne_bytes());
+    entry[20..24].copy_from_slice(&ty.to_ne_bytes());
+    entry
+}
+
+#[test]
+fn x86_memory_map() {
+    let mut content = Vec::new();
+    // A trailing partial entry is ignored.
+    content.extend_from_slice(&(2 * 24 + 10u32).to_ne_bytes());
+    content.extend_from_slice(&memory_map_entry(0, 0x9_fc00, 1));
+    content.extend_from_slice(&memory_map_entry(u64::MAX - 0xfff, 0x2000, 7));
+    content.extend_from_slice(&[0; 10]);
+    let map = X86MemoryMap::new(&content).unwrap();
+    assert_eq!(map.len(), 2);
+    assert!(!map.is_empty());
+
+    let entries = map.iter().collect::<Vec<_>>();
+    assert_eq!(
+        entries[0],
+        X86MemoryMapEntry {
+            base_addr: 0,
+            length: 0x9_fc00,
+            ty: X86MemoryMapEntryType::Available,
+        }
+    );
+    assert_eq!(entries[0].range(), 0..0x9_fc00);
+    assert_eq!(entries[1].ty, X86MemoryMapEntryType::Other(7));
+    assert_eq!(entries[1].range(), u64::MAX - 0xfff..u64::MAX);
+    assert_eq!(map.get(1), Some(entries[1]));
+    assert_eq!(map.get(2), None);
+
+    assert!(X86MemoryMap::new(&0u32.to_ne_bytes()).unwrap().is_empty());
+    assert_eq!(
+        X86MemoryMap::new(&content[..4 + 24]),
+        Err(BootInfoExtraTruncatedError {
+            expected: 4 + 2 * 24,
+            actual: 4 + 24,
+        })
+    );
+    assert_eq!(
+        X86MemoryMap::new(&content[..3]),
+        Err(BootInfoExtraTruncatedError {
+            expected: 4,
+            actual: 3,
+        })
+    );
+}
+
+fn rsdp(revision: u8) -> [u8; 36] {
+    let mut content = [0; 36];
+    content[0..8].copy_from_slice(b"RSD PTR ");
+    content[9..15].copy_from_slice(b"BOCHS ");
+    content[15] = revision;
+    content[16..20].copy_from_slice(&0x7fe_14d2u32.to_ne_bytes());
+    content[20..24].copy_from_slice(&36u32.to_ne_bytes());
+    content[24..32].copy_from_slice(&0x7fe_1500u64.to_ne_bytes());
+    let checksum =
+        |bytes: &[u8]| 0u8.wrapping_sub(bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)));
+    content[8] = checksum(&content[..20]);
+    content[32] = checksum(&content);
+    content
+}
+
+#[test]
+fn x86_acpi_rsdp_v1() {
+    let content = rsdp(0);
+    // Only the first 20 bytes are part of a revision 0 structure.
+    let rsdp = X86AcpiRsdp::new(&content[..20]).unwrap();
+    assert_eq!(rsdp.signature(), b"RSD PTR ");
+    assert_eq!(rsdp.oem_id(), b"BOCHS ");
+    assert_eq!(rsdp.revision(), 0);
+    assert_eq!(rsdp.rsdt_address(), 0x7fe_14d2);
+    assert_eq!(rsdp.xsdt_address(), None);
+    assert_eq!(rsdp.as_bytes(), &content[..20]);
+    assert!(rsdp.is_valid());
+    assert_eq!(
+        X86AcpiRsdp::new(&content).unwrap().as_bytes(),
+        &content[..20]
+    );
+
+    let mut corrupted = content;
+    corrupted[16] ^= 1;
+    assert!(!X86AcpiRsdp::new(&corrupted).unwrap().is_valid());
+
+    assert_eq!(
+        X86AcpiRsdp::new(&content[..19]),
+        Err(BootInfoExtraTruncatedError {
+            expected: 20,
+            actual: 19,
+        })
+    );
+}
+
+#[test]
+fn x86_acpi_rsdp_v2() {
+    let content = rsdp(2);
+    let rsdp = X86AcpiRsdp::new(&content).unwrap();
+    assert_eq!(rsdp.revision(), 2);
+    assert_eq!(rsdp.rsdt_address(), 0x7fe_14d2);
+    assert_eq!(rsdp.xsdt_address(), Some(0x7fe_1500));
+    assert_eq!(rsdp.as_bytes(), &content[..]);
+    assert!(rsdp.is_valid());
+
+    // The extended checksum covers the XSDT address.
+    let mut corrupted = content;
+    corrupted[24] ^= 1;
+    assert!(!X86AcpiRsdp::new(&corrupted).unwrap().is_valid());
+
+    assert_eq!(
+        X86AcpiRsdp::new(&content[..20]),
+        Err(BootInfoExtraTruncatedError {
+            expected: 36,
+            actual: 20,
+        })
+    );
+}
+
+#[test]
+fn x86_vbe() {
+    let mut content = [0; 784];
+    content[0..4].copy_from_slice(b"VESA");
+    let mode_info_block = &mut content[512..768];
+    mode_info_block[16..18].copy_from_slice(&4096u16.to_ne_bytes());
+    mode_info_block[18..20].copy_from_slice(&1024u16.to_ne_bytes());
+    mode_info_block[20..22].copy_from_slice(&768u16.to_ne_bytes());
+    mode_info_block[25] = 32;
+    mode_info_block[40..44].copy_from_slice(&0xfd00_0000u32.to_ne_bytes());
+    content[768..772].copy_from_slice(&0x118u32.to_ne_bytes());
+    content[772..776].copy_from_slice(&0xc000u32.to_ne_bytes());
+    content[776..780].copy_from_slice(&0x1234u32.to_ne_bytes());
+    content[780..784].copy_from_slice(&0x56u32.to_ne_bytes());
+    let vbe = X86Vbe::new(&content).unwrap();
+    assert_eq!(vbe.info_block(), &content[..512]);
+    assert_eq!(vbe.mode_info_block(), &content[512..768]);
+    assert_eq!(vbe.bytes_per_scan_line(), 4096);
+    assert_eq!(vbe.x_resolution(), 1024);
+    assert_eq!(vbe.y_resolution(), 768);
+    assert_eq!(vbe.bits_per_pixel(), 32);
+    assert_eq!(vbe.phys_base_ptr(), 0xfd00_0000);
+    assert_eq!(vbe.mode(), 0x118);
+    assert_eq!(vbe.interface_seg(), 0xc000);
+    assert_eq!(vbe.interface_off(), 0x1234);
+    assert_eq!(vbe.interface_len(), 0x56);
+
+    assert_eq!(
+        X86Vbe::new(&content[..783]),
+        Err(BootInfoExtraTruncatedError {
+            expected: 784,
+            actual: 783,
+        })
+    );
+}
@@ -0,0 +1,47 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+#![allow(dead_code)]
+
+use sel4::{CNodeCapData, CPtr, Cap, CapType, ObjectBlueprint, UserContext, Word};
+use sel4::{cap, init_thread};
+
+pub type Entry = extern "C-unwind" fn(Word, Word, Word);
+
+/// Retypes `untyped` into a single object in slot `index` of the initial thread's CNode.
+pub fn retype<T: CapType>(
+    untyped: cap::Untyped,
+    blueprint: &ObjectBlueprint,
+    index: usize,
+) -> Cap<T> {
+    untyped
+        .untyped_retype(
+            blueprint,
+            &init_thread::slot::CNODE.cap().absolute_cptr_for_self(),
+            index,
+            1,
+        )
+        .unwrap();
+    init_thread::Slot::<T>::from_index(index).cap()
+}
+
+/// Starts `tcb` at `entry`, with `cspace_root` as its CSpace root and `args` in its first three C
+/// parameter registers.
+pub fn start(tcb: cap::Tcb, cspace_root: cap::CNode, entry: Entry, args: [Word; 3]) {
+    tcb.tcb_set_space(
+        CPtr::from_bits(0),
+        cspace_root,
+        CNodeCapData::new(0, 0),
+        cap::VSpace::from_bits(0),
+    )
+    .unwrap();
+    let mut regs = UserContext::default();
+    *regs.pc_mut() = entry as usize as Word;
+    for (i, arg) in args.into_iter().enumerate() {
+        *regs.c_param_mut(i) = arg;
+    }
+    tcb.tcb_write_all_registers(true, &mut regs).unwrap();
+}
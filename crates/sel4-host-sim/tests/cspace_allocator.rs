@@ -0,0 +1,148 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::ops::Range;
+
+use sel4::{CNodeCapData, CPtr, CapRights, ObjectBlueprint, WORD_SIZE, Word};
+use sel4::{cap, cap_type, init_thread};
+use sel4_abstract_allocator::basic::BasicAllocator;
+use sel4_cspace_allocator::{CSpaceAllocator, Error, Slot};
+use sel4_host_sim::{Sim, SimConfig};
+
+mod common;
+
+const SECOND_LEVEL_RADIX_BITS: usize = 4;
+
+const TOP_RADIX_BITS: usize = 6;
+
+// In guarded_growth, the top-level CNode resolves only half of the bits of a CPtr.
+const GUARDED_TOP_DEPTH: usize = WORD_SIZE / 2;
+
+fn new_allocator(top: cap::CNode, top_depth: usize, free_slots: Range<usize>) -> CSpaceAllocator {
+    CSpaceAllocator::new(top, top_depth, free_slots, |num_slots| {
+        BasicAllocator::with_granule_size(num_slots, 1)
+    })
+}
+
+fn allocate_notification(cspace: &mut CSpaceAllocator, untyped: cap::Untyped) -> Slot {
+    let slot = cspace.allocate().unwrap();
+    cspace
+        .retype(untyped, &ObjectBlueprint::Notification, &slot.into())
+        .unwrap();
+    if let Some(ntfn) = cspace.cap::<cap_type::Notification>(slot) {
+        // Faults, and so panics, unless the slot is where the allocator says it is.
+        ntfn.signal();
+    }
+    slot
+}
+
+// Fills the top-level CNode, of which `num_top_level_slots` are available, and then the first
+// second-level CNode, and then checks that freeing a top-level slot allows another second-level
+// CNode to be created.
+fn exhaust(cspace: &mut CSpaceAllocator, untyped: cap::Untyped, num_top_level_slots: usize) {
+    let mut top_level = (0..num_top_level_slots)
+        .map(|_| allocate_notification(cspace, untyped))
+        .collect::<Vec<_>>();
+    assert_eq!(cspace.num_cnodes(), 1);
+    for _ in 0..1 << SECOND_LEVEL_RADIX_BITS {
+        allocate_notification(cspace, untyped);
+    }
+    assert_eq!(cspace.num_cnodes(), 2);
+    assert_eq!(cspace.allocate(), Err(Error::OutOfSlots));
+    assert_eq!(
+        cspace.allocate_range((1 << SECOND_LEVEL_RADIX_BITS) + 1),
+        Err(Error::RangeTooLarge)
+    );
+    cspace.delete_and_free(top_level.pop().unwrap()).unwrap();
+    allocate_notification(cspace, untyped);
+    assert_eq!(cspace.num_cnodes(), 3);
+}
+
+#[test]
+fn growth() {
+    let mut sim = Sim::new(&SimConfig {
+        cnode_size_bits: TOP_RADIX_BITS,
+        ..SimConfig::default()
+    });
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let mut cspace = new_allocator(
+            init_thread::slot::CNODE.cap(),
+            WORD_SIZE,
+            slots.empty.clone(),
+        );
+        cspace.grow_from(untyped, SECOND_LEVEL_RADIX_BITS).unwrap();
+        // One top-level slot is reserved for growth.
+        exhaust(&mut cspace, untyped, slots.empty.len() - 1);
+    });
+}
+
+extern "C-unwind" fn guarded_growth_thread(_: Word, _: Word, _: Word) {
+    let shift = WORD_SIZE - GUARDED_TOP_DEPTH;
+    let top = CPtr::from_bits(0).cast::<cap_type::CNode>();
+    let untyped = CPtr::from_bits(1 << shift).cast::<cap_type::Untyped>();
+    let done = CPtr::from_bits(2 << shift).cast::<cap_type::Notification>();
+    let mut cspace = new_allocator(top, GUARDED_TOP_DEPTH, 3..1 << TOP_RADIX_BITS);
+    cspace.grow_from(untyped, SECOND_LEVEL_RADIX_BITS).unwrap();
+    // Two top-level slots are reserved for growth.
+    exhaust(&mut cspace, untyped, (1 << TOP_RADIX_BITS) - 3 - 2);
+    done.signal();
+}
+
+#[test]
+fn guarded_growth() {
+    let mut sim = Sim::new(&SimConfig::default());
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let cnode = init_thread::slot::CNODE.cap();
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let guarded_slot = slots.empty.start + 1;
+
+        let top: cap::CNode = common::retype(
+            untyped,
+            &ObjectBlueprint::CNode {
+                size_bits: TOP_RADIX_BITS,
+            },
+            slots.empty.start,
+        );
+        cnode
+            .absolute_cptr(CPtr::from_bits(guarded_slot as Word))
+            .mint(
+                &cnode.absolute_cptr(top.cptr()),
+                CapRights::all(),
+                CNodeCapData::skip(GUARDED_TOP_DEPTH - TOP_RADIX_BITS).into_word(),
+            )
+            .unwrap();
+        let guarded = init_thread::Slot::<cap_type::CNode>::from_index(guarded_slot).cap();
+
+        // In the new thread's CSpace, slot 0 holds the top-level CNode itself, which is then at
+        // CPtr 0, slot 1 an untyped, and slot 2 a notification to signal when done.
+        top.absolute_cptr_from_bits_with_depth(0, TOP_RADIX_BITS)
+            .copy(&cnode.absolute_cptr(guarded.cptr()), CapRights::all())
+            .unwrap();
+        untyped
+            .untyped_retype(
+                &ObjectBlueprint::Untyped { size_bits: 16 },
+                &top.absolute_cptr_for_self(),
+                1,
+                1,
+            )
+            .unwrap();
+        let done: cap::Notification = common::retype(
+            untyped,
+            &ObjectBlueprint::Notification,
+            slots.empty.start + 2,
+        );
+        top.absolute_cptr_from_bits_with_depth(2, TOP_RADIX_BITS)
+            .copy(&cnode.absolute_cptr(done.cptr()), CapRights::all())
+            .unwrap();
+
+        let tcb = common::retype(untyped, &ObjectBlueprint::Tcb, slots.empty.start + 3);
+        common::start(tcb, guarded, guarded_growth_thread, [0; 3]);
+        done.wait();
+    });
+}
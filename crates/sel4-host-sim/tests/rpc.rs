@@ -0,0 +1,118 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use serde::{Deserialize, Serialize};
+
+use sel4::{CPtr, CapRights, ObjectBlueprint, Word};
+use sel4::{cap, cap_type, init_thread};
+use sel4_host_sim::{Sim, SimConfig};
+use sel4_simple_task_rpc::caps::{CapTransferError, ReceivedCap};
+use sel4_simple_task_rpc::easy::{Client, Error, server};
+
+mod common;
+
+const BADGE: Word = 7;
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    value: Word,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct Response {
+    value: Word,
+    badge: Word,
+}
+
+extern "C-unwind" fn server(ep: Word, recv_slot: Word, _: Word) {
+    let ep = cap::Endpoint::from_bits(ep);
+    sel4::with_ipc_buffer_mut(|ipc_buffer| {
+        ipc_buffer.set_recv_slot(
+            &init_thread::slot::CNODE
+                .cap()
+                .absolute_cptr(CPtr::from_bits(recv_slot)),
+        )
+    });
+    let response = server::recv(ep, |reception| {
+        let request = reception.read::<Request>().unwrap();
+        assert_eq!(
+            reception.caps::<(
+                ReceivedCap<cap_type::Notification>,
+                ReceivedCap<cap_type::Endpoint>,
+                ReceivedCap<cap_type::Endpoint>,
+            )>(),
+            Err(Error::CapTransferError(CapTransferError::MissingCaps {
+                expected: 3,
+                received: 2,
+            })),
+        );
+        let (ntfn, badged): (
+            ReceivedCap<cap_type::Notification>,
+            ReceivedCap<cap_type::Endpoint>,
+        ) = reception.caps().unwrap();
+        let ntfn = ntfn.transferred().unwrap();
+        assert_eq!(ntfn.bits(), recv_slot);
+        ntfn.signal();
+        Response {
+            value: request.value + 1,
+            badge: badged.unwrapped().unwrap(),
+        }
+    });
+    server::reply(&response).unwrap();
+}
+
+#[test]
+fn call_with_caps() {
+    let mut sim = Sim::new(&SimConfig::default());
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let cnode = init_thread::slot::CNODE.cap();
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let ep = common::retype::<cap_type::Endpoint>(
+            untyped,
+            &ObjectBlueprint::Endpoint,
+            slots.empty.start,
+        );
+        let ntfn = common::retype::<cap_type::Notification>(
+            untyped,
+            &ObjectBlueprint::Notification,
+            slots.empty.start + 1,
+        );
+        let tcb =
+            common::retype::<cap_type::Tcb>(untyped, &ObjectBlueprint::Tcb, slots.empty.start + 2);
+        let badged = init_thread::Slot::<cap_type::Endpoint>::from_index(slots.empty.start + 3);
+        let recv_slot = slots.empty.start + 4;
+
+        cnode
+            .absolute_cptr(badged.cptr())
+            .mint(&cnode.absolute_cptr(ep), CapRights::all(), BADGE)
+            .unwrap();
+
+        common::start(
+            tcb,
+            cnode,
+            server,
+            [ep.bits(), recv_slot.try_into().unwrap(), 0],
+        );
+
+        // The notification is transferred into the server's receive slot, while the badged
+        // endpoint refers to the endpoint on which the server receives, and so is unwrapped.
+        let client = Client::<Request>::new(badged.cap());
+        let (response, ()) = client
+            .call_with_caps::<Response, ()>(&Request { value: 41 }, &(ntfn, badged.cap()))
+            .unwrap();
+        assert_eq!(
+            response,
+            Response {
+                value: 42,
+                badge: BADGE,
+            }
+        );
+
+        // The server signalled through the transferred copy.
+        ntfn.wait();
+    });
+}
@@ -0,0 +1,117 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::{ObjectBlueprint, WORD_SIZE};
+use sel4::{cap_type, init_thread};
+use sel4_abstract_allocator::basic::BasicAllocator;
+use sel4_cspace_allocator::CSpaceAllocator;
+use sel4_host_sim::{Sim, SimConfig};
+use sel4_untyped_manager::{Error, UntypedManager};
+
+const BASE: usize = 0x1000_0000;
+
+const SIZE_BITS: usize = 20;
+
+fn with_manager(f: impl FnOnce(&mut CSpaceAllocator, &mut UntypedManager)) {
+    let mut sim = Sim::new(&SimConfig {
+        untyped_size_bits: vec![SIZE_BITS],
+        ..SimConfig::default()
+    });
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let cnode = init_thread::slot::CNODE.cap();
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let mut cspace = CSpaceAllocator::new(cnode, WORD_SIZE, slots.empty.clone(), |num_slots| {
+            BasicAllocator::with_granule_size(num_slots, 1)
+        });
+        let mut manager = UntypedManager::new();
+        manager.add_untyped(
+            untyped,
+            cnode.absolute_cptr(untyped),
+            BASE,
+            SIZE_BITS,
+            false,
+        );
+        f(&mut cspace, &mut manager)
+    });
+}
+
+#[test]
+fn split_and_merge() {
+    with_manager(|cspace, manager| {
+        let a = manager
+            .allocate_fixed_sized::<cap_type::Notification>(cspace)
+            .unwrap();
+        let b = manager
+            .allocate_fixed_sized::<cap_type::Notification>(cspace)
+            .unwrap();
+        let block_size = 1 << a.size_bits();
+        assert_eq!(a.paddr(), BASE);
+        assert_eq!(b.paddr(), BASE + block_size);
+        assert_eq!(manager.free_bytes(), ((1 << SIZE_BITS) - 2 * block_size, 0));
+        for object in [&a, &b] {
+            object
+                .cap::<cap_type::Notification>(cspace)
+                .unwrap()
+                .signal();
+        }
+
+        manager.free(cspace, a).unwrap();
+        assert_eq!(manager.free_bytes(), ((1 << SIZE_BITS) - block_size, 0));
+        manager.free(cspace, b).unwrap();
+        assert_eq!(manager.free_bytes(), (1 << SIZE_BITS, 0));
+
+        // Retyping the whole untyped succeeds only if every split has been undone.
+        let whole = manager
+            .allocate(
+                cspace,
+                &ObjectBlueprint::Untyped {
+                    size_bits: SIZE_BITS,
+                },
+            )
+            .unwrap();
+        assert_eq!(whole.paddr(), BASE);
+        assert_eq!(manager.free_bytes(), (0, 0));
+        assert_eq!(
+            manager.allocate_fixed_sized::<cap_type::Notification>(cspace),
+            Err(Error::OutOfMemory)
+        );
+        manager.free(cspace, whole).unwrap();
+        assert_eq!(manager.free_bytes(), (1 << SIZE_BITS, 0));
+    });
+}
+
+#[test]
+fn allocate_at() {
+    with_manager(|cspace, manager| {
+        let blueprint = ObjectBlueprint::Untyped { size_bits: 12 };
+        assert_eq!(
+            manager.allocate_at(cspace, &blueprint, BASE + 0x800),
+            Err(Error::Misaligned)
+        );
+        assert_eq!(
+            manager.allocate_at(cspace, &blueprint, BASE + (1 << SIZE_BITS)),
+            Err(Error::Unavailable)
+        );
+
+        let object = manager
+            .allocate_at(cspace, &blueprint, BASE + 0x5000)
+            .unwrap();
+        assert_eq!(object.paddr(), BASE + 0x5000);
+        assert_eq!(
+            manager.allocate_at(cspace, &blueprint, BASE + 0x5000),
+            Err(Error::Unavailable)
+        );
+        let neighbour = manager
+            .allocate_at(cspace, &blueprint, BASE + 0x4000)
+            .unwrap();
+        assert_eq!(neighbour.paddr(), BASE + 0x4000);
+
+        manager.free(cspace, object).unwrap();
+        manager.free(cspace, neighbour).unwrap();
+        assert_eq!(manager.free_bytes(), (1 << SIZE_BITS, 0));
+    });
+}
@@ -0,0 +1,316 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// The simulator only models translation structures on x86_64.
+#![cfg(target_arch = "x86_64")]
+
+use sel4::{CapRights, FrameObjectType, VmAttributes, WORD_SIZE};
+use sel4::{cap, cap_type, init_thread};
+use sel4_abstract_allocator::basic::BasicAllocator;
+use sel4_cspace_allocator::CSpaceAllocator;
+use sel4_host_sim::{Sim, SimConfig};
+use sel4_untyped_manager::UntypedManager;
+use sel4_vspace_manager::{Error, UntypedTranslationTableAllocator, VSpaceManager};
+
+const SIZE_BITS: usize = 24;
+
+const GRANULE_SIZE: usize = FrameObjectType::GRANULE.bytes();
+
+// Translated by the second entry of a PDPT, and then by the second entry of a PD.
+const VADDR: usize = 0x4020_0000;
+
+fn with_manager(f: impl FnOnce(&mut CSpaceAllocator, &mut UntypedManager, &mut VSpaceManager)) {
+    let mut sim = Sim::new(&SimConfig {
+        untyped_size_bits: vec![SIZE_BITS],
+        ..SimConfig::default()
+    });
+    let slots = sim.initial_slots().clone();
+    sim.run(|| {
+        let cnode = init_thread::slot::CNODE.cap();
+        let untyped = init_thread::Slot::<cap_type::Untyped>::from_index(slots.untyped.start).cap();
+        let mut cspace = CSpaceAllocator::new(cnode, WORD_SIZE, slots.empty.clone(), |num_slots| {
+            BasicAllocator::with_granule_size(num_slots, 1)
+        });
+        let mut untyped_manager = UntypedManager::new();
+        untyped_manager.add_untyped(untyped, cnode.absolute_cptr(untyped), 0, SIZE_BITS, false);
+        let vspace = untyped_manager
+            .allocate_fixed_sized::<cap_type::VSpace>(&mut cspace)
+            .unwrap()
+            .cap::<cap_type::VSpace>(&cspace)
+            .unwrap();
+        init_thread::slot::ASID_POOL
+            .cap()
+            .asid_pool_assign(vspace)
+            .unwrap();
+        f(
+            &mut cspace,
+            &mut untyped_manager,
+            &mut VSpaceManager::new(vspace),
+        )
+    });
+}
+
+fn granule(cspace: &mut CSpaceAllocator, untyped: &mut UntypedManager) -> cap::Granule {
+    untyped
+        .allocate_fixed_sized::<cap_type::Granule>(cspace)
+        .unwrap()
+        .cap(cspace)
+        .unwrap()
+}
+
+fn tables(manager: &VSpaceManager) -> Vec<(usize, usize)> {
+    manager
+        .translation_tables()
+        .map(|(level, vaddr, _)| (level, vaddr))
+        .collect()
+}
+
+#[test]
+fn map_and_unmap() {
+    with_manager(|cspace, untyped, manager| {
+        let a = granule(cspace, untyped);
+        let b = granule(cspace, untyped);
+        let mut allocator = UntypedTranslationTableAllocator { untyped, cspace };
+        let mut map = |frame: cap::Granule, vaddr| {
+            manager.map(
+                &mut allocator,
+                frame,
+                vaddr,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            )
+        };
+
+        map(a, VADDR).unwrap();
+        assert_eq!(map(b, VADDR + 1), Err(Error::Misaligned));
+        assert_eq!(
+            map(b, VADDR),
+            Err(Error::AlreadyMapped(VADDR..VADDR + GRANULE_SIZE))
+        );
+        map(b, VADDR + GRANULE_SIZE).unwrap();
+
+        // Each missing table was created once.
+        assert_eq!(
+            tables(manager),
+            [(1, 0), (2, 0x4000_0000), (3, 0x4020_0000)]
+        );
+
+        let (mapping, offset) = manager.lookup(VADDR + GRANULE_SIZE + 0x10).unwrap();
+        assert_eq!(mapping.frame(), b.cast::<cap_type::UnspecifiedPage>());
+        assert_eq!(mapping.frame_type(), FrameObjectType::GRANULE);
+        assert_eq!(offset, 0x10);
+        assert!(manager.lookup(VADDR + 2 * GRANULE_SIZE).is_none());
+
+        let mapping = manager.unmap(VADDR).unwrap();
+        assert_eq!(mapping.frame(), a.cast::<cap_type::UnspecifiedPage>());
+        assert_eq!(manager.unmap(VADDR), Err(Error::NotMapped(VADDR)));
+        assert!(manager.lookup(VADDR).is_none());
+
+        // The frame can be mapped again, using the existing tables.
+        manager
+            .map(
+                &mut allocator,
+                a,
+                VADDR + 2 * GRANULE_SIZE,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            manager
+                .mappings()
+                .map(|(vaddr, _)| vaddr)
+                .collect::<Vec<_>>(),
+            [VADDR + GRANULE_SIZE, VADDR + 2 * GRANULE_SIZE]
+        );
+        assert_eq!(tables(manager).len(), 3);
+    });
+}
+
+#[test]
+fn large_pages() {
+    with_manager(|cspace, untyped, manager| {
+        let large_page = untyped
+            .allocate_fixed_sized::<cap_type::LargePage>(cspace)
+            .unwrap()
+            .cap::<cap_type::LargePage>(cspace)
+            .unwrap();
+        let small_page = granule(cspace, untyped);
+        let mut allocator = UntypedTranslationTableAllocator { untyped, cspace };
+        let large_size = FrameObjectType::LargePage.bytes();
+
+        manager
+            .map(
+                &mut allocator,
+                large_page,
+                VADDR,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            )
+            .unwrap();
+        // No page table is needed for a large page.
+        assert_eq!(tables(manager), [(1, 0), (2, 0x4000_0000)]);
+        assert_eq!(
+            manager.map(
+                &mut allocator,
+                small_page,
+                VADDR + GRANULE_SIZE,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            ),
+            Err(Error::AlreadyMapped(VADDR..VADDR + large_size))
+        );
+        assert_eq!(
+            manager.lookup(VADDR + large_size - 1).unwrap().1,
+            large_size - 1
+        );
+    });
+}
+
+#[test]
+fn existing_tables() {
+    with_manager(|cspace, untyped, manager| {
+        let a = granule(cspace, untyped);
+        let b = granule(cspace, untyped);
+        let mut allocator = UntypedTranslationTableAllocator { untyped, cspace };
+        manager
+            .map(
+                &mut allocator,
+                a,
+                VADDR,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            )
+            .unwrap();
+
+        // A second manager of the same VSpace finds the tables which the first one created.
+        let mut other = VSpaceManager::new(manager.vspace());
+        other
+            .map(
+                &mut allocator,
+                b,
+                VADDR + GRANULE_SIZE,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            )
+            .unwrap();
+        assert!(tables(&other).is_empty());
+        assert_eq!(tables(manager).len(), 3);
+    });
+}
+
+#[test]
+fn prepare() {
+    with_manager(|cspace, untyped, manager| {
+        let mut allocator = UntypedTranslationTableAllocator { untyped, cspace };
+        manager
+            .prepare(
+                &mut allocator,
+                0x4000_0000..0x4050_0000,
+                FrameObjectType::GRANULE,
+            )
+            .unwrap();
+        assert_eq!(
+            tables(manager),
+            [
+                (1, 0),
+                (2, 0x4000_0000),
+                (3, 0x4000_0000),
+                (3, 0x4020_0000),
+                (3, 0x4040_0000),
+            ]
+        );
+        // Preparing an overlapping range only creates what is missing.
+        manager
+            .prepare(
+                &mut allocator,
+                0x4040_0000..0x4070_0000,
+                FrameObjectType::GRANULE,
+            )
+            .unwrap();
+        assert_eq!(tables(manager).len(), 6);
+    });
+}
+
+#[test]
+fn reserve() {
+    with_manager(|cspace, untyped, manager| {
+        let frame = granule(cspace, untyped);
+        let mut allocator = UntypedTranslationTableAllocator { untyped, cspace };
+        manager
+            .map(
+                &mut allocator,
+                frame,
+                0x5000,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            )
+            .unwrap();
+
+        manager.reserve(0x1000..0x3000).unwrap();
+        assert_eq!(
+            manager.reserve(0x2000..0x4000),
+            Err(Error::AlreadyReserved(0x1000..0x3000))
+        );
+
+        // Both reservations and mappings are avoided.
+        let within = 0x1000..0x8000;
+        assert_eq!(
+            manager.reserve_anywhere(within.clone(), 0x2000, 0x1000),
+            Ok(0x3000..0x5000)
+        );
+        assert_eq!(
+            manager.reserve_anywhere(within.clone(), 0x1000, 0x1000),
+            Ok(0x6000..0x7000)
+        );
+        assert_eq!(
+            manager.reserve_anywhere(within.clone(), 0x2000, 0x1000),
+            Err(Error::NoFreeRange)
+        );
+        assert_eq!(
+            manager.reserve_anywhere(within.clone(), 0x1000, 0x2000),
+            Err(Error::NoFreeRange)
+        );
+
+        assert_eq!(manager.release(0x3000), Ok(0x3000..0x5000));
+        assert_eq!(manager.release(0x3000), Err(Error::NotReserved(0x3000)));
+        assert_eq!(
+            manager.reserve_anywhere(within, 0x2000, 0x1000),
+            Ok(0x3000..0x5000)
+        );
+    });
+}
+
+#[test]
+fn overflow() {
+    with_manager(|cspace, untyped, manager| {
+        let frame = granule(cspace, untyped);
+        let mut allocator = UntypedTranslationTableAllocator { untyped, cspace };
+        let last_page = usize::MAX - (GRANULE_SIZE - 1);
+        assert_eq!(
+            manager.map(
+                &mut allocator,
+                frame,
+                last_page,
+                CapRights::read_write(),
+                VmAttributes::default(),
+            ),
+            Err(Error::AddressOverflow)
+        );
+        assert_eq!(
+            manager.reserve_anywhere(usize::MAX - 10..usize::MAX, 1, 0x1000),
+            Err(Error::NoFreeRange)
+        );
+        assert_eq!(
+            manager.reserve_anywhere(last_page..usize::MAX, 2 * GRANULE_SIZE, 0x1000),
+            Err(Error::NoFreeRange)
+        );
+        assert_eq!(
+            manager.reserve_anywhere(0..0x1000, 0x1000, 0),
+            Err(Error::NoFreeRange)
+        );
+    });
+}
@@ -23,6 +23,17 @@ mk {
     sel4-panicking = localCrates.sel4-panicking // { features = [ "personality" "panic-handler" ]; };
     sel4-runtime-common = localCrates.sel4-runtime-common // { features = [ "sel4" ]; };
     sel4 = localCrates.sel4 // { features = [ "single-threaded" ]; };
+    futures = {
+      version = versions.futures;
+      default-features = false;
+      features = [
+        "alloc"
+      ];
+      optional = true;
+    };
+    sel4-async-single-threaded-executor = localCrates.sel4-async-single-threaded-executor // { optional = true; };
+    sel4-async-time = localCrates.sel4-async-time // { optional = true; };
+    sel4-driver-interfaces = localCrates.sel4-driver-interfaces // { optional = true; };
   };
   features = {
     full = [
@@ -31,5 +42,12 @@ mk {
     alloc = [
       "sel4-panicking/alloc"
     ];
+    async = [
+      "alloc"
+      "dep:futures"
+      "dep:sel4-async-single-threaded-executor"
+      "dep:sel4-async-time"
+      "dep:sel4-driver-interfaces"
+    ];
   };
 }
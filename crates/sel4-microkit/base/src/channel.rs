@@ -14,7 +14,13 @@ use crate::{
 const BASE_OUTPUT_NOTIFICATION_SLOT: usize = 10;
 const BASE_ENDPOINT_SLOT: usize = BASE_OUTPUT_NOTIFICATION_SLOT + 64;
 const BASE_IRQ_SLOT: usize = BASE_ENDPOINT_SLOT + 64;
-const BASE_TCB_SLOT: usize = BASE_IRQ_SLOT + 64;
+pub(crate) const BASE_TCB_SLOT: usize = BASE_IRQ_SLOT + 64;
+#[sel4::sel4_cfg(any(ARM_HYPERVISOR_SUPPORT, ARCH_X86_64))]
+pub(crate) const BASE_VM_TCB_SLOT: usize = BASE_TCB_SLOT + 64;
+#[sel4::sel4_cfg(any(ARM_HYPERVISOR_SUPPORT, ARCH_X86_64))]
+pub(crate) const BASE_VCPU_SLOT: usize = BASE_VM_TCB_SLOT + 64;
+#[sel4::sel4_cfg(ARCH_X86_64)]
+pub(crate) const BASE_IOPORT_SLOT: usize = BASE_VCPU_SLOT + 64;
 
 const MAX_CHANNELS: usize = 62;
 
@@ -124,24 +130,3 @@ impl fmt::Display for IrqAckError {
         write!(f, "irq ack error: {:?}", self.inner())
     }
 }
-
-/// A handle to a child protection domain, identified by a child protection domain index.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct Child {
-    index: usize,
-}
-
-impl Child {
-    pub const fn new(index: usize) -> Self {
-        Self { index }
-    }
-
-    pub const fn index(&self) -> usize {
-        self.index
-    }
-
-    #[doc(hidden)]
-    pub fn tcb(&self) -> sel4::cap::Tcb {
-        sel4::Cap::from_bits((BASE_TCB_SLOT + self.index) as sel4::CPtrBits)
-    }
-}
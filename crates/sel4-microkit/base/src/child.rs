@@ -0,0 +1,98 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::fmt;
+
+use crate::channel::BASE_TCB_SLOT;
+
+/// A handle to a child protection domain, identified by a child protection domain index.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Child {
+    index: usize,
+}
+
+impl Child {
+    pub const fn new(index: usize) -> Self {
+        Self { index }
+    }
+
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    #[doc(hidden)]
+    pub fn tcb(&self) -> sel4::cap::Tcb {
+        sel4::Cap::from_bits((BASE_TCB_SLOT + self.index) as sel4::CPtrBits)
+    }
+
+    /// Restarts the child at `entry_point`, leaving its other registers unchanged.
+    ///
+    /// Corresponds to `microkit_pd_restart`. This can be used from [`Handler::fault`] to restart a
+    /// child which has crashed, in which case `None` should be returned so that the child is not
+    /// also replied to.
+    ///
+    /// [`Handler::fault`]: crate::Handler::fault
+    pub fn restart(&self, entry_point: usize) -> Result<(), ChildError> {
+        restart(self.tcb(), entry_point).map_err(ChildError::from_inner)
+    }
+
+    /// Suspends the child.
+    ///
+    /// Corresponds to `microkit_pd_stop`.
+    pub fn stop(&self) -> Result<(), ChildError> {
+        self.tcb().tcb_suspend().map_err(ChildError::from_inner)
+    }
+
+    /// Resumes the child after a call to [`stop`](Self::stop).
+    pub fn resume(&self) -> Result<(), ChildError> {
+        self.tcb().tcb_resume().map_err(ChildError::from_inner)
+    }
+
+    /// Reads the child's registers, first suspending it if `suspend` is `true`.
+    pub fn read_registers(&self, suspend: bool) -> Result<sel4::UserContext, ChildError> {
+        self.tcb()
+            .tcb_read_all_registers(suspend)
+            .map_err(ChildError::from_inner)
+    }
+
+    /// Writes the child's registers, then resumes it if `resume` is `true`.
+    pub fn write_registers(
+        &self,
+        resume: bool,
+        regs: &sel4::UserContext,
+    ) -> Result<(), ChildError> {
+        self.tcb()
+            .tcb_write_all_registers(resume, &mut regs.clone())
+            .map_err(ChildError::from_inner)
+    }
+}
+
+pub(crate) fn restart(tcb: sel4::cap::Tcb, entry_point: usize) -> sel4::Result<()> {
+    let mut regs = sel4::UserContext::default();
+    *regs.pc_mut() = entry_point.try_into().unwrap();
+    // The program counter is the first register in every architecture's seL4_UserContext.
+    tcb.tcb_write_registers(true, 1, &mut regs)
+}
+
+/// Error type returned by [`Child`] methods.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChildError(sel4::Error);
+
+impl ChildError {
+    fn from_inner(inner: sel4::Error) -> Self {
+        Self(inner)
+    }
+
+    fn inner(&self) -> &sel4::Error {
+        &self.0
+    }
+}
+
+impl fmt::Display for ChildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "child error: {:?}", self.inner())
+    }
+}
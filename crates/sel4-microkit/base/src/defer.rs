@@ -10,7 +10,7 @@ use crate::{Channel, IrqAckError};
 #[allow(unused_imports)]
 use crate::Handler;
 
-/// An action deferred for syscall coalescing using [`Handler::take_deferred_action`].
+/// An action deferred for syscall coalescing using [`Handler::take_deferred_actions`].
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct DeferredAction {
     channel: Channel,
@@ -89,9 +89,146 @@ impl PreparedDeferredAction {
     pub(crate) fn msg_info(&self) -> sel4::MessageInfo {
         self.msg_info.clone() // TODO
     }
+
+    /// Performs this action with `seL4_Send`, which, like the send phase of `seL4_NBSendRecv`,
+    /// discards any error.
+    pub(crate) fn send(&self) {
+        self.cptr()
+            .cast::<sel4::cap_type::Endpoint>()
+            .send(self.msg_info())
+    }
+}
+
+/// A set of deferred actions, for implementing [`Handler::take_deferred_actions`].
+///
+/// Deferring the same action more than once has the same effect as deferring it once, so each of
+/// a channel's notifications and IRQ acknowledgements is performed at most once per set. When the
+/// set is performed, IRQ acknowledgements come before notifications, and each kind is performed in
+/// order of channel index. The main loop fuses the last of these actions with its next
+/// `seL4_Recv` using `seL4_NBSendRecv`, and performs the others with `seL4_Send`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct DeferredActions {
+    irq_acks: u64,
+    notifies: u64,
+}
+
+impl DeferredActions {
+    pub const fn new() -> Self {
+        Self {
+            irq_acks: 0,
+            notifies: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.irq_acks == 0 && self.notifies == 0
+    }
+
+    pub fn len(&self) -> usize {
+        (self.irq_acks.count_ones() + self.notifies.count_ones())
+            .try_into()
+            .unwrap()
+    }
+
+    pub fn contains(&self, action: &DeferredAction) -> bool {
+        self.mask(action.interface()) & (1 << action.channel().index()) != 0
+    }
+
+    pub fn defer(&mut self, action: DeferredAction) {
+        *self.mask_mut(action.interface()) |= 1 << action.channel().index();
+    }
+
+    pub fn defer_notify(&mut self, channel: Channel) {
+        self.defer(DeferredAction::new_notify(channel))
+    }
+
+    pub fn defer_irq_ack(&mut self, channel: Channel) {
+        self.defer(DeferredAction::new_irq_ack(channel))
+    }
+
+    /// Takes the set's actions, leaving it empty.
+    pub fn take(&mut self) -> Self {
+        core::mem::take(self)
+    }
+
+    /// Returns the set's actions, in the order in which they are performed.
+    pub fn iter(&self) -> impl Iterator<Item = DeferredAction> {
+        let interfaces = [
+            DeferredActionInterface::IrqAck,
+            DeferredActionInterface::Notify,
+        ];
+        let this = *self;
+        interfaces.into_iter().flat_map(move |interface| {
+            channels(this.mask(interface))
+                .map(move |channel| DeferredAction::new(channel, interface))
+        })
+    }
+
+    /// Performs the set's actions now, rather than deferring them to the main loop.
+    pub fn execute_now(self) -> Result<(), IrqAckError> {
+        self.iter().try_for_each(DeferredAction::execute_now)
+    }
+
+    pub(crate) fn pop_last(&mut self) -> Option<DeferredAction> {
+        let action = self.iter().last()?;
+        *self.mask_mut(action.interface()) &= !(1 << action.channel().index());
+        Some(action)
+    }
+
+    fn mask(&self, interface: DeferredActionInterface) -> u64 {
+        match interface {
+            DeferredActionInterface::Notify => self.notifies,
+            DeferredActionInterface::IrqAck => self.irq_acks,
+        }
+    }
+
+    fn mask_mut(&mut self, interface: DeferredActionInterface) -> &mut u64 {
+        match interface {
+            DeferredActionInterface::Notify => &mut self.notifies,
+            DeferredActionInterface::IrqAck => &mut self.irq_acks,
+        }
+    }
+}
+
+fn channels(mut mask: u64) -> impl Iterator<Item = Channel> {
+    core::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let i = mask.trailing_zeros();
+        mask &= !(1 << i);
+        Some(Channel::new(i.try_into().unwrap()))
+    })
+}
+
+impl From<DeferredAction> for DeferredActions {
+    fn from(action: DeferredAction) -> Self {
+        let mut this = Self::new();
+        this.defer(action);
+        this
+    }
+}
+
+impl Extend<DeferredAction> for DeferredActions {
+    fn extend<T: IntoIterator<Item = DeferredAction>>(&mut self, iter: T) {
+        for action in iter {
+            self.defer(action);
+        }
+    }
+}
+
+impl FromIterator<DeferredAction> for DeferredActions {
+    fn from_iter<T: IntoIterator<Item = DeferredAction>>(iter: T) -> Self {
+        let mut this = Self::new();
+        this.extend(iter);
+        this
+    }
 }
 
 /// Utility type for implementing [`Handler::take_deferred_action`].
+///
+/// Unlike [`DeferredActions`], this holds at most one action, and deferring another performs the
+/// first immediately.
 // TODO maybe excessive. remove?
 pub struct DeferredActionSlot {
     inner: Option<DeferredAction>,
@@ -8,7 +8,7 @@ use core::fmt;
 
 use crate::{
     Channel, Child, MessageInfo,
-    defer::{DeferredAction, PreparedDeferredAction},
+    defer::{DeferredAction, DeferredActions, PreparedDeferredAction},
     ipc::{self, ChannelSet, Event},
     pd_is_passive,
 };
@@ -45,6 +45,10 @@ pub trait Handler {
 
     /// This method has the same meaning and type as its analog in `libmicrokit`.
     ///
+    /// The fault can be interpreted with [`MessageInfo::fault`]. Returning `Some` replies to the
+    /// faulting child, resuming it. Returning `None` leaves it blocked, for example because it has
+    /// been restarted with [`Child::restart`] or stopped with [`Child::stop`].
+    ///
     /// The default implementation just panics.
     fn fault(
         &mut self,
@@ -58,27 +62,59 @@ pub trait Handler {
     /// possible.
     ///
     /// This method is used by the main loop to fuse a queued `seL4_Send` call with the next
-    /// `seL4_Recv` using `seL4_NBSendRecv`. Its default implementation just returns `None`.
+    /// `seL4_Recv` using `seL4_NBSendRecv`. It is only called by the default implementation of
+    /// [`take_deferred_actions`](Self::take_deferred_actions), which supersedes it. Its default
+    /// implementation just returns `None`.
     fn take_deferred_action(&mut self) -> Option<DeferredAction> {
         None
     }
 
+    /// An advanced feature for use by protection domains which seek to coalesce syscalls when
+    /// possible.
+    ///
+    /// This method is called by the main loop after each event is handled. The last of the
+    /// returned actions is fused with the next `seL4_Recv` using `seL4_NBSendRecv`, and the
+    /// others are performed with `seL4_Send` just before it. If the event was a protected
+    /// procedure call, which must be replied to with `seL4_ReplyRecv`, then all of the actions
+    /// are performed with `seL4_Send` before replying. See [`DeferredActions`].
+    ///
+    /// The default implementation returns the action returned by
+    /// [`take_deferred_action`](Self::take_deferred_action), if any.
+    fn take_deferred_actions(&mut self) -> DeferredActions {
+        self.take_deferred_action().into_iter().collect()
+    }
+
     #[doc(hidden)]
     fn run(&mut self) -> Result<Never, Self::Error> {
         let mut reply_tag: Option<MessageInfo> = None;
 
-        let mut prepared_deferred_action: Option<PreparedDeferredAction> = if pd_is_passive() {
+        let mut deferred_actions = DeferredActions::new();
+
+        let mut forfeit_sc: Option<PreparedDeferredAction> = if pd_is_passive() {
             Some(ipc::forfeit_sc())
         } else {
             None
         };
 
         loop {
-            let event = match (reply_tag.take(), prepared_deferred_action.take()) {
-                (Some(msg_info), None) => ipc::reply_recv(msg_info),
-                (None, Some(action)) => ipc::nb_send_recv(action),
-                (None, None) => ipc::recv(),
-                _ => panic!("handler yielded deferred action after call to 'protected()'"),
+            let event = match reply_tag.take() {
+                Some(msg_info) => {
+                    deferred_actions.take().iter().for_each(send);
+                    ipc::reply_recv(msg_info)
+                }
+                None => {
+                    let fused = forfeit_sc.take().or_else(|| {
+                        deferred_actions
+                            .pop_last()
+                            .as_ref()
+                            .map(DeferredAction::prepare)
+                    });
+                    deferred_actions.take().iter().for_each(send);
+                    match fused {
+                        Some(action) => ipc::nb_send_recv(action),
+                        None => ipc::recv(),
+                    }
+                }
             };
 
             match event {
@@ -93,14 +129,15 @@ pub trait Handler {
                 }
             };
 
-            prepared_deferred_action = self
-                .take_deferred_action()
-                .as_ref()
-                .map(DeferredAction::prepare);
+            deferred_actions = self.take_deferred_actions();
         }
     }
 }
 
+fn send(action: DeferredAction) {
+    action.prepare().send()
+}
+
 #[doc(hidden)]
 pub enum Never {}
 
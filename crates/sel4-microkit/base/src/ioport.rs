@@ -0,0 +1,91 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::fmt;
+
+use crate::channel::BASE_IOPORT_SLOT;
+use crate::symbols::pd_ioports;
+
+const MAX_IOPORTS: usize = 64;
+
+/// An I/O port range granted to this protection domain, identified by an I/O port index.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct IoPort {
+    index: usize,
+}
+
+impl IoPort {
+    pub const fn new(index: usize) -> Self {
+        assert!(index < MAX_IOPORTS);
+        Self { index }
+    }
+
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    #[doc(hidden)]
+    pub fn cap(&self) -> sel4::cap::IOPort {
+        if pd_ioports() & (1 << self.index) == 0 {
+            panic!("ioport: not valid for I/O port '{}'", self.index);
+        }
+        sel4::Cap::from_bits((BASE_IOPORT_SLOT + self.index) as sel4::CPtrBits)
+    }
+
+    pub fn read_u8(&self, port: u16) -> Result<u8, IoPortError> {
+        self.cap().ioport_in8(port).map_err(IoPortError::from_inner)
+    }
+
+    pub fn read_u16(&self, port: u16) -> Result<u16, IoPortError> {
+        self.cap()
+            .ioport_in16(port)
+            .map_err(IoPortError::from_inner)
+    }
+
+    pub fn read_u32(&self, port: u16) -> Result<u32, IoPortError> {
+        self.cap()
+            .ioport_in32(port)
+            .map_err(IoPortError::from_inner)
+    }
+
+    pub fn write_u8(&self, port: u16, value: u8) -> Result<(), IoPortError> {
+        self.cap()
+            .ioport_out8(port, value)
+            .map_err(IoPortError::from_inner)
+    }
+
+    pub fn write_u16(&self, port: u16, value: u16) -> Result<(), IoPortError> {
+        self.cap()
+            .ioport_out16(port, value)
+            .map_err(IoPortError::from_inner)
+    }
+
+    pub fn write_u32(&self, port: u16, value: u32) -> Result<(), IoPortError> {
+        self.cap()
+            .ioport_out32(port, value)
+            .map_err(IoPortError::from_inner)
+    }
+}
+
+/// Error type returned by [`IoPort`] methods.
+#[derive(Debug, PartialEq, Eq)]
+pub struct IoPortError(sel4::Error);
+
+impl IoPortError {
+    fn from_inner(inner: sel4::Error) -> Self {
+        Self(inner)
+    }
+
+    fn inner(&self) -> &sel4::Error {
+        &self.0
+    }
+}
+
+impl fmt::Display for IoPortError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ioport error: {:?}", self.inner())
+    }
+}
@@ -8,17 +8,25 @@
 #![feature(used_with_arg)]
 
 mod channel;
+mod child;
 mod defer;
 mod handler;
 mod message;
 mod symbols;
 
+#[sel4::sel4_cfg(ARCH_X86_64)]
+mod ioport;
+
+#[sel4::sel4_cfg(ARM_HYPERVISOR_SUPPORT)]
+mod vcpu;
+
 // TODO
 #[doc(hidden)]
 pub mod ipc;
 
-pub use channel::{Channel, Child, IrqAckError};
-pub use defer::{DeferredAction, DeferredActionInterface, DeferredActionSlot};
+pub use channel::{Channel, IrqAckError};
+pub use child::{Child, ChildError};
+pub use defer::{DeferredAction, DeferredActionInterface, DeferredActionSlot, DeferredActions};
 pub use handler::{Handler, Infallible, Never, NullHandler};
 pub use ipc::{ChannelSet, DisplayChannelSet};
 pub use message::{
@@ -27,6 +35,12 @@ pub use message::{
 };
 pub use symbols::{ipc_buffer_ptr, pd_is_passive, pd_name};
 
+#[sel4::sel4_cfg(ARCH_X86_64)]
+pub use ioport::{IoPort, IoPortError};
+
+#[sel4::sel4_cfg(ARM_HYPERVISOR_SUPPORT)]
+pub use vcpu::{Vcpu, VcpuError};
+
 // For macros
 #[doc(hidden)]
 pub mod _private {
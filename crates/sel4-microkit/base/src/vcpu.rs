@@ -0,0 +1,127 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::fmt;
+
+use crate::channel::{BASE_VCPU_SLOT, BASE_VM_TCB_SLOT};
+use crate::child;
+
+const MAX_VCPUS: usize = 64;
+
+/// A virtual CPU of a virtual machine belonging to this protection domain, identified by a VCPU
+/// index.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Vcpu {
+    index: usize,
+}
+
+impl Vcpu {
+    pub const fn new(index: usize) -> Self {
+        assert!(index < MAX_VCPUS);
+        Self { index }
+    }
+
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    #[doc(hidden)]
+    pub fn tcb(&self) -> sel4::cap::Tcb {
+        sel4::Cap::from_bits((BASE_VM_TCB_SLOT + self.index) as sel4::CPtrBits)
+    }
+
+    #[doc(hidden)]
+    pub fn vcpu(&self) -> sel4::cap::VCpu {
+        sel4::Cap::from_bits((BASE_VCPU_SLOT + self.index) as sel4::CPtrBits)
+    }
+
+    /// Restarts the VCPU at `entry_point`, leaving its other registers unchanged.
+    ///
+    /// Corresponds to `microkit_vcpu_restart`.
+    pub fn restart(&self, entry_point: usize) -> Result<(), VcpuError> {
+        child::restart(self.tcb(), entry_point).map_err(VcpuError::from_inner)
+    }
+
+    /// Suspends the VCPU.
+    ///
+    /// Corresponds to `microkit_vcpu_stop`.
+    pub fn stop(&self) -> Result<(), VcpuError> {
+        self.tcb().tcb_suspend().map_err(VcpuError::from_inner)
+    }
+
+    /// Resumes the VCPU after a call to [`stop`](Self::stop).
+    pub fn resume(&self) -> Result<(), VcpuError> {
+        self.tcb().tcb_resume().map_err(VcpuError::from_inner)
+    }
+
+    /// Reads the VCPU's general-purpose registers, first suspending it if `suspend` is `true`.
+    pub fn read_registers(&self, suspend: bool) -> Result<sel4::UserContext, VcpuError> {
+        self.tcb()
+            .tcb_read_all_registers(suspend)
+            .map_err(VcpuError::from_inner)
+    }
+
+    /// Writes the VCPU's general-purpose registers, then resumes it if `resume` is `true`.
+    pub fn write_registers(&self, resume: bool, regs: &sel4::UserContext) -> Result<(), VcpuError> {
+        self.tcb()
+            .tcb_write_all_registers(resume, &mut regs.clone())
+            .map_err(VcpuError::from_inner)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_inject_irq`.
+    pub fn inject_irq(
+        &self,
+        irq: u16,
+        priority: u8,
+        group: u8,
+        index: u8,
+    ) -> Result<(), VcpuError> {
+        self.vcpu()
+            .vcpu_inject_irq(irq, priority, group, index)
+            .map_err(VcpuError::from_inner)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_ack_vppi`.
+    pub fn ack_vppi(&self, irq: sel4::Word) -> Result<(), VcpuError> {
+        self.vcpu()
+            .vcpu_ack_vppi(irq)
+            .map_err(VcpuError::from_inner)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_read_reg`.
+    pub fn read_reg(&self, reg: sel4::VCpuReg) -> Result<sel4::Word, VcpuError> {
+        self.vcpu()
+            .vcpu_read_regs(reg)
+            .map_err(VcpuError::from_inner)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_write_reg`.
+    pub fn write_reg(&self, reg: sel4::VCpuReg, value: sel4::Word) -> Result<(), VcpuError> {
+        self.vcpu()
+            .vcpu_write_regs(reg, value)
+            .map_err(VcpuError::from_inner)
+    }
+}
+
+/// Error type returned by [`Vcpu`] methods.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VcpuError(sel4::Error);
+
+impl VcpuError {
+    fn from_inner(inner: sel4::Error) -> Self {
+        Self(inner)
+    }
+
+    fn inner(&self) -> &sel4::Error {
+        &self.0
+    }
+}
+
+impl fmt::Display for VcpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "vcpu error: {:?}", self.inner())
+    }
+}
@@ -4,7 +4,7 @@
 # SPDX-License-Identifier: BSD-2-Clause
 #
 
-{ mk, versions }:
+{ mk, localCrates, versions }:
 
 mk {
   package.name = "sel4-microkit-macros";
@@ -12,5 +12,6 @@ mk {
   dependencies = {
     syn = { version = versions.syn; features = [ "full" ]; };
     inherit (versions) proc-macro2 quote;
+    inherit (localCrates) sel4-microkit-system-description;
   };
 }
@@ -9,6 +9,8 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::parse_macro_input;
 
+mod system_description;
+
 #[proc_macro_attribute]
 pub fn protection_domain(attr: TokenStream, item: TokenStream) -> TokenStream {
     let item = parse_macro_input!(item as syn::ItemFn);
@@ -21,3 +23,11 @@ pub fn protection_domain(attr: TokenStream, item: TokenStream) -> TokenStream {
     }
     .into()
 }
+
+#[proc_macro]
+pub fn system_description(input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(input as system_description::Args);
+    system_description::expand(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,460 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    Ident, LitStr, MetaNameValue, Token,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+};
+
+use sel4_microkit_system_description::{Map, ProtectionDomain, SetVar, SystemDescription};
+
+pub(crate) struct Args {
+    path: LitStr,
+    pd: LitStr,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut path = None;
+        let mut pd = None;
+        for arg in Punctuated::<MetaNameValue, Token![,]>::parse_terminated(input)? {
+            let slot = if arg.path.is_ident("path") {
+                &mut path
+            } else if arg.path.is_ident("pd") {
+                &mut pd
+            } else {
+                return Err(syn::Error::new_spanned(arg.path, "unexpected argument"));
+            };
+            let value = match &arg.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) => s.clone(),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        arg.value,
+                        "expected string literal",
+                    ));
+                }
+            };
+            if slot.replace(value).is_some() {
+                return Err(syn::Error::new_spanned(arg.path, "duplicate argument"));
+            }
+        }
+        let missing =
+            |name| syn::Error::new(Span::call_site(), format!("missing argument '{name}'"));
+        Ok(Self {
+            path: path.ok_or_else(|| missing("path"))?,
+            pd: pd.ok_or_else(|| missing("pd"))?,
+        })
+    }
+}
+
+pub(crate) fn expand(args: &Args) -> syn::Result<TokenStream> {
+    let path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join(args.path.value());
+    let err = |msg: String| syn::Error::new(args.path.span(), msg);
+
+    let xml = fs::read_to_string(&path)
+        .map_err(|e| err(format!("failed to read {}: {e}", path.display())))?;
+    let system = SystemDescription::parse(&xml)
+        .map_err(|e| err(format!("failed to parse {}: {e}", path.display())))?;
+    if let Err(errors) = system.validate() {
+        let mut errors = errors
+            .iter()
+            .map(|e| err(format!("{}: {e}", path.display())));
+        let mut combined = errors.next().unwrap();
+        combined.extend(errors);
+        return Err(combined);
+    }
+    let pd = system.protection_domain(&args.pd.value()).ok_or_else(|| {
+        syn::Error::new(
+            args.pd.span(),
+            format!("no protection domain named '{}'", args.pd.value()),
+        )
+    })?;
+
+    let path_str = path.to_str().unwrap();
+    let items = expand_protection_domain(&system, pd).map_err(err)?;
+
+    Ok(quote! {
+        // Rebuild when the system description changes.
+        const _: &[u8] = include_bytes!(#path_str);
+
+        #items
+    })
+}
+
+fn expand_protection_domain(
+    system: &SystemDescription,
+    pd: &ProtectionDomain,
+) -> Result<TokenStream, String> {
+    let channels = expand_channels(system, pd)?;
+    let irqs = expand_irqs(pd);
+    let children = expand_children(pd)?;
+    let memory_regions = expand_memory_regions(system, pd)?;
+
+    Ok(quote! {
+        /// Channels to other protection domains, named after those protection domains.
+        pub mod channels {
+            #channels
+        }
+
+        /// Channels for IRQs, named after their IRQ numbers.
+        pub mod irqs {
+            #irqs
+        }
+
+        /// Child protection domains.
+        pub mod children {
+            #children
+        }
+
+        /// Memory regions mapped by this protection domain, or whose physical addresses it
+        /// receives.
+        pub mod memory_regions {
+            #memory_regions
+        }
+    })
+}
+
+fn expand_channels(
+    system: &SystemDescription,
+    pd: &ProtectionDomain,
+) -> Result<TokenStream, String> {
+    let ends = system.channel_ends_of(&pd.name).collect::<Vec<_>>();
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for (_, remote) in &ends {
+        *counts.entry(&remote.pd).or_default() += 1;
+    }
+    let mut idents = Idents::new("channels");
+    ends.iter()
+        .map(|(local, remote)| {
+            let name = if counts[remote.pd.as_str()] > 1 {
+                format!("{}_{}", remote.pd, local.id)
+            } else {
+                remote.pd.clone()
+            };
+            let name = idents.upper(&name)?;
+            let id = usize_literal(local.id);
+            let doc = format!(
+                "Channel {} to `{}`{}{}.",
+                local.id,
+                remote.pd,
+                if local.pp {
+                    ", which this protection domain may call"
+                } else {
+                    ""
+                },
+                if remote.pp {
+                    ", which may call this protection domain"
+                } else {
+                    ""
+                },
+            );
+            Ok(quote! {
+                #[doc = #doc]
+                pub const #name: ::sel4_microkit::Channel = ::sel4_microkit::Channel::new(#id);
+            })
+        })
+        .collect()
+}
+
+fn expand_irqs(pd: &ProtectionDomain) -> TokenStream {
+    pd.irqs
+        .iter()
+        .map(|irq| {
+            let name = format_ident!("IRQ_{}", irq.irq);
+            let id = usize_literal(irq.id);
+            let doc = format!("Channel {} for IRQ {}.", irq.id, irq.irq);
+            quote! {
+                #[doc = #doc]
+                pub const #name: ::sel4_microkit::Channel = ::sel4_microkit::Channel::new(#id);
+            }
+        })
+        .collect()
+}
+
+fn expand_children(pd: &ProtectionDomain) -> Result<TokenStream, String> {
+    let mut idents = Idents::new("children");
+    pd.children
+        .iter()
+        .map(|child| {
+            let name = idents.upper(&child.name)?;
+            let id = usize_literal(child.id.unwrap());
+            let doc = format!("Child protection domain `{}`.", child.name);
+            Ok(quote! {
+                #[doc = #doc]
+                pub const #name: ::sel4_microkit::Child = ::sel4_microkit::Child::new(#id);
+            })
+        })
+        .collect()
+}
+
+fn expand_memory_regions(
+    system: &SystemDescription,
+    pd: &ProtectionDomain,
+) -> Result<TokenStream, String> {
+    let mut regions = BTreeMap::<&str, (Option<&Map>, Option<&SetVar>)>::new();
+    for map in &pd.maps {
+        if regions.entry(&map.mr).or_default().0.replace(map).is_some() {
+            return Err(format!(
+                "protection domain '{}' maps memory region '{}' more than once, which this macro does not support",
+                pd.name, map.mr
+            ));
+        }
+    }
+    for setvar in &pd.setvars {
+        if regions
+            .entry(&setvar.region_paddr)
+            .or_default()
+            .1
+            .replace(setvar)
+            .is_some()
+        {
+            return Err(format!(
+                "protection domain '{}' receives the physical address of memory region '{}' more than once, which this macro does not support",
+                pd.name, setvar.region_paddr
+            ));
+        }
+    }
+
+    let mut idents = Idents::new("memory_regions");
+    regions
+        .into_iter()
+        .map(|(name, (map, setvar))| {
+            let mr = system.memory_region(name).unwrap();
+            let module = idents.lower(name)?;
+            let size = usize_literal(mr.size);
+            let page_size = usize_literal(mr.page_size_or_default());
+            let doc = format!("Memory region `{name}`.");
+
+            let map_items = map
+                .map(|map| {
+                    let vaddr = usize_literal(map.vaddr);
+                    let read = map.perms.read;
+                    let write = map.perms.write;
+                    let execute = map.perms.execute;
+                    let cached = map.cached;
+                    let region = map
+                        .setvar_vaddr
+                        .as_ref()
+                        .map(|symbol| {
+                            let symbol = symbol_ident(symbol)?;
+                            let doc = format!(
+                                "Returns this memory region, whose address the `microkit` tool writes to `{symbol}`."
+                            );
+                            Ok::<_, String>(quote! {
+                                #[doc = #doc]
+                                pub fn region() -> ::core::ptr::NonNull<[u8]> {
+                                    ::sel4_microkit::memory_region_symbol!(#symbol: *mut [u8], n = SIZE)
+                                }
+                            })
+                        })
+                        .transpose()?;
+                    Ok::<_, String>(quote! {
+                        pub const VADDR: usize = #vaddr;
+                        pub const READ: bool = #read;
+                        pub const WRITE: bool = #write;
+                        pub const EXECUTE: bool = #execute;
+                        pub const CACHED: bool = #cached;
+                        #region
+                    })
+                })
+                .transpose()?;
+
+            let paddr = setvar
+                .map(|setvar| {
+                    let symbol = symbol_ident(&setvar.symbol)?;
+                    let doc = format!(
+                        "Returns this memory region's physical address, which the `microkit` tool writes to `{symbol}`."
+                    );
+                    Ok::<_, String>(quote! {
+                        #[doc = #doc]
+                        pub fn paddr() -> usize {
+                            *::sel4_microkit::var!(#symbol: usize = 0)
+                        }
+                    })
+                })
+                .transpose()?;
+
+            Ok(quote! {
+                #[doc = #doc]
+                pub mod #module {
+                    pub const SIZE: usize = #size;
+                    pub const PAGE_SIZE: usize = #page_size;
+                    #map_items
+                    #paddr
+                }
+            })
+        })
+        .collect()
+}
+
+/// Converts names from a system description into identifiers for the items of one generated
+/// module, rejecting names which would produce invalid or clashing identifiers.
+struct Idents {
+    module: &'static str,
+    seen: BTreeMap<String, String>,
+}
+
+impl Idents {
+    fn new(module: &'static str) -> Self {
+        Self {
+            module,
+            seen: BTreeMap::new(),
+        }
+    }
+
+    fn upper(&mut self, name: &str) -> Result<Ident, String> {
+        self.insert(name, sanitize(name).to_uppercase())
+    }
+
+    fn lower(&mut self, name: &str) -> Result<Ident, String> {
+        self.insert(name, sanitize(name).to_lowercase())
+    }
+
+    fn insert(&mut self, name: &str, s: String) -> Result<Ident, String> {
+        if let Some(other) = self.seen.insert(s.clone(), name.to_owned()) {
+            return Err(format!(
+                "'{other}' and '{name}' both become `{}::{s}`; rename one of them",
+                self.module
+            ));
+        }
+        if let Ok(ident) = syn::parse_str::<Ident>(&s) {
+            return Ok(ident);
+        }
+        if s.is_empty() || matches!(s.as_str(), "_" | "self" | "Self" | "super" | "crate") {
+            return Err(format!(
+                "'{name}' becomes `{s}` in `{}`, which cannot be used as an identifier; rename it",
+                self.module
+            ));
+        }
+        Ok(Ident::new_raw(&s, Span::call_site()))
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    let mut s = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if s.starts_with(|c: char| c.is_ascii_digit()) {
+        s.insert(0, '_');
+    }
+    s
+}
+
+fn symbol_ident(symbol: &str) -> Result<Ident, String> {
+    syn::parse_str::<Ident>(symbol)
+        .map_err(|_| format!("symbol '{symbol}' is not a valid identifier"))
+}
+
+fn usize_literal(value: u64) -> Literal {
+    Literal::usize_unsuffixed(value.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(xml: &str, pd: &str) -> Result<Vec<String>, String> {
+        let system = SystemDescription::parse(xml).unwrap();
+        system.validate().unwrap();
+        let tokens = expand_protection_domain(&system, system.protection_domain(pd).unwrap())?;
+        Ok(item_paths(
+            &syn::parse2::<syn::File>(tokens).unwrap().items,
+            "",
+        ))
+    }
+
+    fn item_paths(items: &[syn::Item], prefix: &str) -> Vec<String> {
+        let mut paths = vec![];
+        for item in items {
+            match item {
+                syn::Item::Const(item) => paths.push(format!("{prefix}{}", item.ident)),
+                syn::Item::Fn(item) => paths.push(format!("{prefix}{}()", item.sig.ident)),
+                syn::Item::Mod(item) => paths.extend(item_paths(
+                    &item.content.as_ref().unwrap().1,
+                    &format!("{prefix}{}::", item.ident),
+                )),
+                _ => panic!(),
+            }
+        }
+        paths
+    }
+
+    fn system(mr: &str, peers: &[&str]) -> String {
+        let mut xml = format!(
+            r#"<system>
+                <memory_region name="{mr}" size="0x1000" />
+                <protection_domain name="client" priority="1">
+                    <program_image path="client.elf" />
+                    <map mr="{mr}" vaddr="0x1000" setvar_vaddr="buf" />
+                    <irq irq="33" id="9" />
+                    <protection_domain name="kid" id="0" priority="0">
+                        <program_image path="kid.elf" />
+                    </protection_domain>
+                </protection_domain>"#
+        );
+        for (i, peer) in peers.iter().enumerate() {
+            xml += &format!(
+                r#"<protection_domain name="{peer}" priority="2">
+                    <program_image path="peer.elf" />
+                </protection_domain>
+                <channel>
+                    <end pd="client" id="{i}" />
+                    <end pd="{peer}" id="0" />
+                </channel>"#
+            );
+        }
+        xml + "</system>"
+    }
+
+    #[test]
+    fn items() {
+        assert_eq!(
+            expand_str(&system("shared-buf", &["server", "type"]), "client").unwrap(),
+            [
+                "channels::SERVER",
+                "channels::TYPE",
+                "irqs::IRQ_33",
+                "children::KID",
+                "memory_regions::shared_buf::SIZE",
+                "memory_regions::shared_buf::PAGE_SIZE",
+                "memory_regions::shared_buf::VADDR",
+                "memory_regions::shared_buf::READ",
+                "memory_regions::shared_buf::WRITE",
+                "memory_regions::shared_buf::EXECUTE",
+                "memory_regions::shared_buf::CACHED",
+                "memory_regions::shared_buf::region()",
+            ]
+        );
+        assert_eq!(
+            expand_str(&system("type", &[]), "client").unwrap()[2],
+            "memory_regions::r#type::SIZE"
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        assert_eq!(
+            expand_str(&system("mr", &["a-b", "a_b"]), "client").unwrap_err(),
+            "'a-b' and 'a_b' both become `channels::A_B`; rename one of them"
+        );
+        assert_eq!(
+            expand_str(&system("self", &[]), "client").unwrap_err(),
+            "'self' becomes `self` in `memory_regions`, which cannot be used as an identifier; rename it"
+        );
+    }
+}
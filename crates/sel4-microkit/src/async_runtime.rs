@@ -0,0 +1,373 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! An async runtime for protection domains.
+//!
+//! [`AsyncHandler`] is a [`Handler`] which runs an async entry point, along with any tasks it
+//! spawns, on a single-threaded executor. Tasks wait for notifications with
+//! [`Runtime::notified`] or [`Runtime::notifications`], and protected procedure calls are served
+//! by an async function registered with [`Runtime::serve_protected`].
+//!
+//! If the runtime is given a [`Timer`], then it drives a [`TimerManager`], and so tasks can also
+//! wait for time to pass with [`Runtime::sleep`] or [`Runtime::timers`]. The timer's notifications
+//! must be delivered to this protection domain on some channel, but the runtime does not need to
+//! know which one.
+//!
+//! ```rust
+//! #[protection_domain(heap_size = 0x10000)]
+//! fn init() -> impl Handler {
+//!     AsyncHandler::new(main)
+//! }
+//!
+//! async fn main(rt: Runtime) {
+//!     loop {
+//!         rt.notified(CLIENT).await;
+//!         // ...
+//!     }
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+
+use futures::future::LocalBoxFuture;
+use futures::stream::Stream;
+use futures::task::LocalSpawnExt;
+
+use sel4_async_single_threaded_executor::{LocalPool, LocalSpawner};
+use sel4_async_time::{Instant, Sleep, TimerManager};
+use sel4_driver_interfaces::timer::Timer;
+
+use crate::{Channel, ChannelSet, Handler, MessageInfo};
+
+const NUM_CHANNEL_BITS: usize = sel4::Badge::BITS as usize;
+
+/// A [`Handler`] which runs an async entry point and the tasks it spawns.
+pub struct AsyncHandler {
+    pool: LocalPool,
+    runtime: Runtime,
+}
+
+impl AsyncHandler {
+    /// Creates a runtime and spawns `main` on it.
+    ///
+    /// `main` runs until it first blocks before this function returns.
+    pub fn new<F, T>(main: F) -> Self
+    where
+        F: FnOnce(Runtime) -> T,
+        T: Future<Output = ()> + 'static,
+    {
+        Self::new_inner(None, main)
+    }
+
+    /// Like [`new`](Self::new), but with `timer` driving the runtime's [`TimerManager`].
+    pub fn with_timer<F, T>(timer: impl Timer + 'static, main: F) -> Self
+    where
+        F: FnOnce(Runtime) -> T,
+        T: Future<Output = ()> + 'static,
+    {
+        Self::new_inner(Some(Box::new(timer)), main)
+    }
+
+    fn new_inner<F, T>(timer: Option<Box<dyn DynTimer>>, main: F) -> Self
+    where
+        F: FnOnce(Runtime) -> T,
+        T: Future<Output = ()> + 'static,
+    {
+        let pool = LocalPool::new();
+        let runtime = Runtime {
+            shared: Rc::new(RefCell::new(Shared {
+                pending: 0,
+                wakers: core::array::from_fn(|_| Vec::new()),
+                timer,
+                protected_handler: None,
+            })),
+            spawner: pool.spawner(),
+            timers: TimerManager::new(),
+        };
+        runtime.spawn(main(runtime.clone()));
+        let mut this = Self { pool, runtime };
+        this.react();
+        this
+    }
+
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    fn react(&mut self) {
+        loop {
+            let _ = self.pool.run_all_until_stalled();
+            if !self.runtime.poll_timers() {
+                break;
+            }
+        }
+    }
+}
+
+impl Handler for AsyncHandler {
+    type Error = AsyncHandlerError;
+
+    fn notified(&mut self, channels: ChannelSet) -> Result<(), Self::Error> {
+        self.runtime.notify(channels);
+        self.react();
+        Ok(())
+    }
+
+    /// Runs the handler registered with [`Runtime::serve_protected`], along with all other
+    /// tasks, until it completes.
+    ///
+    /// The caller's reply capability would be lost if this protection domain were to receive
+    /// another message before replying, so the handler must be able to complete without this
+    /// protection domain receiving any notifications. If it cannot, then this function returns
+    /// [`AsyncHandlerError::ProtectedHandlerStalled`], which ends the main loop.
+    fn protected(
+        &mut self,
+        channel: Channel,
+        msg_info: MessageInfo,
+    ) -> Result<MessageInfo, Self::Error> {
+        let mut fut = self.runtime.call_protected_handler(channel, msg_info);
+        loop {
+            if let Poll::Ready(reply) = self.pool.run_until_stalled(Pin::new(&mut fut)) {
+                self.react();
+                return Ok(reply);
+            }
+            if !self.runtime.poll_timers() {
+                return Err(AsyncHandlerError::ProtectedHandlerStalled(channel));
+            }
+        }
+    }
+}
+
+/// Error type returned by [`AsyncHandler`]'s [`Handler`] methods.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AsyncHandlerError {
+    /// The handler registered with [`Runtime::serve_protected`] could not complete without a
+    /// notification. See [`AsyncHandler::protected`].
+    ProtectedHandlerStalled(Channel),
+}
+
+impl fmt::Display for AsyncHandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ProtectedHandlerStalled(channel) => write!(
+                f,
+                "protected procedure handler for channel {} cannot complete without a notification",
+                channel.index()
+            ),
+        }
+    }
+}
+
+/// A handle to the runtime of an [`AsyncHandler`].
+#[derive(Clone)]
+pub struct Runtime {
+    shared: Rc<RefCell<Shared>>,
+    spawner: LocalSpawner,
+    timers: TimerManager,
+}
+
+type ProtectedHandler =
+    Box<dyn FnMut(Channel, MessageInfo) -> LocalBoxFuture<'static, MessageInfo>>;
+
+struct Shared {
+    pending: sel4::Badge,
+    wakers: [Vec<Waker>; NUM_CHANNEL_BITS],
+    timer: Option<Box<dyn DynTimer>>,
+    protected_handler: Option<ProtectedHandler>,
+}
+
+impl Runtime {
+    pub fn spawner(&self) -> LocalSpawner {
+        self.spawner.clone()
+    }
+
+    pub fn spawn(&self, fut: impl Future<Output = ()> + 'static) {
+        self.spawner.spawn_local(fut).unwrap()
+    }
+
+    /// Returns a future which completes once a notification has been received on `channel`.
+    ///
+    /// Notifications are coalesced: a notification received while no task is waiting completes
+    /// the next such future, and several notifications received at once complete only one.
+    pub fn notified(&self, channel: Channel) -> Notified {
+        Notified {
+            runtime: self.clone(),
+            channel,
+        }
+    }
+
+    /// Returns a stream which yields once for each time [`notified`](Self::notified) would
+    /// complete.
+    pub fn notifications(&self, channel: Channel) -> Notifications {
+        Notifications {
+            runtime: self.clone(),
+            channel,
+        }
+    }
+
+    /// Makes a protected procedure call on `channel`.
+    ///
+    /// This blocks the whole protection domain, including all other tasks, until the callee
+    /// replies. The callee runs at a higher priority than this protection domain, so it does not
+    /// wait on anything which this protection domain's tasks could provide.
+    pub fn pp_call(&self, channel: Channel, msg_info: MessageInfo) -> MessageInfo {
+        channel.pp_call(msg_info)
+    }
+
+    /// Registers `f` to serve protected procedure calls, replacing any previously registered
+    /// function.
+    ///
+    /// See [`AsyncHandler::protected`] for restrictions on what the resulting futures may wait
+    /// for.
+    pub fn serve_protected<F, T>(&self, mut f: F)
+    where
+        F: FnMut(Channel, MessageInfo) -> T + 'static,
+        T: Future<Output = MessageInfo> + 'static,
+    {
+        self.shared.borrow_mut().protected_handler = Some(Box::new(move |channel, msg_info| {
+            Box::pin(f(channel, msg_info))
+        }));
+    }
+
+    /// Returns the [`TimerManager`] driven by this runtime's timer.
+    pub fn timers(&self) -> &TimerManager {
+        &self.timers
+    }
+
+    /// Returns the current time according to this runtime's timer.
+    ///
+    /// Panics if the runtime was not created with a timer.
+    pub fn now(&self) -> Instant {
+        self.shared
+            .borrow_mut()
+            .timer
+            .as_mut()
+            .expect("runtime has no timer")
+            .now()
+    }
+
+    /// Returns a future which completes after `duration` has passed.
+    ///
+    /// Panics if the runtime was not created with a timer.
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        self.timers.sleep_until(self.now() + duration)
+    }
+
+    fn notify(&self, channels: ChannelSet) {
+        let mut shared = self.shared.borrow_mut();
+        for channel in channels.iter() {
+            shared.pending |= 1 << channel.index();
+            for waker in shared.wakers[channel.index()].drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    fn poll_notified(&self, channel: Channel, cx: &mut Context<'_>) -> Poll<()> {
+        let mut shared = self.shared.borrow_mut();
+        let mask = 1 << channel.index();
+        if shared.pending & mask != 0 {
+            shared.pending &= !mask;
+            Poll::Ready(())
+        } else {
+            let wakers = &mut shared.wakers[channel.index()];
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+
+    fn call_protected_handler(
+        &self,
+        channel: Channel,
+        msg_info: MessageInfo,
+    ) -> LocalBoxFuture<'static, MessageInfo> {
+        // Release the borrow while `f` runs, in case it uses the runtime.
+        let mut f = self
+            .shared
+            .borrow_mut()
+            .protected_handler
+            .take()
+            .unwrap_or_else(|| {
+                panic!(
+                    "unexpected protected procedure call from channel {channel:?} with msg_info={msg_info:?}"
+                )
+            });
+        let fut = f(channel, msg_info);
+        self.shared.borrow_mut().protected_handler.get_or_insert(f);
+        fut
+    }
+
+    /// Expires due timers and arms the timer for the next deadline. Returns whether any timers
+    /// expired.
+    fn poll_timers(&self) -> bool {
+        let mut shared = self.shared.borrow_mut();
+        let Some(timer) = shared.timer.as_mut() else {
+            return false;
+        };
+        let now = timer.now();
+        if self.timers.poll(now) {
+            return true;
+        }
+        if let Some(deadline) = self.timers.poll_at() {
+            timer.set_timeout(deadline.saturating_duration_since(now));
+        }
+        false
+    }
+}
+
+/// Future returned by [`Runtime::notified`].
+pub struct Notified {
+    runtime: Runtime,
+    channel: Channel,
+}
+
+impl Future for Notified {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.runtime.poll_notified(self.channel, cx)
+    }
+}
+
+/// Stream returned by [`Runtime::notifications`].
+pub struct Notifications {
+    runtime: Runtime,
+    channel: Channel,
+}
+
+impl Stream for Notifications {
+    type Item = ();
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.runtime.poll_notified(self.channel, cx).map(Some)
+    }
+}
+
+trait DynTimer {
+    fn now(&mut self) -> Instant;
+
+    fn set_timeout(&mut self, relative: Duration);
+}
+
+impl<T: Timer> DynTimer for T {
+    fn now(&mut self) -> Instant {
+        Instant::new(self.get_time().unwrap())
+    }
+
+    fn set_timeout(&mut self, relative: Duration) {
+        Timer::set_timeout(self, relative).unwrap()
+    }
+}
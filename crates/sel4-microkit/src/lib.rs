@@ -29,7 +29,11 @@
 //! symbols.
 //!
 //! Use the [`protection_domain`] macro to declare the initialization function, stack size, and,
-//! optionally, heap and heap size.
+//! optionally, heap and heap size. Use the [`system_description`] macro to generate constants for
+//! a protection domain's channels and memory regions from a `.system` file.
+//!
+//! With the `async` feature, [`async_runtime::AsyncHandler`] provides a [`Handler`] which runs an
+//! async entry point.
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
@@ -42,6 +46,9 @@ mod printing;
 
 pub mod panicking;
 
+#[cfg(feature = "async")]
+pub mod async_runtime;
+
 #[sel4::sel4_cfg(PRINTING)]
 pub use printing::{debug_print, debug_println};
 
@@ -76,6 +83,35 @@ pub use printing::{debug_print, debug_println};
 /// link-time error.
 pub use sel4_microkit_macros::protection_domain;
 
+/// Generates typed constants for a protection domain from the system description which contains
+/// it.
+///
+/// For example:
+///
+/// ```rust
+/// system_description!(path = "../../x.system", pd = "client");
+/// ```
+///
+/// The path is relative to the directory containing the crate's `Cargo.toml`. The system
+/// description is parsed and validated at compile time, and any inconsistencies are reported as
+/// compile errors. The macro expands to the following modules:
+///
+/// - `channels`: a [`Channel`] for each channel to another protection domain, named after that
+///   protection domain (or, if there are several, after that protection domain and the channel's
+///   id).
+/// - `irqs`: a [`Channel`] for each IRQ, named `IRQ_<irq>`.
+/// - `children`: a [`Child`] for each child protection domain, named after it.
+/// - `memory_regions`: a module for each memory region which is mapped by this protection domain
+///   or whose physical address it receives, containing the region's `SIZE`, `PAGE_SIZE`, and
+///   mapping attributes, and, where the system description names the corresponding symbols,
+///   `region()` and `paddr()` functions which read them (see [`memory_region_symbol`] and
+///   [`var`]).
+///
+/// Names are converted to identifiers by replacing characters which are not ASCII alphanumeric
+/// with `_`. Names which would become the same identifier, or an identifier such as `self` which
+/// cannot be used, are reported as compile errors.
+pub use sel4_microkit_macros::system_description;
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! declare_protection_domain {
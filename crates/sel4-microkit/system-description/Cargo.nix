@@ -0,0 +1,14 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, versions }:
+
+mk {
+  package.name = "sel4-microkit-system-description";
+  dependencies = {
+    inherit (versions) thiserror xmltree;
+  };
+}
@@ -0,0 +1,145 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::fmt::{self, Display, Write};
+
+use crate::{Map, SystemDescription};
+
+/// A [DOT](https://graphviz.org/doc/info/lang.html) rendering of a system description, returned by
+/// [`SystemDescription::dot`].
+///
+/// Protection domains and virtual machines are drawn as boxes, labeled with their priorities and
+/// IRQs, and memory regions as ellipses. Solid edges are channels, pointing from caller to callee
+/// for channels which permit protected procedure calls, and labeled with the ids at each end.
+/// Dashed edges lead from parents to children and virtual machines. Dotted edges are maps,
+/// labeled with their permissions.
+pub struct Dot<'a> {
+    system: &'a SystemDescription,
+}
+
+impl<'a> Dot<'a> {
+    pub(crate) fn new(system: &'a SystemDescription) -> Self {
+        Self { system }
+    }
+}
+
+impl Display for Dot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "digraph system {{")?;
+
+        for mr in &self.system.memory_regions {
+            let mut label = format!("{}\n{:#x} bytes", mr.name, mr.size);
+            if let Some(phys_addr) = mr.phys_addr {
+                write!(label, "\nat {phys_addr:#x}").unwrap();
+            }
+            writeln!(
+                f,
+                "    {} [shape=ellipse, label={}];",
+                Id("mr", &mr.name),
+                Quoted(&label),
+            )?;
+        }
+
+        for pd in self.system.all_protection_domains() {
+            let pd_id = Id("pd", &pd.name);
+            let mut label = format!("{}\npriority {}", pd.name, pd.priority);
+            if pd.passive {
+                label.push_str(", passive");
+            }
+            for irq in &pd.irqs {
+                write!(label, "\nirq {} (id {})", irq.irq, irq.id).unwrap();
+            }
+            writeln!(f, "    {pd_id} [shape=box, label={}];", Quoted(&label))?;
+            print_maps(f, &pd_id, &pd.maps)?;
+
+            for child in &pd.children {
+                writeln!(
+                    f,
+                    "    {pd_id} -> {} [style=dashed, label={}];",
+                    Id("pd", &child.name),
+                    Quoted(&format!("child {}", child.id.unwrap_or_default())),
+                )?;
+            }
+
+            if let Some(vm) = &pd.virtual_machine {
+                let vm_id = Id("vm", &vm.name);
+                let label = format!("{}\npriority {}", vm.name, vm.priority);
+                writeln!(f, "    {vm_id} [shape=box3d, label={}];", Quoted(&label))?;
+                let vcpus = vm
+                    .vcpus
+                    .iter()
+                    .map(|vcpu| vcpu.id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                writeln!(
+                    f,
+                    "    {pd_id} -> {vm_id} [style=dashed, label={}];",
+                    Quoted(&format!("vcpus {vcpus}")),
+                )?;
+                print_maps(f, &vm_id, &vm.maps)?;
+            }
+        }
+
+        for channel in &self.system.channels {
+            let [a, b] = &channel.ends;
+            let dir = match (a.pp, b.pp) {
+                (false, false) => "none",
+                (true, false) => "forward",
+                (false, true) => "back",
+                (true, true) => "both",
+            };
+            writeln!(
+                f,
+                "    {} -> {} [dir={dir}, taillabel={}, headlabel={}];",
+                Id("pd", &a.pd),
+                Id("pd", &b.pd),
+                Quoted(&a.id.to_string()),
+                Quoted(&b.id.to_string()),
+            )?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+fn print_maps(f: &mut fmt::Formatter, from: &Id, maps: &[Map]) -> fmt::Result {
+    for map in maps {
+        writeln!(
+            f,
+            "    {from} -> {} [style=dotted, label={}];",
+            Id("mr", &map.mr),
+            Quoted(&format!("{} at {:#x}", map.perms, map.vaddr)),
+        )?;
+    }
+    Ok(())
+}
+
+/// A node id, namespaced by kind so that, for example, a protection domain and a memory region
+/// may share a name.
+struct Id<'a>(&'static str, &'a str);
+
+impl Display for Id<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Quoted(&format!("{}:{}", self.0, self.1)).fmt(f)
+    }
+}
+
+struct Quoted<'a>(&'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                _ => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
@@ -0,0 +1,429 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A model of the [seL4 Microkit](https://github.com/seL4/microkit) system description format.
+//!
+//! See the [seL4 Microkit manual](https://github.com/seL4/microkit/blob/main/docs/manual.md) for
+//! the meaning of each element and attribute. Numeric attributes may be written in decimal or, with
+//! a `0x` prefix, in hexadecimal, and may contain `_` separators.
+//!
+//! [`SystemDescription::parse`] checks only that a description is well-formed.
+//! [`SystemDescription::validate`] checks that it is consistent. The [`Display`](fmt::Display)
+//! implementation of [`SystemDescription`] prints it in a canonical form, which can be parsed
+//! again, and [`SystemDescription::dot`] renders it as a [DOT](https://graphviz.org/doc/info/lang.html)
+//! graph.
+
+use std::fmt;
+use std::path::PathBuf;
+
+mod dot;
+mod parse;
+mod print;
+mod validate;
+
+pub use dot::Dot;
+pub use parse::ParseError;
+pub use validate::ValidationError;
+
+/// The page size used for memory regions which do not specify one.
+pub const DEFAULT_PAGE_SIZE: u64 = 0x1000;
+
+/// The stack size used for protection domains which do not specify one.
+pub const DEFAULT_STACK_SIZE: u64 = 0x2000;
+
+/// The page sizes which memory regions may use.
+pub const PAGE_SIZES: &[u64] = &[0x1000, 0x200_000];
+
+/// The largest channel, IRQ, child protection domain, or virtual CPU id.
+pub const MAX_ID: u64 = 61;
+
+/// The largest I/O port id.
+pub const MAX_IOPORT_ID: u64 = 63;
+
+/// The largest priority a protection domain or virtual machine may have.
+pub const MAX_PRIORITY: u8 = 254;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SystemDescription {
+    pub memory_regions: Vec<MemoryRegion>,
+    pub protection_domains: Vec<ProtectionDomain>,
+    pub channels: Vec<Channel>,
+}
+
+impl SystemDescription {
+    pub fn parse(xml: &str) -> Result<Self, ParseError> {
+        parse::parse(xml)
+    }
+
+    pub fn memory_region(&self, name: &str) -> Option<&MemoryRegion> {
+        self.memory_regions.iter().find(|mr| mr.name == name)
+    }
+
+    /// Returns the protection domain named `name`, which may be a child of another protection
+    /// domain.
+    pub fn protection_domain(&self, name: &str) -> Option<&ProtectionDomain> {
+        self.all_protection_domains().find(|pd| pd.name == name)
+    }
+
+    /// Returns all protection domains, including children, in depth-first order.
+    pub fn all_protection_domains(&self) -> impl Iterator<Item = &ProtectionDomain> {
+        let mut stack = self.protection_domains.iter().rev().collect::<Vec<_>>();
+        std::iter::from_fn(move || {
+            let pd = stack.pop()?;
+            stack.extend(pd.children.iter().rev());
+            Some(pd)
+        })
+    }
+
+    /// Returns the ends of the channels with an end in the protection domain named `pd`, as pairs
+    /// of the form `(local, remote)`.
+    pub fn channel_ends_of<'a>(
+        &'a self,
+        pd: &'a str,
+    ) -> impl Iterator<Item = (&'a ChannelEnd, &'a ChannelEnd)> {
+        self.channels.iter().flat_map(move |channel| {
+            let [a, b] = &channel.ends;
+            [(a, b), (b, a)]
+                .into_iter()
+                .filter(move |(local, _)| local.pd == pd)
+        })
+    }
+
+    /// Returns a [DOT](https://graphviz.org/doc/info/lang.html) rendering of this system
+    /// description, showing protection domains, virtual machines, memory regions, and the
+    /// relationships between them.
+    pub fn dot(&self) -> Dot<'_> {
+        Dot::new(self)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub size: u64,
+    pub page_size: Option<u64>,
+    pub phys_addr: Option<u64>,
+}
+
+impl MemoryRegion {
+    pub fn page_size_or_default(&self) -> u64 {
+        self.page_size.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProtectionDomain {
+    pub name: String,
+    /// The child protection domain id, for protection domains which are children of others.
+    pub id: Option<u64>,
+    pub priority: u8,
+    pub budget: Option<u64>,
+    pub period: Option<u64>,
+    pub passive: bool,
+    pub stack_size: u64,
+    pub program_image: PathBuf,
+    pub maps: Vec<Map>,
+    pub irqs: Vec<Irq>,
+    pub setvars: Vec<SetVar>,
+    pub ioports: Vec<IoPort>,
+    pub children: Vec<ProtectionDomain>,
+    pub virtual_machine: Option<VirtualMachine>,
+}
+
+/// A virtual machine, whose virtual CPUs are managed by the protection domain which contains it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VirtualMachine {
+    pub name: String,
+    pub priority: u8,
+    pub budget: Option<u64>,
+    pub period: Option<u64>,
+    pub vcpus: Vec<Vcpu>,
+    /// Mappings into the virtual machine's guest physical address space.
+    pub maps: Vec<Map>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Vcpu {
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Map {
+    pub mr: String,
+    pub vaddr: u64,
+    pub perms: Perms,
+    pub cached: bool,
+    pub setvar_vaddr: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Perms {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+/// Formats these permissions as in the `perms` attribute, for example `rw`.
+impl fmt::Display for Perms {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (flag, c) in [(self.read, 'r'), (self.write, 'w'), (self.execute, 'x')] {
+            if flag {
+                write!(f, "{c}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A range of x86 I/O ports.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IoPort {
+    pub id: u64,
+    pub addr: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Irq {
+    pub irq: u64,
+    pub id: u64,
+    pub trigger: Option<IrqTrigger>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IrqTrigger {
+    Level,
+    Edge,
+}
+
+/// A symbol whose value is patched by the `microkit` tool.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SetVar {
+    pub symbol: String,
+    /// The memory region whose physical address is the symbol's value.
+    pub region_paddr: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Channel {
+    pub ends: [ChannelEnd; 2],
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ChannelEnd {
+    pub pd: String,
+    pub id: u64,
+    /// Whether this end may make protected procedure calls to the other.
+    pub pp: bool,
+    /// Whether this end may notify the other.
+    pub notify: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: &[&str] = &[
+        include_str!("../../../examples/microkit/http-server/http-server.system"),
+        include_str!("../../../examples/microkit/hello/hello.system"),
+        include_str!("../../../private/tests/microkit/deferred-actions-benchmark/x.system"),
+    ];
+
+    #[test]
+    fn examples() {
+        for xml in EXAMPLES {
+            let system = SystemDescription::parse(xml).unwrap();
+            system.validate().unwrap();
+            assert_eq!(
+                SystemDescription::parse(&system.to_string()).unwrap(),
+                system
+            );
+            assert!(system.dot().to_string().starts_with("digraph system {"));
+        }
+    }
+
+    #[test]
+    fn children_and_channel_ends() {
+        let xml = r#"
+            <system>
+                <memory_region name="mr" size="0x1_000" />
+                <protection_domain name="parent" priority="2">
+                    <program_image path="parent.elf" />
+                    <map mr="mr" vaddr="0x2_000" perms="r" setvar_vaddr="mr_vaddr" />
+                    <protection_domain name="child" id="3" priority="1">
+                        <program_image path="child.elf" />
+                    </protection_domain>
+                </protection_domain>
+                <channel>
+                    <end pd="parent" id="0" />
+                    <end pd="child" id="1" pp="true" />
+                </channel>
+            </system>
+        "#;
+        let system = SystemDescription::parse(xml).unwrap();
+        assert_eq!(
+            system
+                .all_protection_domains()
+                .map(|pd| (pd.name.as_str(), pd.id))
+                .collect::<Vec<_>>(),
+            [("parent", None), ("child", Some(3))]
+        );
+        let parent = system.protection_domain("parent").unwrap();
+        assert_eq!(parent.stack_size, DEFAULT_STACK_SIZE);
+        assert_eq!(
+            parent.maps[0].perms,
+            Perms {
+                read: true,
+                ..Perms::default()
+            }
+        );
+        let ends = system.channel_ends_of("child").collect::<Vec<_>>();
+        assert_eq!(ends.len(), 1);
+        assert_eq!((ends[0].0.id, ends[0].1.pd.as_str()), (1, "parent"));
+        assert!(ends[0].0.pp);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(
+            SystemDescription::parse(r#"<system><memory_region size="0x1000" /></system>"#),
+            Err(ParseError::MissingAttribute { .. })
+        ));
+        assert!(matches!(
+            SystemDescription::parse(r#"<system><memory_region name="mr" size="big" /></system>"#),
+            Err(ParseError::InvalidAttribute { .. })
+        ));
+    }
+
+    #[test]
+    fn inconsistencies() {
+        let xml = r#"
+            <system>
+                <memory_region name="mr" size="0x1800" />
+                <protection_domain name="a" priority="1">
+                    <program_image path="a.elf" />
+                    <map mr="mr" vaddr="0x2_000" setvar_vaddr="x" />
+                    <map mr="missing" vaddr="0x10_000" setvar_vaddr="x" />
+                    <irq irq="33" id="0" />
+                </protection_domain>
+                <protection_domain name="b" priority="2">
+                    <program_image path="b.elf" />
+                </protection_domain>
+                <channel>
+                    <end pd="a" id="0" />
+                    <end pd="b" id="62" />
+                </channel>
+            </system>
+        "#;
+        let errors = SystemDescription::parse(xml)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                ValidationError::UnalignedSize {
+                    mr: "mr".to_owned(),
+                    size: 0x1800,
+                    page_size: 0x1000,
+                },
+                ValidationError::UnknownMemoryRegion {
+                    pd: "a".to_owned(),
+                    mr: "missing".to_owned(),
+                },
+                ValidationError::DuplicateSymbol {
+                    pd: "a".to_owned(),
+                    symbol: "x".to_owned(),
+                },
+                ValidationError::DuplicateChannelId {
+                    pd: "a".to_owned(),
+                    id: 0,
+                },
+                ValidationError::IdOutOfRange {
+                    pd: "b".to_owned(),
+                    id: 62,
+                    max: MAX_ID,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn virtual_machines_and_priorities() {
+        let xml = r#"
+            <system>
+                <memory_region name="ram" size="0x200_000" page_size="0x200_000" phys_addr="0x4000_0000" />
+                <memory_region name="dev" size="0x1000" phys_addr="0x401f_f000" />
+                <memory_region name="odd" size="0x3000" page_size="0x3000" />
+                <protection_domain name="vmm" priority="100" budget="200" period="100">
+                    <program_image path="vmm.elf" />
+                    <map mr="ram" vaddr="0x200_000" />
+                    <map mr="dev" vaddr="0x3ff_000" />
+                    <ioport id="64" addr="0xfff8" size="0x10" />
+                    <protection_domain name="helper" id="0" priority="1">
+                        <program_image path="helper.elf" />
+                    </protection_domain>
+                    <virtual_machine name="guest" priority="99">
+                        <vcpu id="0" />
+                        <map mr="ram" vaddr="0x4000_0000" perms="rwx" />
+                    </virtual_machine>
+                </protection_domain>
+                <channel>
+                    <end pd="vmm" id="0" pp="true" />
+                    <end pd="helper" id="0" />
+                </channel>
+            </system>
+        "#;
+        let system = SystemDescription::parse(xml).unwrap();
+        assert_eq!(
+            SystemDescription::parse(&system.to_string()).unwrap(),
+            system
+        );
+        let errors = system.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                ValidationError::InvalidPageSize {
+                    mr: "odd".to_owned(),
+                    page_size: 0x3000,
+                },
+                ValidationError::OverlappingPhysAddrs {
+                    a: "ram".to_owned(),
+                    b: "dev".to_owned(),
+                },
+                ValidationError::BudgetExceedsPeriod {
+                    pd: "vmm".to_owned(),
+                    budget: 200,
+                    period: 100,
+                },
+                ValidationError::OverlappingMaps {
+                    pd: "vmm".to_owned(),
+                    a: "ram".to_owned(),
+                    b: "dev".to_owned(),
+                },
+                ValidationError::IdOutOfRange {
+                    pd: "vmm".to_owned(),
+                    id: 64,
+                    max: MAX_IOPORT_ID,
+                },
+                ValidationError::DuplicateChildId {
+                    pd: "vmm".to_owned(),
+                    id: 0,
+                },
+                ValidationError::IoPortOutOfRange {
+                    pd: "vmm".to_owned(),
+                    id: 64,
+                },
+                ValidationError::InvalidProtectedProcedureCall {
+                    caller: "vmm".to_owned(),
+                    caller_priority: 100,
+                    callee: "helper".to_owned(),
+                    callee_priority: 1,
+                },
+            ]
+        );
+    }
+}
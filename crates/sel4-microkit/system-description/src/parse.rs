@@ -0,0 +1,314 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::str::FromStr;
+
+use thiserror::Error;
+use xmltree::{Element, XMLNode};
+
+use crate::{
+    Channel, ChannelEnd, DEFAULT_STACK_SIZE, IoPort, Irq, IrqTrigger, Map, MemoryRegion, Perms,
+    ProtectionDomain, SetVar, SystemDescription, Vcpu, VirtualMachine,
+};
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("invalid XML: {0}")]
+    Xml(#[from] xmltree::ParseError),
+    #[error("unexpected element <{element}> in <{parent}>")]
+    UnexpectedElement { element: String, parent: String },
+    #[error("missing attribute '{attribute}' on <{element}>")]
+    MissingAttribute { element: String, attribute: String },
+    #[error("invalid value '{value}' for attribute '{attribute}' on <{element}>")]
+    InvalidAttribute {
+        element: String,
+        attribute: String,
+        value: String,
+    },
+    #[error("expected {expected} <{child}> element(s) in <{element}>, found {found}")]
+    WrongNumberOfChildren {
+        element: String,
+        child: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("more than one <{child}> element in <{element}>")]
+    DuplicateChild { element: String, child: String },
+}
+
+pub(crate) fn parse(xml: &str) -> Result<SystemDescription, ParseError> {
+    let root = Element::parse(xml.as_bytes())?;
+    if root.name != "system" {
+        return Err(ParseError::UnexpectedElement {
+            element: root.name.clone(),
+            parent: "".to_owned(),
+        });
+    }
+    let mut system = SystemDescription {
+        memory_regions: vec![],
+        protection_domains: vec![],
+        channels: vec![],
+    };
+    for e in child_elements(&root) {
+        match e.name.as_str() {
+            "memory_region" => system.memory_regions.push(parse_memory_region(e)?),
+            "protection_domain" => system
+                .protection_domains
+                .push(parse_protection_domain(e, false)?),
+            "channel" => system.channels.push(parse_channel(e)?),
+            _ => return Err(unexpected(e, &root)),
+        }
+    }
+    Ok(system)
+}
+
+fn parse_memory_region(e: &Element) -> Result<MemoryRegion, ParseError> {
+    Ok(MemoryRegion {
+        name: required(e, "name")?,
+        size: required_num(e, "size")?,
+        page_size: optional_num(e, "page_size")?,
+        phys_addr: optional_num(e, "phys_addr")?,
+    })
+}
+
+fn parse_protection_domain(e: &Element, is_child: bool) -> Result<ProtectionDomain, ParseError> {
+    let mut program_images = vec![];
+    let mut pd = ProtectionDomain {
+        name: required(e, "name")?,
+        id: if is_child {
+            Some(required_num(e, "id")?)
+        } else {
+            None
+        },
+        priority: optional_num(e, "priority")?.unwrap_or(0),
+        budget: optional_num(e, "budget")?,
+        period: optional_num(e, "period")?,
+        passive: optional_bool(e, "passive")?.unwrap_or(false),
+        stack_size: optional_num(e, "stack_size")?.unwrap_or(DEFAULT_STACK_SIZE),
+        program_image: Default::default(),
+        maps: vec![],
+        irqs: vec![],
+        setvars: vec![],
+        ioports: vec![],
+        children: vec![],
+        virtual_machine: None,
+    };
+    for child in child_elements(e) {
+        match child.name.as_str() {
+            "program_image" => program_images.push(required(child, "path")?.into()),
+            "map" => pd.maps.push(parse_map(child)?),
+            "irq" => pd.irqs.push(parse_irq(child)?),
+            "setvar" => pd.setvars.push(parse_setvar(child)?),
+            "ioport" => pd.ioports.push(parse_ioport(child)?),
+            "protection_domain" => pd.children.push(parse_protection_domain(child, true)?),
+            "virtual_machine" => {
+                if pd.virtual_machine.is_some() {
+                    return Err(ParseError::DuplicateChild {
+                        element: e.name.clone(),
+                        child: child.name.clone(),
+                    });
+                }
+                pd.virtual_machine = Some(parse_virtual_machine(child)?);
+            }
+            _ => return Err(unexpected(child, e)),
+        }
+    }
+    pd.program_image = exactly_one(e, "program_image", program_images)?;
+    Ok(pd)
+}
+
+fn parse_virtual_machine(e: &Element) -> Result<VirtualMachine, ParseError> {
+    let mut vm = VirtualMachine {
+        name: required(e, "name")?,
+        priority: optional_num(e, "priority")?.unwrap_or(0),
+        budget: optional_num(e, "budget")?,
+        period: optional_num(e, "period")?,
+        vcpus: vec![],
+        maps: vec![],
+    };
+    for child in child_elements(e) {
+        match child.name.as_str() {
+            "vcpu" => vm.vcpus.push(Vcpu {
+                id: required_num(child, "id")?,
+            }),
+            "map" => vm.maps.push(parse_map(child)?),
+            _ => return Err(unexpected(child, e)),
+        }
+    }
+    Ok(vm)
+}
+
+fn parse_map(e: &Element) -> Result<Map, ParseError> {
+    Ok(Map {
+        mr: required(e, "mr")?,
+        vaddr: required_num(e, "vaddr")?,
+        perms: match e.attributes.get("perms") {
+            Some(value) => parse_perms(value).ok_or_else(|| invalid(e, "perms", value))?,
+            None => Perms {
+                read: true,
+                write: true,
+                execute: false,
+            },
+        },
+        cached: optional_bool(e, "cached")?.unwrap_or(true),
+        setvar_vaddr: e.attributes.get("setvar_vaddr").cloned(),
+    })
+}
+
+fn parse_perms(s: &str) -> Option<Perms> {
+    let mut perms = Perms::default();
+    for c in s.chars() {
+        let flag = match c {
+            'r' => &mut perms.read,
+            'w' => &mut perms.write,
+            'x' => &mut perms.execute,
+            _ => return None,
+        };
+        if *flag {
+            return None;
+        }
+        *flag = true;
+    }
+    Some(perms)
+}
+
+fn parse_irq(e: &Element) -> Result<Irq, ParseError> {
+    Ok(Irq {
+        irq: required_num(e, "irq")?,
+        id: required_num(e, "id")?,
+        trigger: match e.attributes.get("trigger").map(String::as_str) {
+            None => None,
+            Some("level") => Some(IrqTrigger::Level),
+            Some("edge") => Some(IrqTrigger::Edge),
+            Some(value) => return Err(invalid(e, "trigger", value)),
+        },
+    })
+}
+
+fn parse_setvar(e: &Element) -> Result<SetVar, ParseError> {
+    Ok(SetVar {
+        symbol: required(e, "symbol")?,
+        region_paddr: required(e, "region_paddr")?,
+    })
+}
+
+fn parse_ioport(e: &Element) -> Result<IoPort, ParseError> {
+    Ok(IoPort {
+        id: required_num(e, "id")?,
+        addr: required_num(e, "addr")?,
+        size: required_num(e, "size")?,
+    })
+}
+
+fn parse_channel(e: &Element) -> Result<Channel, ParseError> {
+    let mut ends = vec![];
+    for child in child_elements(e) {
+        match child.name.as_str() {
+            "end" => ends.push(ChannelEnd {
+                pd: required(child, "pd")?,
+                id: required_num(child, "id")?,
+                pp: optional_bool(child, "pp")?.unwrap_or(false),
+                notify: optional_bool(child, "notify")?.unwrap_or(true),
+            }),
+            _ => return Err(unexpected(child, e)),
+        }
+    }
+    let found = ends.len();
+    let ends = ends
+        .try_into()
+        .map_err(|_| ParseError::WrongNumberOfChildren {
+            element: e.name.clone(),
+            child: "end".to_owned(),
+            expected: 2,
+            found,
+        })?;
+    Ok(Channel { ends })
+}
+
+fn child_elements(e: &Element) -> impl Iterator<Item = &Element> {
+    e.children.iter().filter_map(XMLNode::as_element)
+}
+
+fn exactly_one<T>(e: &Element, child: &str, mut values: Vec<T>) -> Result<T, ParseError> {
+    if values.len() != 1 {
+        return Err(ParseError::WrongNumberOfChildren {
+            element: e.name.clone(),
+            child: child.to_owned(),
+            expected: 1,
+            found: values.len(),
+        });
+    }
+    Ok(values.pop().unwrap())
+}
+
+fn required(e: &Element, attribute: &str) -> Result<String, ParseError> {
+    e.attributes
+        .get(attribute)
+        .cloned()
+        .ok_or_else(|| ParseError::MissingAttribute {
+            element: e.name.clone(),
+            attribute: attribute.to_owned(),
+        })
+}
+
+fn required_num<T: FromStrRadix>(e: &Element, attribute: &str) -> Result<T, ParseError> {
+    let value = required(e, attribute)?;
+    parse_num(&value).ok_or_else(|| invalid(e, attribute, &value))
+}
+
+fn optional_num<T: FromStrRadix>(e: &Element, attribute: &str) -> Result<Option<T>, ParseError> {
+    e.attributes
+        .get(attribute)
+        .map(|value| parse_num(value).ok_or_else(|| invalid(e, attribute, value)))
+        .transpose()
+}
+
+fn optional_bool(e: &Element, attribute: &str) -> Result<Option<bool>, ParseError> {
+    e.attributes
+        .get(attribute)
+        .map(|value| bool::from_str(value).map_err(|_| invalid(e, attribute, value)))
+        .transpose()
+}
+
+fn parse_num<T: FromStrRadix>(s: &str) -> Option<T> {
+    let s = s.replace('_', "");
+    match s.strip_prefix("0x") {
+        Some(hex) => T::from_str_radix(hex, 16),
+        None => T::from_str_radix(&s, 10),
+    }
+}
+
+trait FromStrRadix: Sized {
+    fn from_str_radix(s: &str, radix: u32) -> Option<Self>;
+}
+
+macro_rules! impl_from_str_radix {
+    ($t:ty) => {
+        impl FromStrRadix for $t {
+            fn from_str_radix(s: &str, radix: u32) -> Option<Self> {
+                <$t>::from_str_radix(s, radix).ok()
+            }
+        }
+    };
+}
+
+impl_from_str_radix!(u8);
+impl_from_str_radix!(u64);
+
+fn invalid(e: &Element, attribute: &str, value: &str) -> ParseError {
+    ParseError::InvalidAttribute {
+        element: e.name.clone(),
+        attribute: attribute.to_owned(),
+        value: value.to_owned(),
+    }
+}
+
+fn unexpected(e: &Element, parent: &Element) -> ParseError {
+    ParseError::UnexpectedElement {
+        element: e.name.clone(),
+        parent: parent.name.clone(),
+    }
+}
@@ -0,0 +1,213 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::fmt::{self, Display, Write};
+
+use crate::{
+    Channel, DEFAULT_STACK_SIZE, IrqTrigger, Map, MemoryRegion, ProtectionDomain,
+    SystemDescription, VirtualMachine,
+};
+
+const INDENT: &str = "    ";
+
+/// Prints this system description as XML, with defaulted attributes omitted and addresses and
+/// sizes in hexadecimal.
+impl Display for SystemDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(f, "<system>")?;
+        for mr in &self.memory_regions {
+            print_memory_region(f, mr)?;
+        }
+        for pd in &self.protection_domains {
+            print_protection_domain(f, 1, pd)?;
+        }
+        for channel in &self.channels {
+            print_channel(f, channel)?;
+        }
+        writeln!(f, "</system>")
+    }
+}
+
+fn print_memory_region(f: &mut fmt::Formatter, mr: &MemoryRegion) -> fmt::Result {
+    let mut e = Element::new("memory_region");
+    e.attr("name", &mr.name).attr("size", Hex(mr.size));
+    if let Some(page_size) = mr.page_size {
+        e.attr("page_size", Hex(page_size));
+    }
+    if let Some(phys_addr) = mr.phys_addr {
+        e.attr("phys_addr", Hex(phys_addr));
+    }
+    e.empty(f, 1)
+}
+
+fn print_protection_domain(
+    f: &mut fmt::Formatter,
+    depth: usize,
+    pd: &ProtectionDomain,
+) -> fmt::Result {
+    let mut e = Element::new("protection_domain");
+    e.attr("name", &pd.name);
+    if let Some(id) = pd.id {
+        e.attr("id", id);
+    }
+    e.attr("priority", pd.priority);
+    if let Some(budget) = pd.budget {
+        e.attr("budget", budget);
+    }
+    if let Some(period) = pd.period {
+        e.attr("period", period);
+    }
+    if pd.passive {
+        e.attr("passive", true);
+    }
+    if pd.stack_size != DEFAULT_STACK_SIZE {
+        e.attr("stack_size", Hex(pd.stack_size));
+    }
+    e.start(f, depth)?;
+
+    Element::new("program_image")
+        .attr("path", pd.program_image.display())
+        .empty(f, depth + 1)?;
+    for map in &pd.maps {
+        print_map(f, depth + 1, map)?;
+    }
+    for irq in &pd.irqs {
+        let mut e = Element::new("irq");
+        e.attr("irq", irq.irq).attr("id", irq.id);
+        if let Some(trigger) = irq.trigger {
+            e.attr(
+                "trigger",
+                match trigger {
+                    IrqTrigger::Level => "level",
+                    IrqTrigger::Edge => "edge",
+                },
+            );
+        }
+        e.empty(f, depth + 1)?;
+    }
+    for setvar in &pd.setvars {
+        Element::new("setvar")
+            .attr("symbol", &setvar.symbol)
+            .attr("region_paddr", &setvar.region_paddr)
+            .empty(f, depth + 1)?;
+    }
+    for ioport in &pd.ioports {
+        Element::new("ioport")
+            .attr("id", ioport.id)
+            .attr("addr", Hex(ioport.addr))
+            .attr("size", Hex(ioport.size))
+            .empty(f, depth + 1)?;
+    }
+    for child in &pd.children {
+        print_protection_domain(f, depth + 1, child)?;
+    }
+    if let Some(vm) = &pd.virtual_machine {
+        print_virtual_machine(f, depth + 1, vm)?;
+    }
+
+    e.end(f, depth)
+}
+
+fn print_virtual_machine(f: &mut fmt::Formatter, depth: usize, vm: &VirtualMachine) -> fmt::Result {
+    let mut e = Element::new("virtual_machine");
+    e.attr("name", &vm.name).attr("priority", vm.priority);
+    if let Some(budget) = vm.budget {
+        e.attr("budget", budget);
+    }
+    if let Some(period) = vm.period {
+        e.attr("period", period);
+    }
+    e.start(f, depth)?;
+    for vcpu in &vm.vcpus {
+        Element::new("vcpu")
+            .attr("id", vcpu.id)
+            .empty(f, depth + 1)?;
+    }
+    for map in &vm.maps {
+        print_map(f, depth + 1, map)?;
+    }
+    e.end(f, depth)
+}
+
+fn print_map(f: &mut fmt::Formatter, depth: usize, map: &Map) -> fmt::Result {
+    let mut e = Element::new("map");
+    e.attr("mr", &map.mr)
+        .attr("vaddr", Hex(map.vaddr))
+        .attr("perms", map.perms);
+    if !map.cached {
+        e.attr("cached", false);
+    }
+    if let Some(symbol) = &map.setvar_vaddr {
+        e.attr("setvar_vaddr", symbol);
+    }
+    e.empty(f, depth)
+}
+
+fn print_channel(f: &mut fmt::Formatter, channel: &Channel) -> fmt::Result {
+    writeln!(f, "{INDENT}<channel>")?;
+    for end in &channel.ends {
+        let mut e = Element::new("end");
+        e.attr("pd", &end.pd).attr("id", end.id);
+        if end.pp {
+            e.attr("pp", true);
+        }
+        if !end.notify {
+            e.attr("notify", false);
+        }
+        e.empty(f, 2)?;
+    }
+    writeln!(f, "{INDENT}</channel>")
+}
+
+struct Element {
+    name: &'static str,
+    attrs: String,
+}
+
+impl Element {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            attrs: String::new(),
+        }
+    }
+
+    fn attr(&mut self, name: &str, value: impl Display) -> &mut Self {
+        write!(self.attrs, r#" {name}=""#).unwrap();
+        for c in value.to_string().chars() {
+            match c {
+                '&' => self.attrs.push_str("&amp;"),
+                '<' => self.attrs.push_str("&lt;"),
+                '>' => self.attrs.push_str("&gt;"),
+                '"' => self.attrs.push_str("&quot;"),
+                _ => self.attrs.push(c),
+            }
+        }
+        self.attrs.push('"');
+        self
+    }
+
+    fn empty(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(f, "{}<{}{} />", INDENT.repeat(depth), self.name, self.attrs)
+    }
+
+    fn start(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(f, "{}<{}{}>", INDENT.repeat(depth), self.name, self.attrs)
+    }
+
+    fn end(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(f, "{}</{}>", INDENT.repeat(depth), self.name)
+    }
+}
+
+struct Hex(u64);
+
+impl Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
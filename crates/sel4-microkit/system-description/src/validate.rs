@@ -0,0 +1,381 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::{
+    MAX_ID, MAX_IOPORT_ID, MAX_PRIORITY, Map, MemoryRegion, PAGE_SIZES, ProtectionDomain,
+    SystemDescription,
+};
+
+const IOPORT_SPACE_SIZE: u64 = 0x1_0000;
+
+/// An inconsistency in a system description.
+///
+/// Where a field is named `pd`, it names the protection domain at fault, except in variants
+/// concerning maps or scheduling parameters, which virtual machines may also be at fault for.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum ValidationError {
+    #[error("more than one protection domain or virtual machine is named '{0}'")]
+    DuplicateProtectionDomain(String),
+    #[error("more than one memory region is named '{0}'")]
+    DuplicateMemoryRegion(String),
+    #[error("'{pd}' refers to unknown memory region '{mr}'")]
+    UnknownMemoryRegion { pd: String, mr: String },
+    #[error("channel refers to unknown protection domain '{0}'")]
+    UnknownProtectionDomain(String),
+    #[error("protection domain '{pd}' uses id {id} for more than one channel or IRQ")]
+    DuplicateChannelId { pd: String, id: u64 },
+    #[error("protection domain '{pd}' has more than one child or virtual CPU with id {id}")]
+    DuplicateChildId { pd: String, id: u64 },
+    #[error("protection domain '{pd}' has more than one I/O port range with id {id}")]
+    DuplicateIoPortId { pd: String, id: u64 },
+    #[error("protection domain '{pd}' uses id {id}, which is greater than {max}")]
+    IdOutOfRange { pd: String, id: u64, max: u64 },
+    #[error("protection domain '{pd}' declares symbol '{symbol}' more than once")]
+    DuplicateSymbol { pd: String, symbol: String },
+    #[error("memory region '{mr}' has unsupported page size {page_size:#x}")]
+    InvalidPageSize { mr: String, page_size: u64 },
+    #[error(
+        "size {size:#x} of memory region '{mr}' is not a multiple of its page size {page_size:#x}"
+    )]
+    UnalignedSize {
+        mr: String,
+        size: u64,
+        page_size: u64,
+    },
+    #[error(
+        "physical address {phys_addr:#x} of memory region '{mr}' is not aligned to its page size {page_size:#x}"
+    )]
+    UnalignedPhysAddr {
+        mr: String,
+        phys_addr: u64,
+        page_size: u64,
+    },
+    #[error(
+        "'{pd}' maps memory region '{mr}' at {vaddr:#x}, which is not aligned to its page size {page_size:#x}"
+    )]
+    UnalignedVaddr {
+        pd: String,
+        mr: String,
+        vaddr: u64,
+        page_size: u64,
+    },
+    #[error("physical address ranges of memory regions '{a}' and '{b}' overlap")]
+    OverlappingPhysAddrs { a: String, b: String },
+    #[error("'{pd}' maps memory regions '{a}' and '{b}' at overlapping addresses")]
+    OverlappingMaps { pd: String, a: String, b: String },
+    #[error(
+        "I/O port range {id} of protection domain '{pd}' extends beyond {IOPORT_SPACE_SIZE:#x}"
+    )]
+    IoPortOutOfRange { pd: String, id: u64 },
+    #[error("'{pd}' has priority {priority}, which is greater than {MAX_PRIORITY}")]
+    PriorityOutOfRange { pd: String, priority: u8 },
+    #[error("'{pd}' has budget {budget}, which is greater than its period {period}")]
+    BudgetExceedsPeriod {
+        pd: String,
+        budget: u64,
+        period: u64,
+    },
+    #[error(
+        "protection domain '{caller}' (priority {caller_priority}) may call protection domain '{callee}' (priority {callee_priority}), whose priority is not higher"
+    )]
+    InvalidProtectedProcedureCall {
+        caller: String,
+        caller_priority: u8,
+        callee: String,
+        callee_priority: u8,
+    },
+}
+
+impl SystemDescription {
+    /// Checks that this system description is consistent, returning every inconsistency found.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = vec![];
+        self.validate_names(&mut errors);
+        for mr in &self.memory_regions {
+            validate_memory_region(mr, &mut errors);
+        }
+        self.validate_phys_addrs(&mut errors);
+        for pd in self.all_protection_domains() {
+            self.validate_protection_domain(pd, &mut errors);
+        }
+        self.validate_channels(&mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_names(&self, errors: &mut Vec<ValidationError>) {
+        let thread_names = self.all_protection_domains().flat_map(|pd| {
+            [
+                Some(&pd.name),
+                pd.virtual_machine.as_ref().map(|vm| &vm.name),
+            ]
+            .into_iter()
+            .flatten()
+        });
+        for name in duplicates(thread_names) {
+            errors.push(ValidationError::DuplicateProtectionDomain(name.clone()));
+        }
+        for name in duplicates(self.memory_regions.iter().map(|mr| &mr.name)) {
+            errors.push(ValidationError::DuplicateMemoryRegion(name.clone()));
+        }
+    }
+
+    fn validate_phys_addrs(&self, errors: &mut Vec<ValidationError>) {
+        let ranges = self
+            .memory_regions
+            .iter()
+            .filter_map(|mr| Some((mr.name.as_str(), range(mr.phys_addr?, mr.size))))
+            .collect();
+        for (a, b) in overlaps(ranges) {
+            errors.push(ValidationError::OverlappingPhysAddrs {
+                a: a.to_owned(),
+                b: b.to_owned(),
+            });
+        }
+    }
+
+    fn validate_protection_domain(&self, pd: &ProtectionDomain, errors: &mut Vec<ValidationError>) {
+        validate_scheduling(&pd.name, pd.priority, pd.budget, pd.period, errors);
+        self.validate_maps(&pd.name, &pd.maps, errors);
+
+        for setvar in &pd.setvars {
+            if self.memory_region(&setvar.region_paddr).is_none() {
+                errors.push(ValidationError::UnknownMemoryRegion {
+                    pd: pd.name.clone(),
+                    mr: setvar.region_paddr.clone(),
+                });
+            }
+        }
+
+        let symbols = pd
+            .maps
+            .iter()
+            .filter_map(|map| map.setvar_vaddr.as_ref())
+            .chain(pd.setvars.iter().map(|setvar| &setvar.symbol));
+        for symbol in duplicates(symbols) {
+            errors.push(ValidationError::DuplicateSymbol {
+                pd: pd.name.clone(),
+                symbol: symbol.clone(),
+            });
+        }
+
+        if let Some(vm) = &pd.virtual_machine {
+            validate_scheduling(&vm.name, vm.priority, vm.budget, vm.period, errors);
+            self.validate_maps(&vm.name, &vm.maps, errors);
+        }
+
+        let channel_ids = self
+            .channel_ends_of(&pd.name)
+            .map(|(local, _)| local.id)
+            .chain(pd.irqs.iter().map(|irq| irq.id))
+            .collect::<Vec<_>>();
+        let child_ids = pd
+            .children
+            .iter()
+            .filter_map(|child| child.id)
+            .chain(
+                pd.virtual_machine
+                    .iter()
+                    .flat_map(|vm| vm.vcpus.iter().map(|vcpu| vcpu.id)),
+            )
+            .collect::<Vec<_>>();
+        let ioport_ids = pd
+            .ioports
+            .iter()
+            .map(|ioport| ioport.id)
+            .collect::<Vec<_>>();
+
+        let out_of_range = channel_ids
+            .iter()
+            .chain(&child_ids)
+            .map(|&id| (id, MAX_ID))
+            .chain(ioport_ids.iter().map(|&id| (id, MAX_IOPORT_ID)));
+        for (id, max) in out_of_range {
+            if id > max {
+                errors.push(ValidationError::IdOutOfRange {
+                    pd: pd.name.clone(),
+                    id,
+                    max,
+                });
+            }
+        }
+        for id in duplicates(channel_ids) {
+            errors.push(ValidationError::DuplicateChannelId {
+                pd: pd.name.clone(),
+                id,
+            });
+        }
+        for id in duplicates(child_ids) {
+            errors.push(ValidationError::DuplicateChildId {
+                pd: pd.name.clone(),
+                id,
+            });
+        }
+        for id in duplicates(ioport_ids) {
+            errors.push(ValidationError::DuplicateIoPortId {
+                pd: pd.name.clone(),
+                id,
+            });
+        }
+
+        for ioport in &pd.ioports {
+            if ioport.addr.saturating_add(ioport.size) > IOPORT_SPACE_SIZE {
+                errors.push(ValidationError::IoPortOutOfRange {
+                    pd: pd.name.clone(),
+                    id: ioport.id,
+                });
+            }
+        }
+    }
+
+    /// Validates the maps of the address space of the protection domain or virtual machine named
+    /// `owner`.
+    fn validate_maps(&self, owner: &str, maps: &[Map], errors: &mut Vec<ValidationError>) {
+        let mut ranges = vec![];
+        for map in maps {
+            let Some(mr) = self.memory_region(&map.mr) else {
+                errors.push(ValidationError::UnknownMemoryRegion {
+                    pd: owner.to_owned(),
+                    mr: map.mr.clone(),
+                });
+                continue;
+            };
+            if !map.vaddr.is_multiple_of(mr.page_size_or_default()) {
+                errors.push(ValidationError::UnalignedVaddr {
+                    pd: owner.to_owned(),
+                    mr: mr.name.clone(),
+                    vaddr: map.vaddr,
+                    page_size: mr.page_size_or_default(),
+                });
+            }
+            ranges.push((mr.name.as_str(), range(map.vaddr, mr.size)));
+        }
+        for (a, b) in overlaps(ranges) {
+            errors.push(ValidationError::OverlappingMaps {
+                pd: owner.to_owned(),
+                a: a.to_owned(),
+                b: b.to_owned(),
+            });
+        }
+    }
+
+    fn validate_channels(&self, errors: &mut Vec<ValidationError>) {
+        for channel in &self.channels {
+            let pds = channel
+                .ends
+                .each_ref()
+                .map(|end| self.protection_domain(&end.pd));
+            for (end, pd) in channel.ends.iter().zip(&pds) {
+                if pd.is_none() {
+                    errors.push(ValidationError::UnknownProtectionDomain(end.pd.clone()));
+                }
+            }
+            let [Some(a), Some(b)] = pds else {
+                continue;
+            };
+            for (end, caller, callee) in [(&channel.ends[0], a, b), (&channel.ends[1], b, a)] {
+                if end.pp && caller.priority >= callee.priority {
+                    errors.push(ValidationError::InvalidProtectedProcedureCall {
+                        caller: caller.name.clone(),
+                        caller_priority: caller.priority,
+                        callee: callee.name.clone(),
+                        callee_priority: callee.priority,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn validate_memory_region(mr: &MemoryRegion, errors: &mut Vec<ValidationError>) {
+    let page_size = mr.page_size_or_default();
+    if !PAGE_SIZES.contains(&page_size) {
+        errors.push(ValidationError::InvalidPageSize {
+            mr: mr.name.clone(),
+            page_size,
+        });
+        return;
+    }
+    if !mr.size.is_multiple_of(page_size) {
+        errors.push(ValidationError::UnalignedSize {
+            mr: mr.name.clone(),
+            size: mr.size,
+            page_size,
+        });
+    }
+    if let Some(phys_addr) = mr.phys_addr
+        && !phys_addr.is_multiple_of(page_size)
+    {
+        errors.push(ValidationError::UnalignedPhysAddr {
+            mr: mr.name.clone(),
+            phys_addr,
+            page_size,
+        });
+    }
+}
+
+fn validate_scheduling(
+    name: &str,
+    priority: u8,
+    budget: Option<u64>,
+    period: Option<u64>,
+    errors: &mut Vec<ValidationError>,
+) {
+    if priority > MAX_PRIORITY {
+        errors.push(ValidationError::PriorityOutOfRange {
+            pd: name.to_owned(),
+            priority,
+        });
+    }
+    if let (Some(budget), Some(period)) = (budget, period)
+        && budget > period
+    {
+        errors.push(ValidationError::BudgetExceedsPeriod {
+            pd: name.to_owned(),
+            budget,
+            period,
+        });
+    }
+}
+
+fn range(start: u64, size: u64) -> Range<u64> {
+    start..start.saturating_add(size)
+}
+
+/// Returns each pair of overlapping ranges, ordered by start address.
+fn overlaps<T: Copy>(mut ranges: Vec<(T, Range<u64>)>) -> Vec<(T, T)> {
+    ranges.sort_by_key(|(_, range)| range.start);
+    let mut pairs = vec![];
+    for (i, (a, range_a)) in ranges.iter().enumerate() {
+        for (b, _) in ranges[i + 1..]
+            .iter()
+            .take_while(|(_, range_b)| range_b.start < range_a.end)
+        {
+            pairs.push((*a, *b));
+        }
+    }
+    pairs
+}
+
+fn duplicates<T: Ord>(values: impl IntoIterator<Item = T>) -> BTreeSet<T> {
+    let mut counts = BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(value, _)| value)
+        .collect()
+}
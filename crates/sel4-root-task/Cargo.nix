@@ -4,13 +4,15 @@
 # SPDX-License-Identifier: BSD-2-Clause
 #
 
-{ mk, localCrates }:
+{ mk, versions, localCrates }:
 
 mk {
   package.name = "sel4-root-task";
   dependencies = {
+    inherit (versions) cfg-if;
     inherit (localCrates)
       sel4
+      sel4-elf-header
       sel4-immediate-sync-once-cell
       sel4-panicking-env
       sel4-dlmalloc
@@ -19,6 +21,7 @@ mk {
     ;
     sel4-panicking = localCrates.sel4-panicking // { features = [ "personality" "panic-handler" ]; };
     sel4-runtime-common = localCrates.sel4-runtime-common // { features = [ "sel4" ]; };
+    sel4-initialize-tls = localCrates.sel4-initialize-tls // { features = [ "on-heap" ]; optional = true; };
   };
   features = {
     full = [
@@ -26,6 +29,7 @@ mk {
     ];
     alloc = [
       "sel4-panicking/alloc"
+      "sel4-initialize-tls"
     ];
     single-threaded = [
       "sel4/single-threaded"
@@ -29,8 +29,10 @@
 //! Using a GNU linker will likely require a custom linker script.
 
 #![no_std]
+#![feature(cfg_target_thread_local)]
 #![feature(linkage)]
 #![feature(never_type)]
+#![feature(thread_local)]
 
 pub use sel4_panicking_env::abort;
 
@@ -42,6 +44,13 @@ mod heap;
 mod printing;
 mod termination;
 
+#[cfg(all(
+    feature = "alloc",
+    target_thread_local,
+    not(feature = "single-threaded")
+))]
+pub mod thread;
+
 pub use heap::set_global_allocator_mutex_notification;
 pub use termination::{Never, Termination};
 
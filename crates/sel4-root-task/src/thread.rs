@@ -0,0 +1,488 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Threads which share the root task's CSpace and VSpace, with an API modeled after
+//! `std::thread`.
+//!
+//! Kernel objects for new threads are obtained from the [`ThreadResourceAllocator`] registered
+//! with [`set_thread_resource_allocator`]. Stacks and thread-local storage are allocated on the
+//! heap.
+//!
+//! This crate does not allocate TCBs or IPC buffers itself because doing so requires untyped
+//! memory, free CSpace slots, and free virtual address space in which to map IPC buffers, all of
+//! which are managed by the root task rather than by this crate. A
+//! [`ThreadResourceAllocator`] can be built from whatever the root task uses to manage them.
+//!
+//! For example:
+//!
+//! ```rust
+//! let handle = sel4_root_task::thread::spawn(|| 1 + 1);
+//! assert_eq!(handle.join().unwrap(), 2);
+//! ```
+
+extern crate alloc;
+
+use alloc::alloc::{alloc, dealloc};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::alloc::Layout;
+use core::cell::{OnceCell, UnsafeCell};
+use core::fmt;
+use core::mem;
+use core::num::NonZeroUsize;
+use core::panic::AssertUnwindSafe;
+use core::sync::atomic::{AtomicUsize, Ordering, fence};
+
+use cfg_if::cfg_if;
+
+use sel4_elf_header::{ElfHeader, PT_TLS};
+use sel4_immediate_sync_once_cell::ImmediateSyncOnceCell;
+use sel4_initialize_tls::{HeapTlsReservation, TlsImage, UncheckedTlsImage};
+
+use crate::{abort, panicking::catch_unwind};
+
+const STACK_ALIGN: usize = 16;
+
+/// Kernel objects and memory with which a thread is created.
+pub struct ThreadResources {
+    /// A TCB whose scheduling parameters (and, on MCS configurations, scheduling context) have
+    /// already been configured.
+    pub tcb: sel4::cap::Tcb,
+    /// Signaled by the thread when it finishes.
+    pub notification: sel4::cap::Notification,
+    /// Must be mapped at `ipc_buffer` in the root task's VSpace.
+    pub ipc_buffer_frame: sel4::cap::Granule,
+    pub ipc_buffer: *mut sel4::IpcBuffer,
+}
+
+unsafe impl Send for ThreadResources {}
+
+/// Provides [`ThreadResources`] to [`spawn`].
+///
+/// Resources are returned via [`ThreadResourceAllocator::free`] when a thread is joined, at which
+/// point its TCB has been suspended. The resources of threads which are never joined are never
+/// returned.
+pub trait ThreadResourceAllocator: Sync {
+    fn allocate(&self) -> Option<ThreadResources>;
+
+    fn free(&self, resources: ThreadResources);
+}
+
+static THREAD_RESOURCE_ALLOCATOR: ImmediateSyncOnceCell<&'static dyn ThreadResourceAllocator> =
+    ImmediateSyncOnceCell::new();
+
+/// Registers the source of kernel objects for new threads.
+///
+/// Note that multi-threaded root tasks must also use
+/// [`set_global_allocator_mutex_notification`](crate::set_global_allocator_mutex_notification).
+pub fn set_thread_resource_allocator(allocator: &'static dyn ThreadResourceAllocator) {
+    THREAD_RESOURCE_ALLOCATOR
+        .set(allocator)
+        .unwrap_or_else(|_| abort!("thread resource allocator already initialized"))
+}
+
+fn thread_resource_allocator() -> &'static dyn ThreadResourceAllocator {
+    *THREAD_RESOURCE_ALLOCATOR
+        .get()
+        .unwrap_or_else(|| abort!("thread resource allocator not initialized"))
+}
+
+// // //
+
+/// A unique identifier for a thread.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThreadId(NonZeroUsize);
+
+impl ThreadId {
+    const MAIN: Self = Self(NonZeroUsize::MIN);
+
+    fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(ThreadId::MAIN.0.get() + 1);
+        Self(NonZeroUsize::new(NEXT.fetch_add(1, Ordering::Relaxed)).unwrap())
+    }
+
+    pub fn as_usize(&self) -> NonZeroUsize {
+        self.0
+    }
+}
+
+/// A handle to a thread.
+#[derive(Debug, Clone)]
+pub struct Thread {
+    inner: Arc<ThreadInner>,
+}
+
+#[derive(Debug)]
+struct ThreadInner {
+    id: ThreadId,
+    name: Option<String>,
+    tcb: sel4::cap::Tcb,
+}
+
+impl Thread {
+    fn new(id: ThreadId, name: Option<String>, tcb: sel4::cap::Tcb) -> Self {
+        Self {
+            inner: Arc::new(ThreadInner { id, name, tcb }),
+        }
+    }
+
+    pub fn id(&self) -> ThreadId {
+        self.inner.id
+    }
+
+    /// The main thread is named `"main"`.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name.as_deref()
+    }
+
+    pub fn tcb(&self) -> sel4::cap::Tcb {
+        self.inner.tcb
+    }
+}
+
+#[thread_local]
+static CURRENT: OnceCell<Thread> = OnceCell::new();
+
+/// Returns a handle to the calling thread.
+pub fn current() -> Thread {
+    CURRENT
+        .get_or_init(|| {
+            Thread::new(
+                ThreadId::MAIN,
+                Some(String::from("main")),
+                sel4::init_thread::slot::TCB.cap(),
+            )
+        })
+        .clone()
+}
+
+// // //
+
+/// Returned by [`JoinHandle::join`] when the thread panicked.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ThreadPanicked(());
+
+impl fmt::Display for ThreadPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "thread panicked")
+    }
+}
+
+pub type Result<T> = core::result::Result<T, ThreadPanicked>;
+
+struct Packet<T> {
+    result: UnsafeCell<Option<Result<T>>>,
+}
+
+// Access to `result` is serialized by the thread's notification.
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+struct Allocations {
+    resources: ThreadResources,
+    _stack: HeapStack,
+    _tls: Option<HeapTlsReservation>,
+}
+
+unsafe impl Send for Allocations {}
+
+/// An owned permission to join on a thread.
+///
+/// Dropping a [`JoinHandle`] detaches the thread, whose resources are then never reclaimed.
+pub struct JoinHandle<T> {
+    thread: Thread,
+    packet: Arc<Packet<T>>,
+    allocations: Option<Allocations>,
+}
+
+impl<T> JoinHandle<T> {
+    pub fn thread(&self) -> &Thread {
+        &self.thread
+    }
+
+    /// Waits for the thread to finish, returning its result.
+    pub fn join(mut self) -> Result<T> {
+        let allocations = self.allocations.take().unwrap();
+        allocations.resources.notification.wait();
+        fence(Ordering::Acquire);
+        allocations
+            .resources
+            .tcb
+            .tcb_suspend()
+            .unwrap_or_else(|err| abort!("failed to suspend joined thread: {err:?}"));
+        let result = unsafe { (*self.packet.result.get()).take().unwrap() };
+        thread_resource_allocator().free(allocations.resources);
+        result
+    }
+
+    /// Whether the thread has finished running its closure.
+    pub fn is_finished(&self) -> bool {
+        Arc::strong_count(&self.packet) == 1
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        // The thread may still be using its stack and TLS.
+        if let Some(allocations) = self.allocations.take() {
+            mem::forget(allocations);
+        }
+    }
+}
+
+/// Configuration for a new thread.
+#[derive(Debug, Default)]
+pub struct Builder {
+    name: Option<String>,
+    stack_size: Option<usize>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Must be nonzero. If absent, [`DEFAULT_STACK_SIZE`](crate::DEFAULT_STACK_SIZE) is used.
+    /// Thread-local storage is allocated separately.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Spawns a new thread running `f`. `f` is run under [`catch_unwind`], so that a panic is
+    /// reported by [`JoinHandle::join`].
+    ///
+    /// Returns [`sel4::Error::RangeError`] if the stack size is zero or too large to lay out, and
+    /// [`sel4::Error::NotEnoughMemory`] if either the stack or the thread's kernel objects cannot
+    /// be allocated.
+    pub fn spawn<F, T>(self, f: F) -> sel4::Result<JoinHandle<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let stack = HeapStack::new(self.stack_size.unwrap_or(crate::DEFAULT_STACK_SIZE))?;
+
+        let resources = thread_resource_allocator()
+            .allocate()
+            .ok_or(sel4::Error::NotEnoughMemory)?;
+
+        if let Err(err) = configure_tcb(&resources) {
+            thread_resource_allocator().free(resources);
+            return Err(err);
+        }
+
+        let thread = Thread::new(ThreadId::new(), self.name, resources.tcb);
+
+        let packet = Arc::new(Packet {
+            result: UnsafeCell::new(None),
+        });
+        let their_packet = packet.clone();
+        let main = move || {
+            let result = catch_unwind(AssertUnwindSafe(f)).map_err(|()| ThreadPanicked(()));
+            unsafe {
+                *their_packet.result.get() = Some(result);
+            }
+            drop(their_packet);
+        };
+
+        let start = Box::new(Start {
+            main: Box::new(main),
+            thread: thread.clone(),
+            notification: resources.notification,
+            ipc_buffer: resources.ipc_buffer,
+        });
+
+        let tls = get_tls_image().map(|image| image.initialize_on_heap());
+
+        let start = Box::into_raw(start);
+
+        let mut ctx = sel4::UserContext::default();
+        *ctx.sp_mut() = stack.top() as sel4::Word;
+        *ctx.pc_mut() = (thread_entrypoint as *const () as usize) as sel4::Word;
+        *ctx.c_param_mut(0) = start as sel4::Word;
+        if let Some(tls) = &tls {
+            *user_context_thread_pointer_mut(&mut ctx) = tls.thread_pointer() as sel4::Word;
+        }
+        cfg_if! {
+            if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+                ctx.inner_mut().gp = riscv_get_gp();
+            }
+        }
+
+        fence(Ordering::Release);
+        if let Err(err) = resources.tcb.tcb_write_all_registers(true, &mut ctx) {
+            // The thread never ran, so nothing else refers to these.
+            drop(unsafe { Box::from_raw(start) });
+            drop(stack);
+            drop(tls);
+            thread_resource_allocator().free(resources);
+            return Err(err);
+        }
+
+        Ok(JoinHandle {
+            thread,
+            packet,
+            allocations: Some(Allocations {
+                resources,
+                _stack: stack,
+                _tls: tls,
+            }),
+        })
+    }
+}
+
+fn configure_tcb(resources: &ThreadResources) -> sel4::Result<()> {
+    sel4::sel4_cfg_if! {
+        if #[sel4_cfg(KERNEL_MCS)] {
+            resources.tcb.tcb_configure(
+                sel4::init_thread::slot::CNODE.cap(),
+                sel4::CNodeCapData::new(0, 0),
+                sel4::init_thread::slot::VSPACE.cap(),
+                resources.ipc_buffer as sel4::Word,
+                resources.ipc_buffer_frame,
+            )
+        } else {
+            resources.tcb.tcb_configure(
+                sel4::init_thread::slot::NULL.cptr(),
+                sel4::init_thread::slot::CNODE.cap(),
+                sel4::CNodeCapData::new(0, 0),
+                sel4::init_thread::slot::VSPACE.cap(),
+                resources.ipc_buffer as sel4::Word,
+                resources.ipc_buffer_frame,
+            )
+        }
+    }
+}
+
+/// Spawns a new thread with the default configuration.
+///
+/// Panics if the thread cannot be created. See [`Builder::spawn`].
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Builder::new()
+        .spawn(f)
+        .unwrap_or_else(|err| panic!("failed to spawn thread: {err:?}"))
+}
+
+struct Start {
+    main: Box<dyn FnOnce() + Send>,
+    thread: Thread,
+    notification: sel4::cap::Notification,
+    ipc_buffer: *mut sel4::IpcBuffer,
+}
+
+unsafe extern "C" fn thread_entrypoint(arg: sel4::Word) -> ! {
+    let start = unsafe { Box::from_raw(arg as *mut Start) };
+    unsafe {
+        sel4::set_ipc_buffer(start.ipc_buffer.as_mut().unwrap());
+    }
+    let Start {
+        main,
+        thread,
+        notification,
+        ..
+    } = *start;
+    let tcb = thread.tcb();
+    let _ = CURRENT.set(thread);
+    main();
+    fence(Ordering::Release);
+    notification.signal();
+    // The joining thread will suspend this one anyway, but there is no reason to wait for it.
+    let _ = tcb.tcb_suspend();
+    abort!("thread resumed after finishing")
+}
+
+// // //
+
+struct HeapStack {
+    start: *mut u8,
+    layout: Layout,
+}
+
+impl HeapStack {
+    fn new(size: usize) -> sel4::Result<Self> {
+        // A zero-sized layout must not be passed to `alloc`.
+        if size == 0 {
+            return Err(sel4::Error::RangeError);
+        }
+        let layout = size
+            .checked_next_multiple_of(STACK_ALIGN)
+            .and_then(|size| Layout::from_size_align(size, STACK_ALIGN).ok())
+            .ok_or(sel4::Error::RangeError)?;
+        let start = unsafe { alloc(layout) };
+        if start.is_null() {
+            return Err(sel4::Error::NotEnoughMemory);
+        }
+        Ok(Self { start, layout })
+    }
+
+    fn top(&self) -> usize {
+        self.start as usize + self.layout.size()
+    }
+}
+
+impl Drop for HeapStack {
+    fn drop(&mut self) {
+        unsafe {
+            dealloc(self.start, self.layout);
+        }
+    }
+}
+
+fn get_tls_image() -> Option<TlsImage> {
+    unsafe extern "C" {
+        static __ehdr_start: ElfHeader;
+    }
+    let phdrs = unsafe {
+        assert!(__ehdr_start.is_magic_valid());
+        __ehdr_start.locate_phdrs()
+    };
+    let phdr = phdrs.iter().find(|phdr| phdr.p_type == PT_TLS)?;
+    let unchecked = UncheckedTlsImage {
+        vaddr: phdr.p_vaddr,
+        filesz: phdr.p_filesz,
+        memsz: phdr.p_memsz,
+        align: phdr.p_align,
+    };
+    Some(
+        unchecked
+            .check()
+            .unwrap_or_else(|_| abort!("invalid TLS image: {unchecked:#x?}")),
+    )
+}
+
+fn user_context_thread_pointer_mut(ctx: &mut sel4::UserContext) -> &mut sel4::Word {
+    cfg_if! {
+        if #[cfg(target_arch = "aarch64")] {
+            &mut ctx.inner_mut().tpidr_el0
+        } else if #[cfg(target_arch = "arm")] {
+            &mut ctx.inner_mut().tpidrurw
+        } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+            &mut ctx.inner_mut().tp
+        } else if #[cfg(target_arch = "x86_64")] {
+            &mut ctx.inner_mut().fs_base
+        } else {
+            compile_error!("unsupported architecture");
+        }
+    }
+}
+
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+fn riscv_get_gp() -> sel4::Word {
+    let val: sel4::Word;
+    unsafe {
+        core::arch::asm!("mv {}, gp", out(reg) val);
+    }
+    val
+}
@@ -0,0 +1,21 @@
+#
+# Copyright 2024, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, versions, localCrates }:
+
+mk {
+  package.name = "sel4-spawn";
+  dependencies = {
+    object = { version = versions.object; default-features = false; features = [ "read" ]; };
+    inherit (localCrates)
+      sel4
+      sel4-cspace-allocator
+      sel4-initialize-tls
+      sel4-untyped-manager
+      sel4-vspace-manager
+    ;
+  };
+}
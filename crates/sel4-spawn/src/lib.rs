@@ -0,0 +1,453 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Spawns child processes from ELF images in a root task.
+//!
+//! [`ProcessBuilder`] loads an ELF image into a fresh VSpace, creates a CSpace populated with the
+//! caps listed in [`child_slots`] followed by any granted caps, sets up an IPC buffer, a TLS region
+//! (if the image has a `PT_TLS` segment), and a stack holding the process's arguments, and starts a
+//! thread at the image's entry point.
+//!
+//! On entry, the first three C parameter registers hold `argc`, `argv`, and the address of the IPC
+//! buffer, respectively. `argv` is a null-terminated array of pointers to null-terminated strings.
+//!
+//! On MCS configurations, the thread runs on the scheduling context given to
+//! [`ProcessBuilder::sched_context`], which is required.
+//!
+//! All objects backing a process are allocated from an [`UntypedManager`], and are returned to it
+//! by [`Process::kill`] or [`Process::reclaim`].
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use sel4::{AbsoluteCPtr, Badge, CapRights, CapTypeForObjectOfFixedSize, Word};
+use sel4_cspace_allocator::{CSpaceAllocator, Slot};
+use sel4_untyped_manager::{Object, UntypedManager};
+use sel4_vspace_manager::VSpaceManager;
+
+mod loader;
+
+#[cfg(target_pointer_width = "32")]
+type FileHeader = object::elf::FileHeader32<object::Endianness>;
+
+#[cfg(target_pointer_width = "64")]
+type FileHeader = object::elf::FileHeader64<object::Endianness>;
+
+/// An ELF image for this target's word size.
+pub type ElfFile<'a> = object::read::elf::ElfFile<'a, FileHeader>;
+
+/// Indices of the caps which are present in every child's CSpace.
+///
+/// The child's CNode is guarded such that these indices are also valid CPtrs.
+pub mod child_slots {
+    pub const CNODE: usize = 1;
+    pub const TCB: usize = 2;
+    pub const VSPACE: usize = 3;
+    pub const IPC_BUFFER: usize = 4;
+    /// Holds the fault endpoint, if one was provided. On MCS configurations, this is only a copy.
+    pub const FAULT_ENDPOINT: usize = 5;
+    /// Granted caps occupy consecutive slots starting here, in the order in which they were
+    /// granted.
+    pub const FIRST_GRANTED: usize = 6;
+}
+
+/// The resources with which processes are spawned.
+pub struct SpawnResources<'a> {
+    pub untyped: &'a mut UntypedManager,
+    pub cspace: &'a mut CSpaceAllocator,
+    /// The caller's own VSpace, in which the child's frames are temporarily mapped to be
+    /// initialized.
+    pub vspace: &'a mut VSpaceManager,
+    /// A page-aligned virtual address in the caller's VSpace at which nothing is mapped.
+    pub scratch_vaddr: usize,
+    pub asid_pool: sel4::cap::AsidPool,
+    /// The TCB whose maximum controlled priority bounds that of children.
+    pub authority: sel4::cap::Tcb,
+}
+
+#[derive(Debug, Clone)]
+struct Grant {
+    src: AbsoluteCPtr,
+    rights: CapRights,
+    badge: Badge,
+}
+
+/// Describes a child process to be spawned.
+pub struct ProcessBuilder<'a> {
+    image: &'a ElfFile<'a>,
+    args: Vec<&'a [u8]>,
+    grants: Vec<Grant>,
+    cnode_size_bits: usize,
+    stack_size: usize,
+    priority: Word,
+    fault_endpoint: Option<(sel4::cap::Endpoint, Badge)>,
+    // Only used on MCS configurations.
+    sched_context: Option<sel4::CPtr>,
+}
+
+impl<'a> ProcessBuilder<'a> {
+    pub const DEFAULT_CNODE_SIZE_BITS: usize = 8;
+    pub const DEFAULT_STACK_SIZE: usize = 64 * 1024;
+    pub const DEFAULT_PRIORITY: Word = 0;
+
+    pub fn new(image: &'a ElfFile<'a>) -> Self {
+        Self {
+            image,
+            args: Vec::new(),
+            grants: Vec::new(),
+            cnode_size_bits: Self::DEFAULT_CNODE_SIZE_BITS,
+            stack_size: Self::DEFAULT_STACK_SIZE,
+            priority: Self::DEFAULT_PRIORITY,
+            fault_endpoint: None,
+            sched_context: None,
+        }
+    }
+
+    pub fn arg(mut self, arg: &'a [u8]) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        self.args.extend(args);
+        self
+    }
+
+    /// Mints the cap at `src` into the next free slot at or above [`child_slots::FIRST_GRANTED`].
+    pub fn grant(mut self, src: AbsoluteCPtr, rights: CapRights, badge: Badge) -> Self {
+        self.grants.push(Grant { src, rights, badge });
+        self
+    }
+
+    pub fn cnode_size_bits(mut self, cnode_size_bits: usize) -> Self {
+        self.cnode_size_bits = cnode_size_bits;
+        self
+    }
+
+    /// Rounded up to a multiple of the page size.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    pub fn priority(mut self, priority: Word) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Faults raised by the child will be delivered to `endpoint` with `badge`.
+    pub fn fault_endpoint(mut self, endpoint: sel4::cap::Endpoint, badge: Badge) -> Self {
+        self.fault_endpoint = Some((endpoint, badge));
+        self
+    }
+
+    /// The scheduling context which will be bound to the child's TCB. On MCS configurations, this
+    /// is required.
+    #[sel4::sel4_cfg(KERNEL_MCS)]
+    pub fn sched_context(mut self, sched_context: sel4::cap::SchedContext) -> Self {
+        self.sched_context = Some(sched_context.cptr());
+        self
+    }
+
+    /// Creates and starts the process.
+    ///
+    /// On failure, any objects which were allocated for the process are freed.
+    pub fn spawn(&self, res: &mut SpawnResources) -> Result<Process, Error> {
+        if child_slots::FIRST_GRANTED + self.grants.len() > 1 << self.cnode_size_bits {
+            return Err(Error::CNodeTooSmall);
+        }
+        // Without a scheduling context, the child would never run.
+        if sel4::sel4_cfg_bool!(KERNEL_MCS) && self.sched_context.is_none() {
+            return Err(Error::MissingSchedContext);
+        }
+        let mut process = Process {
+            tcb: sel4::cap::Tcb::from_bits(0),
+            cnode: sel4::cap::CNode::from_bits(0),
+            vspace: VSpaceManager::new(sel4::cap::VSpace::from_bits(0)),
+            ipc_buffer_vaddr: 0,
+            objects: Vec::new(),
+            caller_slots: Vec::new(),
+        };
+        match self.spawn_into(res, &mut process) {
+            Ok(()) => Ok(process),
+            Err(err) => {
+                // Failure may leave one of the process's frames mapped at the scratch address,
+                // which would otherwise cause all later spawns to fail.
+                if res.vspace.lookup(res.scratch_vaddr).is_some() {
+                    let _ = res.vspace.unmap(res.scratch_vaddr);
+                }
+                let _ = process.reclaim(res.untyped, res.cspace);
+                Err(err)
+            }
+        }
+    }
+
+    fn spawn_into(&self, res: &mut SpawnResources, process: &mut Process) -> Result<(), Error> {
+        let vspace = process.allocate::<sel4::cap_type::VSpace>(res)?;
+        res.asid_pool.asid_pool_assign(vspace)?;
+        process.vspace = VSpaceManager::new(vspace);
+
+        let layout = loader::load(res, process, self.image, &self.args, self.stack_size)?;
+        process.ipc_buffer_vaddr = layout.ipc_buffer_vaddr;
+
+        let cnode = process.allocate_object(
+            res,
+            &sel4::ObjectBlueprint::CNode {
+                size_bits: self.cnode_size_bits,
+            },
+        )?;
+        process.cnode = cnode.cast();
+        let tcb = process.allocate::<sel4::cap_type::Tcb>(res)?;
+        process.tcb = tcb;
+
+        let cnode_guard =
+            sel4::CNodeCapData::new(0, sel4::WORD_SIZE - self.cnode_size_bits).into_word();
+        let own_caps = [
+            (child_slots::CNODE, process.cnode.cptr(), cnode_guard),
+            (child_slots::TCB, tcb.cptr(), 0),
+            (child_slots::VSPACE, vspace.cptr(), 0),
+            (child_slots::IPC_BUFFER, layout.ipc_buffer_frame.cptr(), 0),
+        ];
+        for (i, cptr, badge) in own_caps {
+            self.child_slot(process, i).mint(
+                &sel4::init_thread::slot::CNODE.cap().absolute_cptr(cptr),
+                CapRights::all(),
+                badge,
+            )?;
+        }
+        let fault_endpoint = match self.fault_endpoint {
+            Some((endpoint, badge)) => {
+                let dst = self.child_slot(process, child_slots::FAULT_ENDPOINT);
+                dst.mint(
+                    &sel4::init_thread::slot::CNODE.cap().absolute_cptr(endpoint),
+                    CapRights::all(),
+                    badge,
+                )?;
+                Some((endpoint, badge))
+            }
+            None => None,
+        };
+        for (i, grant) in self.grants.iter().enumerate() {
+            self.child_slot(process, child_slots::FIRST_GRANTED + i)
+                .mint(&grant.src, grant.rights.clone(), grant.badge)?;
+        }
+
+        sel4::sel4_cfg_if! {
+            if #[sel4_cfg(KERNEL_MCS)] {
+                tcb.tcb_configure(
+                    process.cnode,
+                    sel4::CNodeCapData::new(0, sel4::WORD_SIZE - self.cnode_size_bits),
+                    vspace,
+                    layout.ipc_buffer_vaddr as Word,
+                    layout.ipc_buffer_frame,
+                )?;
+                // The fault endpoint is looked up in the caller's CSpace, so it must carry the
+                // badge there.
+                let fault_endpoint = match fault_endpoint {
+                    Some((endpoint, badge)) => {
+                        let slot = res.cspace.allocate()?;
+                        process.caller_slots.push(slot);
+                        res.cspace.absolute_cptr(slot).mint(
+                            &sel4::init_thread::slot::CNODE.cap().absolute_cptr(endpoint),
+                            CapRights::all(),
+                            badge,
+                        )?;
+                        res.cspace.cap(slot).ok_or(Error::SlotNotDirectlyAddressable)?
+                    }
+                    None => sel4::cap::Endpoint::from_bits(0),
+                };
+                tcb.tcb_set_sched_params(
+                    res.authority,
+                    self.priority,
+                    self.priority,
+                    sel4::cap::SchedContext::from_cptr(self.sched_context.unwrap()),
+                    fault_endpoint,
+                )?;
+            } else {
+                tcb.tcb_configure(
+                    match fault_endpoint {
+                        Some(_) => sel4::CPtr::from_bits(child_slots::FAULT_ENDPOINT as sel4::CPtrBits),
+                        None => sel4::init_thread::slot::NULL.cptr(),
+                    },
+                    process.cnode,
+                    sel4::CNodeCapData::new(0, sel4::WORD_SIZE - self.cnode_size_bits),
+                    vspace,
+                    layout.ipc_buffer_vaddr as Word,
+                    layout.ipc_buffer_frame,
+                )?;
+                tcb.tcb_set_sched_params(res.authority, self.priority, self.priority)?;
+            }
+        }
+
+        if let Some(thread_pointer) = layout.thread_pointer {
+            tcb.tcb_set_tls_base(thread_pointer as Word)?;
+        }
+
+        let mut ctx = sel4::UserContext::default();
+        *ctx.pc_mut() = layout.entry as Word;
+        *ctx.sp_mut() = layout.sp as Word;
+        *ctx.c_param_mut(0) = self.args.len() as Word;
+        *ctx.c_param_mut(1) = layout.argv as Word;
+        *ctx.c_param_mut(2) = layout.ipc_buffer_vaddr as Word;
+        tcb.tcb_write_all_registers(true, &mut ctx)?;
+
+        Ok(())
+    }
+
+    fn child_slot(&self, process: &Process, i: usize) -> AbsoluteCPtr {
+        process
+            .cnode
+            .absolute_cptr_from_bits_with_depth(i as sel4::CPtrBits, self.cnode_size_bits)
+    }
+}
+
+/// A handle to a spawned process.
+pub struct Process {
+    tcb: sel4::cap::Tcb,
+    cnode: sel4::cap::CNode,
+    vspace: VSpaceManager,
+    ipc_buffer_vaddr: usize,
+    objects: Vec<Object>,
+    caller_slots: Vec<Slot>,
+}
+
+impl Process {
+    pub fn tcb(&self) -> sel4::cap::Tcb {
+        self.tcb
+    }
+
+    pub fn cnode(&self) -> sel4::cap::CNode {
+        self.cnode
+    }
+
+    /// The child's VSpace, which can be used to map additional frames, such as shared memory.
+    pub fn vspace(&mut self) -> &mut VSpaceManager {
+        &mut self.vspace
+    }
+
+    pub fn ipc_buffer_vaddr(&self) -> usize {
+        self.ipc_buffer_vaddr
+    }
+
+    pub fn suspend(&self) -> Result<(), Error> {
+        self.tcb.tcb_suspend()?;
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<(), Error> {
+        self.tcb.tcb_resume()?;
+        Ok(())
+    }
+
+    /// Suspends the process and then destroys it, as [`Process::reclaim`] does, so that it does not
+    /// run while the objects backing it are being freed.
+    pub fn kill(
+        self,
+        untyped: &mut UntypedManager,
+        cspace: &mut CSpaceAllocator,
+    ) -> Result<(), Error> {
+        self.suspend()?;
+        self.reclaim(untyped, cspace)
+    }
+
+    /// Destroys the process and returns all of the objects backing it to `untyped`.
+    ///
+    /// Any caps derived from those objects, including caps granted by the child to others, are
+    /// deleted.
+    pub fn reclaim(
+        mut self,
+        untyped: &mut UntypedManager,
+        cspace: &mut CSpaceAllocator,
+    ) -> Result<(), Error> {
+        for slot in self.caller_slots.drain(..) {
+            cspace.delete_and_free(slot)?;
+        }
+        while let Some(object) = self.objects.pop() {
+            untyped.free(cspace, object)?;
+        }
+        Ok(())
+    }
+
+    fn allocate_object(
+        &mut self,
+        res: &mut SpawnResources,
+        blueprint: &sel4::ObjectBlueprint,
+    ) -> Result<sel4::cap::Unspecified, Error> {
+        let object = res.untyped.allocate(res.cspace, blueprint)?;
+        let cap = object.cap(res.cspace);
+        self.objects.push(object);
+        cap.ok_or(Error::SlotNotDirectlyAddressable)
+    }
+
+    fn allocate<T: CapTypeForObjectOfFixedSize>(
+        &mut self,
+        res: &mut SpawnResources,
+    ) -> Result<sel4::Cap<T>, Error> {
+        Ok(self.allocate_object(res, &T::object_blueprint())?.cast())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The image has no loadable segments, or they could not be read.
+    InvalidImage,
+    InvalidTlsImage,
+    /// The arguments do not fit on the stack.
+    ArgsTooLarge,
+    CNodeTooSmall,
+    SlotNotDirectlyAddressable,
+    /// No scheduling context was provided on an MCS configuration.
+    MissingSchedContext,
+    CSpace(sel4_cspace_allocator::Error),
+    Untyped(sel4_untyped_manager::Error),
+    VSpace(sel4_vspace_manager::Error),
+    Sel4(sel4::Error),
+}
+
+impl From<sel4_cspace_allocator::Error> for Error {
+    fn from(err: sel4_cspace_allocator::Error) -> Self {
+        Self::CSpace(err)
+    }
+}
+
+impl From<sel4_untyped_manager::Error> for Error {
+    fn from(err: sel4_untyped_manager::Error) -> Self {
+        Self::Untyped(err)
+    }
+}
+
+impl From<sel4_vspace_manager::Error> for Error {
+    fn from(err: sel4_vspace_manager::Error) -> Self {
+        Self::VSpace(err)
+    }
+}
+
+impl From<sel4::Error> for Error {
+    fn from(err: sel4::Error) -> Self {
+        Self::Sel4(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidImage => write!(f, "invalid ELF image"),
+            Self::InvalidTlsImage => write!(f, "invalid TLS segment"),
+            Self::ArgsTooLarge => write!(f, "arguments do not fit on the stack"),
+            Self::CNodeTooSmall => write!(f, "CNode is too small for granted caps"),
+            Self::SlotNotDirectlyAddressable => write!(f, "slot is not directly addressable"),
+            Self::MissingSchedContext => write!(f, "no scheduling context was provided"),
+            Self::CSpace(err) => write!(f, "{err}"),
+            Self::Untyped(err) => write!(f, "{err}"),
+            Self::VSpace(err) => write!(f, "{err}"),
+            Self::Sel4(err) => write!(f, "seL4 error: {err:?}"),
+        }
+    }
+}
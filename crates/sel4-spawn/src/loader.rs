@@ -0,0 +1,311 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+use core::ops::Range;
+use core::slice;
+
+use object::Object as _;
+use object::elf::{PF_W, PF_X, PT_LOAD, PT_TLS};
+use object::read::elf::ProgramHeader as _;
+
+use sel4::{CapRights, CapRightsBuilder, TranslationTableObjectType, VmAttributes};
+use sel4_initialize_tls::UncheckedTlsImage;
+use sel4_vspace_manager::UntypedTranslationTableAllocator;
+
+use crate::{ElfFile, Error, Process, SpawnResources};
+
+const GRANULE_SIZE: usize = sel4::FrameObjectType::GRANULE.bytes();
+
+const STACK_ALIGN: usize = 16;
+
+pub(crate) struct Layout {
+    pub(crate) entry: usize,
+    pub(crate) sp: usize,
+    pub(crate) argv: usize,
+    pub(crate) ipc_buffer_vaddr: usize,
+    pub(crate) ipc_buffer_frame: sel4::cap::Granule,
+    pub(crate) thread_pointer: Option<usize>,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Perms {
+    write: bool,
+    execute: bool,
+}
+
+impl Perms {
+    fn add(&mut self, p_flags: u32) {
+        self.write |= p_flags & PF_W != 0;
+        self.execute |= p_flags & PF_X != 0;
+    }
+
+    // Mappings without read rights are not supported on all architectures.
+    fn rights(&self) -> CapRights {
+        CapRightsBuilder::none()
+            .read(true)
+            .write(self.write)
+            .build()
+    }
+}
+
+/// Loads `image` into `process`'s VSpace, followed by (in increasing address order and separated
+/// by unmapped guard pages) an IPC buffer, a TLS region if `image` has a `PT_TLS` segment, and a
+/// stack.
+pub(crate) fn load(
+    res: &mut SpawnResources,
+    process: &mut Process,
+    image: &ElfFile,
+    args: &[&[u8]],
+    stack_size: usize,
+) -> Result<Layout, Error> {
+    let endian = image.endian();
+    let phdrs = image.elf_program_headers();
+
+    let footprint = coarsen(
+        &phdrs
+            .iter()
+            .filter(|phdr| phdr.p_type(endian) == PT_LOAD)
+            .map(|phdr| {
+                let vaddr = to_usize(phdr.p_vaddr(endian));
+                vaddr..(vaddr + to_usize(phdr.p_memsz(endian)))
+            })
+            .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+            .ok_or(Error::InvalidImage)?,
+    );
+
+    let frames = allocate_frames(res, process, footprint.len() / GRANULE_SIZE)?;
+    let mut perms = vec![Perms::default(); frames.len()];
+
+    for phdr in phdrs.iter().filter(|phdr| phdr.p_type(endian) == PT_LOAD) {
+        let vaddr = to_usize(phdr.p_vaddr(endian));
+        let segment_footprint = coarsen(&(vaddr..(vaddr + to_usize(phdr.p_memsz(endian)))));
+        let first_page = (segment_footprint.start - footprint.start) / GRANULE_SIZE;
+        for page_perms in &mut perms[first_page..][..segment_footprint.len() / GRANULE_SIZE] {
+            page_perms.add(phdr.p_flags(endian));
+        }
+        let data = phdr
+            .data(endian, image.data())
+            .map_err(|_| Error::InvalidImage)?;
+        write_region(res, &frames, footprint.start, vaddr, data)?;
+    }
+
+    for (i, (frame, page_perms)) in frames.iter().zip(&perms).enumerate() {
+        map_in_child(
+            res,
+            process,
+            *frame,
+            footprint.start + i * GRANULE_SIZE,
+            page_perms.rights(),
+        )?;
+        if page_perms.execute {
+            sel4::sel4_cfg_if! {
+                if #[sel4_cfg(ARCH_ARM)] {
+                    frame.frame_unify_instruction(0..GRANULE_SIZE)?;
+                }
+            }
+        }
+    }
+
+    let mut next_vaddr = footprint.end + GRANULE_SIZE;
+
+    let ipc_buffer_vaddr = next_vaddr;
+    let ipc_buffer_frame = allocate_frames(res, process, 1)?[0];
+    map_in_child(
+        res,
+        process,
+        ipc_buffer_frame,
+        ipc_buffer_vaddr,
+        CapRights::read_write(),
+    )?;
+    next_vaddr += 2 * GRANULE_SIZE;
+
+    let thread_pointer = match phdrs.iter().find(|phdr| phdr.p_type(endian) == PT_TLS) {
+        Some(phdr) => {
+            let data = phdr
+                .data(endian, image.data())
+                .map_err(|_| Error::InvalidImage)?;
+            let tls_image = UncheckedTlsImage {
+                vaddr: data.as_ptr() as usize,
+                filesz: data.len(),
+                memsz: to_usize(phdr.p_memsz(endian)),
+                align: to_usize(phdr.p_align(endian)).max(1),
+            }
+            .check()
+            .map_err(|_| Error::InvalidTlsImage)?;
+            let footprint = tls_image.reservation_layout().footprint();
+            if footprint.align() > GRANULE_SIZE {
+                return Err(Error::InvalidTlsImage);
+            }
+            // The reservation is initialized in a buffer in the caller's address space, and then
+            // copied into the child's.
+            let mut reservation = vec![0; footprint.size()];
+            let thread_pointer_offset =
+                unsafe { tls_image.initialize_reservation(reservation.as_mut_ptr()) }
+                    - reservation.as_ptr() as usize;
+            let region_start = next_vaddr;
+            let thread_pointer = region_start + thread_pointer_offset;
+            if cfg!(target_arch = "x86_64") {
+                // The TCB's self-pointer must refer to the child's address space.
+                reservation[thread_pointer_offset..][..mem::size_of::<usize>()]
+                    .copy_from_slice(&thread_pointer.to_ne_bytes());
+            }
+            next_vaddr = load_region(res, process, region_start, &reservation, region_start)?;
+            Some(thread_pointer)
+        }
+        None => None,
+    };
+
+    let stack_start = next_vaddr;
+    let stack_top = stack_start + stack_size.max(1).next_multiple_of(GRANULE_SIZE);
+    let (argv, stack_contents) = build_args(stack_top, args);
+    if argv <= stack_start {
+        return Err(Error::ArgsTooLarge);
+    }
+    load_region(res, process, stack_start, &stack_contents, argv)?;
+
+    Ok(Layout {
+        entry: usize::try_from(image.entry()).unwrap(),
+        sp: argv,
+        argv,
+        ipc_buffer_vaddr,
+        ipc_buffer_frame,
+        thread_pointer,
+    })
+}
+
+/// Lays out `args` at the top of a stack ending at `stack_top`. Returns the address of `argv`
+/// along with the contents of the stack from there to `stack_top`.
+fn build_args(stack_top: usize, args: &[&[u8]]) -> (usize, Vec<u8>) {
+    let word_size = mem::size_of::<usize>();
+    let strings_size = args.iter().map(|arg| arg.len() + 1).sum::<usize>();
+    let strings_start = stack_top - strings_size;
+    let argv = (strings_start - (args.len() + 1) * word_size) & !(STACK_ALIGN - 1);
+    let mut contents = vec![0; stack_top - argv];
+    let mut string_vaddr = strings_start;
+    for (i, arg) in args.iter().enumerate() {
+        contents[i * word_size..][..word_size].copy_from_slice(&string_vaddr.to_ne_bytes());
+        contents[string_vaddr - argv..][..arg.len()].copy_from_slice(arg);
+        string_vaddr += arg.len() + 1;
+    }
+    (argv, contents)
+}
+
+/// Maps read-write frames into the child starting at `region_start`, covering `data` which begins
+/// at `data_vaddr`. Returns the first page-aligned address past the region and a guard page.
+fn load_region(
+    res: &mut SpawnResources,
+    process: &mut Process,
+    region_start: usize,
+    data: &[u8],
+    data_vaddr: usize,
+) -> Result<usize, Error> {
+    let region_end = (data_vaddr + data.len()).next_multiple_of(GRANULE_SIZE);
+    let frames = allocate_frames(res, process, (region_end - region_start) / GRANULE_SIZE)?;
+    write_region(res, &frames, region_start, data_vaddr, data)?;
+    for (i, frame) in frames.iter().enumerate() {
+        map_in_child(
+            res,
+            process,
+            *frame,
+            region_start + i * GRANULE_SIZE,
+            CapRights::read_write(),
+        )?;
+    }
+    Ok(region_end + GRANULE_SIZE)
+}
+
+fn allocate_frames(
+    res: &mut SpawnResources,
+    process: &mut Process,
+    n: usize,
+) -> Result<Vec<sel4::cap::Granule>, Error> {
+    (0..n)
+        .map(|_| process.allocate::<sel4::cap_type::Granule>(res))
+        .collect()
+}
+
+/// Writes `data`, which begins at `data_vaddr`, into `frames`, which are to be mapped contiguously
+/// in the child starting at `region_start`.
+fn write_region(
+    res: &mut SpawnResources,
+    frames: &[sel4::cap::Granule],
+    region_start: usize,
+    data_vaddr: usize,
+    mut data: &[u8],
+) -> Result<(), Error> {
+    let mut offset = data_vaddr - region_start;
+    while !data.is_empty() {
+        let offset_into_page = offset % GRANULE_SIZE;
+        let n = (GRANULE_SIZE - offset_into_page).min(data.len());
+        with_scratch_mapping(res, frames[offset / GRANULE_SIZE], |page| {
+            page[offset_into_page..][..n].copy_from_slice(&data[..n]);
+        })?;
+        data = &data[n..];
+        offset += n;
+    }
+    Ok(())
+}
+
+fn with_scratch_mapping(
+    res: &mut SpawnResources,
+    frame: sel4::cap::Granule,
+    f: impl FnOnce(&mut [u8]),
+) -> Result<(), Error> {
+    let mut allocator = UntypedTranslationTableAllocator {
+        untyped: &mut *res.untyped,
+        cspace: &mut *res.cspace,
+    };
+    res.vspace.map(
+        &mut allocator,
+        frame,
+        res.scratch_vaddr,
+        CapRights::read_write(),
+        VmAttributes::default(),
+    )?;
+    f(unsafe { slice::from_raw_parts_mut(res.scratch_vaddr as *mut u8, GRANULE_SIZE) });
+    res.vspace.unmap(res.scratch_vaddr)?;
+    Ok(())
+}
+
+fn map_in_child(
+    res: &mut SpawnResources,
+    process: &mut Process,
+    frame: sel4::cap::Granule,
+    vaddr: usize,
+    rights: CapRights,
+) -> Result<(), Error> {
+    let untyped = &mut *res.untyped;
+    let cspace = &mut *res.cspace;
+    let objects = &mut process.objects;
+    // Translation tables belong to the process, so that they are freed along with it.
+    let mut allocator = |ty: TranslationTableObjectType| -> Result<_, sel4_vspace_manager::Error> {
+        let object = untyped.allocate(cspace, &ty.blueprint())?;
+        let cap = object.cap(cspace);
+        objects.push(object);
+        cap.ok_or(sel4_vspace_manager::Error::Untyped(
+            sel4_untyped_manager::Error::SlotNotDirectlyAddressable,
+        ))
+    };
+    process.vspace.map(
+        &mut allocator,
+        frame,
+        vaddr,
+        rights,
+        VmAttributes::default(),
+    )?;
+    Ok(())
+}
+
+fn coarsen(range: &Range<usize>) -> Range<usize> {
+    (range.start - range.start % GRANULE_SIZE)..range.end.next_multiple_of(GRANULE_SIZE)
+}
+
+fn to_usize(x: impl Into<u64>) -> usize {
+    usize::try_from(x.into()).unwrap()
+}
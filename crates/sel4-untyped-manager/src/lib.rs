@@ -0,0 +1,460 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A buddy-style manager for untyped memory.
+//!
+//! [`UntypedManager`] tracks a set of untypeds, such as those described by
+//! [`BootInfo::untyped_list`](sel4::BootInfo::untyped_list), and splits them in halves as needed to
+//! satisfy allocations. Each object is retyped from its own untyped block, so that freeing the
+//! object amounts to revoking that block. Freed blocks are coalesced with their buddies.
+//!
+//! Because blocks are always naturally aligned, specific physical addresses can be carved out of
+//! device untypeds (for MMIO) or kernel untypeds by splitting toward the target address.
+//!
+//! Capability slots for split untypeds and for objects are taken from a
+//! [`CSpaceAllocator`](sel4_cspace_allocator::CSpaceAllocator), which may use any
+//! [`AbstractAllocator`] to track its slots. Slots which hold untypeds must be directly
+//! addressable, since untypeds are invoked.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use sel4::{AbsoluteCPtr, Cap, CapType, ObjectBlueprint};
+use sel4_abstract_allocator::AbstractAllocator;
+use sel4_cspace_allocator::{CSpaceAllocator, Slot, SlotRange};
+
+const MIN_UNTYPED_BITS: usize = sel4::sys::seL4_MinUntypedBits as usize;
+
+type BlockId = usize;
+
+struct Block {
+    cap: sel4::cap::Untyped,
+    location: Location,
+    paddr: usize,
+    size_bits: usize,
+    is_device: bool,
+    parent: Option<BlockId>,
+    state: BlockState,
+}
+
+// Where a block's capability lives, for revoking it.
+enum Location {
+    // An untyped which was added with add_untyped.
+    Root(AbsoluteCPtr),
+    // One of the two halves of a split, whose capabilities are in `slots`.
+    Half { slots: SlotRange, index: usize },
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum BlockState {
+    Free,
+    Split([BlockId; 2]),
+    Allocated,
+    Vacant,
+}
+
+impl Block {
+    fn paddr_range(&self) -> Range<usize> {
+        self.paddr..(self.paddr + (1 << self.size_bits))
+    }
+
+    fn free_key(&self, id: BlockId) -> FreeKey {
+        (self.is_device, self.size_bits, self.paddr, id)
+    }
+}
+
+// Ordered so that the smallest sufficiently large block is found first.
+type FreeKey = (bool, usize, usize, BlockId);
+
+/// An object allocated by an [`UntypedManager`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Object {
+    block: BlockId,
+    slot: Slot,
+    paddr: usize,
+    size_bits: usize,
+}
+
+impl Object {
+    /// The slot, allocated from the [`CSpaceAllocator`], which holds the object's capability.
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    pub fn paddr(&self) -> usize {
+        self.paddr
+    }
+
+    pub fn size_bits(&self) -> usize {
+        self.size_bits
+    }
+
+    /// The object's capability, if its slot is directly addressable.
+    pub fn cap<T: CapType>(
+        &self,
+        cspace: &CSpaceAllocator<impl AbstractAllocator>,
+    ) -> Option<Cap<T>> {
+        cspace.cap(self.slot)
+    }
+}
+
+/// Splits untypeds buddy-style to allocate objects of any size.
+pub struct UntypedManager {
+    blocks: Vec<Block>,
+    vacant: Vec<BlockId>,
+    free: BTreeSet<FreeKey>,
+}
+
+impl UntypedManager {
+    pub fn new() -> Self {
+        Self {
+            blocks: Vec::new(),
+            vacant: Vec::new(),
+            free: BTreeSet::new(),
+        }
+    }
+
+    /// Manages all of the untypeds described by `bootinfo`.
+    pub fn from_bootinfo(bootinfo: &sel4::BootInfo) -> Self {
+        let mut this = Self::new();
+        for (i, desc) in bootinfo.untyped_list().iter().enumerate() {
+            let cap = bootinfo.untyped().index(i).cap();
+            this.add_untyped(
+                cap,
+                sel4::init_thread::slot::CNODE.cap().absolute_cptr(cap),
+                desc.paddr(),
+                desc.size_bits(),
+                desc.is_device(),
+            );
+        }
+        this
+    }
+
+    /// Adds an untyped, which must not have any children, to this manager.
+    ///
+    /// `slot` must address the slot which holds `cap`. It is used to revoke `cap` when freeing
+    /// objects, and so must remain valid for as long as this manager is in use.
+    pub fn add_untyped(
+        &mut self,
+        cap: sel4::cap::Untyped,
+        slot: AbsoluteCPtr,
+        paddr: usize,
+        size_bits: usize,
+        is_device: bool,
+    ) {
+        assert_eq!(paddr % (1 << size_bits), 0);
+        let id = self.insert_block(Block {
+            cap,
+            location: Location::Root(slot),
+            paddr,
+            size_bits,
+            is_device,
+            parent: None,
+            state: BlockState::Free,
+        });
+        self.mark_free(id);
+    }
+
+    /// Allocates an object of the given blueprint from kernel memory.
+    pub fn allocate<A: AbstractAllocator>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<A>,
+        blueprint: &ObjectBlueprint,
+    ) -> Result<Object, Error> {
+        let size_bits = block_size_bits(blueprint);
+        let id = self
+            .free
+            .range((false, size_bits, 0, 0)..(true, 0, 0, 0))
+            .next()
+            .map(|key| key.3)
+            .ok_or(Error::OutOfMemory)?;
+        let mut id = id;
+        while self.blocks[id].size_bits > size_bits {
+            id = self.split(cspace, id)?[0];
+        }
+        self.retype(cspace, id, blueprint)
+    }
+
+    pub fn allocate_fixed_sized<T: sel4::CapTypeForObjectOfFixedSize>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<impl AbstractAllocator>,
+    ) -> Result<Object, Error> {
+        self.allocate(cspace, &T::object_blueprint())
+    }
+
+    pub fn allocate_variable_sized<T: sel4::CapTypeForObjectOfVariableSize>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<impl AbstractAllocator>,
+        size_bits: usize,
+    ) -> Result<Object, Error> {
+        self.allocate(cspace, &T::object_blueprint(size_bits))
+    }
+
+    /// Allocates an object of the given blueprint at `paddr`, which must be aligned to the object's
+    /// size, from either device or kernel memory.
+    ///
+    /// This is how frames for MMIO regions are obtained.
+    pub fn allocate_at<A: AbstractAllocator>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<A>,
+        blueprint: &ObjectBlueprint,
+        paddr: usize,
+    ) -> Result<Object, Error> {
+        let size_bits = block_size_bits(blueprint);
+        if paddr % (1 << size_bits) != 0 {
+            return Err(Error::Misaligned);
+        }
+        let target = paddr..(paddr + (1 << size_bits));
+        let mut id = self
+            .free
+            .iter()
+            .map(|key| key.3)
+            .find(|id| {
+                let range = self.blocks[*id].paddr_range();
+                range.start <= target.start && target.end <= range.end
+            })
+            .ok_or(Error::Unavailable)?;
+        while self.blocks[id].size_bits > size_bits {
+            let halves = self.split(cspace, id)?;
+            id = if self.blocks[halves[1]].paddr <= paddr {
+                halves[1]
+            } else {
+                halves[0]
+            };
+        }
+        self.retype(cspace, id, blueprint)
+    }
+
+    /// Frees `object` by revoking the untyped from which it was retyped, which deletes all
+    /// capabilities derived from it, and then coalesces free buddies.
+    ///
+    /// If revoking that untyped fails, then nothing is changed and `object` remains allocated. If
+    /// coalescing fails, then `object` has nonetheless been freed, the blocks which could not be
+    /// coalesced remain free, and the error is returned.
+    pub fn free<A: AbstractAllocator>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<A>,
+        object: Object,
+    ) -> Result<(), Error> {
+        assert_eq!(self.blocks[object.block].state, BlockState::Allocated);
+        self.revoke(cspace, object.block)?;
+        cspace.free(object.slot);
+        let mut id = object.block;
+        let result = loop {
+            let Some(parent) = self.blocks[id].parent else {
+                break Ok(());
+            };
+            let BlockState::Split(halves) = self.blocks[parent].state else {
+                unreachable!()
+            };
+            let sibling = if halves[0] == id {
+                halves[1]
+            } else {
+                halves[0]
+            };
+            if self.blocks[sibling].state != BlockState::Free {
+                break Ok(());
+            }
+            // Revoking the parent deletes both halves. Only once that has succeeded is any
+            // bookkeeping updated, so that a failure leaves both halves free.
+            if let Err(err) = self.revoke(cspace, parent) {
+                break Err(err);
+            }
+            self.free.remove(&self.blocks[sibling].free_key(sibling));
+            self.release_halves(cspace, halves);
+            id = parent;
+        };
+        self.mark_free(id);
+        result
+    }
+
+    /// The total size of free blocks, in bytes, as `(kernel, device)`.
+    pub fn free_bytes(&self) -> (usize, usize) {
+        self.free.iter().fold((0, 0), |(kernel, device), key| {
+            let (is_device, size_bits, _, _) = *key;
+            if is_device {
+                (kernel, device + (1 << size_bits))
+            } else {
+                (kernel + (1 << size_bits), device)
+            }
+        })
+    }
+
+    fn insert_block(&mut self, block: Block) -> BlockId {
+        match self.vacant.pop() {
+            Some(id) => {
+                self.blocks[id] = block;
+                id
+            }
+            None => {
+                self.blocks.push(block);
+                self.blocks.len() - 1
+            }
+        }
+    }
+
+    fn mark_free(&mut self, id: BlockId) {
+        self.blocks[id].state = BlockState::Free;
+        self.free.insert(self.blocks[id].free_key(id));
+    }
+
+    fn split<A: AbstractAllocator>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<A>,
+        id: BlockId,
+    ) -> Result<[BlockId; 2], Error> {
+        let block = &self.blocks[id];
+        assert_eq!(block.state, BlockState::Free);
+        let half_size_bits = block.size_bits - 1;
+        if half_size_bits < MIN_UNTYPED_BITS {
+            return Err(Error::OutOfMemory);
+        }
+        let slots = cspace.allocate_range(2)?;
+        let caps = match [0, 1].map(|i| cspace.cap::<sel4::cap_type::Untyped>(slots.get(i))) {
+            [Some(a), Some(b)] => [a, b],
+            _ => {
+                cspace.free_range(slots);
+                return Err(Error::SlotNotDirectlyAddressable);
+            }
+        };
+        if let Err(err) = cspace.retype(
+            block.cap,
+            &ObjectBlueprint::Untyped {
+                size_bits: half_size_bits,
+            },
+            &slots,
+        ) {
+            cspace.free_range(slots);
+            return Err(err.into());
+        }
+        let (paddr, is_device) = (block.paddr, block.is_device);
+        self.free.remove(&block.free_key(id));
+        let halves = [0, 1].map(|i| {
+            self.insert_block(Block {
+                cap: caps[i],
+                location: Location::Half {
+                    slots: slots.clone(),
+                    index: i,
+                },
+                paddr: paddr + (i << half_size_bits),
+                size_bits: half_size_bits,
+                is_device,
+                parent: Some(id),
+                state: BlockState::Free,
+            })
+        });
+        for half in halves {
+            self.mark_free(half);
+        }
+        self.blocks[id].state = BlockState::Split(halves);
+        Ok(halves)
+    }
+
+    // Called once the halves' capabilities have been deleted by revoking their parent.
+    fn release_halves<A: AbstractAllocator>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<A>,
+        halves: [BlockId; 2],
+    ) {
+        let Location::Half { slots, .. } = &self.blocks[halves[0]].location else {
+            unreachable!()
+        };
+        cspace.free_range(slots.clone());
+        for half in halves {
+            self.blocks[half].state = BlockState::Vacant;
+            self.vacant.push(half);
+        }
+    }
+
+    fn retype<A: AbstractAllocator>(
+        &mut self,
+        cspace: &mut CSpaceAllocator<A>,
+        id: BlockId,
+        blueprint: &ObjectBlueprint,
+    ) -> Result<Object, Error> {
+        let slot = cspace.allocate()?;
+        let block = &self.blocks[id];
+        if let Err(err) = cspace.retype(block.cap, blueprint, &slot.into()) {
+            cspace.free(slot);
+            return Err(err.into());
+        }
+        let object = Object {
+            block: id,
+            slot,
+            paddr: block.paddr,
+            size_bits: block.size_bits,
+        };
+        self.free.remove(&block.free_key(id));
+        self.blocks[id].state = BlockState::Allocated;
+        Ok(object)
+    }
+
+    fn revoke<A: AbstractAllocator>(
+        &self,
+        cspace: &CSpaceAllocator<A>,
+        id: BlockId,
+    ) -> Result<(), Error> {
+        let cptr = match &self.blocks[id].location {
+            Location::Root(cptr) => *cptr,
+            Location::Half { slots, index } => cspace.absolute_cptr(slots.get(*index)),
+        };
+        cptr.revoke()?;
+        Ok(())
+    }
+}
+
+impl Default for UntypedManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn block_size_bits(blueprint: &ObjectBlueprint) -> usize {
+    blueprint.physical_size_bits().max(MIN_UNTYPED_BITS)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    OutOfMemory,
+    Unavailable,
+    Misaligned,
+    SlotNotDirectlyAddressable,
+    CSpace(sel4_cspace_allocator::Error),
+    Sel4(sel4::Error),
+}
+
+impl From<sel4_cspace_allocator::Error> for Error {
+    fn from(err: sel4_cspace_allocator::Error) -> Self {
+        Self::CSpace(err)
+    }
+}
+
+impl From<sel4::Error> for Error {
+    fn from(err: sel4::Error) -> Self {
+        Self::Sel4(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfMemory => write!(f, "out of untyped memory"),
+            Self::Unavailable => write!(f, "requested physical address is not available"),
+            Self::Misaligned => write!(f, "requested physical address is misaligned"),
+            Self::SlotNotDirectlyAddressable => {
+                write!(f, "slot for untyped is not directly addressable")
+            }
+            Self::CSpace(err) => write!(f, "{err}"),
+            Self::Sel4(err) => write!(f, "seL4 error: {err:?}"),
+        }
+    }
+}
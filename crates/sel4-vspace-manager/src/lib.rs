@@ -0,0 +1,430 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! An architecture-independent VSpace manager.
+//!
+//! [`VSpaceManager`] maps frames of any size at arbitrary virtual addresses within a VSpace,
+//! allocating and mapping any missing intermediate translation tables along the way. The layout of
+//! translation structures is taken from [`sel4::vspace_levels`], so the same code serves every
+//! architecture.
+//!
+//! The manager also keeps track of what it has mapped and of reserved virtual address ranges, so
+//! that free regions can be found for new mappings.
+//!
+//! Translation tables which were already present in the VSpace (for example, those of the root
+//! task's own VSpace) are detected as they are encountered, so the manager can be used with
+//! VSpaces which it did not create.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use sel4::{
+    CapRights, CapTypeForFrameObjectOfFixedSize, FrameObjectType, TranslationTableObjectType,
+    VmAttributes, vspace_levels,
+};
+use sel4_cspace_allocator::CSpaceAllocator;
+use sel4_untyped_manager::UntypedManager;
+
+/// A source of translation table objects for a [`VSpaceManager`].
+pub trait TranslationTableAllocator {
+    fn allocate_translation_table(
+        &mut self,
+        ty: TranslationTableObjectType,
+    ) -> Result<sel4::cap::UnspecifiedIntermediateTranslationTable, Error>;
+}
+
+impl<F> TranslationTableAllocator for F
+where
+    F: FnMut(
+        TranslationTableObjectType,
+    ) -> Result<sel4::cap::UnspecifiedIntermediateTranslationTable, Error>,
+{
+    fn allocate_translation_table(
+        &mut self,
+        ty: TranslationTableObjectType,
+    ) -> Result<sel4::cap::UnspecifiedIntermediateTranslationTable, Error> {
+        (self)(ty)
+    }
+}
+
+/// Allocates translation tables from an [`UntypedManager`] into slots from a [`CSpaceAllocator`].
+pub struct UntypedTranslationTableAllocator<'a> {
+    pub untyped: &'a mut UntypedManager,
+    pub cspace: &'a mut CSpaceAllocator,
+}
+
+impl TranslationTableAllocator for UntypedTranslationTableAllocator<'_> {
+    fn allocate_translation_table(
+        &mut self,
+        ty: TranslationTableObjectType,
+    ) -> Result<sel4::cap::UnspecifiedIntermediateTranslationTable, Error> {
+        let object = self.untyped.allocate(self.cspace, &ty.blueprint())?;
+        object.cap(self.cspace).ok_or(Error::Untyped(
+            sel4_untyped_manager::Error::SlotNotDirectlyAddressable,
+        ))
+    }
+}
+
+/// A frame mapped by a [`VSpaceManager`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    frame: sel4::cap::UnspecifiedPage,
+    frame_type: FrameObjectType,
+    rights: CapRights,
+    attrs: VmAttributes,
+}
+
+impl Mapping {
+    pub fn frame(&self) -> sel4::cap::UnspecifiedPage {
+        self.frame
+    }
+
+    pub fn frame_type(&self) -> FrameObjectType {
+        self.frame_type
+    }
+
+    pub fn rights(&self) -> &CapRights {
+        &self.rights
+    }
+
+    pub fn attrs(&self) -> VmAttributes {
+        self.attrs
+    }
+
+    pub fn size(&self) -> usize {
+        self.frame_type.bytes()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Table {
+    // None for tables which were already present in the VSpace.
+    cap: Option<sel4::cap::UnspecifiedIntermediateTranslationTable>,
+}
+
+/// Maps frames into a VSpace, allocating intermediate translation tables on demand.
+pub struct VSpaceManager {
+    vspace: sel4::cap::VSpace,
+    tables: BTreeMap<(usize, usize), Table>,
+    spare_tables: Vec<(
+        TranslationTableObjectType,
+        sel4::cap::UnspecifiedIntermediateTranslationTable,
+    )>,
+    mappings: BTreeMap<usize, Mapping>,
+    reservations: BTreeMap<usize, usize>,
+}
+
+impl VSpaceManager {
+    /// Manages `vspace`, which must already be assigned to an ASID pool.
+    pub fn new(vspace: sel4::cap::VSpace) -> Self {
+        Self {
+            vspace,
+            tables: BTreeMap::new(),
+            spare_tables: Vec::new(),
+            mappings: BTreeMap::new(),
+            reservations: BTreeMap::new(),
+        }
+    }
+
+    pub fn vspace(&self) -> sel4::cap::VSpace {
+        self.vspace
+    }
+
+    /// Maps `frame` at `vaddr`, which must be aligned to the frame's size.
+    pub fn map<T: CapTypeForFrameObjectOfFixedSize>(
+        &mut self,
+        allocator: &mut impl TranslationTableAllocator,
+        frame: sel4::Cap<T>,
+        vaddr: usize,
+        rights: CapRights,
+        attrs: VmAttributes,
+    ) -> Result<(), Error> {
+        self.map_unspecified(
+            allocator,
+            frame.cast(),
+            T::FRAME_OBJECT_TYPE,
+            vaddr,
+            rights,
+            attrs,
+        )
+    }
+
+    /// Like [`VSpaceManager::map`], but for frames whose type is only known at runtime.
+    pub fn map_unspecified(
+        &mut self,
+        allocator: &mut impl TranslationTableAllocator,
+        frame: sel4::cap::UnspecifiedPage,
+        frame_type: FrameObjectType,
+        vaddr: usize,
+        rights: CapRights,
+        attrs: VmAttributes,
+    ) -> Result<(), Error> {
+        if vaddr % frame_type.bytes() != 0 {
+            return Err(Error::Misaligned);
+        }
+        let level = frame_level(frame_type)?;
+        let end = vaddr
+            .checked_add(frame_type.bytes())
+            .ok_or(Error::AddressOverflow)?;
+        if let Some(overlap) = self.mapping_overlap(&(vaddr..end)) {
+            return Err(Error::AlreadyMapped(overlap));
+        }
+        for table_level in 1..=level {
+            self.ensure_table(allocator, table_level, vaddr)?;
+        }
+        frame.frame_map(self.vspace, vaddr, rights.clone(), attrs)?;
+        self.mappings.insert(
+            vaddr,
+            Mapping {
+                frame,
+                frame_type,
+                rights,
+                attrs,
+            },
+        );
+        Ok(())
+    }
+
+    /// Unmaps the frame mapped at `vaddr`, returning a description of the mapping so that the
+    /// caller can dispose of the frame.
+    pub fn unmap(&mut self, vaddr: usize) -> Result<Mapping, Error> {
+        let mapping = self.mappings.get(&vaddr).ok_or(Error::NotMapped(vaddr))?;
+        mapping.frame.frame_unmap()?;
+        Ok(self.mappings.remove(&vaddr).unwrap())
+    }
+
+    /// Ensures that all translation tables required to map a frame of type `frame_type` at each
+    /// frame-aligned address in `range` are present.
+    pub fn prepare(
+        &mut self,
+        allocator: &mut impl TranslationTableAllocator,
+        range: Range<usize>,
+        frame_type: FrameObjectType,
+    ) -> Result<(), Error> {
+        let level = frame_level(frame_type)?;
+        for table_level in 1..=level {
+            let span = 1 << vspace_levels::span_bits(table_level);
+            let mut vaddr = range.start - range.start % span;
+            while vaddr < range.end {
+                self.ensure_table(allocator, table_level, vaddr)?;
+                match vaddr.checked_add(span) {
+                    Some(next) => vaddr = next,
+                    None => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reserves `range` so that it is not returned by [`VSpaceManager::reserve_anywhere`].
+    ///
+    /// Reservations do not restrict explicit calls to [`VSpaceManager::map`].
+    pub fn reserve(&mut self, range: Range<usize>) -> Result<(), Error> {
+        if range.is_empty() {
+            return Ok(());
+        }
+        if let Some(overlap) = first_overlap(&self.reservations, &range) {
+            return Err(Error::AlreadyReserved(overlap));
+        }
+        self.reservations.insert(range.start, range.end);
+        Ok(())
+    }
+
+    /// Reserves and returns the lowest range of `size` bytes, aligned to `align`, within `within`,
+    /// which overlaps neither an existing reservation nor a mapping.
+    ///
+    /// Returns [`Error::NoFreeRange`] if there is no such range, including if `align` is zero.
+    pub fn reserve_anywhere(
+        &mut self,
+        within: Range<usize>,
+        size: usize,
+        align: usize,
+    ) -> Result<Range<usize>, Error> {
+        let mut start = within
+            .start
+            .checked_next_multiple_of(align)
+            .ok_or(Error::NoFreeRange)?;
+        loop {
+            let candidate = start..start.checked_add(size).ok_or(Error::NoFreeRange)?;
+            if candidate.end > within.end {
+                return Err(Error::NoFreeRange);
+            }
+            match first_overlap(&self.reservations, &candidate)
+                .or_else(|| self.mapping_overlap(&candidate))
+            {
+                Some(overlap) => {
+                    start = overlap
+                        .end
+                        .checked_next_multiple_of(align)
+                        .ok_or(Error::NoFreeRange)?;
+                }
+                None => {
+                    self.reservations.insert(candidate.start, candidate.end);
+                    return Ok(candidate);
+                }
+            }
+        }
+    }
+
+    /// Releases the reservation starting at `start`.
+    pub fn release(&mut self, start: usize) -> Result<Range<usize>, Error> {
+        self.reservations
+            .remove(&start)
+            .map(|end| start..end)
+            .ok_or(Error::NotReserved(start))
+    }
+
+    /// The frames mapped by this manager, in order of virtual address.
+    pub fn mappings(&self) -> impl Iterator<Item = (usize, &Mapping)> {
+        self.mappings
+            .iter()
+            .map(|(vaddr, mapping)| (*vaddr, mapping))
+    }
+
+    /// Finds the mapping which contains `vaddr`, along with `vaddr`'s offset into it.
+    pub fn lookup(&self, vaddr: usize) -> Option<(&Mapping, usize)> {
+        let (start, mapping) = self.mappings.range(..=vaddr).next_back()?;
+        let offset = vaddr - start;
+        (offset < mapping.size()).then_some((mapping, offset))
+    }
+
+    pub fn reservations(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.reservations.iter().map(|(start, end)| *start..*end)
+    }
+
+    /// The translation tables which this manager has allocated and mapped, as `(level, vaddr,
+    /// cap)`, where `vaddr` is the start of the region spanned by the table.
+    pub fn translation_tables(
+        &self,
+    ) -> impl Iterator<
+        Item = (
+            usize,
+            usize,
+            sel4::cap::UnspecifiedIntermediateTranslationTable,
+        ),
+    > + '_ {
+        self.tables
+            .iter()
+            .filter_map(|((level, vaddr), table)| table.cap.map(|cap| (*level, *vaddr, cap)))
+    }
+
+    fn ensure_table(
+        &mut self,
+        allocator: &mut impl TranslationTableAllocator,
+        level: usize,
+        vaddr: usize,
+    ) -> Result<(), Error> {
+        let span = 1 << vspace_levels::span_bits(level);
+        let key = (level, vaddr - vaddr % span);
+        if self.tables.contains_key(&key) {
+            return Ok(());
+        }
+        let ty = TranslationTableObjectType::from_level(level).unwrap();
+        let cap = match self
+            .spare_tables
+            .iter()
+            .position(|(spare_ty, _)| *spare_ty == ty)
+        {
+            Some(i) => self.spare_tables.swap_remove(i).1,
+            None => allocator.allocate_translation_table(ty)?,
+        };
+        match cap.generic_intermediate_translation_table_map(
+            ty,
+            self.vspace,
+            key.1,
+            VmAttributes::default(),
+        ) {
+            Ok(()) => {
+                self.tables.insert(key, Table { cap: Some(cap) });
+            }
+            Err(sel4::Error::DeleteFirst) => {
+                // The table is already present. Keep the new one for later use.
+                self.spare_tables.push((ty, cap));
+                self.tables.insert(key, Table { cap: None });
+            }
+            Err(err) => {
+                self.spare_tables.push((ty, cap));
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    fn mapping_overlap(&self, range: &Range<usize>) -> Option<Range<usize>> {
+        let (start, mapping) = self.mappings.range(..range.end).next_back()?;
+        // Mappings were checked not to overflow when they were made.
+        let end = start + mapping.size();
+        (end > range.start).then_some(*start..end)
+    }
+}
+
+/// The level of the translation table whose entries are frames of type `frame_type`.
+///
+/// Some frame types, such as sections on 32-bit Arm, span several consecutive entries of a table,
+/// and so have no such level.
+fn frame_level(frame_type: FrameObjectType) -> Result<usize, Error> {
+    (vspace_levels::HIGHEST_LEVEL_WITH_PAGE_ENTRIES..vspace_levels::NUM_LEVELS)
+        .find(|level| vspace_levels::step_bits(*level) == frame_type.bits())
+        .ok_or(Error::UnsupportedFrameType(frame_type))
+}
+
+fn first_overlap(ranges: &BTreeMap<usize, usize>, range: &Range<usize>) -> Option<Range<usize>> {
+    let (start, end) = ranges.range(..range.end).next_back()?;
+    (*end > range.start).then_some(*start..*end)
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    Misaligned,
+    AlreadyMapped(Range<usize>),
+    NotMapped(usize),
+    AlreadyReserved(Range<usize>),
+    NotReserved(usize),
+    NoFreeRange,
+    /// Frames of this type do not correspond to single entries of any level of translation table.
+    UnsupportedFrameType(FrameObjectType),
+    /// The end of the frame would be beyond the end of the address space.
+    AddressOverflow,
+    Untyped(sel4_untyped_manager::Error),
+    Sel4(sel4::Error),
+}
+
+impl From<sel4_untyped_manager::Error> for Error {
+    fn from(err: sel4_untyped_manager::Error) -> Self {
+        Self::Untyped(err)
+    }
+}
+
+impl From<sel4::Error> for Error {
+    fn from(err: sel4::Error) -> Self {
+        Self::Sel4(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Misaligned => write!(f, "virtual address is misaligned for frame"),
+            Self::AlreadyMapped(range) => write!(f, "{range:#x?} is already mapped"),
+            Self::NotMapped(vaddr) => write!(f, "no frame is mapped at {vaddr:#x}"),
+            Self::AlreadyReserved(range) => write!(f, "{range:#x?} is already reserved"),
+            Self::NotReserved(vaddr) => write!(f, "no reservation starts at {vaddr:#x}"),
+            Self::NoFreeRange => write!(f, "no free virtual address range"),
+            Self::UnsupportedFrameType(frame_type) => {
+                write!(f, "unsupported frame type {frame_type:?}")
+            }
+            Self::AddressOverflow => write!(f, "frame extends beyond the address space"),
+            Self::Untyped(err) => write!(f, "{err}"),
+            Self::Sel4(err) => write!(f, "seL4 error: {err:?}"),
+        }
+    }
+}
@@ -25,6 +25,7 @@ mk rec {
     state = [];
     exposed-state = [];
     extern-state = [];
+    host-sim = [ "sel4-sys/host-sim" ];
     thread-local-state = [];
     non-thread-local-state = [];
     tls = [];
@@ -4,7 +4,7 @@
 // SPDX-License-Identifier: MIT
 //
 
-use crate::{UnknownSyscall, Word};
+use crate::{UnknownSyscall, UserException, Word};
 
 impl UnknownSyscall {
     pub fn cpsr(&self) -> Word {
@@ -25,3 +25,9 @@ impl UnknownSyscall {
         }
     }
 }
+
+impl UserException {
+    pub fn cpsr(&self) -> Word {
+        self.inner().get_CPSR()
+    }
+}
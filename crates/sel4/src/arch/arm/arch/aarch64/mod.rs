@@ -6,6 +6,7 @@
 
 mod fault;
 mod object;
+mod smccc;
 mod user_context;
 
 sel4_config::sel4_cfg_if! {
@@ -14,8 +15,16 @@ sel4_config::sel4_cfg_if! {
     }
 }
 
+sel4_config::sel4_cfg_if! {
+    if #[sel4_cfg(ALLOW_SMC_CALLS)] {
+        mod smc;
+    }
+}
+
 // HACK for rustfmt
 #[cfg(false)]
+mod smc;
+#[cfg(false)]
 mod vcpu_reg;
 
 pub(crate) mod top_level {
@@ -23,9 +32,13 @@ pub(crate) mod top_level {
         object::{
             ObjectBlueprintAArch64, ObjectBlueprintSeL4Arch, ObjectTypeAArch64, ObjectTypeSeL4Arch,
         },
+        smccc::{PsciVersion, SmcFunctionId, SmcOwner},
         user_context::UserContext,
     };
 
     #[sel4_config::sel4_cfg(ARM_HYPERVISOR_SUPPORT)]
     pub use super::vcpu_reg::VCpuReg;
+
+    #[sel4_config::sel4_cfg(ALLOW_SMC_CALLS)]
+    pub use super::smc::{NUM_SMC_REGISTERS, SmcArgs};
 }
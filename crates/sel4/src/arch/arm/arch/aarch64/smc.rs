@@ -0,0 +1,137 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::{
+    Error, InvocationContext, PsciVersion, Result, SmcFunctionId, Word, cap::ArmSmc,
+    newtype_methods, sys,
+};
+
+/// The number of argument and result registers in an SMC call.
+pub const NUM_SMC_REGISTERS: usize = 8;
+
+/// Corresponds to `seL4_ARM_SMCContext`.
+///
+/// Registers `x0` through `x7`, which hold the arguments to or results of an SMC call.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SmcArgs(sys::seL4_ARM_SMCContext);
+
+impl SmcArgs {
+    newtype_methods!(pub sys::seL4_ARM_SMCContext);
+
+    /// Constructs a register set with `x0` set to `function_id` and `x1..` set to `args`.
+    ///
+    /// At most `NUM_SMC_REGISTERS - 1` arguments may be passed, which is checked at compile time.
+    pub fn new<const N: usize>(function_id: SmcFunctionId, args: &[Word; N]) -> Self {
+        const { assert!(N < NUM_SMC_REGISTERS) };
+        let mut this = Self::default();
+        *this.x_mut(0) = function_id.into_word();
+        for (i, arg) in args.iter().enumerate() {
+            *this.x_mut(i + 1) = *arg;
+        }
+        this
+    }
+
+    pub fn x(&self, ix: usize) -> &Word {
+        match ix {
+            0 => &self.inner().x0,
+            1 => &self.inner().x1,
+            2 => &self.inner().x2,
+            3 => &self.inner().x3,
+            4 => &self.inner().x4,
+            5 => &self.inner().x5,
+            6 => &self.inner().x6,
+            7 => &self.inner().x7,
+            _ => panic!(),
+        }
+    }
+
+    pub fn x_mut(&mut self, ix: usize) -> &mut Word {
+        match ix {
+            0 => &mut self.inner_mut().x0,
+            1 => &mut self.inner_mut().x1,
+            2 => &mut self.inner_mut().x2,
+            3 => &mut self.inner_mut().x3,
+            4 => &mut self.inner_mut().x4,
+            5 => &mut self.inner_mut().x5,
+            6 => &mut self.inner_mut().x6,
+            7 => &mut self.inner_mut().x7,
+            _ => panic!(),
+        }
+    }
+}
+
+impl<C: InvocationContext> ArmSmc<C> {
+    /// Corresponds to `seL4_ARM_SMC_Call`.
+    pub fn smc_call(self, args: &SmcArgs) -> Result<SmcArgs> {
+        let mut response = SmcArgs::default();
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_ARM_SMC_Call(
+                cptr.bits(),
+                args.inner(),
+                response.inner_mut(),
+            )
+        }))?;
+        Ok(response)
+    }
+
+    /// Makes an SMC call with function identifier `function_id` and arguments `args`.
+    pub fn smc_call_with<const N: usize>(
+        self,
+        function_id: SmcFunctionId,
+        args: &[Word; N],
+    ) -> Result<SmcArgs> {
+        self.smc_call(&SmcArgs::new(function_id, args))
+    }
+
+    /// Calls `PSCI_VERSION`.
+    ///
+    /// Returns `None` if the firmware reports that PSCI is not supported.
+    pub fn psci_version(self) -> Result<Option<PsciVersion>> {
+        let ret = *self.smc_call_with(SmcFunctionId::PSCI_VERSION, &[])?.x(0) as u32;
+        Ok(if (ret as i32) < 0 {
+            None
+        } else {
+            Some(PsciVersion {
+                major: (ret >> 16) as u16,
+                minor: ret as u16,
+            })
+        })
+    }
+
+    /// Calls `SYSTEM_RESET`.
+    ///
+    /// Returns only if the call fails.
+    pub fn psci_system_reset(self) -> Result<SmcArgs> {
+        self.smc_call_with(SmcFunctionId::PSCI_SYSTEM_RESET, &[])
+    }
+
+    /// Calls `SYSTEM_OFF`.
+    ///
+    /// Returns only if the call fails.
+    pub fn psci_system_off(self) -> Result<SmcArgs> {
+        self.smc_call_with(SmcFunctionId::PSCI_SYSTEM_OFF, &[])
+    }
+
+    /// Makes a silicon-provider-specific fast call.
+    pub fn sip_call<const N: usize>(
+        self,
+        smc64: bool,
+        function_number: u16,
+        args: &[Word; N],
+    ) -> Result<SmcArgs> {
+        self.smc_call_with(SmcFunctionId::sip(smc64, function_number), args)
+    }
+
+    /// Makes an OEM-specific fast call.
+    pub fn oem_call<const N: usize>(
+        self,
+        smc64: bool,
+        function_number: u16,
+        args: &[Word; N],
+    ) -> Result<SmcArgs> {
+        self.smc_call_with(SmcFunctionId::oem(smc64, function_number), args)
+    }
+}
@@ -0,0 +1,155 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::Word;
+
+/// An SMC Calling Convention function identifier, as passed in `x0`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SmcFunctionId(u32);
+
+impl SmcFunctionId {
+    const FAST_CALL: u32 = 1 << 31;
+    const SMC64: u32 = 1 << 30;
+    const OWNER_SHIFT: u32 = 24;
+    const OWNER_MASK: u32 = 0x3f;
+    const FUNCTION_NUMBER_MASK: u32 = 0xffff;
+
+    /// `SMCCC_VERSION`.
+    pub const SMCCC_VERSION: Self = Self::from_raw(0x8000_0000);
+
+    /// `PSCI_VERSION`.
+    pub const PSCI_VERSION: Self = Self::from_raw(0x8400_0000);
+
+    /// `CPU_SUSPEND`.
+    pub const PSCI_CPU_SUSPEND: Self = Self::from_raw(0x8400_0001);
+
+    /// `CPU_OFF`.
+    pub const PSCI_CPU_OFF: Self = Self::from_raw(0x8400_0002);
+
+    /// `CPU_ON`.
+    pub const PSCI_CPU_ON: Self = Self::from_raw(0x8400_0003);
+
+    /// `AFFINITY_INFO`.
+    pub const PSCI_AFFINITY_INFO: Self = Self::from_raw(0x8400_0004);
+
+    /// `MIGRATE_INFO_TYPE`.
+    pub const PSCI_MIGRATE_INFO_TYPE: Self = Self::from_raw(0x8400_0006);
+
+    /// `SYSTEM_OFF`.
+    pub const PSCI_SYSTEM_OFF: Self = Self::from_raw(0x8400_0008);
+
+    /// `SYSTEM_RESET`.
+    pub const PSCI_SYSTEM_RESET: Self = Self::from_raw(0x8400_0009);
+
+    /// `PSCI_FEATURES`.
+    pub const PSCI_FEATURES: Self = Self::from_raw(0x8400_000a);
+
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    pub const fn into_raw(self) -> u32 {
+        self.0
+    }
+
+    /// Constructs a fast call identifier.
+    pub const fn fast_call(smc64: bool, owner: SmcOwner, function_number: u16) -> Self {
+        Self::from_raw(
+            Self::FAST_CALL
+                | if smc64 { Self::SMC64 } else { 0 }
+                | ((owner.into_raw() as u32 & Self::OWNER_MASK) << Self::OWNER_SHIFT)
+                | function_number as u32,
+        )
+    }
+
+    /// Constructs a fast call identifier for a silicon-provider-specific service.
+    pub const fn sip(smc64: bool, function_number: u16) -> Self {
+        Self::fast_call(smc64, SmcOwner::SiP, function_number)
+    }
+
+    /// Constructs a fast call identifier for an OEM-specific service.
+    pub const fn oem(smc64: bool, function_number: u16) -> Self {
+        Self::fast_call(smc64, SmcOwner::Oem, function_number)
+    }
+
+    pub const fn is_fast_call(self) -> bool {
+        self.0 & Self::FAST_CALL != 0
+    }
+
+    pub const fn is_smc64(self) -> bool {
+        self.0 & Self::SMC64 != 0
+    }
+
+    /// Returns the SMC32 (`smc64 == false`) or SMC64 (`smc64 == true`) variant of this function
+    /// identifier.
+    pub const fn with_smc64(self, smc64: bool) -> Self {
+        Self::from_raw(self.0 & !Self::SMC64 | if smc64 { Self::SMC64 } else { 0 })
+    }
+
+    pub const fn owner(self) -> SmcOwner {
+        SmcOwner::from_raw(((self.0 >> Self::OWNER_SHIFT) & Self::OWNER_MASK) as u8)
+    }
+
+    pub const fn function_number(self) -> u16 {
+        (self.0 & Self::FUNCTION_NUMBER_MASK) as u16
+    }
+
+    pub(crate) fn into_word(self) -> Word {
+        self.0.into()
+    }
+}
+
+/// The owning entity number of an SMC Calling Convention function identifier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SmcOwner {
+    Arch,
+    Cpu,
+    SiP,
+    Oem,
+    StandardSecure,
+    StandardHypervisor,
+    VendorHypervisor,
+    TrustedApplication(u8),
+    TrustedOs(u8),
+    Reserved(u8),
+}
+
+impl SmcOwner {
+    pub const fn from_raw(raw: u8) -> Self {
+        match raw {
+            0 => Self::Arch,
+            1 => Self::Cpu,
+            2 => Self::SiP,
+            3 => Self::Oem,
+            4 => Self::StandardSecure,
+            5 => Self::StandardHypervisor,
+            6 => Self::VendorHypervisor,
+            0x30..=0x31 => Self::TrustedApplication(raw),
+            0x32..=0x3f => Self::TrustedOs(raw),
+            _ => Self::Reserved(raw),
+        }
+    }
+
+    pub const fn into_raw(self) -> u8 {
+        match self {
+            Self::Arch => 0,
+            Self::Cpu => 1,
+            Self::SiP => 2,
+            Self::Oem => 3,
+            Self::StandardSecure => 4,
+            Self::StandardHypervisor => 5,
+            Self::VendorHypervisor => 6,
+            Self::TrustedApplication(raw) | Self::TrustedOs(raw) | Self::Reserved(raw) => raw,
+        }
+    }
+}
+
+/// A PSCI version, as returned by `PSCI_VERSION`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PsciVersion {
+    pub major: u16,
+    pub minor: u16,
+}
@@ -0,0 +1,254 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use core::ops::Range;
+
+use sel4_config::sel4_cfg;
+
+use crate::{
+    Cap, CapTypeForFrameObject, CapTypeForFrameObjectOfFixedSize, Error, FrameObjectType,
+    InvocationContext, Result, Word, cap::*, sys,
+};
+
+/// Cache maintenance operations which can be applied to ranges of memory.
+///
+/// Each variant corresponds to the `seL4_ARM_VSpace_*` and `seL4_ARM_Page_*` invocation of the same
+/// name.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CacheOp {
+    /// Corresponds to `*_Clean_Data`.
+    CleanData,
+    /// Corresponds to `*_Invalidate_Data`.
+    InvalidateData,
+    /// Corresponds to `*_CleanInvalidate_Data`.
+    CleanInvalidateData,
+    /// Corresponds to `*_Unify_Instruction`.
+    UnifyInstruction,
+}
+
+impl<C: InvocationContext> VSpace<C> {
+    /// Applies `op` to `vaddr_range`, which must lie within a single mapped frame.
+    ///
+    /// Use [`VSpace::vspace_cache_op_range`] for ranges which may span multiple frames.
+    pub fn vspace_cache_op(self, op: CacheOp, vaddr_range: Range<usize>) -> Result<()> {
+        check_range(&vaddr_range)?;
+        if vaddr_range.is_empty() {
+            return Ok(());
+        }
+        let start = word(vaddr_range.start);
+        let end = word(vaddr_range.end);
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            let ipc_buffer = ipc_buffer.inner_mut();
+            let cptr = cptr.bits();
+            match op {
+                CacheOp::CleanData => vspace_sys::clean_data(ipc_buffer, cptr, start, end),
+                CacheOp::InvalidateData => {
+                    vspace_sys::invalidate_data(ipc_buffer, cptr, start, end)
+                }
+                CacheOp::CleanInvalidateData => {
+                    vspace_sys::clean_invalidate_data(ipc_buffer, cptr, start, end)
+                }
+                CacheOp::UnifyInstruction => {
+                    vspace_sys::unify_instruction(ipc_buffer, cptr, start, end)
+                }
+            }
+        }))
+    }
+
+    /// Applies `op` to `vaddr_range`, splitting it at granule boundaries so that each invocation
+    /// lies within a single mapped frame, regardless of the sizes of the frames backing the range.
+    pub fn vspace_cache_op_range(self, op: CacheOp, vaddr_range: Range<usize>) -> Result<()>
+    where
+        C: Clone,
+    {
+        check_range(&vaddr_range)?;
+        let granule_size = FrameObjectType::GRANULE.bytes();
+        let mut start = vaddr_range.start;
+        while start < vaddr_range.end {
+            let end = (start - start % granule_size)
+                .checked_add(granule_size)
+                .map_or(vaddr_range.end, |next| next.min(vaddr_range.end));
+            self.clone().vspace_cache_op(op, start..end)?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Corresponds to `seL4_ARM_VSpace_Clean_Data`.
+    pub fn vspace_clean_data(self, vaddr_range: Range<usize>) -> Result<()> {
+        self.vspace_cache_op(CacheOp::CleanData, vaddr_range)
+    }
+
+    /// Corresponds to `seL4_ARM_VSpace_Invalidate_Data`.
+    pub fn vspace_invalidate_data(self, vaddr_range: Range<usize>) -> Result<()> {
+        self.vspace_cache_op(CacheOp::InvalidateData, vaddr_range)
+    }
+
+    /// Corresponds to `seL4_ARM_VSpace_CleanInvalidate_Data`.
+    pub fn vspace_clean_invalidate_data(self, vaddr_range: Range<usize>) -> Result<()> {
+        self.vspace_cache_op(CacheOp::CleanInvalidateData, vaddr_range)
+    }
+
+    /// Corresponds to `seL4_ARM_VSpace_Unify_Instruction`.
+    pub fn vspace_unify_instruction(self, vaddr_range: Range<usize>) -> Result<()> {
+        self.vspace_cache_op(CacheOp::UnifyInstruction, vaddr_range)
+    }
+}
+
+impl<T: CapTypeForFrameObject, C: InvocationContext> Cap<T, C> {
+    /// Applies `op` to `offset_range`, which is relative to the start of the frame.
+    ///
+    /// The range is checked against the size of the frame by the kernel. For frames of known size,
+    /// prefer [`Cap::frame_cache_op_checked`].
+    pub fn frame_cache_op(self, op: CacheOp, offset_range: Range<usize>) -> Result<()> {
+        check_range(&offset_range)?;
+        if offset_range.is_empty() {
+            return Ok(());
+        }
+        let start = word(offset_range.start);
+        let end = word(offset_range.end);
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            let ipc_buffer = ipc_buffer.inner_mut();
+            let cptr = cptr.bits();
+            match op {
+                CacheOp::CleanData => ipc_buffer.seL4_ARM_Page_Clean_Data(cptr, start, end),
+                CacheOp::InvalidateData => {
+                    ipc_buffer.seL4_ARM_Page_Invalidate_Data(cptr, start, end)
+                }
+                CacheOp::CleanInvalidateData => {
+                    ipc_buffer.seL4_ARM_Page_CleanInvalidate_Data(cptr, start, end)
+                }
+                CacheOp::UnifyInstruction => {
+                    ipc_buffer.seL4_ARM_Page_Unify_Instruction(cptr, start, end)
+                }
+            }
+        }))
+    }
+
+    /// Corresponds to `seL4_ARM_Page_Clean_Data`.
+    pub fn frame_clean_data(self, offset_range: Range<usize>) -> Result<()> {
+        self.frame_cache_op(CacheOp::CleanData, offset_range)
+    }
+
+    /// Corresponds to `seL4_ARM_Page_Invalidate_Data`.
+    pub fn frame_invalidate_data(self, offset_range: Range<usize>) -> Result<()> {
+        self.frame_cache_op(CacheOp::InvalidateData, offset_range)
+    }
+
+    /// Corresponds to `seL4_ARM_Page_CleanInvalidate_Data`.
+    pub fn frame_clean_invalidate_data(self, offset_range: Range<usize>) -> Result<()> {
+        self.frame_cache_op(CacheOp::CleanInvalidateData, offset_range)
+    }
+
+    /// Corresponds to `seL4_ARM_Page_Unify_Instruction`.
+    pub fn frame_unify_instruction(self, offset_range: Range<usize>) -> Result<()> {
+        self.frame_cache_op(CacheOp::UnifyInstruction, offset_range)
+    }
+}
+
+impl<T: CapTypeForFrameObjectOfFixedSize, C: InvocationContext> Cap<T, C> {
+    /// Like [`Cap::frame_cache_op`], but returns [`Error::RangeError`] without invoking the kernel
+    /// if `offset_range` does not lie within the frame.
+    pub fn frame_cache_op_checked(self, op: CacheOp, offset_range: Range<usize>) -> Result<()> {
+        if offset_range.end > T::FRAME_OBJECT_TYPE.bytes() {
+            return Err(Error::RangeError);
+        }
+        self.frame_cache_op(op, offset_range)
+    }
+}
+
+fn check_range(range: &Range<usize>) -> Result<()> {
+    if range.start > range.end {
+        return Err(Error::RangeError);
+    }
+    Ok(())
+}
+
+fn word(x: usize) -> Word {
+    x.try_into().unwrap()
+}
+
+#[sel4_cfg(ARCH_AARCH64)]
+mod vspace_sys {
+    use super::sys::{seL4_CPtr, seL4_Error, seL4_IPCBuffer, seL4_Word};
+
+    pub(super) fn clean_data(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_VSpace_Clean_Data(cptr, start, end)
+    }
+
+    pub(super) fn invalidate_data(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_VSpace_Invalidate_Data(cptr, start, end)
+    }
+
+    pub(super) fn clean_invalidate_data(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_VSpace_CleanInvalidate_Data(cptr, start, end)
+    }
+
+    pub(super) fn unify_instruction(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_VSpace_Unify_Instruction(cptr, start, end)
+    }
+}
+
+#[sel4_cfg(ARCH_AARCH32)]
+mod vspace_sys {
+    use super::sys::{seL4_CPtr, seL4_Error, seL4_IPCBuffer, seL4_Word};
+
+    pub(super) fn clean_data(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_PageDirectory_Clean_Data(cptr, start, end)
+    }
+
+    pub(super) fn invalidate_data(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_PageDirectory_Invalidate_Data(cptr, start, end)
+    }
+
+    pub(super) fn clean_invalidate_data(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_PageDirectory_CleanInvalidate_Data(cptr, start, end)
+    }
+
+    pub(super) fn unify_instruction(
+        ipc_buffer: &mut seL4_IPCBuffer,
+        cptr: seL4_CPtr,
+        start: seL4_Word,
+        end: seL4_Word,
+    ) -> seL4_Error::Type {
+        ipc_buffer.seL4_ARM_PageDirectory_Unify_Instruction(cptr, start, end)
+    }
+}
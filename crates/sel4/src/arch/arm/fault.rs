@@ -111,7 +111,21 @@ impl UnknownSyscall {
 }
 
 impl UserException {
-    // TODO
+    pub fn fault_ip(&self) -> Word {
+        self.inner().get_FaultIP()
+    }
+
+    pub fn sp(&self) -> Word {
+        self.inner().get_Stack()
+    }
+
+    pub fn number(&self) -> Word {
+        self.inner().get_Number()
+    }
+
+    pub fn code(&self) -> Word {
+        self.inner().get_Code()
+    }
 }
 
 impl VmFault {
@@ -0,0 +1,117 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use core::fmt;
+
+use sel4_config::sel4_cfg_if;
+
+use crate::{Fault, UserContext, Word};
+
+/// Displays a [`Fault`] along with its decoded cause and the faulting thread's IP, SP, and LR.
+///
+/// Registers which are not carried by the fault message are taken from a [`UserContext`], if one
+/// is provided via [`FaultReport::with_user_context`], and are otherwise omitted.
+///
+/// Obtained via [`Fault::report`].
+#[derive(Debug, Clone)]
+pub struct FaultReport<'a> {
+    fault: &'a Fault,
+    user_context: Option<&'a UserContext>,
+}
+
+impl Fault {
+    pub fn report(&self) -> FaultReport<'_> {
+        FaultReport {
+            fault: self,
+            user_context: None,
+        }
+    }
+}
+
+impl<'a> FaultReport<'a> {
+    /// Use `user_context`, which could be obtained with
+    /// [`Tcb::tcb_read_all_registers`](crate::cap::Tcb::tcb_read_all_registers), for registers
+    /// which are not carried by the fault message.
+    pub fn with_user_context(self, user_context: &'a UserContext) -> Self {
+        Self {
+            user_context: Some(user_context),
+            ..self
+        }
+    }
+
+    fn ip(&self) -> Option<Word> {
+        match self.fault {
+            Fault::UnknownSyscall(fault) => Some(fault.fault_ip()),
+            Fault::UserException(fault) => Some(fault.fault_ip()),
+            Fault::VmFault(fault) => Some(fault.ip()),
+            _ => self.user_context.map(|ctx| *ctx.pc()),
+        }
+    }
+
+    fn sp(&self) -> Option<Word> {
+        match self.fault {
+            Fault::UnknownSyscall(fault) => Some(fault.sp()),
+            Fault::UserException(fault) => Some(fault.sp()),
+            _ => self.user_context.map(|ctx| *ctx.sp()),
+        }
+    }
+
+    fn lr(&self) -> Option<Word> {
+        match self.fault {
+            Fault::UnknownSyscall(fault) => Some(fault.lr()),
+            _ => self.user_context.map(|ctx| *ctx.gpr(LR_GPR_INDEX)),
+        }
+    }
+
+    fn fmt_cause(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fault {
+            Fault::VmFault(fault) => {
+                write!(
+                    f,
+                    "VM fault at address {:#x}: {}",
+                    fault.addr(),
+                    fault.syndrome()
+                )
+            }
+            Fault::UserException(fault) => {
+                sel4_cfg_if! {
+                    if #[sel4_cfg(ARCH_AARCH64)] {
+                        write!(f, "user exception: {}", fault.esr())
+                    } else {
+                        write!(
+                            f,
+                            "user exception: number {:#x}, code {:#x}",
+                            fault.number(),
+                            fault.code()
+                        )
+                    }
+                }
+            }
+            Fault::UnknownSyscall(fault) => write!(f, "unknown syscall {:#x}", fault.syscall()),
+            fault => write!(f, "{fault:x?}"),
+        }
+    }
+}
+
+sel4_cfg_if! {
+    if #[sel4_cfg(ARCH_AARCH64)] {
+        const LR_GPR_INDEX: usize = 30;
+    } else {
+        const LR_GPR_INDEX: usize = 14;
+    }
+}
+
+impl fmt::Display for FaultReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_cause(f)?;
+        for (name, value) in [("ip", self.ip()), ("sp", self.sp()), ("lr", self.lr())] {
+            if let Some(value) = value {
+                write!(f, "\n    {name}: {value:#x}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,421 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+//! Decoders for the syndrome values carried by ARM faults.
+
+use core::fmt;
+
+use sel4_config::{sel4_cfg, sel4_cfg_if};
+
+use crate::{UserException, VmFault, Word};
+
+/// Whether an abort was caused by an instruction fetch or a data access.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AbortKind {
+    Instruction,
+    Data,
+}
+
+/// The decoded fault status field (`DFSC`/`IFSC` or `FS`) of an abort syndrome.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FaultStatus {
+    AddressSize,
+    Translation,
+    AccessFlag,
+    Permission,
+    Domain,
+    Alignment,
+    Debug,
+    SynchronousExternal,
+    SynchronousExternalOnWalk,
+    SynchronousTagCheck,
+    SynchronousParity,
+    SynchronousParityOnWalk,
+    AsynchronousExternal,
+    AsynchronousParity,
+    CacheMaintenance,
+    TlbConflict,
+    UnsupportedAtomicHardwareUpdate,
+    UnsupportedExclusive,
+    ImplementationDefined,
+    Unknown(u8),
+}
+
+impl FaultStatus {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::AddressSize => "address size fault",
+            Self::Translation => "translation fault",
+            Self::AccessFlag => "access flag fault",
+            Self::Permission => "permission fault",
+            Self::Domain => "domain fault",
+            Self::Alignment => "alignment fault",
+            Self::Debug => "debug event",
+            Self::SynchronousExternal => "synchronous external abort",
+            Self::SynchronousExternalOnWalk => {
+                "synchronous external abort on translation table walk"
+            }
+            Self::SynchronousTagCheck => "synchronous tag check fault",
+            Self::SynchronousParity => "synchronous parity or ECC error",
+            Self::SynchronousParityOnWalk => {
+                "synchronous parity or ECC error on translation table walk"
+            }
+            Self::AsynchronousExternal => "asynchronous external abort",
+            Self::AsynchronousParity => "asynchronous parity or ECC error",
+            Self::CacheMaintenance => "cache maintenance fault",
+            Self::TlbConflict => "TLB conflict abort",
+            Self::UnsupportedAtomicHardwareUpdate => "unsupported atomic hardware update fault",
+            Self::UnsupportedExclusive => "unsupported exclusive or atomic access",
+            Self::ImplementationDefined => "implementation defined fault",
+            Self::Unknown(_) => "unknown fault",
+        }
+    }
+}
+
+impl fmt::Display for FaultStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unknown(raw) => write!(f, "{} ({:#x})", self.description(), raw),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+/// A decoded instruction or data abort syndrome.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AbortSyndrome {
+    pub kind: AbortKind,
+    pub status: FaultStatus,
+    /// The translation table level associated with the fault, if applicable.
+    pub level: Option<u8>,
+    /// For data aborts, whether the fault was caused by a write rather than a read.
+    pub write_not_read: bool,
+}
+
+impl AbortSyndrome {
+    pub fn is_alignment_fault(&self) -> bool {
+        self.status == FaultStatus::Alignment
+    }
+
+    // Long-descriptor (AArch64 and AArch32 LPAE) fault status codes.
+    fn decode_long_status(code: u8) -> (FaultStatus, Option<u8>) {
+        let level = Some(code & 0b11);
+        match code {
+            0b00_0000..=0b00_0011 => (FaultStatus::AddressSize, level),
+            0b00_0100..=0b00_0111 => (FaultStatus::Translation, level),
+            0b00_1000..=0b00_1011 => (FaultStatus::AccessFlag, level),
+            0b00_1100..=0b00_1111 => (FaultStatus::Permission, level),
+            0b01_0000 => (FaultStatus::SynchronousExternal, None),
+            0b01_0001 => (FaultStatus::SynchronousTagCheck, None),
+            0b01_0100..=0b01_0111 => (FaultStatus::SynchronousExternalOnWalk, level),
+            0b01_1000 => (FaultStatus::SynchronousParity, None),
+            0b01_1100..=0b01_1111 => (FaultStatus::SynchronousParityOnWalk, level),
+            0b10_0001 => (FaultStatus::Alignment, None),
+            0b10_0010 => (FaultStatus::Debug, None),
+            0b11_0000 => (FaultStatus::TlbConflict, None),
+            0b11_0001 => (FaultStatus::UnsupportedAtomicHardwareUpdate, None),
+            0b11_0100 | 0b11_1010 => (FaultStatus::ImplementationDefined, None),
+            0b11_0101 => (FaultStatus::UnsupportedExclusive, None),
+            _ => (FaultStatus::Unknown(code), None),
+        }
+    }
+}
+
+impl fmt::Display for AbortSyndrome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            AbortKind::Instruction => write!(f, "instruction abort")?,
+            AbortKind::Data => write!(
+                f,
+                "data abort on {}",
+                if self.write_not_read { "write" } else { "read" }
+            )?,
+        }
+        write!(f, ": {}", self.status)?;
+        if let Some(level) = self.level {
+            write!(f, " at level {level}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The exception class field of an `ESR_ELx` value.
+#[sel4_cfg(ARCH_AARCH64)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExceptionClass {
+    Unknown,
+    WfiWfe,
+    FpAsimd,
+    IllegalExecutionState,
+    Svc,
+    Hvc,
+    Smc,
+    MsrMrsSystem,
+    Sve,
+    PointerAuthentication,
+    InstructionAbortLowerEl,
+    InstructionAbortSameEl,
+    PcAlignment,
+    DataAbortLowerEl,
+    DataAbortSameEl,
+    SpAlignment,
+    FloatingPoint,
+    SError,
+    BreakpointLowerEl,
+    BreakpointSameEl,
+    SoftwareStepLowerEl,
+    SoftwareStepSameEl,
+    WatchpointLowerEl,
+    WatchpointSameEl,
+    Brk,
+    Other(u8),
+}
+
+#[sel4_cfg(ARCH_AARCH64)]
+impl ExceptionClass {
+    pub const fn from_raw(raw: u8) -> Self {
+        match raw {
+            0x00 => Self::Unknown,
+            0x01 => Self::WfiWfe,
+            0x07 => Self::FpAsimd,
+            0x0e => Self::IllegalExecutionState,
+            0x11 | 0x15 => Self::Svc,
+            0x12 | 0x16 => Self::Hvc,
+            0x13 | 0x17 => Self::Smc,
+            0x18 => Self::MsrMrsSystem,
+            0x19 => Self::Sve,
+            0x1c => Self::PointerAuthentication,
+            0x20 => Self::InstructionAbortLowerEl,
+            0x21 => Self::InstructionAbortSameEl,
+            0x22 => Self::PcAlignment,
+            0x24 => Self::DataAbortLowerEl,
+            0x25 => Self::DataAbortSameEl,
+            0x26 => Self::SpAlignment,
+            0x28 | 0x2c => Self::FloatingPoint,
+            0x2f => Self::SError,
+            0x30 => Self::BreakpointLowerEl,
+            0x31 => Self::BreakpointSameEl,
+            0x32 => Self::SoftwareStepLowerEl,
+            0x33 => Self::SoftwareStepSameEl,
+            0x34 => Self::WatchpointLowerEl,
+            0x35 => Self::WatchpointSameEl,
+            0x38 | 0x3c => Self::Brk,
+            _ => Self::Other(raw),
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown reason",
+            Self::WfiWfe => "trapped WFI or WFE",
+            Self::FpAsimd => "trapped floating-point or SIMD access",
+            Self::IllegalExecutionState => "illegal execution state",
+            Self::Svc => "SVC",
+            Self::Hvc => "HVC",
+            Self::Smc => "SMC",
+            Self::MsrMrsSystem => "trapped MSR, MRS, or system instruction",
+            Self::Sve => "trapped SVE access",
+            Self::PointerAuthentication => "pointer authentication failure",
+            Self::InstructionAbortLowerEl | Self::InstructionAbortSameEl => "instruction abort",
+            Self::PcAlignment => "PC alignment fault",
+            Self::DataAbortLowerEl | Self::DataAbortSameEl => "data abort",
+            Self::SpAlignment => "SP alignment fault",
+            Self::FloatingPoint => "floating-point exception",
+            Self::SError => "SError interrupt",
+            Self::BreakpointLowerEl | Self::BreakpointSameEl => "breakpoint",
+            Self::SoftwareStepLowerEl | Self::SoftwareStepSameEl => "software step",
+            Self::WatchpointLowerEl | Self::WatchpointSameEl => "watchpoint",
+            Self::Brk => "BRK instruction",
+            Self::Other(_) => "unrecognized exception class",
+        }
+    }
+}
+
+#[sel4_cfg(ARCH_AARCH64)]
+impl fmt::Display for ExceptionClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Other(raw) => write!(f, "{} ({:#x})", self.description(), raw),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+/// A raw `ESR_ELx` value.
+#[sel4_cfg(ARCH_AARCH64)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Esr(Word);
+
+#[sel4_cfg(ARCH_AARCH64)]
+impl Esr {
+    const EC_SHIFT: u32 = 26;
+    const EC_MASK: Word = 0x3f;
+    const IL: Word = 1 << 25;
+    const ISS_MASK: Word = (1 << 25) - 1;
+    const WNR: Word = 1 << 6;
+    const FSC_MASK: Word = 0x3f;
+
+    pub const fn from_raw(raw: Word) -> Self {
+        Self(raw)
+    }
+
+    pub const fn into_raw(self) -> Word {
+        self.0
+    }
+
+    pub const fn exception_class(self) -> ExceptionClass {
+        ExceptionClass::from_raw(((self.0 >> Self::EC_SHIFT) & Self::EC_MASK) as u8)
+    }
+
+    /// Whether the trapped instruction was 32 bits wide rather than 16.
+    pub const fn is_32_bit_instruction(self) -> bool {
+        self.0 & Self::IL != 0
+    }
+
+    /// The instruction specific syndrome.
+    pub const fn iss(self) -> Word {
+        self.0 & Self::ISS_MASK
+    }
+
+    /// Decodes the instruction specific syndrome, if this is an instruction or data abort.
+    pub fn abort(self) -> Option<AbortSyndrome> {
+        let kind = match self.exception_class() {
+            ExceptionClass::InstructionAbortLowerEl | ExceptionClass::InstructionAbortSameEl => {
+                AbortKind::Instruction
+            }
+            ExceptionClass::DataAbortLowerEl | ExceptionClass::DataAbortSameEl => AbortKind::Data,
+            _ => return None,
+        };
+        let (status, level) =
+            AbortSyndrome::decode_long_status((self.iss() & Self::FSC_MASK) as u8);
+        Some(AbortSyndrome {
+            kind,
+            status,
+            level,
+            write_not_read: kind == AbortKind::Data && self.iss() & Self::WNR != 0,
+        })
+    }
+}
+
+#[sel4_cfg(ARCH_AARCH64)]
+impl fmt::Display for Esr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.abort() {
+            Some(abort) => write!(f, "{abort}"),
+            None => write!(f, "{} (ISS {:#x})", self.exception_class(), self.iss()),
+        }
+    }
+}
+
+/// A raw `DFSR` or `IFSR` value.
+#[sel4_cfg(ARCH_AARCH32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fsr(Word);
+
+#[sel4_cfg(ARCH_AARCH32)]
+impl Fsr {
+    const WNR: Word = 1 << 11;
+    const FS_4: Word = 1 << 10;
+    const LPAE: Word = 1 << 9;
+    const FS_LOW_MASK: Word = 0xf;
+    const STATUS_MASK: Word = 0x3f;
+
+    pub const fn from_raw(raw: Word) -> Self {
+        Self(raw)
+    }
+
+    pub const fn into_raw(self) -> Word {
+        self.0
+    }
+
+    /// Whether the value is in the long-descriptor (LPAE) format.
+    pub const fn is_long_descriptor_format(self) -> bool {
+        self.0 & Self::LPAE != 0
+    }
+
+    pub fn abort(self, kind: AbortKind) -> AbortSyndrome {
+        let (status, level) = if self.is_long_descriptor_format() {
+            AbortSyndrome::decode_long_status((self.0 & Self::STATUS_MASK) as u8)
+        } else {
+            let fs = (((self.0 & Self::FS_4) >> 6) | (self.0 & Self::FS_LOW_MASK)) as u8;
+            Self::decode_short_status(fs)
+        };
+        AbortSyndrome {
+            kind,
+            status,
+            level,
+            write_not_read: kind == AbortKind::Data && self.0 & Self::WNR != 0,
+        }
+    }
+
+    // Short-descriptor fault status codes.
+    fn decode_short_status(fs: u8) -> (FaultStatus, Option<u8>) {
+        match fs {
+            0b0_0001 => (FaultStatus::Alignment, None),
+            0b0_0010 => (FaultStatus::Debug, None),
+            0b0_0011 => (FaultStatus::AccessFlag, Some(1)),
+            0b0_0110 => (FaultStatus::AccessFlag, Some(2)),
+            0b0_0100 => (FaultStatus::CacheMaintenance, None),
+            0b0_0101 => (FaultStatus::Translation, Some(1)),
+            0b0_0111 => (FaultStatus::Translation, Some(2)),
+            0b0_1000 => (FaultStatus::SynchronousExternal, None),
+            0b0_1001 => (FaultStatus::Domain, Some(1)),
+            0b0_1011 => (FaultStatus::Domain, Some(2)),
+            0b0_1100 => (FaultStatus::SynchronousExternalOnWalk, Some(1)),
+            0b0_1110 => (FaultStatus::SynchronousExternalOnWalk, Some(2)),
+            0b0_1101 => (FaultStatus::Permission, Some(1)),
+            0b0_1111 => (FaultStatus::Permission, Some(2)),
+            0b1_0000 => (FaultStatus::TlbConflict, None),
+            0b1_0100 | 0b1_1010 => (FaultStatus::ImplementationDefined, None),
+            0b1_0110 => (FaultStatus::AsynchronousExternal, None),
+            0b1_1000 => (FaultStatus::AsynchronousParity, None),
+            0b1_1001 => (FaultStatus::SynchronousParity, None),
+            0b1_1100 => (FaultStatus::SynchronousParityOnWalk, Some(1)),
+            0b1_1110 => (FaultStatus::SynchronousParityOnWalk, Some(2)),
+            _ => (FaultStatus::Unknown(fs), None),
+        }
+    }
+}
+
+impl VmFault {
+    fn abort_kind(&self) -> AbortKind {
+        if self.is_prefetch() {
+            AbortKind::Instruction
+        } else {
+            AbortKind::Data
+        }
+    }
+
+    /// Decodes [`VmFault::fsr`].
+    pub fn syndrome(&self) -> AbortSyndrome {
+        sel4_cfg_if! {
+            if #[sel4_cfg(ARCH_AARCH64)] {
+                self.esr().abort().unwrap_or(AbortSyndrome {
+                    kind: self.abort_kind(),
+                    status: FaultStatus::Unknown(0),
+                    level: None,
+                    write_not_read: false,
+                })
+            } else {
+                Fsr::from_raw(self.fsr()).abort(self.abort_kind())
+            }
+        }
+    }
+
+    /// [`VmFault::fsr`], which holds the full `ESR_EL1` value on AArch64.
+    #[sel4_cfg(ARCH_AARCH64)]
+    pub fn esr(&self) -> Esr {
+        Esr::from_raw(self.fsr())
+    }
+}
+
+impl UserException {
+    /// [`UserException::number`], which holds the full `ESR_EL1` value on AArch64.
+    #[sel4_cfg(ARCH_AARCH64)]
+    pub fn esr(&self) -> Esr {
+        Esr::from_raw(self.number())
+    }
+}
@@ -7,6 +7,9 @@
 use crate::{const_helpers::u32_into_usize, sys};
 
 mod arch;
+mod cache_op;
+mod fault_report;
+mod fault_syndrome;
 mod invocations;
 mod object;
 mod vm_attributes;
@@ -18,10 +21,19 @@ pub(crate) mod top_level {
     pub use super::{
         NUM_FAST_MESSAGE_REGISTERS,
         arch::top_level::*,
+        cache_op::CacheOp,
+        fault_report::FaultReport,
+        fault_syndrome::{AbortKind, AbortSyndrome, FaultStatus},
         object::{ObjectBlueprintArch, ObjectBlueprintArm, ObjectTypeArch, ObjectTypeArm},
         vm_attributes::VmAttributes,
         vspace::{FrameObjectType, TranslationTableObjectType},
     };
+
+    #[sel4_config::sel4_cfg(ARCH_AARCH64)]
+    pub use super::fault_syndrome::{Esr, ExceptionClass};
+
+    #[sel4_config::sel4_cfg(ARCH_AARCH32)]
+    pub use super::fault_syndrome::Fsr;
 }
 
 pub(crate) use vspace::vspace_levels;
@@ -30,7 +42,7 @@ pub(crate) use vspace::vspace_levels;
 pub const NUM_FAST_MESSAGE_REGISTERS: usize = u32_into_usize(sys::seL4_FastMessageRegisters);
 
 pub(crate) mod cap_type_arch {
-    use crate::{declare_cap_type_for_object_of_fixed_size, sel4_cfg};
+    use crate::{declare_cap_type, declare_cap_type_for_object_of_fixed_size, sel4_cfg};
 
     #[sel4_cfg(ARM_HYPERVISOR_SUPPORT)]
     declare_cap_type_for_object_of_fixed_size! {
@@ -72,6 +84,12 @@ pub(crate) mod cap_type_arch {
         PD { ObjectTypeSeL4Arch, ObjectBlueprintSeL4Arch }
     }
 
+    #[sel4_cfg(all(ARCH_AARCH64, ALLOW_SMC_CALLS))]
+    declare_cap_type! {
+        /// Corresponds to `seL4_ARM_SMC`.
+        ArmSmc
+    }
+
     declare_cap_type_for_object_of_fixed_size! {
         /// Corresponds to `seL4_ARM_PageTable`.
         PT { ObjectTypeArch, ObjectBlueprintArch }
@@ -100,5 +118,8 @@ pub(crate) mod cap_arch {
     #[sel4_cfg(ARCH_AARCH32)]
     declare_cap_alias!(PD);
 
+    #[sel4_cfg(all(ARCH_AARCH64, ALLOW_SMC_CALLS))]
+    declare_cap_alias!(ArmSmc);
+
     declare_cap_alias!(PT);
 }
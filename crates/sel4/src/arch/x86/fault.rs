@@ -6,7 +6,10 @@
 
 use sel4_config::{sel4_cfg, sel4_cfg_enum, sel4_cfg_wrap_match};
 
-use crate::{declare_fault_newtype, sys};
+use crate::{Word, declare_fault_newtype, sys};
+
+#[sel4_cfg(VTX)]
+use crate::VCpuFault;
 
 declare_fault_newtype!(NullFault, seL4_Fault_NullFault);
 declare_fault_newtype!(CapFault, seL4_Fault_CapFault);
@@ -27,6 +30,13 @@ pub enum Fault {
     VmFault(VmFault),
     #[sel4_cfg(KERNEL_MCS)]
     Timeout(Timeout),
+    /// A VM exit returned by [`vm_enter`](crate::vm_enter).
+    ///
+    /// The kernel does not send VM exits to fault endpoints, so [`Fault::new`] and
+    /// [`Fault::from_sys`] never produce this variant. It exists so that VM exits can be handled
+    /// alongside other faults, as with VCPU faults on Arm.
+    #[sel4_cfg(VTX)]
+    VCpuFault(VCpuFault),
 }
 
 impl Fault {
@@ -50,3 +60,56 @@ impl Fault {
         }
     }
 }
+
+#[sel4_cfg(VTX)]
+impl From<VCpuFault> for Fault {
+    fn from(fault: VCpuFault) -> Self {
+        Self::VCpuFault(fault)
+    }
+}
+
+impl UnknownSyscall {
+    pub fn fault_ip(&self) -> Word {
+        self.inner().get_FaultIP()
+    }
+
+    pub fn sp(&self) -> Word {
+        self.inner().get_RSP()
+    }
+
+    pub fn syscall(&self) -> Word {
+        self.inner().get_Syscall()
+    }
+}
+
+impl UserException {
+    pub fn fault_ip(&self) -> Word {
+        self.inner().get_FaultIP()
+    }
+
+    pub fn number(&self) -> Word {
+        self.inner().get_Number()
+    }
+
+    pub fn code(&self) -> Word {
+        self.inner().get_Code()
+    }
+}
+
+impl VmFault {
+    pub fn ip(&self) -> Word {
+        self.inner().get_IP()
+    }
+
+    pub fn addr(&self) -> Word {
+        self.inner().get_Addr()
+    }
+
+    pub fn is_prefetch(&self) -> bool {
+        self.inner().get_PrefetchFault() != 0
+    }
+
+    pub fn fsr(&self) -> Word {
+        self.inner().get_FSR()
+    }
+}
@@ -11,6 +11,9 @@ use crate::{
     TranslationTableObjectType, VmAttributes, Word, cap::*, cap_type, sel4_cfg_wrap_match,
 };
 
+#[sel4_cfg(VTX)]
+use crate::{VCpuContext, VmcsField};
+
 #[sel4_cfg(VTX)]
 impl<C: InvocationContext> VCpu<C> {
     /// Corresponds to `seL4_X86_VCPU_SetTCB`.
@@ -21,6 +24,62 @@ impl<C: InvocationContext> VCpu<C> {
                 .seL4_X86_VCPU_SetTCB(cptr.bits(), tcb.bits())
         }))
     }
+
+    /// Corresponds to `seL4_X86_VCPU_ReadVMCS`.
+    pub fn vcpu_read_vmcs(self, field: VmcsField) -> Result<Word> {
+        let res = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_VCPU_ReadVMCS(cptr.bits(), field.into_word())
+        });
+        Error::or(res.error, res.value)
+    }
+
+    /// Corresponds to `seL4_X86_VCPU_WriteVMCS`.
+    ///
+    /// Returns the value actually written, which may differ from `value` where the kernel forces
+    /// some bits of a control field to fixed values.
+    pub fn vcpu_write_vmcs(self, field: VmcsField, value: Word) -> Result<Word> {
+        let res = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_VCPU_WriteVMCS(cptr.bits(), field.into_word(), value)
+        });
+        Error::or(res.error, res.written)
+    }
+
+    /// Corresponds to `seL4_X86_VCPU_EnableIOPort`.
+    ///
+    /// Allows the guest direct access to the ports from `low` to `high` inclusive, all of which
+    /// must be covered by `io_port`.
+    pub fn vcpu_enable_io_port(self, io_port: IOPort, low: Word, high: Word) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_X86_VCPU_EnableIOPort(
+                cptr.bits(),
+                io_port.bits(),
+                low,
+                high,
+            )
+        }))
+    }
+
+    /// Corresponds to `seL4_X86_VCPU_DisableIOPort`.
+    pub fn vcpu_disable_io_port(self, low: Word, high: Word) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_VCPU_DisableIOPort(cptr.bits(), low, high)
+        }))
+    }
+
+    /// Corresponds to `seL4_X86_VCPU_WriteRegisters`.
+    pub fn vcpu_write_registers(self, regs: &VCpuContext) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_VCPU_WriteRegisters(cptr.bits(), regs.inner())
+        }))
+    }
 }
 
 impl<T: CapTypeForFrameObject, C: InvocationContext> Cap<T, C> {
@@ -310,6 +369,65 @@ impl<C: InvocationContext> IOPortControl<C> {
     }
 }
 
+impl<C: InvocationContext> IOPort<C> {
+    /// Corresponds to `seL4_X86_IOPort_In8`.
+    pub fn ioport_in8(self, port: u16) -> Result<u8> {
+        let ret = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_IOPort_In8(cptr.bits(), port)
+        });
+        Error::or(ret.error, ret.result)
+    }
+
+    /// Corresponds to `seL4_X86_IOPort_In16`.
+    pub fn ioport_in16(self, port: u16) -> Result<u16> {
+        let ret = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_IOPort_In16(cptr.bits(), port)
+        });
+        Error::or(ret.error, ret.result)
+    }
+
+    /// Corresponds to `seL4_X86_IOPort_In32`.
+    pub fn ioport_in32(self, port: u16) -> Result<u32> {
+        let ret = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_IOPort_In32(cptr.bits(), port)
+        });
+        Error::or(ret.error, ret.result)
+    }
+
+    /// Corresponds to `seL4_X86_IOPort_Out8`.
+    pub fn ioport_out8(self, port: u16, data: u8) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_IOPort_Out8(cptr.bits(), port.into(), data.into())
+        }))
+    }
+
+    /// Corresponds to `seL4_X86_IOPort_Out16`.
+    pub fn ioport_out16(self, port: u16, data: u16) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_IOPort_Out16(cptr.bits(), port.into(), data.into())
+        }))
+    }
+
+    /// Corresponds to `seL4_X86_IOPort_Out32`.
+    pub fn ioport_out32(self, port: u16, data: u32) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_X86_IOPort_Out32(cptr.bits(), port.into(), data.into())
+        }))
+    }
+}
+
 impl<C: InvocationContext> AsidControl<C> {
     /// Corresponds to `seL4_X86_ASIDControl_MakePool`.
     pub fn asid_control_make_pool(self, untyped: Untyped, dst: &AbsoluteCPtr) -> Result<()> {
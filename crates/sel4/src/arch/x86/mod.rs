@@ -12,6 +12,11 @@ mod object;
 mod vm_attributes;
 mod vspace;
 
+#[sel4_config::sel4_cfg(VTX)]
+mod vcpu;
+#[sel4_config::sel4_cfg(VTX)]
+mod vmcs;
+
 pub(crate) mod fault;
 
 pub(crate) mod top_level {
@@ -22,6 +27,12 @@ pub(crate) mod top_level {
         vm_attributes::VmAttributes,
         vspace::{FrameObjectType, TranslationTableObjectType},
     };
+
+    #[sel4_config::sel4_cfg(VTX)]
+    pub use super::{
+        vcpu::{VCpuContext, VCpuFault, VmEnterResult, VmEntry, vm_enter},
+        vmcs::{VmcsField, VmcsFieldWidth},
+    };
 }
 
 pub(crate) use vspace::vspace_levels;
@@ -94,6 +105,7 @@ pub(crate) mod cap_type_arch {
     pub type Granule = _4k;
 
     declare_cap_type!(IOPortControl);
+    declare_cap_type!(IOPort);
 }
 
 pub(crate) mod cap_arch {
@@ -119,4 +131,5 @@ pub(crate) mod cap_arch {
     declare_cap_alias!(PageTable);
 
     declare_cap_alias!(IOPortControl);
+    declare_cap_alias!(IOPort);
 }
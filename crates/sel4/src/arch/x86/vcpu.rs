@@ -0,0 +1,191 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::{Badge, IpcBuffer, Word, newtype_methods, sys};
+
+/// Corresponds to `seL4_VCPUContext`.
+///
+/// The guest general-purpose registers which are not held in the VMCS.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VCpuContext(sys::seL4_VCPUContext);
+
+macro_rules! register_accessors {
+    ($($reg:ident, $reg_mut:ident;)*) => {
+        $(
+            pub fn $reg(&self) -> &Word {
+                &self.0.$reg
+            }
+
+            pub fn $reg_mut(&mut self) -> &mut Word {
+                &mut self.0.$reg
+            }
+        )*
+    };
+}
+
+impl VCpuContext {
+    newtype_methods!(pub sys::seL4_VCPUContext);
+
+    register_accessors! {
+        eax, eax_mut;
+        ebx, ebx_mut;
+        ecx, ecx_mut;
+        edx, edx_mut;
+        esi, esi_mut;
+        edi, edi_mut;
+        ebp, ebp_mut;
+    }
+}
+
+/// Guest state passed to and returned from [`vm_enter`].
+///
+/// These fields are cached by the kernel outside of the VMCS, so they are exchanged with every
+/// entry rather than through [`VCpu::vcpu_write_vmcs`](crate::cap::VCpu::vcpu_write_vmcs).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct VmEntry {
+    pub rip: Word,
+    /// Corresponds to `PRIMARY_PROCESSOR_BASED_VM_EXEC_CONTROL`.
+    pub control_ppc: Word,
+    /// Corresponds to `VM_ENTRY_INTERRUPTION_INFO`.
+    pub control_entry: Word,
+}
+
+/// The outcome of [`vm_enter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmEnterResult {
+    /// The guest was preempted by a signal on the notification bound to the calling thread.
+    Notification(Badge),
+    /// The guest caused a VM exit which the kernel did not handle itself.
+    Fault(VCpuFault),
+}
+
+/// A VM exit, as delivered by [`vm_enter`].
+///
+/// Unlike other architectures, where VCPU faults are sent to a fault endpoint, VM exits on x86 are
+/// returned directly to the thread which entered the guest. This type converts into
+/// [`Fault::VCpuFault`](crate::Fault::VCpuFault) for handling alongside other faults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VCpuFault {
+    reason: Word,
+    qualification: Word,
+    instruction_len: Word,
+    guest_physical: Word,
+    rflags: Word,
+    guest_int: Word,
+    cr3: Word,
+    context: VCpuContext,
+}
+
+impl VCpuFault {
+    const REASON: usize = 3;
+    const QUALIFICATION: usize = 4;
+    const INSTRUCTION_LEN: usize = 5;
+    const GUEST_PHYSICAL: usize = 6;
+    const RFLAGS: usize = 7;
+    const GUEST_INT: usize = 8;
+    const CR3: usize = 9;
+    const EAX: usize = 10;
+    const EBX: usize = 11;
+    const ECX: usize = 12;
+    const EDX: usize = 13;
+    const ESI: usize = 14;
+    const EDI: usize = 15;
+    const EBP: usize = 16;
+
+    /// Decodes the VM exit which [`vm_enter`] left in the message registers of `ipc_buffer`.
+    pub fn new(ipc_buffer: &IpcBuffer) -> Self {
+        Self::from_msg_regs(ipc_buffer.msg_regs())
+    }
+
+    fn from_msg_regs(mrs: &[Word]) -> Self {
+        Self {
+            reason: mrs[Self::REASON],
+            qualification: mrs[Self::QUALIFICATION],
+            instruction_len: mrs[Self::INSTRUCTION_LEN],
+            guest_physical: mrs[Self::GUEST_PHYSICAL],
+            rflags: mrs[Self::RFLAGS],
+            guest_int: mrs[Self::GUEST_INT],
+            cr3: mrs[Self::CR3],
+            context: VCpuContext::from_inner(sys::seL4_VCPUContext {
+                eax: mrs[Self::EAX],
+                ebx: mrs[Self::EBX],
+                ecx: mrs[Self::ECX],
+                edx: mrs[Self::EDX],
+                esi: mrs[Self::ESI],
+                edi: mrs[Self::EDI],
+                ebp: mrs[Self::EBP],
+            }),
+        }
+    }
+
+    /// The basic exit reason, from bits 15:0 of `VM_EXIT_REASON`.
+    pub fn reason(&self) -> Word {
+        self.reason & 0xffff
+    }
+
+    pub fn raw_reason(&self) -> Word {
+        self.reason
+    }
+
+    pub fn qualification(&self) -> Word {
+        self.qualification
+    }
+
+    pub fn instruction_len(&self) -> Word {
+        self.instruction_len
+    }
+
+    pub fn guest_physical(&self) -> Word {
+        self.guest_physical
+    }
+
+    pub fn rflags(&self) -> Word {
+        self.rflags
+    }
+
+    /// Corresponds to `GUEST_INTERRUPTIBILITY_STATE`.
+    pub fn guest_int(&self) -> Word {
+        self.guest_int
+    }
+
+    pub fn cr3(&self) -> Word {
+        self.cr3
+    }
+
+    /// The guest registers at the time of the exit. These are not written back on the next entry
+    /// unless passed to [`VCpu::vcpu_write_registers`](crate::cap::VCpu::vcpu_write_registers).
+    pub fn context(&self) -> &VCpuContext {
+        &self.context
+    }
+}
+
+const CALL_EIP: usize = 0;
+const CALL_CONTROL_PPC: usize = 1;
+const CALL_CONTROL_ENTRY: usize = 2;
+
+const RESULT_NOTIF: Word = 0;
+
+/// Corresponds to `seL4_VMEnter`.
+///
+/// Runs the guest of the VCPU bound to the calling thread, starting from `entry`. On return,
+/// `entry` holds the guest state at the point at which it stopped, such that passing it back
+/// unmodified resumes the guest where it left off.
+pub fn vm_enter(ipc_buffer: &mut IpcBuffer, entry: &mut VmEntry) -> VmEnterResult {
+    let mrs = ipc_buffer.msg_regs_mut();
+    mrs[CALL_EIP] = entry.rip;
+    mrs[CALL_CONTROL_PPC] = entry.control_ppc;
+    mrs[CALL_CONTROL_ENTRY] = entry.control_entry;
+    let (result, badge) = ipc_buffer.inner_mut().seL4_VMEnter();
+    let mrs = ipc_buffer.msg_regs();
+    entry.rip = mrs[CALL_EIP];
+    entry.control_ppc = mrs[CALL_CONTROL_PPC];
+    entry.control_entry = mrs[CALL_CONTROL_ENTRY];
+    if result == RESULT_NOTIF {
+        VmEnterResult::Notification(badge)
+    } else {
+        VmEnterResult::Fault(VCpuFault::from_msg_regs(mrs))
+    }
+}
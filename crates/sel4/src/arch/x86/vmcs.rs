@@ -0,0 +1,175 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::Word;
+
+/// A VMCS field encoding, for use with [`VCpu::vcpu_read_vmcs`](crate::cap::VCpu::vcpu_read_vmcs)
+/// and [`VCpu::vcpu_write_vmcs`](crate::cap::VCpu::vcpu_write_vmcs).
+///
+/// Host-state fields, and control fields which the kernel manages itself (such as the I/O bitmap
+/// and EPT pointer), are omitted, as the kernel does not permit access to them.
+#[repr(u32)]
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VmcsField {
+    // 16-bit guest-state fields
+    GUEST_ES_SELECTOR = 0x0800,
+    GUEST_CS_SELECTOR = 0x0802,
+    GUEST_SS_SELECTOR = 0x0804,
+    GUEST_DS_SELECTOR = 0x0806,
+    GUEST_FS_SELECTOR = 0x0808,
+    GUEST_GS_SELECTOR = 0x080a,
+    GUEST_LDTR_SELECTOR = 0x080c,
+    GUEST_TR_SELECTOR = 0x080e,
+
+    // 64-bit control fields
+    TSC_OFFSET = 0x2010,
+
+    // 64-bit read-only data fields
+    GUEST_PHYSICAL_ADDRESS = 0x2400,
+
+    // 64-bit guest-state fields
+    VMCS_LINK_POINTER = 0x2800,
+    GUEST_IA32_DEBUGCTL = 0x2802,
+    GUEST_IA32_PAT = 0x2804,
+    GUEST_IA32_EFER = 0x2806,
+    GUEST_IA32_PERF_GLOBAL_CTRL = 0x2808,
+    GUEST_PDPTE0 = 0x280a,
+    GUEST_PDPTE1 = 0x280c,
+    GUEST_PDPTE2 = 0x280e,
+    GUEST_PDPTE3 = 0x2810,
+
+    // 32-bit control fields
+    PIN_BASED_VM_EXEC_CONTROL = 0x4000,
+    CPU_BASED_VM_EXEC_CONTROL = 0x4002,
+    EXCEPTION_BITMAP = 0x4004,
+    PAGE_FAULT_ERROR_CODE_MASK = 0x4006,
+    PAGE_FAULT_ERROR_CODE_MATCH = 0x4008,
+    CR3_TARGET_COUNT = 0x400a,
+    VM_EXIT_CONTROLS = 0x400c,
+    VM_EXIT_MSR_STORE_COUNT = 0x400e,
+    VM_EXIT_MSR_LOAD_COUNT = 0x4010,
+    VM_ENTRY_CONTROLS = 0x4012,
+    VM_ENTRY_MSR_LOAD_COUNT = 0x4014,
+    VM_ENTRY_INTERRUPTION_INFO = 0x4016,
+    VM_ENTRY_EXCEPTION_ERROR_CODE = 0x4018,
+    VM_ENTRY_INSTRUCTION_LENGTH = 0x401a,
+    TPR_THRESHOLD = 0x401c,
+    SECONDARY_VM_EXEC_CONTROL = 0x401e,
+
+    // 32-bit read-only data fields
+    VM_INSTRUCTION_ERROR = 0x4400,
+    VM_EXIT_REASON = 0x4402,
+    VM_EXIT_INTERRUPTION_INFO = 0x4404,
+    VM_EXIT_INTERRUPTION_ERROR_CODE = 0x4406,
+    IDT_VECTORING_INFO = 0x4408,
+    IDT_VECTORING_ERROR_CODE = 0x440a,
+    VM_EXIT_INSTRUCTION_LENGTH = 0x440c,
+    VM_EXIT_INSTRUCTION_INFO = 0x440e,
+
+    // 32-bit guest-state fields
+    GUEST_ES_LIMIT = 0x4800,
+    GUEST_CS_LIMIT = 0x4802,
+    GUEST_SS_LIMIT = 0x4804,
+    GUEST_DS_LIMIT = 0x4806,
+    GUEST_FS_LIMIT = 0x4808,
+    GUEST_GS_LIMIT = 0x480a,
+    GUEST_LDTR_LIMIT = 0x480c,
+    GUEST_TR_LIMIT = 0x480e,
+    GUEST_GDTR_LIMIT = 0x4810,
+    GUEST_IDTR_LIMIT = 0x4812,
+    GUEST_ES_ACCESS_RIGHTS = 0x4814,
+    GUEST_CS_ACCESS_RIGHTS = 0x4816,
+    GUEST_SS_ACCESS_RIGHTS = 0x4818,
+    GUEST_DS_ACCESS_RIGHTS = 0x481a,
+    GUEST_FS_ACCESS_RIGHTS = 0x481c,
+    GUEST_GS_ACCESS_RIGHTS = 0x481e,
+    GUEST_LDTR_ACCESS_RIGHTS = 0x4820,
+    GUEST_TR_ACCESS_RIGHTS = 0x4822,
+    GUEST_INTERRUPTIBILITY_STATE = 0x4824,
+    GUEST_ACTIVITY_STATE = 0x4826,
+    GUEST_SMBASE = 0x4828,
+    GUEST_IA32_SYSENTER_CS = 0x482a,
+    VMX_PREEMPTION_TIMER_VALUE = 0x482e,
+
+    // Natural-width control fields
+    CR0_GUEST_HOST_MASK = 0x6000,
+    CR4_GUEST_HOST_MASK = 0x6002,
+    CR0_READ_SHADOW = 0x6004,
+    CR4_READ_SHADOW = 0x6006,
+    CR3_TARGET_VALUE0 = 0x6008,
+    CR3_TARGET_VALUE1 = 0x600a,
+    CR3_TARGET_VALUE2 = 0x600c,
+    CR3_TARGET_VALUE3 = 0x600e,
+
+    // Natural-width read-only data fields
+    EXIT_QUALIFICATION = 0x6400,
+    IO_RCX = 0x6402,
+    IO_RSI = 0x6404,
+    IO_RDI = 0x6406,
+    IO_RIP = 0x6408,
+    GUEST_LINEAR_ADDRESS = 0x640a,
+
+    // Natural-width guest-state fields
+    GUEST_CR0 = 0x6800,
+    GUEST_CR3 = 0x6802,
+    GUEST_CR4 = 0x6804,
+    GUEST_ES_BASE = 0x6806,
+    GUEST_CS_BASE = 0x6808,
+    GUEST_SS_BASE = 0x680a,
+    GUEST_DS_BASE = 0x680c,
+    GUEST_FS_BASE = 0x680e,
+    GUEST_GS_BASE = 0x6810,
+    GUEST_LDTR_BASE = 0x6812,
+    GUEST_TR_BASE = 0x6814,
+    GUEST_GDTR_BASE = 0x6816,
+    GUEST_IDTR_BASE = 0x6818,
+    GUEST_DR7 = 0x681a,
+    GUEST_RSP = 0x681c,
+    GUEST_RIP = 0x681e,
+    GUEST_RFLAGS = 0x6820,
+    GUEST_PENDING_DEBUG_EXCEPTIONS = 0x6822,
+    GUEST_IA32_SYSENTER_ESP = 0x6824,
+    GUEST_IA32_SYSENTER_EIP = 0x6826,
+}
+
+/// The width of a [`VmcsField`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VmcsFieldWidth {
+    Bits16,
+    Bits64,
+    Bits32,
+    Natural,
+}
+
+impl VmcsField {
+    const WIDTH_SHIFT: u32 = 13;
+    const TYPE_SHIFT: u32 = 10;
+    const TYPE_READ_ONLY_DATA: u32 = 1;
+
+    pub const fn encoding(self) -> u32 {
+        self as u32
+    }
+
+    pub const fn into_word(self) -> Word {
+        self.encoding() as Word
+    }
+
+    pub const fn width(self) -> VmcsFieldWidth {
+        match (self.encoding() >> Self::WIDTH_SHIFT) & 0b11 {
+            0 => VmcsFieldWidth::Bits16,
+            1 => VmcsFieldWidth::Bits64,
+            2 => VmcsFieldWidth::Bits32,
+            _ => VmcsFieldWidth::Natural,
+        }
+    }
+
+    /// Whether the field holds information about the most recent VM exit, and so cannot be
+    /// written.
+    pub const fn is_read_only(self) -> bool {
+        (self.encoding() >> Self::TYPE_SHIFT) & 0b11 == Self::TYPE_READ_ONLY_DATA
+    }
+}
@@ -13,7 +13,16 @@ use core::slice;
 
 use sel4_config::sel4_cfg;
 
-use crate::{FrameObjectType, IpcBuffer, cap_type, init_thread::SlotRegion, newtype_methods, sys};
+use crate::{
+    FrameObjectType, IpcBuffer,
+    bootinfo_extra::{
+        BootInfoExtraContent, BootInfoExtraTruncatedError, X86AcpiRsdp, X86Framebuffer,
+        X86MemoryMap, X86TscFreq, X86Vbe,
+    },
+    cap_type,
+    init_thread::SlotRegion,
+    newtype_methods, sys,
+};
 
 /// A wrapped pointer to a [`BootInfo`] block.
 ///
@@ -152,21 +161,49 @@ pub struct BootInfoExtra<'a> {
     pub content_with_header: &'a [u8],
 }
 
-impl BootInfoExtra<'_> {
-    pub fn content_with_header(&self) -> &[u8] {
+impl<'a> BootInfoExtra<'a> {
+    pub fn content_with_header(&self) -> &'a [u8] {
         self.content_with_header
     }
 
-    pub fn content(&self) -> &[u8] {
+    pub fn content(&self) -> &'a [u8] {
         let content_with_header = self.content_with_header();
         &content_with_header[mem::size_of::<sys::seL4_BootInfoHeader>()..]
     }
+
+    /// Interprets [`content`](BootInfoExtra::content) according to [`id`](BootInfoExtra::id).
+    pub fn content_typed(&self) -> Result<BootInfoExtraContent<'a>, BootInfoExtraTruncatedError> {
+        let content = self.content();
+        Ok(match self.id {
+            BootInfoExtraId::Padding => BootInfoExtraContent::Padding,
+            BootInfoExtraId::X86Vbe => BootInfoExtraContent::X86Vbe(X86Vbe::new(content)?),
+            BootInfoExtraId::X86MbMmap => {
+                BootInfoExtraContent::X86MemoryMap(X86MemoryMap::new(content)?)
+            }
+            BootInfoExtraId::X86AcpiRsdp => {
+                BootInfoExtraContent::X86AcpiRsdp(X86AcpiRsdp::new(content)?)
+            }
+            BootInfoExtraId::X86Framebuffer => {
+                BootInfoExtraContent::X86Framebuffer(X86Framebuffer::new(content)?)
+            }
+            BootInfoExtraId::X86TscFreq => {
+                BootInfoExtraContent::X86TscFreq(X86TscFreq::new(content)?)
+            }
+            BootInfoExtraId::Fdt => BootInfoExtraContent::Fdt(content),
+        })
+    }
 }
 
 /// Corresponds to `seL4_BootInfoID`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum BootInfoExtraId {
     Padding,
+    X86Vbe,
+    X86MbMmap,
+    X86AcpiRsdp,
+    X86Framebuffer,
+    X86TscFreq,
     Fdt,
 }
 
@@ -174,10 +211,35 @@ impl BootInfoExtraId {
     pub fn from_sys(id: sys::seL4_BootInfoID::Type) -> Option<Self> {
         match id {
             sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_PADDING => Some(BootInfoExtraId::Padding),
+            sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_VBE => Some(BootInfoExtraId::X86Vbe),
+            sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_MBMMAP => {
+                Some(BootInfoExtraId::X86MbMmap)
+            }
+            sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_ACPI_RSDP => {
+                Some(BootInfoExtraId::X86AcpiRsdp)
+            }
+            sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_FRAMEBUFFER => {
+                Some(BootInfoExtraId::X86Framebuffer)
+            }
+            sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_TSC_FREQ => {
+                Some(BootInfoExtraId::X86TscFreq)
+            }
             sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_FDT => Some(BootInfoExtraId::Fdt),
             _ => None,
         }
     }
+
+    pub fn into_sys(self) -> sys::seL4_BootInfoID::Type {
+        match self {
+            Self::Padding => sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_PADDING,
+            Self::X86Vbe => sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_VBE,
+            Self::X86MbMmap => sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_MBMMAP,
+            Self::X86AcpiRsdp => sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_ACPI_RSDP,
+            Self::X86Framebuffer => sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_FRAMEBUFFER,
+            Self::X86TscFreq => sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_X86_TSC_FREQ,
+            Self::Fdt => sys::seL4_BootInfoID::SEL4_BOOTINFO_HEADER_FDT,
+        }
+    }
 }
 
 /// An iterator for accessing the [`BootInfoExtra`] entires associated with a [`BootInfoPtr`].
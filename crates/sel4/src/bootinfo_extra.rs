@@ -0,0 +1,403 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+//! Zero-copy views of the contents of extra bootinfo chunks.
+//!
+//! The layouts of these chunks are defined by the kernel in `libsel4`'s `bootinfo_types.h` headers.
+//! All multi-byte fields are in native byte order, and all structures are packed.
+
+use core::fmt;
+use core::ops::Range;
+
+/// Typed contents of a [`BootInfoExtra`](crate::BootInfoExtra).
+///
+/// See [`BootInfoExtra::content_typed`](crate::BootInfoExtra::content_typed).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BootInfoExtraContent<'a> {
+    Padding,
+    X86Vbe(X86Vbe<'a>),
+    X86MemoryMap(X86MemoryMap<'a>),
+    X86AcpiRsdp(X86AcpiRsdp<'a>),
+    X86Framebuffer(X86Framebuffer<'a>),
+    X86TscFreq(X86TscFreq<'a>),
+    Fdt(&'a [u8]),
+}
+
+/// Error returned when the contents of an extra bootinfo chunk are too short for its type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BootInfoExtraTruncatedError {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for BootInfoExtraTruncatedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "bootinfo extra truncated: expected at least {} bytes, found {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+fn check_len(content: &[u8], expected: usize) -> Result<(), BootInfoExtraTruncatedError> {
+    if content.len() < expected {
+        return Err(BootInfoExtraTruncatedError {
+            expected,
+            actual: content.len(),
+        });
+    }
+    Ok(())
+}
+
+fn read_u8(content: &[u8], offset: usize) -> u8 {
+    content[offset]
+}
+
+fn read_u16(content: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes(content[offset..][..2].try_into().unwrap())
+}
+
+fn read_u32(content: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(content[offset..][..4].try_into().unwrap())
+}
+
+fn read_u64(content: &[u8], offset: usize) -> u64 {
+    u64::from_ne_bytes(content[offset..][..8].try_into().unwrap())
+}
+
+/// View of a `SEL4_BOOTINFO_HEADER_X86_VBE` chunk (`seL4_X86_BootInfo_VBE`, without its header).
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct X86Vbe<'a> {
+    content: &'a [u8],
+}
+
+impl<'a> X86Vbe<'a> {
+    const INFO_BLOCK: Range<usize> = 0..512;
+    const MODE_INFO_BLOCK: Range<usize> = 512..768;
+    const MODE: usize = 768;
+    const INTERFACE_SEG: usize = 772;
+    const INTERFACE_OFF: usize = 776;
+    const INTERFACE_LEN: usize = 780;
+    const SIZE: usize = 784;
+
+    pub fn new(content: &'a [u8]) -> Result<Self, BootInfoExtraTruncatedError> {
+        check_len(content, Self::SIZE)?;
+        Ok(Self { content })
+    }
+
+    /// The raw `seL4_VBEInfoBlock_t`.
+    pub fn info_block(&self) -> &'a [u8] {
+        &self.content[Self::INFO_BLOCK]
+    }
+
+    /// The raw `seL4_VBEModeInfoBlock_t`.
+    pub fn mode_info_block(&self) -> &'a [u8] {
+        &self.content[Self::MODE_INFO_BLOCK]
+    }
+
+    /// The `BytesPerScanLine` field of the mode info block.
+    pub fn bytes_per_scan_line(&self) -> u16 {
+        read_u16(self.mode_info_block(), 16)
+    }
+
+    /// The `XResolution` field of the mode info block.
+    pub fn x_resolution(&self) -> u16 {
+        read_u16(self.mode_info_block(), 18)
+    }
+
+    /// The `YResolution` field of the mode info block.
+    pub fn y_resolution(&self) -> u16 {
+        read_u16(self.mode_info_block(), 20)
+    }
+
+    /// The `BitsPerPixel` field of the mode info block.
+    pub fn bits_per_pixel(&self) -> u8 {
+        read_u8(self.mode_info_block(), 25)
+    }
+
+    /// The `PhysBasePtr` field of the mode info block.
+    pub fn phys_base_ptr(&self) -> u32 {
+        read_u32(self.mode_info_block(), 40)
+    }
+
+    pub fn mode(&self) -> u32 {
+        read_u32(self.content, Self::MODE)
+    }
+
+    pub fn interface_seg(&self) -> u32 {
+        read_u32(self.content, Self::INTERFACE_SEG)
+    }
+
+    pub fn interface_off(&self) -> u32 {
+        read_u32(self.content, Self::INTERFACE_OFF)
+    }
+
+    pub fn interface_len(&self) -> u32 {
+        read_u32(self.content, Self::INTERFACE_LEN)
+    }
+}
+
+impl fmt::Debug for X86Vbe<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("X86Vbe")
+            .field("mode", &self.mode())
+            .field("x_resolution", &self.x_resolution())
+            .field("y_resolution", &self.y_resolution())
+            .field("bits_per_pixel", &self.bits_per_pixel())
+            .field("phys_base_ptr", &self.phys_base_ptr())
+            .finish()
+    }
+}
+
+/// View of a `SEL4_BOOTINFO_HEADER_X86_MBMMAP` chunk (`seL4_X86_BootInfo_mmap_t`, without its
+/// header).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct X86MemoryMap<'a> {
+    entries: &'a [u8],
+}
+
+impl<'a> X86MemoryMap<'a> {
+    const MMAP_LENGTH: usize = 0;
+    const ENTRIES: usize = 4;
+
+    /// The `mmap_length` field holds the size of the valid portion of the `mmap` array in bytes.
+    pub fn new(content: &'a [u8]) -> Result<Self, BootInfoExtraTruncatedError> {
+        check_len(content, Self::ENTRIES)?;
+        let mmap_length = usize::try_from(read_u32(content, Self::MMAP_LENGTH)).unwrap();
+        let entries_len = mmap_length - mmap_length % X86MemoryMapEntry::SIZE;
+        check_len(content, Self::ENTRIES + entries_len)?;
+        Ok(Self {
+            entries: &content[Self::ENTRIES..][..entries_len],
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() / X86MemoryMapEntry::SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<X86MemoryMapEntry> {
+        (i < self.len())
+            .then(|| X86MemoryMapEntry::from_bytes(&self.entries[i * X86MemoryMapEntry::SIZE..]))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = X86MemoryMapEntry> + 'a {
+        self.entries
+            .chunks_exact(X86MemoryMapEntry::SIZE)
+            .map(X86MemoryMapEntry::from_bytes)
+    }
+}
+
+/// Corresponds to `seL4_X86_mb_mmap_t`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct X86MemoryMapEntry {
+    pub base_addr: u64,
+    pub length: u64,
+    pub ty: X86MemoryMapEntryType,
+}
+
+impl X86MemoryMapEntry {
+    const SIZE: usize = 24;
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            base_addr: read_u64(bytes, 4),
+            length: read_u64(bytes, 12),
+            ty: X86MemoryMapEntryType::from_raw(read_u32(bytes, 20)),
+        }
+    }
+
+    /// The end of the range saturates rather than overflowing for a malformed entry.
+    pub fn range(&self) -> Range<u64> {
+        self.base_addr..self.base_addr.saturating_add(self.length)
+    }
+}
+
+/// The type of a multiboot memory map entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum X86MemoryMapEntryType {
+    Available,
+    Reserved,
+    AcpiReclaimable,
+    AcpiNvs,
+    BadMemory,
+    Other(u32),
+}
+
+impl X86MemoryMapEntryType {
+    pub const fn from_raw(raw: u32) -> Self {
+        match raw {
+            1 => Self::Available,
+            2 => Self::Reserved,
+            3 => Self::AcpiReclaimable,
+            4 => Self::AcpiNvs,
+            5 => Self::BadMemory,
+            _ => Self::Other(raw),
+        }
+    }
+}
+
+/// View of a `SEL4_BOOTINFO_HEADER_X86_ACPI_RSDP` chunk, which contains a copy of the ACPI Root
+/// System Description Pointer structure.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct X86AcpiRsdp<'a> {
+    content: &'a [u8],
+}
+
+impl<'a> X86AcpiRsdp<'a> {
+    const V1_SIZE: usize = 20;
+    const V2_SIZE: usize = 36;
+
+    pub fn new(content: &'a [u8]) -> Result<Self, BootInfoExtraTruncatedError> {
+        check_len(content, Self::V1_SIZE)?;
+        let this = Self { content };
+        if this.revision() >= 2 {
+            check_len(content, Self::V2_SIZE)?;
+        }
+        Ok(this)
+    }
+
+    /// The raw structure.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        let n = if self.revision() >= 2 {
+            Self::V2_SIZE
+        } else {
+            Self::V1_SIZE
+        };
+        &self.content[..n]
+    }
+
+    pub fn signature(&self) -> &'a [u8; 8] {
+        self.content[0..8].try_into().unwrap()
+    }
+
+    pub fn oem_id(&self) -> &'a [u8; 6] {
+        self.content[9..15].try_into().unwrap()
+    }
+
+    pub fn revision(&self) -> u8 {
+        read_u8(self.content, 15)
+    }
+
+    /// The physical address of the RSDT.
+    pub fn rsdt_address(&self) -> u32 {
+        read_u32(self.content, 16)
+    }
+
+    /// The physical address of the XSDT, which is only present as of revision 2.
+    pub fn xsdt_address(&self) -> Option<u64> {
+        (self.revision() >= 2).then(|| read_u64(self.content, 24))
+    }
+
+    /// Whether the signature is `"RSD PTR "` and the checksums are valid.
+    pub fn is_valid(&self) -> bool {
+        let sum = |bytes: &[u8]| bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        self.signature() == b"RSD PTR "
+            && sum(&self.content[..Self::V1_SIZE]) == 0
+            && (self.revision() < 2 || sum(&self.content[..Self::V2_SIZE]) == 0)
+    }
+}
+
+impl fmt::Debug for X86AcpiRsdp<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("X86AcpiRsdp")
+            .field("revision", &self.revision())
+            .field("rsdt_address", &self.rsdt_address())
+            .field("xsdt_address", &self.xsdt_address())
+            .finish()
+    }
+}
+
+/// View of a `SEL4_BOOTINFO_HEADER_X86_FRAMEBUFFER` chunk (`seL4_X86_BootInfo_fb_t`, without its
+/// header).
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct X86Framebuffer<'a> {
+    content: &'a [u8],
+}
+
+impl<'a> X86Framebuffer<'a> {
+    const SIZE: usize = 22;
+
+    pub fn new(content: &'a [u8]) -> Result<Self, BootInfoExtraTruncatedError> {
+        check_len(content, Self::SIZE)?;
+        Ok(Self { content })
+    }
+
+    /// The physical address of the framebuffer.
+    pub fn addr(&self) -> u64 {
+        read_u64(self.content, 0)
+    }
+
+    /// The number of bytes per row.
+    pub fn pitch(&self) -> u32 {
+        read_u32(self.content, 8)
+    }
+
+    pub fn width(&self) -> u32 {
+        read_u32(self.content, 12)
+    }
+
+    pub fn height(&self) -> u32 {
+        read_u32(self.content, 16)
+    }
+
+    pub fn bpp(&self) -> u8 {
+        read_u8(self.content, 20)
+    }
+
+    /// The multiboot2 framebuffer type.
+    pub fn ty(&self) -> u8 {
+        read_u8(self.content, 21)
+    }
+
+    /// The size of the framebuffer in bytes.
+    pub fn size(&self) -> u64 {
+        u64::from(self.pitch()) * u64::from(self.height())
+    }
+}
+
+impl fmt::Debug for X86Framebuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("X86Framebuffer")
+            .field("addr", &self.addr())
+            .field("pitch", &self.pitch())
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .field("bpp", &self.bpp())
+            .field("ty", &self.ty())
+            .finish()
+    }
+}
+
+/// View of a `SEL4_BOOTINFO_HEADER_X86_TSC_FREQ` chunk.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct X86TscFreq<'a> {
+    content: &'a [u8],
+}
+
+impl<'a> X86TscFreq<'a> {
+    const SIZE: usize = 4;
+
+    pub fn new(content: &'a [u8]) -> Result<Self, BootInfoExtraTruncatedError> {
+        check_len(content, Self::SIZE)?;
+        Ok(Self { content })
+    }
+
+    /// The TSC frequency in MHz.
+    pub fn mhz(&self) -> u32 {
+        read_u32(self.content, 0)
+    }
+
+    /// The TSC frequency in Hz.
+    pub fn hz(&self) -> u64 {
+        u64::from(self.mhz()) * 1_000_000
+    }
+}
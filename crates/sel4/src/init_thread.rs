@@ -154,7 +154,7 @@ pub mod slot {
         #[sel4_cfg(KERNEL_MCS)]
         (SC, SchedContext, seL4_CapInitThreadSC),
         #[sel4_cfg(all(ARCH_AARCH64, ALLOW_SMC_CALLS))]
-        (SMC, Null, seL4_CapSMC),
+        (SMC, ArmSmc, seL4_CapSMC),
     ];
 }
 
@@ -16,12 +16,30 @@ use crate::{
 };
 
 #[sel4_cfg(KERNEL_MCS)]
-use crate::Badge;
+use crate::{Badge, Cap, CapType, cap_type};
 
 /// Corresponds to `seL4_Time`.
 #[sel4_cfg(KERNEL_MCS)]
 pub type Time = u64;
 
+/// The amount of time consumed by a scheduling context, as returned by
+/// [`SchedContext::sched_context_consumed`] and [`SchedContext::sched_context_yield_to`].
+#[sel4_cfg(KERNEL_MCS)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Consumed {
+    pub consumed: Time,
+}
+
+/// Trait for [`CapType`]s whose objects can be bound to a scheduling context.
+#[sel4_cfg(KERNEL_MCS)]
+pub trait CapTypeForSchedContextBinding: CapType {}
+
+#[sel4_cfg(KERNEL_MCS)]
+impl CapTypeForSchedContextBinding for cap_type::Tcb {}
+
+#[sel4_cfg(KERNEL_MCS)]
+impl CapTypeForSchedContextBinding for cap_type::Notification {}
+
 impl<C: InvocationContext> Untyped<C> {
     /// Corresponds to `seL4_Untyped_Retype`.
     pub fn untyped_retype(
@@ -81,6 +99,80 @@ impl Default for TcbFlagsBuilder {
     }
 }
 
+/// A hardware breakpoint or watchpoint, as passed to [`Tcb::tcb_set_breakpoint`].
+#[sel4_cfg(HARDWARE_DEBUG_API)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Breakpoint {
+    pub vaddr: Word,
+    pub ty: BreakpointType,
+    pub size: Word,
+    pub access: BreakpointAccess,
+    pub is_enabled: bool,
+}
+
+/// Corresponds to `seL4_BreakpointType`.
+#[sel4_cfg(HARDWARE_DEBUG_API)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BreakpointType {
+    Data,
+    Instruction,
+    SingleStep,
+    SoftwareBreakRequest,
+}
+
+#[sel4_cfg(HARDWARE_DEBUG_API)]
+impl BreakpointType {
+    pub fn into_word(self) -> Word {
+        match self {
+            Self::Data => sys::seL4_BreakpointType::seL4_DataBreakpoint,
+            Self::Instruction => sys::seL4_BreakpointType::seL4_InstructionBreakpoint,
+            Self::SingleStep => sys::seL4_BreakpointType::seL4_SingleStep,
+            Self::SoftwareBreakRequest => sys::seL4_BreakpointType::seL4_SoftwareBreakRequest,
+        }
+        .into()
+    }
+
+    pub fn from_word(word: Word) -> Option<Self> {
+        Some(match word.try_into().ok()? {
+            sys::seL4_BreakpointType::seL4_DataBreakpoint => Self::Data,
+            sys::seL4_BreakpointType::seL4_InstructionBreakpoint => Self::Instruction,
+            sys::seL4_BreakpointType::seL4_SingleStep => Self::SingleStep,
+            sys::seL4_BreakpointType::seL4_SoftwareBreakRequest => Self::SoftwareBreakRequest,
+            _ => return None,
+        })
+    }
+}
+
+/// Corresponds to `seL4_BreakpointAccess`.
+#[sel4_cfg(HARDWARE_DEBUG_API)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BreakpointAccess {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[sel4_cfg(HARDWARE_DEBUG_API)]
+impl BreakpointAccess {
+    pub fn into_word(self) -> Word {
+        match self {
+            Self::Read => sys::seL4_BreakpointAccess::seL4_BreakOnRead,
+            Self::Write => sys::seL4_BreakpointAccess::seL4_BreakOnWrite,
+            Self::ReadWrite => sys::seL4_BreakpointAccess::seL4_BreakOnReadWrite,
+        }
+        .into()
+    }
+
+    pub fn from_word(word: Word) -> Option<Self> {
+        Some(match word.try_into().ok()? {
+            sys::seL4_BreakpointAccess::seL4_BreakOnRead => Self::Read,
+            sys::seL4_BreakpointAccess::seL4_BreakOnWrite => Self::Write,
+            sys::seL4_BreakpointAccess::seL4_BreakOnReadWrite => Self::ReadWrite,
+            _ => return None,
+        })
+    }
+}
+
 impl<C: InvocationContext> Tcb<C> {
     /// Corresponds to `seL4_TCB_ReadRegisters`.
     pub fn tcb_read_registers(self, suspend: bool, count: Word) -> Result<UserContext> {
@@ -318,6 +410,71 @@ impl<C: InvocationContext> Tcb<C> {
                 .seL4_TCB_UnbindNotification(cptr.bits())
         }))
     }
+
+    /// Corresponds to `seL4_TCB_SetBreakpoint`.
+    #[sel4_cfg(HARDWARE_DEBUG_API)]
+    pub fn tcb_set_breakpoint(self, bp_num: u16, breakpoint: &Breakpoint) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_TCB_SetBreakpoint(
+                cptr.bits(),
+                bp_num,
+                breakpoint.vaddr,
+                breakpoint.ty.into_word(),
+                breakpoint.size,
+                breakpoint.access.into_word(),
+                breakpoint.is_enabled.into(),
+            )
+        }))
+    }
+
+    /// Corresponds to `seL4_TCB_GetBreakpoint`.
+    #[sel4_cfg(HARDWARE_DEBUG_API)]
+    pub fn tcb_get_breakpoint(self, bp_num: u16) -> Result<Breakpoint> {
+        let ret = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_TCB_GetBreakpoint(cptr.bits(), bp_num)
+        });
+        // The other fields are meaningless if the invocation failed.
+        Error::wrap(ret.error)?;
+        // The kernel only reports values which it accepts from seL4_TCB_SetBreakpoint.
+        Ok(Breakpoint {
+            vaddr: ret.vaddr,
+            ty: BreakpointType::from_word(ret.r#type).ok_or(Error::InvalidArgument)?,
+            size: ret.size,
+            access: BreakpointAccess::from_word(ret.rw).ok_or(Error::InvalidArgument)?,
+            is_enabled: ret.is_enabled != 0,
+        })
+    }
+
+    /// Corresponds to `seL4_TCB_UnsetBreakpoint`.
+    #[sel4_cfg(HARDWARE_DEBUG_API)]
+    pub fn tcb_unset_breakpoint(self, bp_num: u16) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_TCB_UnsetBreakpoint(cptr.bits(), bp_num)
+        }))
+    }
+
+    /// Corresponds to `seL4_TCB_ConfigureSingleStepping`.
+    ///
+    /// Returns whether the breakpoint register `bp_num` was consumed to implement single-stepping.
+    #[sel4_cfg(HARDWARE_DEBUG_API)]
+    pub fn tcb_configure_single_stepping(
+        self,
+        bp_num: u16,
+        num_instructions: Word,
+    ) -> Result<bool> {
+        let ret = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_TCB_ConfigureSingleStepping(
+                cptr.bits(),
+                bp_num,
+                num_instructions,
+            )
+        });
+        Error::or(ret.error, ret.bp_was_consumed != 0)
+    }
 }
 
 #[sel4_cfg(KERNEL_MCS)]
@@ -348,12 +505,66 @@ impl<C: InvocationContext> SchedControl<C> {
 
 #[sel4_cfg(KERNEL_MCS)]
 impl<C: InvocationContext> SchedContext<C> {
+    /// Corresponds to `seL4_SchedContext_Bind`.
+    pub fn sched_context_bind<T: CapTypeForSchedContextBinding>(
+        self,
+        object: Cap<T>,
+    ) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_SchedContext_Bind(cptr.bits(), object.bits())
+        }))
+    }
+
     /// Corresponds to `seL4_SchedContext_Unbind`.
     pub fn unbind(self) -> Result<()> {
         Error::wrap(self.invoke(|cptr, ipc_buffer| {
             ipc_buffer.inner_mut().seL4_SchedContext_Unbind(cptr.bits())
         }))
     }
+
+    /// Corresponds to `seL4_SchedContext_UnbindObject`.
+    pub fn sched_context_unbind_object<T: CapTypeForSchedContextBinding>(
+        self,
+        object: Cap<T>,
+    ) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_SchedContext_UnbindObject(cptr.bits(), object.bits())
+        }))
+    }
+
+    /// Corresponds to `seL4_SchedContext_Consumed`.
+    pub fn sched_context_consumed(self) -> Result<Consumed> {
+        let ret = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_SchedContext_Consumed(cptr.bits())
+        });
+        Error::or(
+            ret.error,
+            Consumed {
+                consumed: ret.consumed,
+            },
+        )
+    }
+
+    /// Corresponds to `seL4_SchedContext_YieldTo`.
+    pub fn sched_context_yield_to(self) -> Result<Consumed> {
+        let ret = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_SchedContext_YieldTo(cptr.bits())
+        });
+        Error::or(
+            ret.error,
+            Consumed {
+                consumed: ret.consumed,
+            },
+        )
+    }
 }
 
 impl<C: InvocationContext> IrqControl<C> {
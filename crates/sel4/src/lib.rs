@@ -96,6 +96,7 @@ pub use sel4_sys as sys;
 
 mod arch;
 mod bootinfo;
+mod bootinfo_extra;
 mod cap_rights;
 mod cnode_cap_data;
 mod const_helpers;
@@ -117,6 +118,10 @@ pub mod init_thread;
 pub use bootinfo::{
     BootInfo, BootInfoExtra, BootInfoExtraId, BootInfoExtraIter, BootInfoPtr, UntypedDesc,
 };
+pub use bootinfo_extra::{
+    BootInfoExtraContent, BootInfoExtraTruncatedError, X86AcpiRsdp, X86Framebuffer, X86MemoryMap,
+    X86MemoryMapEntry, X86MemoryMapEntryType, X86TscFreq, X86Vbe,
+};
 pub use cap_rights::{CapRights, CapRightsBuilder};
 pub use cnode_cap_data::CNodeCapData;
 pub use cptr::{
@@ -126,6 +131,9 @@ pub use error::{Error, Result};
 pub use fault::*;
 pub use invocation_context::{InvocationContext, NoExplicitInvocationContext, NoInvocationContext};
 pub use invocations::TcbFlagsBuilder;
+
+#[sel4_cfg(HARDWARE_DEBUG_API)]
+pub use invocations::{Breakpoint, BreakpointAccess, BreakpointType};
 pub use ipc_buffer::IpcBuffer;
 pub use message_info::{MessageInfo, MessageInfoBuilder};
 pub use object::{
@@ -143,7 +151,7 @@ pub use vspace::{
 
 sel4_cfg_if! {
     if #[sel4_cfg(KERNEL_MCS)] {
-        pub use invocations::Time;
+        pub use invocations::{CapTypeForSchedContextBinding, Consumed, Time};
     } else {
         pub use syscalls::reply;
         pub use reply_authority::ImplicitReplyAuthority;
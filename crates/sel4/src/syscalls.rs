@@ -188,6 +188,28 @@ impl<C: InvocationContext> cap::Notification<C> {
             self.invoke(|cptr, ipc_buffer| ipc_buffer.inner_mut().seL4_Wait(cptr.bits()));
         (wait_message_info_from_sys(info), badge)
     }
+
+    /// Corresponds to `seL4_NBWait`.
+    ///
+    /// On non-MCS kernels, which do not provide `seL4_NBWait`, this is implemented in terms of
+    /// `seL4_NBRecv`.
+    pub fn nb_wait(self) -> (WaitMessageInfo, Badge) {
+        let (info, badge) =
+            self.invoke(|cptr, ipc_buffer| ipc_buffer.inner_mut().seL4_NBWait(cptr.bits()));
+        (wait_message_info_from_sys(info), badge)
+    }
+
+    /// Corresponds to `seL4_Poll`.
+    ///
+    /// Returns the pending notification bits, which are `0` if none were pending. The kernel
+    /// reports a signal through a capability with badge `0` in the same way, so, to tell whether
+    /// a signal arrived, each capability through which the notification is signaled must have a
+    /// nonzero badge.
+    pub fn poll(self) -> Badge {
+        let (_info, badge) =
+            self.invoke(|cptr, ipc_buffer| ipc_buffer.inner_mut().seL4_Poll(cptr.bits()));
+        badge
+    }
 }
 
 #[sel4_cfg(KERNEL_MCS)]
@@ -224,6 +246,21 @@ impl<T: IpcCapType, C: InvocationContext> Cap<T, C> {
         });
         (MessageInfo::from_inner(raw_msg_info), badge)
     }
+
+    /// Corresponds to `seL4_NBSendWait`.
+    #[sel4_cfg(KERNEL_MCS)]
+    pub fn nb_send_wait<U: IpcCapType>(
+        self,
+        info: MessageInfo,
+        src: Cap<U>,
+    ) -> (MessageInfo, Badge) {
+        let (raw_msg_info, badge) = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_NBSendWait(cptr.bits(), info.into_inner(), src.bits())
+        });
+        (MessageInfo::from_inner(raw_msg_info), badge)
+    }
 }
 
 /// Corresponds to `seL4_Reply`.
@@ -10,6 +10,9 @@ mk {
   package.name = "sel4-sys";
   package.links = "sel4";
   package.build = "build/main.rs";
+  features = {
+    host-sim = [];
+  };
   dependencies = {
     inherit (versions) log;
     inherit (localCrates)
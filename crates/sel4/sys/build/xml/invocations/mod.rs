@@ -64,6 +64,13 @@ pub fn generate_rust(
                 pub const #ident: #ty = #i;
             })
         }
+        toks.extend(quote! {
+            /// Returns the name of the invocation with the given label.
+            pub fn name(label: #ty) -> Option<&'static str> {
+                const NAMES: &[&str] = &[#(#invocation_labels),*];
+                NAMES.get(usize::try_from(label).ok()?).copied()
+            }
+        });
         toks
     };
 
@@ -529,6 +536,7 @@ impl ParameterTypes {
                 this.insert_capability("seL4_ARM_PageUpperDirectory");
                 this.insert_capability("seL4_ARM_PageGlobalDirectory");
                 this.insert_capability("seL4_ARM_VSpace");
+                this.insert_capability("seL4_ARM_SMC");
             }
             this.insert_capability("seL4_ARM_ASIDControl");
             this.insert_capability("seL4_ARM_ASIDPool");
@@ -20,6 +20,7 @@ pub fn generate_rust(syscalls_xml_path: impl AsRef<Path>) -> TokenStream {
     let ty = quote!(i32);
     let mut i = -1i32;
     let mut toks = quote!();
+    let mut name_arms = quote!();
     let syscalls_for_api = if sel4_config::sel4_cfg_bool!(KERNEL_MCS) {
         &syscalls.api_mcs
     } else {
@@ -33,10 +34,22 @@ pub fn generate_rust(syscalls_xml_path: impl AsRef<Path>) -> TokenStream {
                     toks.extend(quote! {
                         pub const #ident: #ty = #i;
                     });
+                    name_arms.extend(quote! {
+                        #ident => Some(#syscall),
+                    });
                 }
                 i -= 1;
             }
         }
     }
+    toks.extend(quote! {
+        /// Returns the name of the syscall with the given ID.
+        pub fn name(id: #ty) -> Option<&'static str> {
+            match id {
+                #name_arms
+                _ => None,
+            }
+        }
+    });
     toks
 }
@@ -467,6 +467,11 @@ impl seL4_IPCBuffer {
                 ((), badge)
             }
 
+            pub fn seL4_NBWait(&mut self, src: seL4_CPtr) -> (WaitMessageInfo, seL4_Word) {
+                let (_msg_info, badge) = self.seL4_NBRecv(src, ());
+                ((), badge)
+            }
+
         }
     }
 
@@ -479,6 +484,33 @@ impl seL4_IPCBuffer {
             }
         }
     }
+
+    /// Returns `(fault, badge)`. If `fault` is zero, then the guest was interrupted by a
+    /// notification with badge `badge`. Otherwise, the message registers describe a VM exit.
+    #[sel4_cfg(VTX)]
+    pub fn seL4_VMEnter(&mut self) -> (seL4_Word, seL4_Word) {
+        let mut mr0;
+        let mut mr1;
+        let mut mr2;
+        let mut mr3;
+
+        fill_mrs_from_ipc_buffer!(self, mr0, mr1, mr2, mr3);
+
+        let (fault, badge) = sys_send_recv(
+            syscall_id::VMEnter,
+            0,
+            seL4_MessageInfo::from_word(0),
+            &mut mr0,
+            &mut mr1,
+            &mut mr2,
+            &mut mr3,
+            UNUSED_REPLY_ARG,
+        );
+
+        empty_mrs_to_ipc_buffer!(self, mr0, mr1, mr2, mr3);
+
+        (fault.into_word(), badge)
+    }
 }
 
 pub fn seL4_SendWithMRsWithoutIPCBuffer(
@@ -0,0 +1,159 @@
+//
+// Copyright 2024, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// Instead of trapping into the kernel, each syscall is passed to a function provided by the
+// simulator, which fills in the same outputs the kernel would have.
+
+use core::ffi::c_int;
+
+use sel4_config::sel4_cfg;
+
+use crate::{seL4_MessageInfo, seL4_Word};
+
+/// The register state of a syscall, as seen by a simulated kernel.
+///
+/// On entry, the fields hold the syscall's arguments. On return, `info`, `badge`, and `mrs` hold
+/// its results.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct HostSyscall {
+    pub sys: c_int,
+    pub dest: seL4_Word,
+    pub src: seL4_Word,
+    pub info: seL4_Word,
+    pub mrs: [seL4_Word; 4],
+    pub reply: seL4_Word,
+    pub badge: seL4_Word,
+}
+
+unsafe extern "Rust" {
+    fn __sel4_sys__host_syscall(syscall: &mut HostSyscall);
+}
+
+fn host_syscall(mut syscall: HostSyscall) -> HostSyscall {
+    unsafe {
+        __sel4_sys__host_syscall(&mut syscall);
+    }
+    syscall
+}
+
+pub fn sys_send(
+    sys: c_int,
+    dest: seL4_Word,
+    info_arg: seL4_MessageInfo,
+    mr0: seL4_Word,
+    mr1: seL4_Word,
+    mr2: seL4_Word,
+    mr3: seL4_Word,
+) {
+    host_syscall(HostSyscall {
+        sys,
+        dest,
+        info: info_arg.into_word(),
+        mrs: [mr0, mr1, mr2, mr3],
+        ..Default::default()
+    });
+}
+
+#[sel4_cfg(not(KERNEL_MCS))]
+pub fn sys_reply(
+    sys: c_int,
+    info_arg: seL4_MessageInfo,
+    mr0: seL4_Word,
+    mr1: seL4_Word,
+    mr2: seL4_Word,
+    mr3: seL4_Word,
+) {
+    host_syscall(HostSyscall {
+        sys,
+        info: info_arg.into_word(),
+        mrs: [mr0, mr1, mr2, mr3],
+        ..Default::default()
+    });
+}
+
+pub fn sys_send_null(sys: c_int, src: seL4_Word, info_arg: seL4_MessageInfo) {
+    host_syscall(HostSyscall {
+        sys,
+        dest: src,
+        src,
+        info: info_arg.into_word(),
+        ..Default::default()
+    });
+}
+
+pub fn sys_recv(
+    sys: c_int,
+    src: seL4_Word,
+    out_mr0: &mut seL4_Word,
+    out_mr1: &mut seL4_Word,
+    out_mr2: &mut seL4_Word,
+    out_mr3: &mut seL4_Word,
+    reply: seL4_Word,
+) -> (seL4_MessageInfo, seL4_Word) {
+    let ret = host_syscall(HostSyscall {
+        sys,
+        src,
+        reply,
+        ..Default::default()
+    });
+    [*out_mr0, *out_mr1, *out_mr2, *out_mr3] = ret.mrs;
+    (seL4_MessageInfo::from_word(ret.info), ret.badge)
+}
+
+pub fn sys_send_recv(
+    sys: c_int,
+    dest: seL4_Word,
+    info_arg: seL4_MessageInfo,
+    in_out_mr0: &mut seL4_Word,
+    in_out_mr1: &mut seL4_Word,
+    in_out_mr2: &mut seL4_Word,
+    in_out_mr3: &mut seL4_Word,
+    reply: seL4_Word,
+) -> (seL4_MessageInfo, seL4_Word) {
+    let ret = host_syscall(HostSyscall {
+        sys,
+        dest,
+        src: dest,
+        info: info_arg.into_word(),
+        mrs: [*in_out_mr0, *in_out_mr1, *in_out_mr2, *in_out_mr3],
+        reply,
+        ..Default::default()
+    });
+    [*in_out_mr0, *in_out_mr1, *in_out_mr2, *in_out_mr3] = ret.mrs;
+    (seL4_MessageInfo::from_word(ret.info), ret.badge)
+}
+
+#[sel4_cfg(KERNEL_MCS)]
+pub fn sys_nb_send_recv(
+    sys: c_int,
+    dest: seL4_Word,
+    src: seL4_Word,
+    info_arg: seL4_MessageInfo,
+    in_out_mr0: &mut seL4_Word,
+    in_out_mr1: &mut seL4_Word,
+    in_out_mr2: &mut seL4_Word,
+    in_out_mr3: &mut seL4_Word,
+    reply: seL4_Word,
+) -> (seL4_MessageInfo, seL4_Word) {
+    let ret = host_syscall(HostSyscall {
+        sys,
+        dest,
+        src,
+        info: info_arg.into_word(),
+        mrs: [*in_out_mr0, *in_out_mr1, *in_out_mr2, *in_out_mr3],
+        reply,
+        ..Default::default()
+    });
+    [*in_out_mr0, *in_out_mr1, *in_out_mr2, *in_out_mr3] = ret.mrs;
+    (seL4_MessageInfo::from_word(ret.info), ret.badge)
+}
+
+pub fn sys_null(sys: c_int) {
+    host_syscall(HostSyscall {
+        sys,
+        ..Default::default()
+    });
+}
@@ -10,6 +10,11 @@ use sel4_config::sel4_cfg_if;
 
 use crate::seL4_Word;
 
+#[cfg(feature = "host-sim")]
+#[path = "host.rs"]
+mod imp;
+
+#[cfg(not(feature = "host-sim"))]
 sel4_cfg_if! {
     if #[sel4_cfg(ARCH_AARCH64)] {
         #[path = "aarch64.rs"]
@@ -32,12 +37,15 @@ mod aarch32;
 #[cfg(false)]
 mod aarch64;
 #[cfg(false)]
+mod host;
+#[cfg(false)]
 mod riscv;
 #[cfg(false)]
 mod x86_64;
 
 pub use imp::*;
 
+#[cfg_attr(feature = "host-sim", allow(dead_code))]
 fn sys_id_to_word(sys_id: c_int) -> seL4_Word {
     sys_id as seL4_Word
 }
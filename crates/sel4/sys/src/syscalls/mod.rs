@@ -9,6 +9,9 @@ mod helpers;
 
 pub use calls::*;
 
+#[cfg(feature = "host-sim")]
+pub use helpers::HostSyscall;
+
 pub mod syscall_id {
     include!(concat!(env!("OUT_DIR"), "/syscall_ids.rs"));
 }
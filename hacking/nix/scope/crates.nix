@@ -35,6 +35,11 @@ let
         "asm"
       ];
     };
+    tests-microkit-async-runtime = {
+      extraPaths = [
+        "x.system"
+      ];
+    };
     tests-root-task-c = {
       extraPaths = [
         "cbits"
@@ -335,9 +335,11 @@ superCallPackage ../rust-utils {} self //
       sel4-bitfield-ops
       sel4-kernel-loader-embed-page-tables
       sel4-backtrace-types
+      sel4-benchmark-log
     ];
     features = [
       "sel4-backtrace-types/full"
+      "sel4-benchmark-log/alloc"
     ];
   };
 
@@ -87,6 +87,8 @@ in rec {
     tests.root-task.loader
     tests.root-task.config
     tests.root-task.tls
+    tests.root-task.mcs
+    tests.root-task.fault-handler
     tests.root-task.backtrace
     tests.root-task.panicking
     tests.root-task.c
@@ -101,6 +103,8 @@ in rec {
     microkit.examples.http-server
     microkit.tests.minimal
     microkit.tests.passive-server-with-deferred-action
+    microkit.tests.async-runtime
+    microkit.tests.deferred-actions-benchmark
     microkit.tests.reset
     examples.root-task.hello
     examples.root-task.example-root-task
@@ -108,6 +112,7 @@ in rec {
     examples.root-task.spawn-thread
     examples.root-task.spawn-task
     examples.root-task.serial-device
+    examples.root-task.arm-vmm
 
     # tests.root-task.ring
   ];
@@ -159,6 +164,26 @@ in rec {
         };
       });
 
+      mcs = maybe (haveFullRuntime && seL4Config.KERNEL_MCS) (mkInstance {
+        rootTask = mkTask {
+          rootCrate = crates.tests-root-task-mcs;
+          release = false;
+        };
+        extraPlatformArgs = lib.optionalAttrs canSimulate {
+          canAutomateSimply = true;
+        };
+      });
+
+      fault-handler = maybe (haveFullRuntime && !seL4Config.KERNEL_MCS) (mkInstance {
+        rootTask = mkTask {
+          rootCrate = crates.tests-root-task-fault-handler;
+          release = false;
+        };
+        extraPlatformArgs = lib.optionalAttrs canSimulate {
+          canAutomateSimply = true;
+        };
+      });
+
       backtrace = maybe (haveFullRuntime && haveUnwindingSupport) (mkInstance rec {
         rootTask =
           let
@@ -467,6 +492,19 @@ in rec {
             '';
       }));
 
+      arm-vmm = maybe
+        (haveFullRuntime && seL4Config.PLAT == "qemu-arm-virt" && stdenv.hostPlatform.isAarch64
+          && (seL4Config.ARM_HYPERVISOR_SUPPORT or false) && !seL4Config.KERNEL_MCS)
+        (mkInstance {
+          rootTask = mkTask {
+            rootCrate = crates.arm-vmm;
+            release = false;
+          };
+          extraPlatformArgs = lib.optionalAttrs canSimulate {
+            canAutomateSimply = true;
+          };
+        });
+
       spawn-thread = maybe haveFullRuntime (mkInstance {
         rootTask = mkTask {
           rootCrate = crates.spawn-thread;
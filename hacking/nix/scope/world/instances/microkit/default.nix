@@ -151,6 +151,56 @@ in {
         }
     );
 
+    async-runtime = maybe isMicrokit (
+      let
+        pd = mkPD rec {
+          rootCrate = crates.tests-microkit-async-runtime;
+        };
+      in
+        callPlatform {
+          system = microkit.mkSystem {
+            searchPath = [
+              "${pd}/bin"
+            ];
+            systemXML = sources.srcRoot + "/crates/private/tests/microkit/async-runtime/x.system";
+          };
+          extraPlatformArgs = lib.optionalAttrs canSimulate  {
+            canAutomateSimply = true;
+          };
+        } // {
+          inherit pd;
+        }
+    );
+
+    deferred-actions-benchmark = maybe isMicrokit (
+      let
+        mkCrateName = role: "tests-microkit-deferred-actions-benchmark-pds-${role}";
+
+        pds = {
+          bench = mkPD rec {
+            rootCrate = crates.${mkCrateName "bench"};
+          };
+          driver = mkPD rec {
+            rootCrate = crates.${mkCrateName "driver"};
+          };
+        };
+      in
+        callPlatform {
+          system = microkit.mkSystem {
+            searchPath = [
+              "${pds.bench}/bin"
+              "${pds.driver}/bin"
+            ];
+            systemXML = sources.srcRoot + "/crates/private/tests/microkit/deferred-actions-benchmark/x.system";
+          };
+          extraPlatformArgs = lib.optionalAttrs canSimulate  {
+            canAutomateSimply = true;
+          };
+        } // {
+          inherit pds;
+        }
+    );
+
     reset = maybe (isMicrokit && stdenv.hostPlatform.isAarch64) (
       let
         pd = rec {
@@ -45,6 +45,40 @@ in {
 
   sel4testInstancesList = lib.attrValues sel4testInstances;
 
+  # sel4-host-sim's tests run on the build platform, so they need libsel4 for a configuration of
+  # the build platform's architecture. That configuration must not be MCS. The unit tests of other
+  # crates which depend on sel4 but make no syscalls are run alongside them.
+  hostSimTests = pkgs.build.this.buildCratesInLayers {
+    name = "sel4-host-sim-tests";
+    test = true;
+    rootCrates = with pkgs.build.this.crates; [
+      sel4-host-sim
+      sel4-gdb-stub
+      sel4-fdt-devices
+    ];
+    commonModifications = {
+      modifyDerivation = drv: drv.overrideAttrs (self: super: {
+        SEL4_PREFIX = pkgs.host.x86_64.none.this.worlds.default.seL4;
+      });
+    };
+  };
+
+  # sel4-arm-vmm's unit tests exercise only its emulation of guest-facing interfaces, which makes
+  # no syscalls, but the crate only builds against libsel4 for an AArch64 configuration with
+  # ARM_HYPERVISOR_SUPPORT, so they can only run on an AArch64 build platform.
+  armVmmHostTests = pkgs.build.this.buildCratesInLayers {
+    name = "sel4-arm-vmm-host-tests";
+    test = true;
+    rootCrates = with pkgs.build.this.crates; [
+      sel4-arm-vmm
+    ];
+    commonModifications = {
+      modifyDerivation = drv: drv.overrideAttrs (self: super: {
+        SEL4_PREFIX = pkgs.host.aarch64.none.this.worlds.default.seL4;
+      });
+    };
+  };
+
   prerequisites = aggregate "prerequisites" [
     pkgs.build.this.qemuForSeL4
     pkgs.build.this.capdl-tool
@@ -70,6 +104,11 @@ in {
     (lib.optionals pkgs.build.stdenv.hostPlatform.isx86_64 [
       pkgs.build.this.kani
       pkgs.build.this.verus
+      hostSimTests
+    ])
+
+    (lib.optionals pkgs.build.stdenv.hostPlatform.isAarch64 [
+      armVmmHostTests
     ])
 
     pkgs.host.aarch32.none.this.worlds.default.seL4